@@ -2,16 +2,41 @@
 
 #![warn(clippy::pedantic, clippy::nursery)]
 
-use clap::{Parser, Subcommand};
+use anyhow::{bail, Context};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::json;
+use sifredb::ciphertext::Ciphertext;
+use sifredb::context::EncryptionContext;
+use sifredb::header::{peek_header, EncryptionHeader};
+use sifredb::key_provider::KeyProvider;
+use sifredb::vault::{CipherMode, Vault};
+use sifredb_key_file::FileKeyProvider;
+use std::fs;
+use std::io::{self, BufRead, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
 #[derive(Parser)]
 #[command(name = "sifredb")]
 #[command(about = "SifreDB key management CLI", long_about = None)]
 struct Cli {
+    /// Output format; `json` prints one JSON object per line for scripts
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Human-readable text
+    Text,
+    /// Machine-readable JSON
+    Json,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Generate new encryption keys
@@ -21,6 +46,9 @@ enum Commands {
         output: String,
     },
     /// Rewrap encrypted data with new KEK
+    ///
+    /// Reads hex-encoded ciphertexts from stdin, one per line, and writes
+    /// each rewrapped ciphertext to stdout in the same order.
     Rewrap {
         /// Old KEK identifier
         #[arg(long)]
@@ -28,20 +56,234 @@ enum Commands {
         /// New KEK identifier
         #[arg(long)]
         new_kek: String,
+        /// Tenant the ciphertexts were encrypted for, if any
+        ///
+        /// The wrapped DEKs of tenant ciphertexts are bound to the tenant,
+        /// so they only rewrap when it is given.
+        #[arg(long)]
+        tenant: Option<String>,
+        /// Key directory
+        #[arg(long, default_value = "./keys")]
+        keys: String,
+    },
+    /// Print the header of a ciphertext without decrypting it
+    ///
+    /// Takes one hex or base64 encoded ciphertext. No keys are needed, and
+    /// nothing is authenticated: the output describes what the header
+    /// claims, not proof that the ciphertext is genuine.
+    Inspect {
+        /// File holding the encoded ciphertext [default: stdin]
+        #[arg(long)]
+        input: Option<PathBuf>,
     },
 }
 
-fn main() {
+fn main() -> ExitCode {
     let cli = Cli::parse();
 
-    match cli.command {
-        Commands::Keygen { output } => {
-            println!("Generating keys in: {output}");
-            println!("(Implementation pending)");
+    let result = match &cli.command {
+        Commands::Keygen { output } => keygen(Path::new(output), cli.format),
+        Commands::Rewrap { old_kek, new_kek, tenant, keys } => {
+            rewrap(Path::new(keys), old_kek, new_kek, tenant.as_deref(), cli.format)
+        }
+        Commands::Inspect { input } => inspect(input.as_deref(), cli.format),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            match cli.format {
+                Format::Text => eprintln!("error: {error:#}"),
+                Format::Json => eprintln!("{}", json!({ "error": format!("{error:#}") })),
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Initializes a key directory with a KEK and a pepper.
+fn keygen(dir: &Path, format: Format) -> anyhow::Result<()> {
+    // `init` would overwrite the first KEK of an existing directory
+    if dir.join("kek_v1.key").exists() {
+        bail!("{} already holds keys", dir.display());
+    }
+
+    FileKeyProvider::init(dir).context("Failed to initialize keys")?;
+    let kek_id = FileKeyProvider::new(dir)?.current_kek_id()?;
+    let pepper_path = dir.join("pepper.key");
+
+    match format {
+        Format::Text => {
+            println!("Generated keys in: {}", dir.display());
+            println!("  KEK:    {kek_id}");
+            println!("  Pepper: {}", pepper_path.display());
+        }
+        Format::Json => println!(
+            "{}",
+            json!({
+                "kek_id": kek_id,
+                "pepper_path": pepper_path.display().to_string(),
+                "dir": dir.display().to_string(),
+            })
+        ),
+    }
+
+    Ok(())
+}
+
+/// Rewraps each ciphertext read from stdin from `old_kek` to `new_kek`.
+///
+/// Every blob is attempted; the command fails afterwards if any didn't
+/// rewrap.
+fn rewrap(
+    keys: &Path,
+    old_kek: &str,
+    new_kek: &str,
+    tenant: Option<&str>,
+    format: Format,
+) -> anyhow::Result<()> {
+    let vault = Vault::new(FileKeyProvider::new(keys)?, CipherMode::default());
+
+    // Only the tenant reaches the key provider when rewrapping; the table
+    // and column are placeholders
+    let mut context = EncryptionContext::new("rewrap", "dek");
+    if let Some(tenant) = tenant {
+        context = context.try_with_tenant(tenant)?;
+    }
+
+    let mut failed = 0;
+    let mut total = 0;
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
         }
-        Commands::Rewrap { old_kek, new_kek } => {
-            println!("Rewrapping from {old_kek} to {new_kek}");
-            println!("(Implementation pending)");
+        let index = total;
+        total += 1;
+
+        let result = rewrap_blob(&vault, line.trim(), old_kek, new_kek, &context);
+        match (format, &result) {
+            (Format::Text, Ok(rewrapped)) => println!("{rewrapped}"),
+            (Format::Text, Err(error)) => eprintln!("blob {index}: {error:#}"),
+            (Format::Json, Ok(rewrapped)) => println!(
+                "{}",
+                json!({
+                    "index": index,
+                    "old_kek": old_kek,
+                    "new_kek": new_kek,
+                    "ok": true,
+                    "ciphertext": rewrapped,
+                })
+            ),
+            (Format::Json, Err(error)) => println!(
+                "{}",
+                json!({
+                    "index": index,
+                    "old_kek": old_kek,
+                    "new_kek": new_kek,
+                    "ok": false,
+                    "error": format!("{error:#}"),
+                })
+            ),
         }
+        failed += usize::from(result.is_err());
     }
+
+    if failed > 0 {
+        bail!("{failed} of {total} ciphertexts failed to rewrap");
+    }
+    Ok(())
+}
+
+/// Rewraps one hex-encoded ciphertext, checking it is under `old_kek`.
+fn rewrap_blob(
+    vault: &Vault<FileKeyProvider>,
+    encoded: &str,
+    old_kek: &str,
+    new_kek: &str,
+    context: &EncryptionContext,
+) -> anyhow::Result<String> {
+    let ciphertext = Ciphertext::from_hex(encoded)?;
+    if ciphertext.kek_id() != old_kek {
+        bail!("wrapped under {}, not {old_kek}", ciphertext.kek_id());
+    }
+
+    Ok(vault.rewrap_with_context(&ciphertext, new_kek, context)?.to_hex())
+}
+
+/// Prints the parsed header of the ciphertext in `input`, or stdin.
+fn inspect(input: Option<&Path>, format: Format) -> anyhow::Result<()> {
+    let encoded = if let Some(path) = input {
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?
+    } else {
+        let mut encoded = String::new();
+        io::stdin().read_to_string(&mut encoded)?;
+        encoded
+    };
+
+    let header = peek_header(&decode_ciphertext(encoded.trim())?)?;
+    let flags = flag_names(&header);
+    // Headers without a cipher id predate AES support
+    let cipher = header
+        .cipher_id()
+        .map_or(Ok(CipherMode::ChaCha20Poly1305), CipherMode::from_id)
+        .ok()
+        .map(|mode| format!("{mode:?}"));
+
+    match format {
+        Format::Text => {
+            println!("Version:            {}", header.version());
+            println!("KEK:                {}", header.kek_id());
+            println!("Flags:              {}", flags.join(", "));
+            match (header.cipher_id(), &cipher) {
+                (Some(id), Some(name)) => println!("Cipher:             {name} (id {id})"),
+                (Some(id), None) => println!("Cipher:             unknown (id {id})"),
+                (None, _) => println!("Cipher:             ChaCha20Poly1305 (no id recorded)"),
+            }
+            println!("Nonce length:       {}", header.nonce().len());
+            println!("Wrapped DEK length: {}", header.wrapped_dek().len());
+        }
+        Format::Json => println!(
+            "{}",
+            json!({
+                "version": header.version(),
+                "kek_id": header.kek_id(),
+                "flags": flags,
+                "cipher_id": header.cipher_id(),
+                "cipher": cipher,
+                "nonce_len": header.nonce().len(),
+                "wrapped_dek_len": header.wrapped_dek().len(),
+            })
+        ),
+    }
+
+    Ok(())
+}
+
+/// Decodes a hex or, failing that, base64 encoded ciphertext.
+fn decode_ciphertext(encoded: &str) -> anyhow::Result<Vec<u8>> {
+    if let Ok(bytes) = hex::decode(encoded) {
+        return Ok(bytes);
+    }
+    STANDARD.decode(encoded).context("Ciphertext is neither hex nor base64")
+}
+
+/// Names the flags set in `header`, including extension flags.
+fn flag_names(header: &EncryptionHeader) -> Vec<&'static str> {
+    let flags = header.flags();
+    [
+        (flags.is_deterministic(), "deterministic"),
+        (flags.is_compressed(), "compressed"),
+        (flags.has_timestamp(), "timestamp"),
+        (flags.has_multiple_recipients(), "multiple_recipients"),
+        (flags.has_cipher_id(), "cipher_id"),
+        (flags.has_context_version(), "context_version"),
+        (flags.has_payload_len(), "payload_len"),
+        (header.commitment().is_some(), "committed"),
+        (header.header_mac().is_some(), "header_mac"),
+        (header.derives_dek(), "derived_dek"),
+    ]
+    .into_iter()
+    .filter_map(|(set, name)| set.then_some(name))
+    .collect()
 }
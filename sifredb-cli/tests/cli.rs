@@ -0,0 +1,170 @@
+//! Tests of the `sifredb` binary's JSON output.
+
+use serde_json::Value;
+use sifredb::ciphertext::Ciphertext;
+use sifredb::context::EncryptionContext;
+use sifredb::key_provider::KeyProvider;
+use sifredb::vault::{CipherMode, Vault};
+use sifredb_key_file::FileKeyProvider;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use tempfile::TempDir;
+
+/// Runs the CLI with `args`, feeding it `stdin`.
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sifredb"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run CLI");
+
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).expect("Failed to write stdin");
+    child.wait_with_output().expect("Failed to wait for CLI")
+}
+
+/// Parses each line of `output` as a JSON object.
+fn json_lines(output: &[u8]) -> Vec<Value> {
+    String::from_utf8_lossy(output)
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("Output is not JSON"))
+        .collect()
+}
+
+fn keygen(dir: &Path) -> Output {
+    run(&["--format", "json", "keygen", "--output", dir.to_str().unwrap()], "")
+}
+
+#[test]
+fn test_keygen_json_output() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let dir = temp_dir.path().join("keys");
+
+    let output = keygen(&dir);
+    assert!(output.status.success());
+
+    let lines = json_lines(&output.stdout);
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["kek_id"], "kek_v1");
+    assert_eq!(lines[0]["dir"], dir.to_str().unwrap());
+    assert_eq!(lines[0]["pepper_path"], dir.join("pepper.key").to_str().unwrap());
+    assert!(dir.join("pepper.key").exists());
+
+    let provider = FileKeyProvider::new(&dir).expect("Failed to create provider");
+    assert_eq!(provider.current_kek_id().unwrap(), "kek_v1");
+}
+
+#[test]
+fn test_keygen_json_error_on_existing_keys() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    assert!(keygen(temp_dir.path()).status.success());
+
+    let output = keygen(temp_dir.path());
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let errors = json_lines(&output.stderr);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0]["error"].as_str().unwrap().contains("already holds keys"));
+}
+
+#[test]
+fn test_inspect_reports_header_without_keys() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let dir = temp_dir.path().join("keys");
+    assert!(keygen(&dir).status.success());
+
+    let vault = Vault::new(FileKeyProvider::new(&dir).unwrap(), CipherMode::Aes128Gcm);
+    let context = EncryptionContext::new("users", "email");
+    let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+
+    // The keys are gone, so nothing but the header can be read
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let output = run(&["--format", "json", "inspect"], &format!("{}\n", ciphertext.to_hex()));
+    assert!(output.status.success());
+    let header = &json_lines(&output.stdout)[0];
+    assert_eq!(header["kek_id"], "kek_v1");
+    assert_eq!(header["version"], 3);
+    assert_eq!(header["cipher_id"], 5);
+    assert_eq!(header["cipher"], "Aes128Gcm");
+    assert_eq!(header["nonce_len"], 12);
+    assert_eq!(header["wrapped_dek_len"], ciphertext.header().wrapped_dek().len());
+    assert_eq!(
+        header["flags"],
+        serde_json::json!(["timestamp", "cipher_id", "context_version", "payload_len"])
+    );
+
+    // Base64 from a file reads the same header
+    let input = temp_dir.path().join("blob.txt");
+    std::fs::write(&input, ciphertext.to_base64()).unwrap();
+    let output = run(&["--format", "json", "inspect", "--input", input.to_str().unwrap()], "");
+    assert_eq!(json_lines(&output.stdout)[0], *header);
+
+    let output = run(&["inspect"], "not a ciphertext");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_rewrap_json_output() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let dir = temp_dir.path();
+    assert!(keygen(dir).status.success());
+
+    let vault = Vault::new(FileKeyProvider::new(dir).unwrap(), CipherMode::default());
+    let context = EncryptionContext::new("users", "email");
+    let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+    let new_kek = FileKeyProvider::new(dir).unwrap().create_kek().unwrap();
+
+    let stdin = format!("{}\n\nnot-hex\n", ciphertext.to_hex());
+    let keys = dir.to_str().unwrap();
+    let args = ["--format", "json", "rewrap", "--old-kek", "kek_v1", "--new-kek", &new_kek];
+    let output = run(&[&args[..], &["--keys", keys]].concat(), &stdin);
+
+    // The bad blob fails the command, but not the blob before it
+    assert!(!output.status.success());
+    let lines = json_lines(&output.stdout);
+    assert_eq!(lines.len(), 2);
+
+    assert_eq!(lines[0]["index"], 0);
+    assert_eq!(lines[0]["old_kek"], "kek_v1");
+    assert_eq!(lines[0]["new_kek"], "kek_v2");
+    assert_eq!(lines[0]["ok"], true);
+    let rewrapped = Ciphertext::from_hex(lines[0]["ciphertext"].as_str().unwrap()).unwrap();
+    assert_eq!(rewrapped.kek_id(), "kek_v2");
+    assert_eq!(vault.decrypt(&rewrapped, &context).unwrap(), b"alice@example.com");
+
+    assert_eq!(lines[1]["index"], 1);
+    assert_eq!(lines[1]["ok"], false);
+    assert!(lines[1]["error"].is_string());
+    assert!(json_lines(&output.stderr)[0]["error"].as_str().unwrap().contains("1 of 2"));
+}
+
+#[test]
+fn test_rewrap_tenant_ciphertexts() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let dir = temp_dir.path();
+    assert!(keygen(dir).status.success());
+
+    let provider = FileKeyProvider::new(dir).unwrap();
+    let old_kek = provider.create_tenant_kek("acme").unwrap();
+    let vault = Vault::new(FileKeyProvider::new(dir).unwrap(), CipherMode::default());
+    let context = EncryptionContext::new("users", "email").with_tenant("acme");
+    let ciphertext = vault.encrypt(b"alice@acme.test", &context).unwrap();
+    let new_kek = provider.create_tenant_kek("acme").unwrap();
+
+    let stdin = format!("{}\n", ciphertext.to_hex());
+    let keys = dir.to_str().unwrap();
+    let args = ["rewrap", "--old-kek", &old_kek, "--new-kek", &new_kek, "--keys", keys];
+
+    // Without the tenant the bound DEK doesn't unwrap
+    assert!(!run(&args, &stdin).status.success());
+
+    let output = run(&[&args[..], &["--tenant", "acme"]].concat(), &stdin);
+    assert!(output.status.success());
+    let rewrapped = Ciphertext::from_hex(String::from_utf8(output.stdout).unwrap().trim()).unwrap();
+    assert_eq!(rewrapped.kek_id(), new_kek);
+    assert_eq!(vault.decrypt(&rewrapped, &context).unwrap(), b"alice@acme.test");
+}
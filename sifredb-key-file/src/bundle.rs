@@ -0,0 +1,391 @@
+//! Passphrase-protected export and import of a whole key directory.
+
+use crate::{
+    create_symlink, is_valid_tenant_id, read_current_link, write_key_file, FileKeyProvider,
+    NONCE_SIZE, TENANTS_DIR,
+};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Nonce,
+};
+use rand::RngCore;
+use secrecy::ExposeSecret;
+use sifredb::error::KeyProviderError;
+use sifredb::kdf::{derive_kek_from_passphrase, Argon2Params};
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// Magic bytes at the start of every key bundle.
+const BUNDLE_MAGIC: &[u8; 4] = b"SDKB";
+
+/// Current key bundle format version.
+///
+/// Version 2 added per-tenant KEKs; version 1 bundles still import.
+const BUNDLE_VERSION: u8 = 2;
+
+const SALT_SIZE: usize = 16;
+
+/// Largest Argon2 memory cost accepted on import (1 GiB), so a crafted bundle
+/// can't make the importer allocate without bound.
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+
+/// Length of the unencrypted bundle header, which is authenticated as AAD.
+///
+/// `[magic:4][version:1][memory_kib:4][iterations:4][parallelism:4][salt:16][nonce:12]`
+const HEADER_SIZE: usize = 4 + 1 + 4 + 4 + 4 + SALT_SIZE + NONCE_SIZE;
+
+impl FileKeyProvider {
+    /// Exports every KEK, the current KEK pointer, and every pepper version
+    /// as a single bundle encrypted under `passphrase`, along with each
+    /// tenant's KEKs and current KEK pointer under `tenants/`.
+    ///
+    /// The passphrase is stretched with Argon2id (see
+    /// [`derive_kek_from_passphrase`]) and the contents are sealed with
+    /// ChaCha20-Poly1305. Restore with [`FileKeyProvider::import_bundle`].
+    ///
+    /// The bundle is only as strong as the passphrase; treat it like the key
+    /// directory itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the key directory can't be read or encryption fails.
+    pub fn export_bundle(&self, passphrase: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+        let current = self.resolve_current_kek()?;
+        let contents = Zeroizing::new(self.bundle_contents(&current)?);
+
+        let params = Argon2Params::default();
+        let mut salt = [0u8; SALT_SIZE];
+        OsRng.fill_bytes(&mut salt);
+        let mut nonce_bytes = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce_bytes);
+
+        let mut bundle = Vec::with_capacity(HEADER_SIZE + contents.len() + 16);
+        bundle.extend_from_slice(BUNDLE_MAGIC);
+        bundle.push(BUNDLE_VERSION);
+        bundle.extend_from_slice(&params.memory_kib.to_be_bytes());
+        bundle.extend_from_slice(&params.iterations.to_be_bytes());
+        bundle.extend_from_slice(&params.parallelism.to_be_bytes());
+        bundle.extend_from_slice(&salt);
+        bundle.extend_from_slice(&nonce_bytes);
+
+        let cipher = bundle_cipher(passphrase, &salt, params)
+            .map_err(|e| KeyProviderError::wrap_failed("Key bundle", e))?;
+        let ciphertext = cipher
+            .encrypt(&Nonce::from(nonce_bytes), Payload { msg: &contents, aad: &bundle })
+            .map_err(|e| KeyProviderError::wrap_failed("Encryption failed", e))?;
+        bundle.extend_from_slice(&ciphertext);
+
+        Ok(bundle)
+    }
+
+    /// Restores a bundle made by [`FileKeyProvider::export_bundle`] into a new
+    /// key directory.
+    ///
+    /// Key files are written with 0600 permissions and `current`, and each
+    /// tenant's `current`, is pointed at the KEK that was current at export
+    /// time, so [`FileKeyProvider::new`] can load the directory straight away.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::UnwrapFailed` if the passphrase is wrong or
+    /// the bundle is malformed or tampered with, and
+    /// `KeyProviderError::CreationFailed` if `key_dir` already holds keys.
+    pub fn import_bundle(
+        key_dir: impl Into<PathBuf>,
+        bundle: &[u8],
+        passphrase: &[u8],
+    ) -> Result<(), KeyProviderError> {
+        let key_dir = key_dir.into();
+
+        if bundle.len() < HEADER_SIZE || &bundle[..4] != BUNDLE_MAGIC {
+            return Err(invalid_bundle("not a key bundle"));
+        }
+        let version = bundle[4];
+        if !(1..=BUNDLE_VERSION).contains(&version) {
+            return Err(invalid_bundle(&format!("unsupported version {version}")));
+        }
+
+        let (header, ciphertext) = bundle.split_at(HEADER_SIZE);
+        let params = Argon2Params::new(
+            read_u32(&header[5..]),
+            read_u32(&header[9..]),
+            read_u32(&header[13..]),
+        );
+        if params.memory_kib > MAX_MEMORY_KIB {
+            return Err(invalid_bundle("Argon2 memory cost too high"));
+        }
+        let salt = &header[17..17 + SALT_SIZE];
+        let nonce_bytes: [u8; NONCE_SIZE] = header[17 + SALT_SIZE..]
+            .try_into()
+            .map_err(|_| invalid_bundle("invalid nonce size"))?;
+
+        let cipher = bundle_cipher(passphrase, salt, params)
+            .map_err(|e| KeyProviderError::unwrap_failed("Key bundle", e))?;
+        let contents = Zeroizing::new(
+            cipher
+                .decrypt(&Nonce::from(nonce_bytes), Payload { msg: ciphertext, aad: header })
+                .map_err(|_| {
+                    KeyProviderError::UnwrapFailed(
+                        "Key bundle authentication failed (wrong passphrase or corrupted bundle)"
+                            .to_string(),
+                    )
+                })?,
+        );
+
+        let BundleContents { shared, tenants } = parse_contents(&contents, version)?;
+
+        // Never mix imported keys into an existing key directory
+        if fs::symlink_metadata(key_dir.join("current")).is_ok() {
+            return Err(KeyProviderError::CreationFailed(format!(
+                "Key directory already initialized: {}",
+                key_dir.display()
+            )));
+        }
+        fs::create_dir_all(&key_dir)?;
+
+        for (tenant, keys) in &tenants {
+            let tenant_dir = key_dir.join(TENANTS_DIR).join(tenant);
+            fs::create_dir_all(&tenant_dir)?;
+            keys.write_to(&tenant_dir)?;
+        }
+
+        // The shared current KEK goes last, as it marks the directory initialized
+        shared.write_to(&key_dir)
+    }
+
+    /// Serializes the shared key files followed by every tenant's KEKs.
+    ///
+    /// The shared keys are one key set (see [`write_key_set`]), followed by
+    /// `[tenant_count:2]` and `tenant_count` entries of
+    /// `[tenant_len:1][tenant]` plus the tenant's key set.
+    fn bundle_contents(&self, current: &str) -> Result<Vec<u8>, KeyProviderError> {
+        let mut contents = Vec::new();
+        write_key_set(&mut contents, &self.key_dir, current, is_key_file_name)?;
+
+        let mut tenants = Vec::new();
+        let tenants_root = self.key_dir.join(TENANTS_DIR);
+        if tenants_root.is_dir() {
+            for entry in fs::read_dir(&tenants_root)? {
+                let entry = entry?;
+                let tenant = entry.file_name().to_string_lossy().into_owned();
+                if entry.file_type()?.is_dir() && is_valid_tenant_id(&tenant) {
+                    tenants.push(tenant);
+                }
+            }
+        }
+        tenants.sort();
+
+        let mut count: u16 = 0;
+        let mut entries = Vec::new();
+        for tenant in &tenants {
+            let tenant_dir = tenants_root.join(tenant);
+            // A tenant without a current KEK has nothing to restore
+            let current = match read_current_link(&tenant_dir) {
+                Ok(current) => current,
+                Err(KeyProviderError::NoActiveKek) => continue,
+                Err(e) => return Err(e),
+            };
+            count = count
+                .checked_add(1)
+                .ok_or_else(|| KeyProviderError::WrapFailed("Too many tenants".to_string()))?;
+
+            // Safe cast: tenant ids are directory names, at most 255 bytes
+            #[allow(clippy::cast_possible_truncation)]
+            entries.push(tenant.len() as u8);
+            entries.extend_from_slice(tenant.as_bytes());
+            write_key_set(&mut entries, &tenant_dir, &current, is_kek_file_name)?;
+        }
+
+        contents.extend_from_slice(&count.to_be_bytes());
+        contents.extend_from_slice(&entries);
+        Ok(contents)
+    }
+}
+
+/// Serializes `current` and the key files in `dir` that `accept` matches.
+///
+/// `[current_len:1][current][count:2]` followed by `count` entries of
+/// `[name_len:1][name][key_len:2][key]`.
+fn write_key_set(
+    contents: &mut Vec<u8>,
+    dir: &Path,
+    current: &str,
+    accept: fn(&str) -> bool,
+) -> Result<(), KeyProviderError> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type()?.is_file() && accept(&name) {
+            names.push(name);
+        }
+    }
+    names.sort();
+
+    // Safe casts: key file names are short ASCII and key files are 32 bytes
+    #[allow(clippy::cast_possible_truncation)]
+    contents.push(current.len() as u8);
+    contents.extend_from_slice(current.as_bytes());
+    #[allow(clippy::cast_possible_truncation)]
+    contents.extend_from_slice(&(names.len() as u16).to_be_bytes());
+
+    for name in &names {
+        let key = Zeroizing::new(fs::read(dir.join(name))?);
+        #[allow(clippy::cast_possible_truncation)]
+        contents.push(name.len() as u8);
+        contents.extend_from_slice(name.as_bytes());
+        #[allow(clippy::cast_possible_truncation)]
+        contents.extend_from_slice(&(key.len() as u16).to_be_bytes());
+        contents.extend_from_slice(&key);
+    }
+
+    Ok(())
+}
+
+/// Derives the bundle encryption key from a passphrase.
+fn bundle_cipher(
+    passphrase: &[u8],
+    salt: &[u8],
+    params: Argon2Params,
+) -> Result<ChaCha20Poly1305, sifredb::error::Error> {
+    let key = derive_kek_from_passphrase(passphrase, salt, params)?;
+    ChaCha20Poly1305::new_from_slice(key.expose_secret())
+        .map_err(|_| sifredb::error::Error::KeyDerivation)
+}
+
+/// Decrypted bundle contents, borrowed from the plaintext.
+struct BundleContents<'a> {
+    /// The shared KEKs and peppers
+    shared: KeySet<'a>,
+    /// Each tenant id and its KEKs
+    tenants: Vec<(&'a str, KeySet<'a>)>,
+}
+
+/// The key files of one key directory and its current KEK.
+struct KeySet<'a> {
+    /// KEK id that `current` points at
+    current: &'a str,
+    /// Key file names and their contents
+    files: Vec<(&'a str, &'a [u8])>,
+}
+
+impl KeySet<'_> {
+    /// Writes the key files into `dir`, then points its `current` at the KEK.
+    fn write_to(&self, dir: &Path) -> Result<(), KeyProviderError> {
+        for (name, key) in &self.files {
+            write_key_file(&dir.join(name), key)?;
+        }
+
+        // Point current at the KEK only once every key is on disk
+        create_symlink(Path::new(&format!("{}.key", self.current)), &dir.join("current"))
+    }
+}
+
+/// Parses decrypted bundle contents of the given bundle format version.
+fn parse_contents(contents: &[u8], version: u8) -> Result<BundleContents<'_>, KeyProviderError> {
+    let mut pos = 0;
+    let shared = read_key_set(contents, &mut pos, is_key_file_name)?;
+
+    let mut tenants = Vec::new();
+    if version >= 2 {
+        let count = read_u16(contents, &mut pos)?;
+        for _ in 0..count {
+            let tenant = read_name(contents, &mut pos)?;
+            // Tenant ids become directory names, so they must not name a path
+            if !is_valid_tenant_id(tenant) || tenants.iter().any(|(seen, _)| *seen == tenant) {
+                return Err(invalid_bundle(&format!("invalid tenant {tenant}")));
+            }
+            tenants.push((tenant, read_key_set(contents, &mut pos, is_kek_file_name)?));
+        }
+    }
+
+    if pos != contents.len() {
+        return Err(invalid_bundle("trailing data"));
+    }
+
+    Ok(BundleContents { shared, tenants })
+}
+
+/// Reads a key set written by [`write_key_set`], accepting only file names
+/// that `accept` matches.
+fn read_key_set<'a>(
+    contents: &'a [u8],
+    pos: &mut usize,
+    accept: fn(&str) -> bool,
+) -> Result<KeySet<'a>, KeyProviderError> {
+    let current = read_name(contents, pos)?;
+    if !is_kek_file_name(&format!("{current}.key")) {
+        return Err(invalid_bundle("invalid current KEK"));
+    }
+
+    let count = read_u16(contents, pos)?;
+    let mut files = Vec::with_capacity(usize::from(count));
+    for _ in 0..count {
+        let name = read_name(contents, pos)?;
+        if !accept(name) {
+            return Err(invalid_bundle(&format!("unexpected key file {name}")));
+        }
+        let key_len = read_u16(contents, pos)?;
+        files.push((name, take(contents, pos, usize::from(key_len))?));
+    }
+
+    if !files.iter().any(|(name, _)| name.strip_suffix(".key") == Some(current)) {
+        return Err(invalid_bundle("current KEK missing"));
+    }
+
+    Ok(KeySet { current, files })
+}
+
+/// Reads a 1-byte length-prefixed UTF-8 name.
+fn read_name<'a>(contents: &'a [u8], pos: &mut usize) -> Result<&'a str, KeyProviderError> {
+    let len = take(contents, pos, 1)?[0];
+    std::str::from_utf8(take(contents, pos, usize::from(len))?)
+        .map_err(|_| invalid_bundle("name is not UTF-8"))
+}
+
+/// Returns the next `len` bytes, advancing `pos`.
+fn take<'a>(contents: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8], KeyProviderError> {
+    let bytes = contents.get(*pos..*pos + len).ok_or_else(|| invalid_bundle("truncated"))?;
+    *pos += len;
+    Ok(bytes)
+}
+
+/// Reads a big-endian `u16`, advancing `pos`.
+fn read_u16(contents: &[u8], pos: &mut usize) -> Result<u16, KeyProviderError> {
+    let bytes = take(contents, pos, 2)?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// Reads a big-endian `u32` from the start of `bytes`.
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Checks for `kek_v{n}.key`, `pepper.key`, `pepper_v{n}.key`, or a
+/// `kek_v{n}.staged` marker.
+///
+/// Names come from the bundle on import, so this also keeps them from naming
+/// a path outside the key directory.
+fn is_key_file_name(name: &str) -> bool {
+    name == "pepper.key"
+        || is_kek_file_name(name)
+        || is_versioned_name(name, "pepper_v")
+        || name.strip_suffix(".staged").is_some_and(|stem| is_kek_file_name(&format!("{stem}.key")))
+}
+
+/// Checks for `kek_v{n}.key`, the only key files in a tenant's directory.
+fn is_kek_file_name(name: &str) -> bool {
+    is_versioned_name(name, "kek_v")
+}
+
+/// Checks for `{prefix}{n}.key` with a plain decimal `n`.
+fn is_versioned_name(name: &str, prefix: &str) -> bool {
+    name.strip_suffix(".key")
+        .and_then(|stem| stem.strip_prefix(prefix))
+        .is_some_and(|version| version.parse::<u32>().is_ok() && !version.starts_with('+'))
+}
+
+fn invalid_bundle(reason: &str) -> KeyProviderError {
+    KeyProviderError::UnwrapFailed(format!("Invalid key bundle: {reason}"))
+}
@@ -0,0 +1,338 @@
+//! Key provider deriving every KEK from one root key.
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Nonce,
+};
+use hkdf::Hkdf;
+use rand::RngCore;
+use secrecy::{ExposeSecret, SecretVec};
+use sha2::Sha256;
+use sifredb::error::KeyProviderError;
+use sifredb::key_provider::KeyProvider;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use crate::{bound_aad, open_wrapped, KEK_SIZE, NONCE_SIZE, PEPPER_SIZE};
+
+/// Key provider that derives all KEKs and the pepper from a single root key.
+///
+/// KEK `kek_v{n}` is `HKDF-SHA256(root, info = "kek" || n)` with `n` as 4
+/// big-endian bytes, and the pepper is `HKDF-SHA256(root, info = "pepper")`,
+/// so the root key is the only secret to store and back up. Nothing is
+/// written to disk except, optionally, the current version (see
+/// [`DerivedKeyProvider::with_version_file`]).
+///
+/// [`KeyProvider::create_kek`] only bumps the version; every KEK up to the
+/// current one can be derived again at any time, so rotating never makes
+/// old data unreadable. Losing the root key loses every KEK at once, and a
+/// leaked root key exposes all of them: unlike a KMS, there is no way to
+/// retire a single compromised KEK.
+///
+/// DEKs are wrapped with ChaCha20-Poly1305 as `nonce || ciphertext`, bound
+/// to their KEK id and any associated data like [`crate::FileKeyProvider`]'s.
+///
+/// # Example
+///
+/// ```no_run
+/// use secrecy::SecretVec;
+/// use sifredb::key_provider::KeyProvider;
+/// use sifredb_key_file::DerivedKeyProvider;
+///
+/// # let root_key = vec![0u8; 32];
+/// let provider = DerivedKeyProvider::from_root(SecretVec::new(root_key))
+///     .expect("Root key must be 32 bytes")
+///     .with_version_file("./kek_version")
+///     .expect("Failed to read version file");
+///
+/// let kek_id = provider.current_kek_id().expect("No active KEK");
+/// ```
+pub struct DerivedKeyProvider {
+    root: SecretVec<u8>,
+    /// Current KEK version
+    version: Mutex<u32>,
+    /// File the current version is persisted to, if any
+    version_file: Option<PathBuf>,
+}
+
+impl DerivedKeyProvider {
+    /// Creates a provider from a 32-byte root key, starting at `kek_v1`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::CreationFailed` if the root key is not 32
+    /// bytes.
+    pub fn from_root(root: SecretVec<u8>) -> Result<Self, KeyProviderError> {
+        let len = root.expose_secret().len();
+        if len != KEK_SIZE {
+            return Err(KeyProviderError::CreationFailed(format!(
+                "Root key must be {KEK_SIZE} bytes, got {len}"
+            )));
+        }
+
+        Ok(Self { root, version: Mutex::new(1), version_file: None })
+    }
+
+    /// Sets the current KEK version, e.g. restored from configuration.
+    ///
+    /// Version 0 is treated as 1.
+    #[must_use]
+    pub fn with_version(self, version: u32) -> Self {
+        *self.version.lock().unwrap_or_else(PoisonError::into_inner) = version.max(1);
+        self
+    }
+
+    /// Persists the current version to `path`, so rotations survive restarts.
+    ///
+    /// If the file exists, its version becomes current; otherwise it is
+    /// created with the current version. Each [`KeyProvider::create_kek`]
+    /// then rewrites it atomically before the new version takes effect. The
+    /// file is not secret, but processes sharing it don't coordinate: rotate
+    /// from one of them only.
+    ///
+    /// # Errors
+    ///
+    /// Returns an I/O error if the file can't be read or written, or
+    /// `KeyProviderError::CreationFailed` if it doesn't hold a version.
+    pub fn with_version_file(mut self, path: impl Into<PathBuf>) -> Result<Self, KeyProviderError> {
+        let path = path.into();
+
+        let version = self.version.get_mut().unwrap_or_else(PoisonError::into_inner);
+        if path.exists() {
+            *version = read_version_file(&path)?;
+        } else {
+            write_version_file(&path, *version)?;
+        }
+
+        self.version_file = Some(path);
+        Ok(self)
+    }
+
+    /// Returns the current KEK version.
+    #[must_use]
+    pub fn version(&self) -> u32 {
+        *self.version.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Derives the KEK with the given id, which must be `kek_v{n}` with `n`
+    /// no newer than the current version.
+    fn kek(&self, kek_id: &str) -> Result<SecretVec<u8>, KeyProviderError> {
+        let version = kek_id
+            .strip_prefix("kek_v")
+            .and_then(|version| version.parse::<u32>().ok())
+            .filter(|&version| version >= 1 && version <= self.version())
+            .ok_or_else(|| KeyProviderError::KekNotFound(kek_id.to_string()))?;
+
+        let mut info = b"kek".to_vec();
+        info.extend_from_slice(&version.to_be_bytes());
+        self.derive(&info, KEK_SIZE)
+    }
+
+    /// Expands the root key with HKDF-SHA256 for `info`.
+    fn derive(&self, info: &[u8], len: usize) -> Result<SecretVec<u8>, KeyProviderError> {
+        let mut okm = vec![0u8; len];
+        Hkdf::<Sha256>::new(None, self.root.expose_secret())
+            .expand(info, &mut okm)
+            .map_err(|e| KeyProviderError::CreationFailed(format!("HKDF expand failed: {e}")))?;
+        Ok(SecretVec::new(okm))
+    }
+
+    /// Returns the ChaCha20-Poly1305 cipher keyed with the derived KEK.
+    fn cipher(&self, kek_id: &str) -> Result<ChaCha20Poly1305, KeyProviderError> {
+        let kek = self.kek(kek_id)?;
+        ChaCha20Poly1305::new_from_slice(kek.expose_secret())
+            .map_err(|e| KeyProviderError::CreationFailed(format!("Invalid KEK: {e}")))
+    }
+}
+
+impl fmt::Debug for DerivedKeyProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DerivedKeyProvider")
+            .field("version", &self.version())
+            .field("version_file", &self.version_file)
+            .finish_non_exhaustive()
+    }
+}
+
+impl KeyProvider for DerivedKeyProvider {
+    fn create_kek(&self) -> Result<String, KeyProviderError> {
+        let mut version = self.version.lock().unwrap_or_else(PoisonError::into_inner);
+        let next = version
+            .checked_add(1)
+            .ok_or_else(|| KeyProviderError::CreationFailed("KEK versions exhausted".into()))?;
+
+        // Persist first, so a restart never rolls back to an older KEK
+        if let Some(path) = &self.version_file {
+            write_version_file(path, next)?;
+        }
+        *version = next;
+        drop(version);
+
+        Ok(format!("kek_v{next}"))
+    }
+
+    fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+        Ok(format!("kek_v{}", self.version()))
+    }
+
+    fn wrap_dek(&self, kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+        self.wrap_dek_aad(kek_id, dek, &[])
+    }
+
+    fn unwrap_dek(
+        &self,
+        kek_id: &str,
+        wrapped_dek: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        self.unwrap_dek_aad(kek_id, wrapped_dek, &[])
+    }
+
+    fn wrap_dek_aad(
+        &self,
+        kek_id: &str,
+        dek: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        let cipher = self.cipher(kek_id)?;
+
+        let mut nonce_bytes = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let nonce = Nonce::from(nonce_bytes);
+
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: dek, aad: &bound_aad(kek_id, aad) })
+            .map_err(|e| KeyProviderError::wrap_failed("Encryption failed", e))?;
+
+        let mut wrapped = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+        wrapped.extend_from_slice(&nonce_bytes);
+        wrapped.extend_from_slice(&ciphertext);
+        Ok(wrapped)
+    }
+
+    fn unwrap_dek_aad(
+        &self,
+        kek_id: &str,
+        wrapped_dek: &[u8],
+        aad: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        let cipher = self.cipher(kek_id)?;
+        open_wrapped(&cipher, wrapped_dek, &bound_aad(kek_id, aad))
+    }
+
+    fn get_pepper(&self) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+        self.derive(b"pepper", PEPPER_SIZE)
+            .map(Some)
+            .map_err(|e| KeyProviderError::PepperUnavailable(e.to_string()))
+    }
+}
+
+/// Reads a version persisted by [`write_version_file`].
+fn read_version_file(path: &Path) -> Result<u32, KeyProviderError> {
+    let contents = fs::read_to_string(path)?;
+    contents.trim().parse::<u32>().ok().filter(|&version| version >= 1).ok_or_else(|| {
+        KeyProviderError::CreationFailed(format!("Invalid KEK version file: {}", path.display()))
+    })
+}
+
+/// Writes `version` to `path` through a temporary file and a rename, so a
+/// crash leaves either the old or the new version.
+fn write_version_file(path: &Path, version: u32) -> Result<(), KeyProviderError> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+
+    fs::write(&temp, format!("{version}\n"))?;
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn root_provider() -> DerivedKeyProvider {
+        DerivedKeyProvider::from_root(SecretVec::new(vec![7u8; KEK_SIZE])).unwrap()
+    }
+
+    #[test]
+    fn test_wrap_unwrap_round_trip() {
+        let provider = root_provider();
+        let dek = [42u8; 32];
+
+        let wrapped = provider.wrap_dek("kek_v1", &dek).unwrap();
+        assert_ne!(&wrapped[NONCE_SIZE..], &dek[..]);
+        assert_eq!(provider.unwrap_dek("kek_v1", &wrapped).unwrap().expose_secret(), &dek);
+
+        // Any provider seeded with the same root derives the same KEK
+        assert_eq!(root_provider().unwrap_dek("kek_v1", &wrapped).unwrap().expose_secret(), &dek);
+
+        let wrapped = provider.wrap_dek_aad("kek_v1", &dek, b"acme").unwrap();
+        assert!(provider.unwrap_dek_aad("kek_v1", &wrapped, b"globex").is_err());
+        let unwrapped = provider.unwrap_dek_aad("kek_v1", &wrapped, b"acme").unwrap();
+        assert_eq!(unwrapped.expose_secret(), &dek);
+
+        let other = DerivedKeyProvider::from_root(SecretVec::new(vec![8u8; KEK_SIZE])).unwrap();
+        assert!(matches!(
+            other.unwrap_dek_aad("kek_v1", &wrapped, b"acme"),
+            Err(KeyProviderError::UnwrapFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_kek_versions_differ() {
+        let provider = root_provider();
+        assert_eq!(provider.current_kek_id().unwrap(), "kek_v1");
+        assert!(matches!(provider.kek("kek_v2"), Err(KeyProviderError::KekNotFound(_))));
+
+        assert_eq!(provider.create_kek().unwrap(), "kek_v2");
+        assert_eq!(provider.current_kek_id().unwrap(), "kek_v2");
+
+        let v1 = provider.kek("kek_v1").unwrap();
+        let v2 = provider.kek("kek_v2").unwrap();
+        assert_ne!(v1.expose_secret(), v2.expose_secret());
+        assert_eq!(v1.expose_secret(), provider.kek("kek_v1").unwrap().expose_secret());
+
+        let pepper = provider.get_pepper().unwrap().unwrap();
+        assert_eq!(pepper.expose_secret().len(), PEPPER_SIZE);
+        assert_ne!(pepper.expose_secret(), v1.expose_secret());
+
+        // DEKs wrapped before the rotation still unwrap
+        let wrapped = provider.wrap_dek("kek_v1", &[1u8; 32]).unwrap();
+        assert!(provider.unwrap_dek("kek_v1", &wrapped).is_ok());
+
+        for kek_id in ["kek_v0", "kek_v3", "v1", "tenants/acme/kek_v1"] {
+            assert!(matches!(provider.kek(kek_id), Err(KeyProviderError::KekNotFound(_))));
+        }
+    }
+
+    #[test]
+    fn test_from_root_rejects_wrong_size() {
+        let result = DerivedKeyProvider::from_root(SecretVec::new(vec![7u8; 16]));
+        assert!(matches!(result, Err(KeyProviderError::CreationFailed(_))));
+    }
+
+    #[test]
+    fn test_version_file_persists_rotation() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("kek_version");
+
+        let provider = root_provider().with_version_file(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1\n");
+        provider.create_kek().unwrap();
+        provider.create_kek().unwrap();
+
+        let restarted = root_provider().with_version_file(&path).unwrap();
+        assert_eq!(restarted.current_kek_id().unwrap(), "kek_v3");
+
+        fs::write(&path, "garbage").unwrap();
+        assert!(matches!(
+            root_provider().with_version_file(&path),
+            Err(KeyProviderError::CreationFailed(_))
+        ));
+
+        assert_eq!(root_provider().with_version(0).version(), 1);
+        assert_eq!(root_provider().with_version(5).current_kek_id().unwrap(), "kek_v5");
+    }
+}
@@ -1,7 +1,9 @@
 //! File-based key provider for `SifreDB`.
 //!
 //! This provider stores keys in the filesystem and is suitable for
-//! development and testing environments.
+//! development and testing environments. [`DerivedKeyProvider`] instead
+//! derives every KEK from a single root key, for deployments that would
+//! rather protect one secret than a directory of key files.
 //!
 //! # Security Warning
 //!
@@ -11,22 +13,46 @@
 #![warn(clippy::pedantic, clippy::nursery)]
 #![allow(clippy::missing_errors_doc)]
 
+mod bundle;
+mod derived;
+mod lock;
+
+pub use derived::DerivedKeyProvider;
+
 use chacha20poly1305::{
-    aead::{Aead, KeyInit, OsRng},
+    aead::{Aead, KeyInit, OsRng, Payload},
     ChaCha20Poly1305, Nonce,
 };
 use rand::RngCore;
 use secrecy::{ExposeSecret, SecretVec};
 use sifredb::error::KeyProviderError;
-use sifredb::key_provider::KeyProvider;
+use sifredb::key_provider::{KekMetadata, KeyProvider};
+use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::{PoisonError, RwLock};
+use std::time::Duration;
+
+use lock::{DirLock, LOCK_TIMEOUT};
 
 const KEK_SIZE: usize = 32; // 256 bits
 const PEPPER_SIZE: usize = 32; // 256 bits
 const NONCE_SIZE: usize = 12; // 96 bits for ChaCha20-Poly1305
 
+/// Prefix byte of wrapped DEKs whose `kek_id` is bound as associated data.
+///
+/// Wrapped DEKs written before this format are `nonce || ciphertext` with no
+/// prefix and no associated data; they are still accepted on unwrap.
+const WRAP_FORMAT_KEK_BOUND: u8 = 0x02;
+
+/// Prefix byte of wrapped DEKs that also bind caller-supplied associated
+/// data (see [`KeyProvider::wrap_dek_aad`]), as `kek_id || 0x00 || aad`.
+const WRAP_FORMAT_AAD_BOUND: u8 = 0x03;
+
+/// Subdirectory holding one key directory per isolated tenant.
+const TENANTS_DIR: &str = "tenants";
+
 /// File-based key provider for development and testing.
 ///
 /// Keys are stored in the filesystem with the following structure:
@@ -35,9 +61,24 @@ const NONCE_SIZE: usize = 12; // 96 bits for ChaCha20-Poly1305
 /// ├── kek_v1.key      (32 bytes, 0600 permissions)
 /// ├── kek_v2.key      (32 bytes, 0600 permissions)
 /// ├── current -> kek_v2.key  (symlink to active KEK)
-/// └── pepper.key      (32 bytes, 0600 permissions)
+/// ├── pepper.key      (32 bytes, 0600 permissions, pepper version 1)
+/// ├── pepper_v2.key   (32 bytes, 0600 permissions, after a pepper rotation)
+/// └── tenants/
+///     └── acme/
+///         ├── kek_v1.key
+///         └── current -> kek_v1.key
 /// ```
 ///
+/// A tenant with a directory under `tenants/` (see
+/// [`FileKeyProvider::create_tenant_kek`]) has KEKs of its own, with ids
+/// like `tenants/acme/kek_v1`; deleting that directory destroys only that
+/// tenant's data. Other tenants use the shared `current` KEK.
+///
+/// Wrapped DEKs are bound to their KEK id and, through
+/// [`KeyProvider::wrap_dek_aad`], to the tenant `Vault` encrypts for, so a
+/// wrapped DEK copied into another tenant's header doesn't unwrap even when
+/// both tenants share a KEK.
+///
 /// # Example
 ///
 /// ```no_run
@@ -53,8 +94,22 @@ const NONCE_SIZE: usize = 12; // 96 bits for ChaCha20-Poly1305
 /// // Use the provider
 /// let kek_id = provider.current_kek_id().expect("No active KEK");
 /// ```
+///
+/// KEKs are read from disk once and then kept in memory for the lifetime of
+/// the provider; see [`FileKeyProvider::clear_key_cache`].
+///
+/// Creating keys (`init`, `create_kek`, `create_kek_inactive`,
+/// `create_tenant_kek`, `rotate_pepper`) and `activate_kek` take an exclusive
+/// `.lock` file in the key directory, so processes sharing
+/// the directory never pick the same version. A process that crashes while
+/// holding it leaves the file behind, and key creation then fails with
+/// `KeyProviderError::Locked` until it is removed.
 pub struct FileKeyProvider {
     key_dir: PathBuf,
+    /// KEKs already read from disk, zeroized when evicted
+    kek_cache: RwLock<HashMap<String, SecretVec<u8>>>,
+    /// How long key creation waits for another process's lock
+    lock_timeout: Duration,
 }
 
 impl FileKeyProvider {
@@ -68,7 +123,10 @@ impl FileKeyProvider {
     ///
     /// Returns error if:
     /// - Directory doesn't exist
-    /// - No current KEK symlink exists
+    /// - Directory holds no KEK files (`KeyProviderError::NotInitialized`);
+    ///   see [`FileKeyProvider::init`]
+    /// - KEK files exist but the current KEK symlink doesn't
+    ///   (`KeyProviderError::NoActiveKek`); see [`FileKeyProvider::repair`]
     /// - File permissions are incorrect (Unix only)
     pub fn new(key_dir: impl Into<PathBuf>) -> Result<Self, KeyProviderError> {
         let key_dir = key_dir.into();
@@ -82,10 +140,14 @@ impl FileKeyProvider {
 
         let current_link = key_dir.join("current");
         if !current_link.exists() {
+            if latest_kek_version(&key_dir)? == 0 {
+                return Err(KeyProviderError::NotInitialized(key_dir));
+            }
             return Err(KeyProviderError::NoActiveKek);
         }
 
-        let provider = Self { key_dir };
+        let provider =
+            Self { key_dir, kek_cache: RwLock::new(HashMap::new()), lock_timeout: LOCK_TIMEOUT };
 
         // Verify file permissions on Unix
         #[cfg(unix)]
@@ -103,12 +165,15 @@ impl FileKeyProvider {
     ///
     /// # Errors
     ///
-    /// Returns error if directory creation or key generation fails.
+    /// Returns error if directory creation or key generation fails, or
+    /// `KeyProviderError::Locked` if another process is changing the
+    /// directory.
     pub fn init(key_dir: impl Into<PathBuf>) -> Result<(), KeyProviderError> {
         let key_dir = key_dir.into();
 
         // Create directory if it doesn't exist
         fs::create_dir_all(&key_dir)?;
+        let _lock = DirLock::acquire(&key_dir, LOCK_TIMEOUT)?;
 
         // Generate first KEK
         let kek_id = "kek_v1";
@@ -129,11 +194,193 @@ impl FileKeyProvider {
         Ok(())
     }
 
+    /// Points the `current` symlink at the highest-version activated KEK in
+    /// `key_dir`.
+    ///
+    /// An escape hatch for a directory whose `current` symlink was lost while
+    /// its `kek_v*.key` files remain, which [`FileKeyProvider::new`] reports as
+    /// `KeyProviderError::NoActiveKek`. KEKs staged with
+    /// [`FileKeyProvider::create_kek_inactive`] and never activated are
+    /// skipped. An existing symlink is replaced, so only run this when the
+    /// highest activated version really is the one to use. Tenant key
+    /// directories are not touched.
+    ///
+    /// # Returns
+    ///
+    /// The ID of the KEK `current` now points to.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::NotInitialized` if `key_dir` holds no KEK
+    /// files, `KeyProviderError::NoActiveKek` if every KEK is still staged,
+    /// `KeyProviderError::Locked` if another process holds the lock, or an
+    /// I/O error if the directory can't be read or the symlink can't be
+    /// created.
+    pub fn repair(key_dir: impl Into<PathBuf>) -> Result<String, KeyProviderError> {
+        let key_dir = key_dir.into();
+        let _lock = DirLock::acquire(&key_dir, LOCK_TIMEOUT)?;
+
+        let mut version = latest_kek_version(&key_dir)?;
+        if version == 0 {
+            return Err(KeyProviderError::NotInitialized(key_dir));
+        }
+
+        // Staged KEKs were never current, and versions may have gaps
+        let is_active = |version: u32| {
+            let kek_id = format!("kek_v{version}");
+            key_dir.join(format!("{kek_id}.key")).exists()
+                && !staged_marker_path(&key_dir, &kek_id).exists()
+        };
+        while !is_active(version) {
+            version -= 1;
+            if version == 0 {
+                return Err(KeyProviderError::NoActiveKek);
+            }
+        }
+
+        let kek_id = format!("kek_v{version}");
+        swap_current_link(&key_dir, &format!("{kek_id}.key"))?;
+
+        Ok(kek_id)
+    }
+
+    /// Sets how long key creation waits for another process to release the
+    /// directory lock before failing with `KeyProviderError::Locked`.
+    ///
+    /// Defaults to 10 seconds.
+    #[must_use]
+    pub const fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// Evicts every cached KEK, zeroizing it.
+    ///
+    /// The next wrap or unwrap reads the KEK from disk again. Useful after
+    /// replacing key files out of band, or to shorten how long key material
+    /// stays in memory after a bulk job.
+    pub fn clear_key_cache(&self) {
+        self.kek_cache.write().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+
+    /// Creates a new KEK for `tenant` and makes it the tenant's current KEK.
+    ///
+    /// The first call isolates the tenant: from then on
+    /// [`KeyProvider::current_kek_id_for_tenant`] returns the tenant's own KEK
+    /// instead of the shared one. Later calls rotate it. Data already written
+    /// under the shared KEK stays readable.
+    ///
+    /// Tenant ids may only contain ASCII letters, digits, `-`, `_`, and `.`,
+    /// and may not start with `.`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::CreationFailed` if the tenant id is invalid,
+    /// or an I/O error if the key can't be written.
+    pub fn create_tenant_kek(&self, tenant: &str) -> Result<String, KeyProviderError> {
+        if !is_valid_tenant_id(tenant) {
+            return Err(KeyProviderError::CreationFailed(format!("Invalid tenant id: {tenant}")));
+        }
+
+        let _lock = self.lock()?;
+        let tenant_dir = self.tenant_dir(tenant);
+        fs::create_dir_all(&tenant_dir)?;
+
+        let kek_name = format!("kek_v{}", next_kek_version(&tenant_dir)?);
+        let kek_id = format!("{TENANTS_DIR}/{tenant}/{kek_name}");
+        let kek_filename = format!("{kek_name}.key");
+
+        let kek = generate_random_key(KEK_SIZE);
+        write_key_file(&tenant_dir.join(&kek_filename), &kek)?;
+
+        self.kek_cache.write().unwrap_or_else(PoisonError::into_inner).remove(&kek_id);
+        swap_current_link(&tenant_dir, &kek_filename)?;
+
+        Ok(kek_id)
+    }
+
+    /// Creates a new KEK without making it current.
+    ///
+    /// Together with [`FileKeyProvider::activate_kek`] this splits
+    /// [`KeyProvider::create_kek`] in two, so a scheduled job can stage
+    /// several future KEKs ahead of time and activate each when its rotation
+    /// is due. A staged KEK can already unwrap, but nothing is wrapped under
+    /// it until it is activated. Versions keep counting up across staged
+    /// KEKs, so a later `create_kek` never reuses one.
+    ///
+    /// A `kek_v{n}.staged` marker next to the key records that it was never
+    /// activated, so [`FileKeyProvider::repair`] doesn't make it current.
+    ///
+    /// # Returns
+    ///
+    /// The ID of the new KEK, e.g. `kek_v3`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an I/O error if the key can't be written, or
+    /// `KeyProviderError::Locked` if another process holds the lock.
+    pub fn create_kek_inactive(&self) -> Result<String, KeyProviderError> {
+        let _lock = self.lock()?;
+        self.write_next_kek(true)
+    }
+
+    /// Makes an existing KEK the current one.
+    ///
+    /// `current` is repointed atomically, as by [`KeyProvider::create_kek`].
+    /// Activating an older KEK rolls back to it; activating the current KEK
+    /// is a no-op.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::KekNotFound` if `kek_id` is not a shared
+    /// KEK (`kek_v{n}`) in the key directory, or an I/O error if the symlink
+    /// can't be replaced.
+    pub fn activate_kek(&self, kek_id: &str) -> Result<(), KeyProviderError> {
+        let _lock = self.lock()?;
+        if kek_version(kek_id).is_none() || !self.kek_path(kek_id)?.exists() {
+            return Err(KeyProviderError::KekNotFound(kek_id.to_string()));
+        }
+
+        swap_current_link(&self.key_dir, &format!("{kek_id}.key"))?;
+
+        match fs::remove_file(staged_marker_path(&self.key_dir, kek_id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes a KEK with the next free version and returns its ID, marking
+    /// it staged first when `staged` is set.
+    ///
+    /// The caller must hold the directory lock.
+    fn write_next_kek(&self, staged: bool) -> Result<String, KeyProviderError> {
+        let version = next_kek_version(&self.key_dir)?;
+        let kek_id = format!("kek_v{version}");
+        let kek_path = self.kek_path(&kek_id)?;
+
+        // The marker goes first, so a crash can't leave a staged KEK unmarked
+        if staged {
+            write_key_file(&staged_marker_path(&self.key_dir, &kek_id), &[])?;
+        }
+
+        // Generate new KEK
+        let kek = generate_random_key(KEK_SIZE);
+        write_key_file(&kek_path, &kek)?;
+
+        // Never serve a stale KEK that previously had this ID
+        self.kek_cache.write().unwrap_or_else(PoisonError::into_inner).remove(&kek_id);
+
+        Ok(kek_id)
+    }
+
+    /// Takes the key directory lock for a change to the keys.
+    fn lock(&self) -> Result<DirLock, KeyProviderError> {
+        DirLock::acquire(&self.key_dir, self.lock_timeout)
+    }
+
     /// Checks file permissions on Unix systems.
     #[cfg(unix)]
     fn check_permissions(&self) -> Result<(), KeyProviderError> {
-        use std::os::unix::fs::PermissionsExt;
-
         let entries = fs::read_dir(&self.key_dir)?;
 
         for entry in entries {
@@ -145,25 +392,79 @@ impl FileKeyProvider {
                 continue;
             }
 
-            let metadata = fs::metadata(&path)?;
-            let permissions = metadata.permissions();
-            let mode = permissions.mode() & 0o777;
-
-            if mode != 0o600 {
-                return Err(KeyProviderError::CreationFailed(format!(
-                    "Insecure file permissions on {}: {:o} (expected 0600)",
-                    path.display(),
-                    mode
-                )));
-            }
+            check_file_mode(&path)?;
         }
 
         Ok(())
     }
 
+    /// Returns the path of a KEK file.
+    ///
+    /// Tenant KEK ids (`tenants/{tenant}/kek_v{n}`) are relative paths into
+    /// the tenant's directory. KEK ids come from ciphertext headers, so any
+    /// other shape is rejected with `KeyProviderError::KekNotFound` before it
+    /// can name a file outside the key directory.
+    fn kek_path(&self, kek_id: &str) -> Result<PathBuf, KeyProviderError> {
+        if !is_valid_kek_id(kek_id) {
+            return Err(KeyProviderError::KekNotFound(kek_id.to_string()));
+        }
+        Ok(self.key_dir.join(format!("{kek_id}.key")))
+    }
+
+    /// Returns the key directory of an isolated tenant.
+    fn tenant_dir(&self, tenant: &str) -> PathBuf {
+        self.key_dir.join(TENANTS_DIR).join(tenant)
+    }
+
+    /// Returns the current KEK of an isolated tenant, or `None` if the tenant
+    /// has no KEK of its own.
+    ///
+    /// A tenant whose `current` link is dangling, e.g. because its KEK file
+    /// was deleted to revoke it, is an error rather than `None`: falling back
+    /// to the shared KEK would quietly write its data under the shared key.
+    fn resolve_tenant_kek(&self, tenant: &str) -> Result<Option<String>, KeyProviderError> {
+        if !is_valid_tenant_id(tenant) {
+            return Err(KeyProviderError::KekNotFound(format!("Invalid tenant id: {tenant}")));
+        }
+
+        // `symlink_metadata` also sees dangling links, unlike `exists`
+        let tenant_dir = self.tenant_dir(tenant);
+        match fs::symlink_metadata(tenant_dir.join("current")) {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+
+        let target = fs::read_link(tenant_dir.join("current"))?;
+        let kek_name = target
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".key"))
+            .ok_or_else(|| {
+                KeyProviderError::CreationFailed("Invalid current KEK symlink".to_string())
+            })?;
+        let kek_id = format!("{TENANTS_DIR}/{tenant}/{kek_name}");
+        if !self.kek_path(&kek_id)?.exists() {
+            return Err(KeyProviderError::KekNotFound(kek_id));
+        }
+
+        Ok(Some(kek_id))
+    }
+
+    /// Returns the path of a pepper file.
+    ///
+    /// Version 1 is the original `pepper.key`; rotations add `pepper_v{n}.key`.
+    fn pepper_path(&self, version: u32) -> PathBuf {
+        if version == 1 {
+            self.key_dir.join("pepper.key")
+        } else {
+            self.key_dir.join(format!("pepper_v{version}.key"))
+        }
+    }
+
     /// Reads a KEK from disk.
     fn read_kek(&self, kek_id: &str) -> Result<SecretVec<u8>, KeyProviderError> {
-        let kek_path = self.key_dir.join(format!("{kek_id}.key"));
+        let kek_path = self.kek_path(kek_id)?;
 
         if !kek_path.exists() {
             return Err(KeyProviderError::KekNotFound(kek_id.to_string()));
@@ -176,68 +477,74 @@ impl FileKeyProvider {
         Ok(SecretVec::new(kek))
     }
 
-    /// Resolves the current KEK symlink to get the KEK ID.
-    fn resolve_current_kek(&self) -> Result<String, KeyProviderError> {
-        let current_link = self.key_dir.join("current");
-
-        if !current_link.exists() {
-            return Err(KeyProviderError::NoActiveKek);
+    /// Runs `f` with a KEK, reading it from disk only on first use.
+    fn with_kek<T>(
+        &self,
+        kek_id: &str,
+        f: impl FnOnce(&SecretVec<u8>) -> Result<T, KeyProviderError>,
+    ) -> Result<T, KeyProviderError> {
+        if let Some(kek) = self.kek_cache.read().unwrap_or_else(PoisonError::into_inner).get(kek_id)
+        {
+            return f(kek);
         }
 
-        let target = fs::read_link(&current_link)?;
-        let filename = target.file_name().and_then(|n| n.to_str()).ok_or_else(|| {
-            KeyProviderError::CreationFailed("Invalid current KEK symlink".to_string())
-        })?;
+        let kek = self.read_kek(kek_id)?;
+        let mut cache = self.kek_cache.write().unwrap_or_else(PoisonError::into_inner);
+        f(cache.entry(kek_id.to_string()).or_insert(kek))
+    }
 
-        // Extract kek_id from "kek_v1.key" -> "kek_v1"
-        let kek_id = filename.strip_suffix(".key").ok_or_else(|| {
-            KeyProviderError::CreationFailed("Invalid KEK filename format".to_string())
+    /// Encrypts `dek` under `kek_id` as `format || nonce || ciphertext`.
+    fn seal_wrapped(
+        &self,
+        kek_id: &str,
+        dek: &[u8],
+        format: u8,
+        aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        // Use ChaCha20-Poly1305 to wrap the DEK
+        let cipher = self.with_kek(kek_id, |kek| {
+            ChaCha20Poly1305::new_from_slice(kek.expose_secret())
+                .map_err(|e| KeyProviderError::wrap_failed("Invalid KEK", e))
         })?;
 
-        Ok(kek_id.to_string())
-    }
+        // Generate random nonce
+        let mut nonce_bytes = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let nonce = Nonce::from(nonce_bytes);
 
-    /// Finds the next KEK version number.
-    fn next_kek_version(&self) -> Result<u32, KeyProviderError> {
-        let entries = fs::read_dir(&self.key_dir)?;
-        let mut max_version = 0u32;
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: dek, aad })
+            .map_err(|e| KeyProviderError::wrap_failed("Encryption failed", e))?;
 
-        for entry in entries {
-            let entry = entry?;
-            let filename = entry.file_name();
-            let filename_str = filename.to_string_lossy();
+        let mut wrapped = Vec::with_capacity(1 + NONCE_SIZE + ciphertext.len());
+        wrapped.push(format);
+        wrapped.extend_from_slice(&nonce_bytes);
+        wrapped.extend_from_slice(&ciphertext);
 
-            // Parse "kek_v1.key" -> 1
-            if let Some(version_str) =
-                filename_str.strip_prefix("kek_v").and_then(|s| s.strip_suffix(".key"))
-            {
-                if let Ok(version) = version_str.parse::<u32>() {
-                    max_version = max_version.max(version);
-                }
-            }
-        }
+        Ok(wrapped)
+    }
 
-        Ok(max_version + 1)
+    /// Returns the ChaCha20-Poly1305 cipher that unwraps DEKs under `kek_id`.
+    fn unwrap_cipher(&self, kek_id: &str) -> Result<ChaCha20Poly1305, KeyProviderError> {
+        self.with_kek(kek_id, |kek| {
+            ChaCha20Poly1305::new_from_slice(kek.expose_secret())
+                .map_err(|e| KeyProviderError::unwrap_failed("Invalid KEK", e))
+        })
+    }
+
+    /// Resolves the current KEK symlink to get the KEK ID.
+    fn resolve_current_kek(&self) -> Result<String, KeyProviderError> {
+        read_current_link(&self.key_dir)
     }
 }
 
 impl KeyProvider for FileKeyProvider {
     fn create_kek(&self) -> Result<String, KeyProviderError> {
-        let version = self.next_kek_version()?;
-        let kek_id = format!("kek_v{version}");
-        let kek_filename = format!("{kek_id}.key");
-        let kek_path = self.key_dir.join(&kek_filename);
+        let _lock = self.lock()?;
+        let kek_id = self.write_next_kek(false)?;
 
-        // Generate new KEK
-        let kek = generate_random_key(KEK_SIZE);
-        write_key_file(&kek_path, &kek)?;
-
-        // Update current symlink (use relative path for portability)
-        let current_link = self.key_dir.join("current");
-        if current_link.exists() {
-            fs::remove_file(&current_link)?;
-        }
-        create_symlink(kek_filename.as_ref(), &current_link)?;
+        // Point current at the new KEK only once it is fully on disk
+        swap_current_link(&self.key_dir, &format!("{kek_id}.key"))?;
 
         Ok(kek_id)
     }
@@ -246,63 +553,110 @@ impl KeyProvider for FileKeyProvider {
         self.resolve_current_kek()
     }
 
+    fn current_kek_id_for_tenant(&self, tenant: &str) -> Result<String, KeyProviderError> {
+        self.resolve_tenant_kek(tenant)?.map_or_else(|| self.resolve_current_kek(), Ok)
+    }
+
     fn wrap_dek(&self, kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
-        let kek = self.read_kek(kek_id)?;
+        // Bind the KEK ID so the blob can't be relabeled
+        self.seal_wrapped(kek_id, dek, WRAP_FORMAT_KEK_BOUND, kek_id.as_bytes())
+    }
 
-        // Use ChaCha20-Poly1305 to wrap the DEK
-        let cipher = ChaCha20Poly1305::new_from_slice(kek.expose_secret())
-            .map_err(|e| KeyProviderError::WrapFailed(format!("Invalid KEK: {e}")))?;
+    fn unwrap_dek(
+        &self,
+        kek_id: &str,
+        wrapped_dek: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        if wrapped_dek.len() < NONCE_SIZE {
+            return Err(KeyProviderError::UnwrapFailed("Wrapped DEK too short".to_string()));
+        }
 
-        // Generate random nonce
-        let mut nonce_bytes = [0u8; NONCE_SIZE];
-        OsRng.fill_bytes(&mut nonce_bytes);
-        let nonce = Nonce::from(nonce_bytes);
+        let cipher = self.unwrap_cipher(kek_id)?;
 
-        // Encrypt DEK
-        let ciphertext = cipher
-            .encrypt(&nonce, dek)
-            .map_err(|e| KeyProviderError::WrapFailed(format!("Encryption failed: {e}")))?;
+        // A legacy blob's random nonce can also start with the format byte,
+        // so fall back to the legacy layout if the KEK-bound one fails. This
+        // can't strip the binding: a KEK-bound ciphertext never verifies
+        // without its associated data.
+        if let Some(body) = wrapped_dek.strip_prefix(&[WRAP_FORMAT_KEK_BOUND]) {
+            if let Ok(dek) = open_wrapped(&cipher, body, kek_id.as_bytes()) {
+                return Ok(dek);
+            }
+        }
 
-        // Return nonce || ciphertext
-        let mut wrapped = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
-        wrapped.extend_from_slice(&nonce_bytes);
-        wrapped.extend_from_slice(&ciphertext);
+        open_wrapped(&cipher, wrapped_dek, &[])
+    }
 
-        Ok(wrapped)
+    fn wrap_dek_aad(
+        &self,
+        kek_id: &str,
+        dek: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        if aad.is_empty() {
+            return self.wrap_dek(kek_id, dek);
+        }
+        self.seal_wrapped(kek_id, dek, WRAP_FORMAT_AAD_BOUND, &bound_aad(kek_id, aad))
     }
 
-    fn unwrap_dek(
+    fn unwrap_dek_aad(
         &self,
         kek_id: &str,
         wrapped_dek: &[u8],
+        aad: &[u8],
     ) -> Result<SecretVec<u8>, KeyProviderError> {
-        if wrapped_dek.len() < NONCE_SIZE {
-            return Err(KeyProviderError::UnwrapFailed("Wrapped DEK too short".to_string()));
+        if !aad.is_empty() {
+            if let Some(body) = wrapped_dek.strip_prefix(&[WRAP_FORMAT_AAD_BOUND]) {
+                let cipher = self.unwrap_cipher(kek_id)?;
+                if let Ok(dek) = open_wrapped(&cipher, body, &bound_aad(kek_id, aad)) {
+                    return Ok(dek);
+                }
+            }
         }
 
-        let kek = self.read_kek(kek_id)?;
+        // DEKs wrapped without associated data still unwrap. An AAD-bound
+        // blob fails here too, as it never verifies without its AAD.
+        self.unwrap_dek(kek_id, wrapped_dek)
+    }
+
+    fn rewrap_dek_aad(
+        &self,
+        old_kek_id: &str,
+        new_kek_id: &str,
+        wrapped_dek: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        let dek = self.unwrap_dek_aad(old_kek_id, wrapped_dek, aad)?;
+        self.wrap_dek_aad(new_kek_id, dek.expose_secret(), aad)
+    }
 
-        // Use ChaCha20-Poly1305 to unwrap the DEK
-        let cipher = ChaCha20Poly1305::new_from_slice(kek.expose_secret())
-            .map_err(|e| KeyProviderError::UnwrapFailed(format!("Invalid KEK: {e}")))?;
+    fn get_pepper(&self) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+        self.get_pepper_version(self.current_pepper_version()?)
+    }
 
-        // Split nonce and ciphertext
-        let (nonce_bytes, ciphertext) = wrapped_dek.split_at(NONCE_SIZE);
-        let nonce_array: [u8; NONCE_SIZE] = nonce_bytes
-            .try_into()
-            .map_err(|_| KeyProviderError::UnwrapFailed("Invalid nonce size".to_string()))?;
-        let nonce = Nonce::from(nonce_array);
+    fn current_pepper_version(&self) -> Result<u32, KeyProviderError> {
+        let entries = fs::read_dir(&self.key_dir)?;
+        let mut max_version = 1u32;
 
-        // Decrypt DEK
-        let plaintext = cipher
-            .decrypt(&nonce, ciphertext)
-            .map_err(|e| KeyProviderError::UnwrapFailed(format!("Decryption failed: {e}")))?;
+        for entry in entries {
+            let entry = entry?;
+            let filename = entry.file_name();
+            let filename_str = filename.to_string_lossy();
 
-        Ok(SecretVec::new(plaintext))
+            // Parse "pepper_v2.key" -> 2
+            if let Some(version) = filename_str
+                .strip_prefix("pepper_v")
+                .and_then(|s| s.strip_suffix(".key"))
+                .and_then(|s| s.parse::<u32>().ok())
+            {
+                max_version = max_version.max(version);
+            }
+        }
+
+        Ok(max_version)
     }
 
-    fn get_pepper(&self) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
-        let pepper_path = self.key_dir.join("pepper.key");
+    fn get_pepper_version(&self, version: u32) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+        let pepper_path = self.pepper_path(version);
 
         if !pepper_path.exists() {
             return Ok(None);
@@ -314,6 +668,131 @@ impl KeyProvider for FileKeyProvider {
 
         Ok(Some(SecretVec::new(pepper)))
     }
+
+    fn rotate_pepper(&self) -> Result<(), KeyProviderError> {
+        let _lock = self.lock()?;
+        let version = self.current_pepper_version()? + 1;
+
+        // Older pepper files are kept so existing indexes can still be verified
+        let pepper = generate_random_key(PEPPER_SIZE);
+        write_key_file(&self.pepper_path(version), &pepper)?;
+
+        Ok(())
+    }
+
+    fn kek_metadata(&self, kek_id: &str) -> Result<KekMetadata, KeyProviderError> {
+        let kek_path = self.kek_path(kek_id)?;
+
+        if !kek_path.exists() {
+            return Err(KeyProviderError::KekNotFound(kek_id.to_string()));
+        }
+
+        // Key files are written once, so the mtime is the creation time
+        let created_at = fs::metadata(&kek_path)?.modified().ok();
+        let current = tenant_of(kek_id).map_or_else(
+            || self.resolve_current_kek().ok(),
+            |tenant| self.resolve_tenant_kek(tenant).ok().flatten(),
+        );
+        let is_current = current.is_some_and(|current| current == kek_id);
+
+        Ok(KekMetadata { id: kek_id.to_string(), created_at, is_current })
+    }
+
+    fn health_check(&self) -> Result<(), KeyProviderError> {
+        // The current symlink must resolve to an existing KEK file
+        let kek_id = self.resolve_current_kek()?;
+        let kek_path = self.kek_path(&kek_id)?;
+
+        #[cfg(unix)]
+        check_file_mode(&kek_path)?;
+
+        // Reading the KEK proves the file is present, readable, and full length
+        self.read_kek(&kek_id).map(drop).map_err(|e| {
+            KeyProviderError::Unavailable(format!("Cannot read {}: {e}", kek_path.display()))
+        })
+    }
+}
+
+/// Reads the KEK ID (`kek_v{n}`) that `dir/current` points at.
+fn read_current_link(dir: &Path) -> Result<String, KeyProviderError> {
+    let current_link = dir.join("current");
+
+    if !current_link.exists() {
+        return Err(KeyProviderError::NoActiveKek);
+    }
+
+    let target = fs::read_link(&current_link)?;
+    let filename = target.file_name().and_then(|n| n.to_str()).ok_or_else(|| {
+        KeyProviderError::CreationFailed("Invalid current KEK symlink".to_string())
+    })?;
+
+    // Extract kek_id from "kek_v1.key" -> "kek_v1"
+    let kek_id = filename.strip_suffix(".key").ok_or_else(|| {
+        KeyProviderError::CreationFailed("Invalid KEK filename format".to_string())
+    })?;
+
+    Ok(kek_id.to_string())
+}
+
+/// Finds the next KEK version number in `dir`.
+fn next_kek_version(dir: &Path) -> Result<u32, KeyProviderError> {
+    Ok(latest_kek_version(dir)? + 1)
+}
+
+/// Finds the highest KEK version in `dir`, or 0 if it holds no KEK files.
+fn latest_kek_version(dir: &Path) -> Result<u32, KeyProviderError> {
+    let entries = fs::read_dir(dir)?;
+    let mut max_version = 0u32;
+
+    for entry in entries {
+        let entry = entry?;
+        let filename = entry.file_name();
+        let filename_str = filename.to_string_lossy();
+
+        // Parse "kek_v1.key" -> 1
+        if let Some(version) = filename_str.strip_suffix(".key").and_then(kek_version) {
+            max_version = max_version.max(version);
+        }
+    }
+
+    Ok(max_version)
+}
+
+/// Returns the path of the marker recording that a shared KEK was staged
+/// and has not been activated yet.
+fn staged_marker_path(dir: &Path, kek_id: &str) -> PathBuf {
+    dir.join(format!("{kek_id}.staged"))
+}
+
+/// Checks that a KEK id is `kek_v{n}` or `tenants/{tenant}/kek_v{n}` with a
+/// valid tenant id, the only ids that map to a file in the key directory.
+fn is_valid_kek_id(kek_id: &str) -> bool {
+    let kek_name = match kek_id.strip_prefix(TENANTS_DIR).and_then(|rest| rest.strip_prefix('/')) {
+        Some(rest) => match rest.split_once('/') {
+            Some((tenant, kek_name)) if is_valid_tenant_id(tenant) => kek_name,
+            _ => return false,
+        },
+        None => kek_id,
+    };
+    kek_version(kek_name).is_some()
+}
+
+/// Parses the version of a shared KEK id (`kek_v{n}`).
+fn kek_version(kek_id: &str) -> Option<u32> {
+    kek_id.strip_prefix("kek_v")?.parse().ok()
+}
+
+/// Checks that a tenant id is safe to use as a directory name.
+fn is_valid_tenant_id(tenant: &str) -> bool {
+    !tenant.is_empty()
+        && !tenant.starts_with('.')
+        && tenant.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
+
+/// Returns the tenant of a tenant KEK id (`tenants/{tenant}/kek_v{n}`).
+fn tenant_of(kek_id: &str) -> Option<&str> {
+    let (tenant, _) = kek_id.strip_prefix(TENANTS_DIR)?.strip_prefix('/')?.split_once('/')?;
+    Some(tenant)
 }
 
 /// Generates a random key of the specified size.
@@ -323,6 +802,60 @@ fn generate_random_key(size: usize) -> Vec<u8> {
     key
 }
 
+/// Builds the associated data of an AAD-bound wrapped DEK.
+///
+/// KEK ids are file names, so they never contain the NUL separator.
+fn bound_aad(kek_id: &str, aad: &[u8]) -> Vec<u8> {
+    let mut bound = Vec::with_capacity(kek_id.len() + 1 + aad.len());
+    bound.extend_from_slice(kek_id.as_bytes());
+    bound.push(0);
+    bound.extend_from_slice(aad);
+    bound
+}
+
+/// Decrypts a `nonce || ciphertext` wrapped DEK with the given associated data.
+fn open_wrapped(
+    cipher: &ChaCha20Poly1305,
+    wrapped: &[u8],
+    aad: &[u8],
+) -> Result<SecretVec<u8>, KeyProviderError> {
+    if wrapped.len() < NONCE_SIZE {
+        return Err(KeyProviderError::UnwrapFailed("Wrapped DEK too short".to_string()));
+    }
+
+    // Split nonce and ciphertext
+    let (nonce_bytes, ciphertext) = wrapped.split_at(NONCE_SIZE);
+    let nonce_array: [u8; NONCE_SIZE] = nonce_bytes
+        .try_into()
+        .map_err(|_| KeyProviderError::UnwrapFailed("Invalid nonce size".to_string()))?;
+    let nonce = Nonce::from(nonce_array);
+
+    // Decrypt DEK
+    let plaintext = cipher
+        .decrypt(&nonce, Payload { msg: ciphertext, aad })
+        .map_err(|e| KeyProviderError::UnwrapFailed(format!("Decryption failed: {e}")))?;
+
+    Ok(SecretVec::new(plaintext))
+}
+
+/// Checks that a key file is only accessible by its owner (mode 0600).
+#[cfg(unix)]
+fn check_file_mode(path: &Path) -> Result<(), KeyProviderError> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path)?.permissions().mode() & 0o777;
+
+    if mode != 0o600 {
+        return Err(KeyProviderError::CreationFailed(format!(
+            "Insecure file permissions on {}: {:o} (expected 0600)",
+            path.display(),
+            mode
+        )));
+    }
+
+    Ok(())
+}
+
 /// Writes a key to a file with secure permissions.
 fn write_key_file(path: &Path, key: &[u8]) -> Result<(), KeyProviderError> {
     let mut file = File::create(path)?;
@@ -337,6 +870,35 @@ fn write_key_file(path: &Path, key: &[u8]) -> Result<(), KeyProviderError> {
         fs::set_permissions(path, permissions)?;
     }
 
+    // Flush to disk so a crash can't leave a torn key behind
+    file.sync_all()?;
+
+    Ok(())
+}
+
+/// Atomically repoints the `current` symlink at `kek_filename`.
+///
+/// The new link is created as `current.tmp` and renamed over `current`, so
+/// `current` always resolves to either the old or the new KEK, even if the
+/// process dies mid-swap. A stale `current.tmp` left by such a crash is
+/// replaced.
+fn swap_current_link(key_dir: &Path, kek_filename: &str) -> Result<(), KeyProviderError> {
+    let current_link = key_dir.join("current");
+    let temp_link = key_dir.join("current.tmp");
+
+    // `symlink_metadata` also sees dangling links, unlike `exists`
+    if fs::symlink_metadata(&temp_link).is_ok() {
+        fs::remove_file(&temp_link)?;
+    }
+
+    // Use relative path for portability
+    create_symlink(kek_filename.as_ref(), &temp_link)?;
+    fs::rename(&temp_link, &current_link)?;
+
+    // Persist the rename itself
+    #[cfg(unix)]
+    File::open(key_dir)?.sync_all()?;
+
     Ok(())
 }
 
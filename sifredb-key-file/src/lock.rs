@@ -0,0 +1,78 @@
+//! Advisory lock serializing changes to a key directory.
+
+use sifredb::error::KeyProviderError;
+use std::fs::{self, OpenOptions};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Name of the lock file inside a key directory.
+const LOCK_FILE: &str = ".lock";
+
+/// Default time to wait for another process to release the lock.
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pause between attempts to take a held lock.
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Exclusive hold on a key directory, released on drop.
+///
+/// The lock is a `.lock` file created with `O_EXCL`, so it works across
+/// processes on any filesystem with atomic exclusive create. A process that
+/// dies while holding it leaves the file behind; it must then be removed by
+/// hand once no other process is changing the directory.
+pub struct DirLock {
+    path: PathBuf,
+}
+
+impl DirLock {
+    /// Takes the lock on `key_dir`, waiting up to `timeout` for it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::Locked` if the lock is still held after
+    /// `timeout`, or an I/O error if the lock file can't be created.
+    pub fn acquire(key_dir: &Path, timeout: Duration) -> Result<Self, KeyProviderError> {
+        let path = key_dir.join(LOCK_FILE);
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            match create_lock_file(&path) {
+                Ok(()) => return Ok(Self { path }),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    if Instant::now() >= deadline {
+                        return Err(KeyProviderError::Locked(format!(
+                            "{} is held by another process; remove it if none is running",
+                            path.display()
+                        )));
+                    }
+                    thread::sleep(LOCK_POLL_INTERVAL);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+impl Drop for DirLock {
+    fn drop(&mut self) {
+        // Nothing useful can be done if removal fails; the next taker times out
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Creates the lock file, failing if it already exists.
+fn create_lock_file(path: &Path) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+
+    // Owner-only like the key files, so permission checks pass while it exists
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(path).map(drop)
+}
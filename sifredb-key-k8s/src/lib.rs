@@ -0,0 +1,600 @@
+//! Kubernetes secret volume key provider for `SifreDB`.
+//!
+//! Reads KEKs and peppers from a Secret mounted as a volume. Kubernetes
+//! updates such a volume by writing the new contents to a fresh timestamped
+//! directory and atomically swapping the `..data` symlink over to it; every
+//! key in the mount is itself a symlink through `..data`. This provider
+//! re-resolves `..data` on every read, so an updated Secret is picked up
+//! without restarting the process.
+
+#![warn(clippy::pedantic, clippy::nursery)]
+#![allow(clippy::missing_errors_doc)]
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Nonce,
+};
+use rand::RngCore;
+use secrecy::{ExposeSecret, SecretVec};
+use sifredb::error::KeyProviderError;
+use sifredb::key_provider::{KekMetadata, KeyProvider};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+const KEK_SIZE: usize = 32; // 256 bits
+const NONCE_SIZE: usize = 12; // 96 bits for ChaCha20-Poly1305
+
+/// Prefix byte of wrapped DEKs whose `kek_id` is bound as associated data.
+///
+/// This and [`WRAP_FORMAT_AAD_BOUND`] are the formats `sifredb-key-file`
+/// writes, so a key directory can move between the two providers without
+/// rewrapping. The file provider's legacy unprefixed format isn't read.
+const WRAP_FORMAT_KEK_BOUND: u8 = 0x02;
+
+/// Prefix byte of wrapped DEKs that also bind caller-supplied associated
+/// data (see [`KeyProvider::wrap_dek_aad`]), as `kek_id || 0x00 || aad`.
+const WRAP_FORMAT_AAD_BOUND: u8 = 0x03;
+
+/// Name of the symlink Kubernetes swaps when a Secret volume is updated.
+const DATA_LINK: &str = "..data";
+
+/// Name of the Secret key holding the active KEK identifier.
+const CURRENT_KEY: &str = "current";
+
+/// Key provider backed by a Kubernetes Secret mounted as a volume.
+///
+/// The Secret uses the same key files as `sifredb-key-file`, plus a
+/// `current` entry naming the active KEK:
+/// ```text
+/// /etc/sifredb/keys/
+/// ├── current         ("kek_v2", the active KEK id)
+/// ├── kek_v1.key      (32 raw bytes)
+/// ├── kek_v2.key      (32 raw bytes)
+/// ├── pepper.key      (32 raw bytes, pepper version 1)
+/// └── pepper_v2.key   (32 raw bytes, after a pepper rotation)
+/// ```
+///
+/// Such a Secret can be created from an initialized file provider directory:
+/// ```text
+/// kubectl create secret generic sifredb-keys \
+///     --from-file=keys/kek_v1.key --from-file=keys/pepper.key \
+///     --from-literal=current=kek_v1
+/// ```
+///
+/// Nothing is cached: each operation reads the files it needs from the
+/// directory `..data` points at right then, so rotating the KEK is just a
+/// matter of adding the new key to the Secret and updating `current`. Keep
+/// retired KEKs in the Secret for as long as ciphertext wrapped under them
+/// exists. A directory without `..data` (e.g. a plain copy of the Secret) is
+/// read as-is.
+///
+/// Secret volumes are read-only, so [`KeyProvider::create_kek`] and
+/// [`KeyProvider::rotate_pepper`] are unsupported; update the Secret instead.
+///
+/// # Example
+///
+/// ```no_run
+/// use sifredb::key_provider::KeyProvider;
+/// use sifredb_key_k8s::K8sSecretProvider;
+///
+/// let provider = K8sSecretProvider::new("/etc/sifredb/keys").expect("Secret not mounted");
+/// let kek_id = provider.current_kek_id().expect("No active KEK");
+/// ```
+#[derive(Debug, Clone)]
+pub struct K8sSecretProvider {
+    mount_dir: PathBuf,
+}
+
+impl K8sSecretProvider {
+    /// Creates a provider reading from a mounted Secret volume.
+    ///
+    /// # Arguments
+    ///
+    /// * `mount_dir` - The volume's mount path
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - The mount directory doesn't exist
+    /// - The Secret has no valid `current` entry
+    pub fn new(mount_dir: impl Into<PathBuf>) -> Result<Self, KeyProviderError> {
+        let mount_dir = mount_dir.into();
+
+        if !mount_dir.is_dir() {
+            return Err(KeyProviderError::CreationFailed(format!(
+                "Secret mount directory does not exist: {}",
+                mount_dir.display()
+            )));
+        }
+
+        let provider = Self { mount_dir };
+        provider.current_kek_id()?;
+
+        Ok(provider)
+    }
+
+    /// Returns the mount path this provider reads from.
+    #[must_use]
+    pub fn mount_dir(&self) -> &Path {
+        &self.mount_dir
+    }
+
+    /// Resolves the directory holding the Secret's current contents.
+    fn data_dir(&self) -> PathBuf {
+        fs::canonicalize(self.mount_dir.join(DATA_LINK)).unwrap_or_else(|_| self.mount_dir.clone())
+    }
+
+    /// Reads a Secret entry, returning `None` if the Secret has no such key.
+    fn read_entry(&self, name: &str) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+        let data_dir = self.data_dir();
+
+        match fs::read(data_dir.join(name)) {
+            Ok(bytes) => Ok(Some(SecretVec::new(bytes))),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                // Kubernetes deletes the old directory right after swapping
+                // `..data`, so a read racing an update retries on the new one
+                let latest = self.data_dir();
+                if latest == data_dir {
+                    return Ok(None);
+                }
+                match fs::read(latest.join(name)) {
+                    Ok(bytes) => Ok(Some(SecretVec::new(bytes))),
+                    Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Reads a KEK, checking its identifier and size.
+    fn read_kek(&self, kek_id: &str) -> Result<SecretVec<u8>, KeyProviderError> {
+        validate_kek_id(kek_id)?;
+
+        let kek = self
+            .read_entry(&format!("{kek_id}.key"))?
+            .ok_or_else(|| KeyProviderError::KekNotFound(kek_id.to_string()))?;
+
+        let len = kek.expose_secret().len();
+        if len != KEK_SIZE {
+            return Err(KeyProviderError::Unavailable(format!(
+                "KEK {kek_id} is {len} bytes (expected {KEK_SIZE})"
+            )));
+        }
+
+        Ok(kek)
+    }
+
+    /// Returns the Secret key of a pepper version.
+    ///
+    /// Version 1 is `pepper.key`; rotations add `pepper_v{n}.key`.
+    /// Encrypts `dek` under `kek_id` with `aad`, as `format || nonce || ciphertext`.
+    fn seal_wrapped(
+        &self,
+        kek_id: &str,
+        dek: &[u8],
+        format: u8,
+        aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        let kek = self.read_kek(kek_id)?;
+        let cipher = ChaCha20Poly1305::new_from_slice(kek.expose_secret())
+            .map_err(|e| KeyProviderError::WrapFailed(format!("Invalid KEK: {e}")))?;
+
+        // Generate random nonce
+        let mut nonce_bytes = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let nonce = Nonce::from(nonce_bytes);
+
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: dek, aad })
+            .map_err(|e| KeyProviderError::WrapFailed(format!("Encryption failed: {e}")))?;
+
+        // Return format || nonce || ciphertext
+        let mut wrapped = Vec::with_capacity(1 + NONCE_SIZE + ciphertext.len());
+        wrapped.push(format);
+        wrapped.extend_from_slice(&nonce_bytes);
+        wrapped.extend_from_slice(&ciphertext);
+
+        Ok(wrapped)
+    }
+
+    /// Decrypts a wrapped DEK written by [`Self::seal_wrapped`] with `format`.
+    fn open_wrapped(
+        &self,
+        kek_id: &str,
+        wrapped_dek: &[u8],
+        format: u8,
+        aad: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        let body = wrapped_dek
+            .strip_prefix(&[format])
+            .filter(|body| body.len() >= NONCE_SIZE)
+            .ok_or_else(|| KeyProviderError::UnwrapFailed("Malformed wrapped DEK".to_string()))?;
+
+        let kek = self.read_kek(kek_id)?;
+        let cipher = ChaCha20Poly1305::new_from_slice(kek.expose_secret())
+            .map_err(|e| KeyProviderError::UnwrapFailed(format!("Invalid KEK: {e}")))?;
+
+        // Split nonce and ciphertext
+        let (nonce_bytes, ciphertext) = body.split_at(NONCE_SIZE);
+        let nonce_array: [u8; NONCE_SIZE] = nonce_bytes
+            .try_into()
+            .map_err(|_| KeyProviderError::UnwrapFailed("Invalid nonce size".to_string()))?;
+        let nonce = Nonce::from(nonce_array);
+
+        let plaintext = cipher
+            .decrypt(&nonce, Payload { msg: ciphertext, aad })
+            .map_err(|e| KeyProviderError::UnwrapFailed(format!("Decryption failed: {e}")))?;
+
+        Ok(SecretVec::new(plaintext))
+    }
+
+    fn pepper_key(version: u32) -> String {
+        if version == 1 {
+            "pepper.key".to_string()
+        } else {
+            format!("pepper_v{version}.key")
+        }
+    }
+}
+
+impl KeyProvider for K8sSecretProvider {
+    fn create_kek(&self) -> Result<String, KeyProviderError> {
+        Err(KeyProviderError::Unsupported(
+            "KEK creation (Secret volumes are read-only; add the KEK to the Secret)".to_string(),
+        ))
+    }
+
+    fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+        let current = self.read_entry(CURRENT_KEY)?.ok_or(KeyProviderError::NoActiveKek)?;
+
+        // `--from-literal` and `--from-file` may or may not leave a newline
+        let kek_id = std::str::from_utf8(current.expose_secret())
+            .map_err(|_| {
+                KeyProviderError::CreationFailed("Secret key `current` is not UTF-8".to_string())
+            })?
+            .trim();
+        let kek_id = kek_id.strip_suffix(".key").unwrap_or(kek_id);
+
+        if kek_id.is_empty() {
+            return Err(KeyProviderError::NoActiveKek);
+        }
+        validate_kek_id(kek_id)?;
+
+        Ok(kek_id.to_string())
+    }
+
+    fn wrap_dek(&self, kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+        // Bind the KEK ID so the blob can't be relabeled
+        self.seal_wrapped(kek_id, dek, WRAP_FORMAT_KEK_BOUND, kek_id.as_bytes())
+    }
+
+    fn unwrap_dek(
+        &self,
+        kek_id: &str,
+        wrapped_dek: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        self.open_wrapped(kek_id, wrapped_dek, WRAP_FORMAT_KEK_BOUND, kek_id.as_bytes())
+    }
+
+    fn wrap_dek_aad(
+        &self,
+        kek_id: &str,
+        dek: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        if aad.is_empty() {
+            return self.wrap_dek(kek_id, dek);
+        }
+        self.seal_wrapped(kek_id, dek, WRAP_FORMAT_AAD_BOUND, &bound_aad(kek_id, aad))
+    }
+
+    fn unwrap_dek_aad(
+        &self,
+        kek_id: &str,
+        wrapped_dek: &[u8],
+        aad: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        // DEKs wrapped without associated data still unwrap, as with the file provider
+        if aad.is_empty() || wrapped_dek.first() != Some(&WRAP_FORMAT_AAD_BOUND) {
+            return self.unwrap_dek(kek_id, wrapped_dek);
+        }
+        self.open_wrapped(kek_id, wrapped_dek, WRAP_FORMAT_AAD_BOUND, &bound_aad(kek_id, aad))
+    }
+
+    fn rewrap_dek_aad(
+        &self,
+        old_kek_id: &str,
+        new_kek_id: &str,
+        wrapped_dek: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        let dek = self.unwrap_dek_aad(old_kek_id, wrapped_dek, aad)?;
+        self.wrap_dek_aad(new_kek_id, dek.expose_secret(), aad)
+    }
+
+    fn get_pepper(&self) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+        self.get_pepper_version(self.current_pepper_version()?)
+    }
+
+    fn current_pepper_version(&self) -> Result<u32, KeyProviderError> {
+        let mut max_version = 1u32;
+
+        for entry in fs::read_dir(self.data_dir())? {
+            let filename = entry?.file_name();
+
+            // Parse "pepper_v2.key" -> 2
+            if let Some(version) = filename
+                .to_string_lossy()
+                .strip_prefix("pepper_v")
+                .and_then(|s| s.strip_suffix(".key"))
+                .and_then(|s| s.parse::<u32>().ok())
+            {
+                max_version = max_version.max(version);
+            }
+        }
+
+        Ok(max_version)
+    }
+
+    fn get_pepper_version(&self, version: u32) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+        self.read_entry(&Self::pepper_key(version))
+    }
+
+    fn kek_metadata(&self, kek_id: &str) -> Result<KekMetadata, KeyProviderError> {
+        self.read_kek(kek_id)?;
+
+        // Secret volume files carry the mount time, not the key's age
+        let is_current = self.current_kek_id().is_ok_and(|current| current == kek_id);
+        Ok(KekMetadata { id: kek_id.to_string(), created_at: None, is_current })
+    }
+
+    fn health_check(&self) -> Result<(), KeyProviderError> {
+        // `current` must name a KEK that is present and full length
+        let kek_id = self.current_kek_id()?;
+        self.read_kek(&kek_id).map(drop).map_err(|e| {
+            KeyProviderError::Unavailable(format!(
+                "Cannot read KEK {kek_id} from {}: {e}",
+                self.mount_dir.display()
+            ))
+        })
+    }
+}
+
+/// Returns the associated data of an AAD-bound wrapped DEK.
+fn bound_aad(kek_id: &str, aad: &[u8]) -> Vec<u8> {
+    let mut bound = Vec::with_capacity(kek_id.len() + 1 + aad.len());
+    bound.extend_from_slice(kek_id.as_bytes());
+    bound.push(0);
+    bound.extend_from_slice(aad);
+    bound
+}
+
+/// Rejects KEK identifiers that aren't plain Secret keys.
+///
+/// KEK ids come from ciphertext headers, so they must never be able to name a
+/// file outside the Secret, such as `../token` or the `..data` link itself.
+fn validate_kek_id(kek_id: &str) -> Result<(), KeyProviderError> {
+    let valid = !kek_id.is_empty()
+        && !kek_id.starts_with('.')
+        && kek_id.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(KeyProviderError::KekNotFound(kek_id.to_string()))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    use tempfile::TempDir;
+
+    /// Writes a Secret volume the way the kubelet does: the entries go in a
+    /// fresh `..<name>` directory, `..data` points at it, and every entry is
+    /// a symlink through `..data`.
+    fn write_secret(mount: &Path, snapshot: &str, entries: &[(&str, &[u8])]) {
+        let snapshot_dir = mount.join(format!("..{snapshot}"));
+        fs::create_dir(&snapshot_dir).unwrap();
+        for (name, contents) in entries {
+            fs::write(snapshot_dir.join(name), contents).unwrap();
+        }
+
+        // Atomically swap `..data` over to the new snapshot
+        let temp_link = mount.join("..data_tmp");
+        symlink(format!("..{snapshot}"), &temp_link).unwrap();
+        let old_snapshot = fs::read_link(mount.join(DATA_LINK)).ok();
+        fs::rename(&temp_link, mount.join(DATA_LINK)).unwrap();
+
+        for (name, _) in entries {
+            let link = mount.join(name);
+            if fs::symlink_metadata(&link).is_err() {
+                symlink(Path::new(DATA_LINK).join(name), &link).unwrap();
+            }
+        }
+
+        if let Some(old_snapshot) = old_snapshot {
+            fs::remove_dir_all(mount.join(old_snapshot)).unwrap();
+        }
+    }
+
+    fn secret_v1(mount: &Path) {
+        write_secret(
+            mount,
+            "2024_01_01",
+            &[("current", b"kek_v1\n"), ("kek_v1.key", &[1; 32]), ("pepper.key", &[9; 32])],
+        );
+    }
+
+    #[test]
+    fn test_wrap_unwrap_round_trip() {
+        let mount = TempDir::new().unwrap();
+        secret_v1(mount.path());
+        let provider = K8sSecretProvider::new(mount.path()).unwrap();
+
+        assert_eq!(provider.current_kek_id().unwrap(), "kek_v1");
+
+        let dek = [7u8; 32];
+        let wrapped = provider.wrap_dek("kek_v1", &dek).unwrap();
+        let unwrapped = provider.unwrap_dek("kek_v1", &wrapped).unwrap();
+        assert_eq!(unwrapped.expose_secret(), &dek);
+
+        assert_eq!(provider.get_pepper().unwrap().unwrap().expose_secret(), &[9; 32]);
+        provider.health_check().unwrap();
+    }
+
+    #[test]
+    fn test_wrap_aad_binds_tenant() {
+        let mount = TempDir::new().unwrap();
+        secret_v1(mount.path());
+        let provider = K8sSecretProvider::new(mount.path()).unwrap();
+        let dek = [7u8; 32];
+
+        let wrapped = provider.wrap_dek_aad("kek_v1", &dek, b"acme").unwrap();
+        assert_eq!(wrapped[0], WRAP_FORMAT_AAD_BOUND);
+        assert!(provider.unwrap_dek_aad("kek_v1", &wrapped, b"globex").is_err());
+        assert!(provider.unwrap_dek("kek_v1", &wrapped).is_err());
+        let unwrapped = provider.unwrap_dek_aad("kek_v1", &wrapped, b"acme").unwrap();
+        assert_eq!(unwrapped.expose_secret(), &dek);
+
+        // DEKs wrapped before tenants were bound still unwrap
+        let unbound = provider.wrap_dek("kek_v1", &dek).unwrap();
+        let unwrapped = provider.unwrap_dek_aad("kek_v1", &unbound, b"acme").unwrap();
+        assert_eq!(unwrapped.expose_secret(), &dek);
+
+        let rewrapped = provider.rewrap_dek_aad("kek_v1", "kek_v1", &wrapped, b"acme").unwrap();
+        assert!(provider.unwrap_dek_aad("kek_v1", &rewrapped, b"globex").is_err());
+        assert!(provider.unwrap_dek_aad("kek_v1", &rewrapped, b"acme").is_ok());
+    }
+
+    #[test]
+    fn test_secret_update_is_picked_up() {
+        let mount = TempDir::new().unwrap();
+        secret_v1(mount.path());
+        let provider = K8sSecretProvider::new(mount.path()).unwrap();
+
+        let dek = [7u8; 32];
+        let wrapped_v1 = provider.wrap_dek("kek_v1", &dek).unwrap();
+
+        // Rotate: add kek_v2, make it current, and swap the volume over
+        write_secret(
+            mount.path(),
+            "2024_02_01",
+            &[
+                ("current", b"kek_v2"),
+                ("kek_v1.key", &[1; 32]),
+                ("kek_v2.key", &[2; 32]),
+                ("pepper.key", &[9; 32]),
+            ],
+        );
+
+        assert_eq!(provider.current_kek_id().unwrap(), "kek_v2");
+        let wrapped_v2 = provider.wrap_dek("kek_v2", &dek).unwrap();
+        assert_eq!(provider.unwrap_dek("kek_v2", &wrapped_v2).unwrap().expose_secret(), &dek);
+
+        // DEKs wrapped before the update still unwrap with the retained KEK
+        assert_eq!(provider.unwrap_dek("kek_v1", &wrapped_v1).unwrap().expose_secret(), &dek);
+        assert!(provider.kek_metadata("kek_v2").unwrap().is_current);
+        assert!(!provider.kek_metadata("kek_v1").unwrap().is_current);
+    }
+
+    #[test]
+    fn test_kek_dropped_from_secret() {
+        let mount = TempDir::new().unwrap();
+        secret_v1(mount.path());
+        let provider = K8sSecretProvider::new(mount.path()).unwrap();
+        let wrapped_v1 = provider.wrap_dek("kek_v1", &[7; 32]).unwrap();
+
+        write_secret(
+            mount.path(),
+            "2024_02_01",
+            &[("current", b"kek_v2"), ("kek_v2.key", &[2; 32])],
+        );
+
+        let result = provider.unwrap_dek("kek_v1", &wrapped_v1);
+        assert!(matches!(result, Err(KeyProviderError::KekNotFound(id)) if id == "kek_v1"));
+    }
+
+    #[test]
+    fn test_plain_directory() {
+        let mount = TempDir::new().unwrap();
+        fs::write(mount.path().join("current"), "kek_v3.key").unwrap();
+        fs::write(mount.path().join("kek_v3.key"), [3; 32]).unwrap();
+        let provider = K8sSecretProvider::new(mount.path()).unwrap();
+
+        assert_eq!(provider.current_kek_id().unwrap(), "kek_v3");
+        assert!(provider.get_pepper().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_pepper_versions() {
+        let mount = TempDir::new().unwrap();
+        write_secret(
+            mount.path(),
+            "2024_01_01",
+            &[
+                ("current", b"kek_v1"),
+                ("kek_v1.key", &[1; 32]),
+                ("pepper.key", &[9; 32]),
+                ("pepper_v2.key", &[8; 32]),
+            ],
+        );
+        let provider = K8sSecretProvider::new(mount.path()).unwrap();
+
+        assert_eq!(provider.current_pepper_version().unwrap(), 2);
+        assert_eq!(provider.get_pepper().unwrap().unwrap().expose_secret(), &[8; 32]);
+        assert_eq!(provider.get_pepper_version(1).unwrap().unwrap().expose_secret(), &[9; 32]);
+        assert!(provider.get_pepper_version(3).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rejects_path_traversal_kek_ids() {
+        let mount = TempDir::new().unwrap();
+        secret_v1(mount.path());
+        fs::write(mount.path().join("outside.key"), [5; 32]).unwrap();
+        let provider = K8sSecretProvider::new(mount.path().join("..data")).unwrap();
+
+        for kek_id in ["../outside", "..data/kek_v1", "", ".hidden"] {
+            let result = provider.wrap_dek(kek_id, &[7; 32]);
+            assert!(matches!(result, Err(KeyProviderError::KekNotFound(_))), "{kek_id}");
+        }
+    }
+
+    #[test]
+    fn test_new_errors() {
+        let mount = TempDir::new().unwrap();
+
+        let result = K8sSecretProvider::new(mount.path().join("missing"));
+        assert!(matches!(result, Err(KeyProviderError::CreationFailed(_))));
+
+        let result = K8sSecretProvider::new(mount.path());
+        assert!(matches!(result, Err(KeyProviderError::NoActiveKek)));
+    }
+
+    #[test]
+    fn test_read_only_operations_unsupported() {
+        let mount = TempDir::new().unwrap();
+        secret_v1(mount.path());
+        let provider = K8sSecretProvider::new(mount.path()).unwrap();
+
+        assert!(matches!(provider.create_kek(), Err(KeyProviderError::Unsupported(_))));
+        assert!(matches!(provider.rotate_pepper(), Err(KeyProviderError::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_wrong_size_kek_fails_health_check() {
+        let mount = TempDir::new().unwrap();
+        write_secret(
+            mount.path(),
+            "2024_01_01",
+            &[("current", b"kek_v1"), ("kek_v1.key", b"short")],
+        );
+        let provider = K8sSecretProvider::new(mount.path()).unwrap();
+
+        assert!(matches!(provider.health_check(), Err(KeyProviderError::Unavailable(_))));
+        assert!(provider.wrap_dek("kek_v1", &[7; 32]).is_err());
+    }
+}
@@ -0,0 +1,443 @@
+//! Remote key service provider for `SifreDB`.
+//!
+//! This crate lets applications delegate key management to a central key
+//! service instead of holding KEKs locally. The service owns the KEKs and
+//! exposes a small JSON contract over HTTP; the provider never sees KEK
+//! material, only wrapped and unwrapped DEKs.
+//!
+//! # Contract
+//!
+//! All byte fields are standard base64. Paths are relative to the configured
+//! endpoint.
+//!
+//! | Method | Path              | Request                   | Response        |
+//! |--------|-------------------|---------------------------|-----------------|
+//! | `POST` | `/v1/wrap`        | `{ "kek_id", "dek" }`     | `{ "wrapped" }` |
+//! | `POST` | `/v1/unwrap`      | `{ "kek_id", "wrapped" }` | `{ "dek" }`     |
+//! | `GET`  | `/v1/current-kek` | -                         | `{ "kek_id" }`  |
+//! | `GET`  | `/v1/pepper`      | -                         | `{ "pepper" }`  |
+//!
+//! Status codes are mapped to [`KeyProviderError`]: `404` means the KEK (or
+//! active KEK, or pepper) doesn't exist, `429` is [`KeyProviderError::Throttled`],
+//! `5xx` and transport failures are [`KeyProviderError::Unavailable`].
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use sifredb_key_remote::RemoteProvider;
+//! use sifredb::prelude::*;
+//! use std::time::Duration;
+//!
+//! let provider = RemoteProvider::new("https://keys.internal.example")
+//!     .with_bearer_token("service-token")
+//!     .with_timeout(Duration::from_secs(5));
+//! ```
+
+#![warn(clippy::pedantic, clippy::nursery)]
+#![allow(clippy::module_name_repetitions)]
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, SecretVec};
+use serde::{Deserialize, Serialize};
+use sifredb::{
+    error::KeyProviderError,
+    key_provider::{AsyncKeyProvider, WrappedDek},
+};
+use std::time::Duration;
+use zeroize::Zeroize;
+
+#[derive(Serialize)]
+struct WrapRequest<'a> {
+    kek_id: &'a str,
+    dek: &'a str,
+}
+
+#[derive(Deserialize)]
+struct WrapResponse {
+    wrapped: String,
+}
+
+#[derive(Serialize)]
+struct UnwrapRequest<'a> {
+    kek_id: &'a str,
+    wrapped: &'a str,
+}
+
+#[derive(Deserialize)]
+struct UnwrapResponse {
+    dek: String,
+}
+
+#[derive(Deserialize)]
+struct CurrentKekResponse {
+    kek_id: String,
+}
+
+#[derive(Deserialize)]
+struct PepperResponse {
+    pepper: String,
+}
+
+/// Key provider backed by a central key service.
+///
+/// This provider forwards every key operation to the service:
+/// - Wrap/unwrap DEKs under KEKs the service holds
+/// - Resolve the current KEK id (so rotation is driven centrally)
+/// - Fetch the blind index pepper
+pub struct RemoteProvider {
+    /// HTTP client for the key service
+    http: reqwest::Client,
+    /// Base URL of the key service
+    endpoint: String,
+    /// Optional bearer token sent with every request
+    bearer_token: Option<String>,
+    /// Optional per-request timeout
+    timeout: Option<Duration>,
+}
+
+impl RemoteProvider {
+    /// Creates a provider for the key service at `endpoint`.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - Base URL of the key service (e.g. `https://keys.internal`)
+    #[must_use]
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            bearer_token: None,
+            timeout: None,
+        }
+    }
+
+    /// Sends `token` as a bearer token with every request.
+    #[must_use]
+    pub fn with_bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.bearer_token = Some(token.into());
+        self
+    }
+
+    /// Sets a timeout for each request to the key service.
+    ///
+    /// Requests that exceed it fail with [`KeyProviderError::Unavailable`].
+    #[must_use]
+    pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Uses a preconfigured HTTP client (custom TLS roots, proxies, mTLS, ...).
+    #[must_use]
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.http = client;
+        self
+    }
+
+    /// Returns the key service base URL.
+    #[must_use]
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Issues a request to `path` and decodes the JSON response.
+    ///
+    /// `not_found` builds the error for a `404`; other failures without a
+    /// more specific meaning are reported through `fallback`.
+    async fn call<Req, Resp>(
+        &self,
+        path: &str,
+        body: Option<&Req>,
+        not_found: impl FnOnce() -> KeyProviderError + Send,
+        fallback: fn(String) -> KeyProviderError,
+    ) -> Result<Resp, KeyProviderError>
+    where
+        Req: Serialize + Sync,
+        Resp: for<'de> Deserialize<'de>,
+    {
+        let url = format!("{}{path}", self.endpoint);
+        let mut request =
+            body.map_or_else(|| self.http.get(&url), |body| self.http.post(&url).json(body));
+        if let Some(token) = &self.bearer_token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+
+        let response = request.send().await.map_err(|e| {
+            KeyProviderError::Unavailable(format!("key service request to {path} failed: {e}"))
+        })?;
+
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Err(not_found());
+        }
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(classify_status(
+                status,
+                format!("{path} returned {status}: {message}"),
+                fallback,
+            ));
+        }
+
+        response.json::<Resp>().await.map_err(|e| fallback(format!("invalid {path} response: {e}")))
+    }
+}
+
+/// Maps a non-success, non-404 status to a [`KeyProviderError`].
+fn classify_status(
+    status: StatusCode,
+    message: String,
+    fallback: fn(String) -> KeyProviderError,
+) -> KeyProviderError {
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        KeyProviderError::Throttled(message)
+    } else if status.is_server_error() {
+        KeyProviderError::Unavailable(message)
+    } else {
+        fallback(message)
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncKeyProvider for RemoteProvider {
+    async fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+        let response = self
+            .call::<(), CurrentKekResponse>(
+                "/v1/current-kek",
+                None,
+                || KeyProviderError::NoActiveKek,
+                KeyProviderError::Unavailable,
+            )
+            .await?;
+
+        if response.kek_id.is_empty() {
+            return Err(KeyProviderError::NoActiveKek);
+        }
+        Ok(response.kek_id)
+    }
+
+    async fn wrap_dek(
+        &self,
+        dek: &SecretVec<u8>,
+        kek_id: &str,
+    ) -> Result<WrappedDek, KeyProviderError> {
+        let mut encoded = STANDARD.encode(dek.expose_secret());
+        let result = self
+            .call::<_, WrapResponse>(
+                "/v1/wrap",
+                Some(&WrapRequest { kek_id, dek: &encoded }),
+                || KeyProviderError::KekNotFound(kek_id.to_string()),
+                KeyProviderError::WrapFailed,
+            )
+            .await;
+        encoded.zeroize();
+
+        let encrypted_dek = STANDARD
+            .decode(result?.wrapped)
+            .map_err(|e| KeyProviderError::WrapFailed(format!("Base64: {e}")))?;
+
+        Ok(WrappedDek { kek_id: kek_id.to_string(), encrypted_dek })
+    }
+
+    async fn unwrap_dek(&self, wrapped: &WrappedDek) -> Result<SecretVec<u8>, KeyProviderError> {
+        let encoded = STANDARD.encode(&wrapped.encrypted_dek);
+        let mut response = self
+            .call::<_, UnwrapResponse>(
+                "/v1/unwrap",
+                Some(&UnwrapRequest { kek_id: &wrapped.kek_id, wrapped: &encoded }),
+                || KeyProviderError::KekNotFound(wrapped.kek_id.clone()),
+                KeyProviderError::UnwrapFailed,
+            )
+            .await?;
+
+        let dek = STANDARD
+            .decode(&response.dek)
+            .map_err(|e| KeyProviderError::UnwrapFailed(format!("Base64: {e}")));
+        response.dek.zeroize();
+
+        Ok(SecretVec::new(dek?))
+    }
+
+    async fn get_pepper(&self) -> Result<SecretVec<u8>, KeyProviderError> {
+        let mut response = self
+            .call::<(), PepperResponse>(
+                "/v1/pepper",
+                None,
+                || KeyProviderError::PepperUnavailable("key service has no pepper".to_string()),
+                KeyProviderError::PepperUnavailable,
+            )
+            .await?;
+
+        let pepper = STANDARD
+            .decode(&response.pepper)
+            .map_err(|e| KeyProviderError::PepperUnavailable(format!("Base64: {e}")));
+        response.pepper.zeroize();
+
+        Ok(SecretVec::new(pepper?))
+    }
+
+    async fn health_check(&self) -> Result<(), KeyProviderError> {
+        self.current_kek_id().await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    /// Mock key service that "wraps" by XOR-ing with a fixed byte.
+    struct XorKeyService;
+
+    impl Respond for XorKeyService {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let xor = |field: &str| {
+                let bytes = STANDARD.decode(body[field].as_str().unwrap()).unwrap();
+                STANDARD.encode(bytes.iter().map(|b| b ^ 0x5a).collect::<Vec<u8>>())
+            };
+
+            if body["kek_id"] != "kek_v1" {
+                return ResponseTemplate::new(404);
+            }
+            if request.url.path() == "/v1/wrap" {
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "wrapped": xor("dek") }))
+            } else {
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "dek": xor("wrapped") }))
+            }
+        }
+    }
+
+    async fn mock_service() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("authorization", "Bearer test-token"))
+            .respond_with(XorKeyService)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/current-kek"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({ "kek_id": "kek_v1" })),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/pepper"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "pepper": STANDARD.encode([9u8; 32]) })),
+            )
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn provider(server: &MockServer) -> RemoteProvider {
+        RemoteProvider::new(server.uri()).with_bearer_token("test-token")
+    }
+
+    #[tokio::test]
+    async fn test_wrap_unwrap_round_trip() {
+        let server = mock_service().await;
+        let provider = provider(&server);
+
+        let dek = SecretVec::new(vec![7u8; 32]);
+        let kek_id = provider.current_kek_id().await.unwrap();
+        let wrapped = provider.wrap_dek(&dek, &kek_id).await.unwrap();
+
+        assert_eq!(wrapped.kek_id, "kek_v1");
+        assert_ne!(wrapped.encrypted_dek, dek.expose_secret().clone());
+
+        let unwrapped = provider.unwrap_dek(&wrapped).await.unwrap();
+        assert_eq!(unwrapped.expose_secret(), dek.expose_secret());
+    }
+
+    #[tokio::test]
+    async fn test_current_kek_and_pepper() {
+        let server = mock_service().await;
+        let provider = provider(&server);
+
+        assert_eq!(provider.current_kek_id().await.unwrap(), "kek_v1");
+        assert_eq!(provider.get_pepper().await.unwrap().expose_secret(), &vec![9u8; 32]);
+        assert!(provider.health_check().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_kek_maps_to_kek_not_found() {
+        let server = mock_service().await;
+        let provider = provider(&server);
+
+        let wrapped = WrappedDek { kek_id: "kek_v9".to_string(), encrypted_dek: vec![0x5a; 32] };
+        let result = provider.unwrap_dek(&wrapped).await;
+        assert!(matches!(result, Err(KeyProviderError::KekNotFound(id)) if id == "kek_v9"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_current_kek_maps_to_no_active_kek() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+
+        let result = RemoteProvider::new(server.uri()).current_kek_id().await;
+        assert!(matches!(result, Err(KeyProviderError::NoActiveKek)));
+    }
+
+    #[tokio::test]
+    async fn test_status_classification() {
+        let server = MockServer::start().await;
+        Mock::given(path("/v1/wrap")).respond_with(ResponseTemplate::new(429)).mount(&server).await;
+        Mock::given(path("/v1/unwrap"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+        Mock::given(path("/v1/pepper"))
+            .respond_with(ResponseTemplate::new(403).set_body_string("forbidden"))
+            .mount(&server)
+            .await;
+
+        let provider = RemoteProvider::new(server.uri());
+        let dek = SecretVec::new(vec![1u8; 32]);
+        let wrapped = WrappedDek { kek_id: "kek_v1".to_string(), encrypted_dek: vec![0; 32] };
+
+        assert!(matches!(
+            provider.wrap_dek(&dek, "kek_v1").await,
+            Err(KeyProviderError::Throttled(_))
+        ));
+        assert!(matches!(
+            provider.unwrap_dek(&wrapped).await,
+            Err(KeyProviderError::Unavailable(_))
+        ));
+        assert!(matches!(provider.get_pepper().await, Err(KeyProviderError::PepperUnavailable(_))));
+    }
+
+    #[tokio::test]
+    async fn test_transport_failure_maps_to_unavailable() {
+        // Reserve a port, then release it so nothing is listening there
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let provider =
+            RemoteProvider::new(format!("http://{addr}")).with_timeout(Duration::from_secs(2));
+        let dek = SecretVec::new(vec![1u8; 32]);
+
+        assert!(matches!(
+            provider.wrap_dek(&dek, "kek_v1").await,
+            Err(KeyProviderError::Unavailable(_))
+        ));
+        assert!(matches!(provider.health_check().await, Err(KeyProviderError::Unavailable(_))));
+    }
+
+    #[test]
+    fn test_endpoint_trailing_slash_trimmed() {
+        let provider = RemoteProvider::new("https://keys.example/");
+        assert_eq!(provider.endpoint(), "https://keys.example");
+    }
+}
@@ -1,4 +1,4 @@
-//! AWS KMS key provider for SifreDB.
+//! AWS KMS key provider for `SifreDB`.
 //!
 //! This module provides integration with AWS Key Management Service (KMS)
 //! for enterprise-grade key management with hardware security modules (HSM).
@@ -9,7 +9,7 @@
 //! - Hardware-backed key security
 //! - Automatic key rotation
 //! - Fine-grained access control via IAM
-//! - Audit logging via CloudTrail
+//! - Audit logging via `CloudTrail`
 //! - Multi-region support
 //!
 //! # Example
@@ -36,25 +36,43 @@
 //! # AWS Configuration
 //!
 //! The provider uses the AWS SDK's default credential chain:
-//! - Environment variables (AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY)
+//! - Environment variables (`AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`)
 //! - AWS credentials file (~/.aws/credentials)
 //! - IAM instance profile (for EC2)
 //! - ECS task role
 //! - Web identity token (for EKS)
+//!
+//! # Pepper
+//!
+//! Blind indexes only match across processes if every process uses the same
+//! pepper, so it is kept as a KMS-wrapped blob in configuration rather than
+//! generated locally. Create the blob once with
+//! [`AwsKmsProvider::generate_wrapped_pepper`], store it alongside the key ID,
+//! and pass it to [`AwsKmsProvider::with_wrapped_pepper`] on every instance.
 
 #![warn(clippy::pedantic, clippy::nursery)]
 #![allow(clippy::module_name_repetitions)]
 
+use aws_config::BehaviorVersion;
+use aws_sdk_kms::error::{ProvideErrorMetadata, SdkError};
 use aws_sdk_kms::Client as KmsClient;
 use secrecy::{ExposeSecret, SecretVec};
 use sifredb::{
     error::KeyProviderError,
-    key_provider::{KeyProvider, WrappedDek},
+    key_provider::{AsyncKeyProvider, WrappedDek},
 };
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use tokio::sync::RwLock;
 
+/// How long a resolved alias target is reused by default.
+pub const DEFAULT_ALIAS_TTL: Duration = Duration::from_secs(60);
+
+/// KMS encryption context key and value bound to wrapped peppers, so a
+/// wrapped DEK can't be passed off as the pepper or vice versa.
+const PEPPER_CONTEXT: (&str, &str) = ("sifredb-purpose", "pepper");
+
 /// Errors specific to AWS KMS operations.
 #[derive(Debug, Error)]
 pub enum AwsKmsError {
@@ -82,12 +100,12 @@ pub enum AwsKmsError {
 impl From<AwsKmsError> for KeyProviderError {
     fn from(err: AwsKmsError) -> Self {
         match err {
-            AwsKmsError::KeyNotFound(id) => KeyProviderError::KekNotFound(id),
+            AwsKmsError::KeyNotFound(id) => Self::KekNotFound(id),
             AwsKmsError::KmsError(msg) | AwsKmsError::OperationFailed(msg) => {
-                KeyProviderError::UnwrapFailed(msg)
+                Self::UnwrapFailed(msg)
             }
-            AwsKmsError::InvalidKeyId(msg) => KeyProviderError::CreationFailed(msg),
-            AwsKmsError::Base64Error(e) => KeyProviderError::UnwrapFailed(format!("Base64: {e}")),
+            AwsKmsError::InvalidKeyId(msg) => Self::CreationFailed(msg),
+            AwsKmsError::Base64Error(e) => Self::UnwrapFailed(format!("Base64: {e}")),
         }
     }
 }
@@ -98,14 +116,34 @@ impl From<AwsKmsError> for KeyProviderError {
 /// - Store and manage KEKs securely in HSM-backed storage
 /// - Wrap/unwrap DEKs using envelope encryption
 /// - Track key versions for rotation
-/// - Provide audit trails via CloudTrail
+/// - Provide audit trails via `CloudTrail`
+///
+/// When the current key ID is an alias (`alias/...` or an alias ARN),
+/// [`AsyncKeyProvider::current_kek_id`] resolves it to the ARN of the key it
+/// currently points at, so each wrapped DEK records the concrete key and
+/// keeps decrypting after the alias is moved. The resolution is cached for
+/// [`DEFAULT_ALIAS_TTL`] (see [`AwsKmsProvider::with_alias_ttl`]) to avoid a
+/// `DescribeKey` call per encryption.
 pub struct AwsKmsProvider {
     /// AWS KMS client
     client: KmsClient,
     /// Current KMS key ID (ARN or alias)
     current_key_id: Arc<RwLock<String>>,
-    /// Pepper for blind indexes (stored separately, not in KMS)
-    pepper: SecretVec<u8>,
+    /// Most recent alias resolution
+    resolved_alias: Arc<RwLock<Option<ResolvedAlias>>>,
+    /// How long an alias resolution is reused
+    alias_ttl: Duration,
+    /// KMS-wrapped pepper for blind indexes, shared by all instances
+    wrapped_pepper: Option<Vec<u8>>,
+    /// Pepper unwrapped from `wrapped_pepper` on first use
+    pepper: Arc<RwLock<Option<SecretVec<u8>>>>,
+}
+
+/// An alias and the key ARN it pointed at when resolved.
+struct ResolvedAlias {
+    alias: String,
+    key_arn: String,
+    resolved_at: Instant,
 }
 
 impl AwsKmsProvider {
@@ -117,17 +155,10 @@ impl AwsKmsProvider {
     ///
     /// Returns an error if AWS configuration fails.
     pub async fn new() -> Result<Self, AwsKmsError> {
-        let config = aws_config::load_from_env().await;
+        let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
         let client = KmsClient::new(&config);
-        
-        // Generate a random pepper (in production, this should be stored securely)
-        let pepper = SecretVec::new(Self::generate_pepper());
 
-        Ok(Self {
-            client,
-            current_key_id: Arc::new(RwLock::new(String::new())),
-            pepper,
-        })
+        Ok(Self::from_client(client, String::new()))
     }
 
     /// Creates a provider with a specific KMS key ID.
@@ -140,15 +171,54 @@ impl AwsKmsProvider {
     ///
     /// Returns an error if AWS configuration fails.
     pub async fn with_key_id(key_id: impl Into<String>) -> Result<Self, AwsKmsError> {
-        let config = aws_config::load_from_env().await;
+        let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
         let client = KmsClient::new(&config);
-        let pepper = SecretVec::new(Self::generate_pepper());
 
-        Ok(Self {
+        Ok(Self::from_client(client, key_id))
+    }
+
+    /// Creates a provider from an already configured KMS client.
+    ///
+    /// Useful for custom endpoints, credentials, or retry settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - KMS client to issue requests with
+    /// * `key_id` - KMS key ID, ARN, or alias (e.g., "alias/sifredb-kek")
+    #[must_use]
+    pub fn from_client(client: KmsClient, key_id: impl Into<String>) -> Self {
+        Self {
             client,
             current_key_id: Arc::new(RwLock::new(key_id.into())),
-            pepper,
-        })
+            resolved_alias: Arc::new(RwLock::new(None)),
+            alias_ttl: DEFAULT_ALIAS_TTL,
+            wrapped_pepper: None,
+            pepper: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Sets the KMS-wrapped pepper used for blind indexes.
+    ///
+    /// The blob comes from [`AwsKmsProvider::generate_wrapped_pepper`] and is
+    /// unwrapped with KMS the first time the pepper is needed. Every instance
+    /// configured with the same blob gets the same pepper, so blind indexes
+    /// computed on one server match those computed on another. Without it,
+    /// [`AsyncKeyProvider::get_pepper`] fails.
+    #[must_use]
+    pub fn with_wrapped_pepper(mut self, wrapped_pepper: impl Into<Vec<u8>>) -> Self {
+        self.wrapped_pepper = Some(wrapped_pepper.into());
+        self
+    }
+
+    /// Sets how long an alias resolution is reused before `DescribeKey` is
+    /// called again.
+    ///
+    /// After an alias is repointed, encryption keeps using the previous key
+    /// for up to this long. `Duration::ZERO` resolves on every call.
+    #[must_use]
+    pub const fn with_alias_ttl(mut self, ttl: Duration) -> Self {
+        self.alias_ttl = ttl;
+        self
     }
 
     /// Sets the current KMS key ID.
@@ -161,31 +231,103 @@ impl AwsKmsProvider {
         *current = key_id.into();
     }
 
-    /// Generates a random pepper for blind indexes.
-    fn generate_pepper() -> Vec<u8> {
-        use sha2::{Digest, Sha256};
-        let mut hasher = Sha256::new();
-        hasher.update(b"sifredb-pepper-");
-        hasher.update(&std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_nanos()
-            .to_le_bytes());
-        hasher.finalize().to_vec()
+    /// Looks up the ARN of the key an alias currently points at.
+    async fn resolve_alias(&self, alias: &str) -> Result<String, KeyProviderError> {
+        let response = self
+            .client
+            .describe_key()
+            .key_id(alias)
+            .send()
+            .await
+            .map_err(|e| kms_error(&e, "describe key", KeyProviderError::KekNotFound))?;
+
+        response.key_metadata().and_then(|metadata| metadata.arn()).map(str::to_string).ok_or_else(
+            || KeyProviderError::KekNotFound(format!("Alias {alias} has no target key")),
+        )
+    }
+
+    /// Generates a new 32-byte pepper under the current KMS key and returns
+    /// it wrapped.
+    ///
+    /// The plaintext pepper never leaves KMS. Store the returned blob in
+    /// configuration and pass it to [`AwsKmsProvider::with_wrapped_pepper`];
+    /// generating a new one changes every blind index.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no key is configured or the KMS call fails.
+    pub async fn generate_wrapped_pepper(&self) -> Result<Vec<u8>, KeyProviderError> {
+        let key_id = self.current_kek_id().await?;
+        let response = self
+            .client
+            .generate_data_key_without_plaintext()
+            .key_id(&key_id)
+            .key_spec(aws_sdk_kms::types::DataKeySpec::Aes256)
+            .encryption_context(PEPPER_CONTEXT.0, PEPPER_CONTEXT.1)
+            .send()
+            .await
+            .map_err(|e| kms_error(&e, "generate data key", KeyProviderError::PepperUnavailable))?;
+
+        response.ciphertext_blob().map(|blob| blob.as_ref().to_vec()).ok_or_else(|| {
+            KeyProviderError::PepperUnavailable("No ciphertext returned".to_string())
+        })
+    }
+
+    /// Unwraps the configured pepper with KMS.
+    async fn unwrap_pepper(&self) -> Result<SecretVec<u8>, KeyProviderError> {
+        let wrapped = self.wrapped_pepper.as_ref().ok_or_else(|| {
+            KeyProviderError::PepperUnavailable("No wrapped pepper configured".to_string())
+        })?;
+
+        let response = self
+            .client
+            .decrypt()
+            .ciphertext_blob(aws_sdk_kms::primitives::Blob::new(wrapped.clone()))
+            .encryption_context(PEPPER_CONTEXT.0, PEPPER_CONTEXT.1)
+            .send()
+            .await
+            .map_err(|e| kms_error(&e, "decrypt pepper", KeyProviderError::PepperUnavailable))?;
+
+        let plaintext = response.plaintext().ok_or_else(|| {
+            KeyProviderError::PepperUnavailable("No plaintext returned".to_string())
+        })?;
+
+        Ok(SecretVec::new(plaintext.as_ref().to_vec()))
     }
 }
 
 #[async_trait::async_trait]
-impl KeyProvider for AwsKmsProvider {
+impl AsyncKeyProvider for AwsKmsProvider {
     async fn current_kek_id(&self) -> Result<String, KeyProviderError> {
-        let key_id = self.current_key_id.read().await;
+        let key_id = self.current_key_id.read().await.clone();
         if key_id.is_empty() {
             return Err(KeyProviderError::NoActiveKek);
         }
-        Ok(key_id.clone())
+        if !is_alias(&key_id) {
+            return Ok(key_id);
+        }
+
+        if let Some(resolved) = self.resolved_alias.read().await.as_ref() {
+            if resolved.alias == key_id && resolved.resolved_at.elapsed() < self.alias_ttl {
+                return Ok(resolved.key_arn.clone());
+            }
+        }
+
+        let key_arn = self.resolve_alias(&key_id).await?;
+        *self.resolved_alias.write().await = Some(ResolvedAlias {
+            alias: key_id,
+            key_arn: key_arn.clone(),
+            resolved_at: Instant::now(),
+        });
+
+        Ok(key_arn)
     }
 
-    async fn wrap_dek(&self, dek: &SecretVec<u8>, kek_id: &str) -> Result<WrappedDek, KeyProviderError> {
+    async fn wrap_dek(
+        &self,
+        dek: &SecretVec<u8>,
+        kek_id: &str,
+    ) -> Result<WrappedDek, KeyProviderError> {
         let response = self
             .client
             .encrypt()
@@ -193,9 +335,7 @@ impl KeyProvider for AwsKmsProvider {
             .plaintext(aws_sdk_kms::primitives::Blob::new(dek.expose_secret().clone()))
             .send()
             .await
-            .map_err(|e| {
-                KeyProviderError::WrapFailed(format!("KMS encrypt failed: {e}"))
-            })?;
+            .map_err(|e| kms_error(&e, "encrypt", KeyProviderError::WrapFailed))?;
 
         let ciphertext_blob = response
             .ciphertext_blob()
@@ -215,9 +355,7 @@ impl KeyProvider for AwsKmsProvider {
             .ciphertext_blob(aws_sdk_kms::primitives::Blob::new(wrapped.encrypted_dek.clone()))
             .send()
             .await
-            .map_err(|e| {
-                KeyProviderError::UnwrapFailed(format!("KMS decrypt failed: {e}"))
-            })?;
+            .map_err(|e| kms_error(&e, "decrypt", KeyProviderError::UnwrapFailed))?;
 
         let plaintext = response
             .plaintext()
@@ -227,13 +365,186 @@ impl KeyProvider for AwsKmsProvider {
     }
 
     async fn get_pepper(&self) -> Result<SecretVec<u8>, KeyProviderError> {
-        Ok(SecretVec::new(self.pepper.expose_secret().to_vec()))
+        if let Some(pepper) = self.pepper.read().await.as_ref() {
+            return Ok(SecretVec::new(pepper.expose_secret().clone()));
+        }
+
+        let pepper = self.unwrap_pepper().await?;
+        let copy = SecretVec::new(pepper.expose_secret().clone());
+        *self.pepper.write().await = Some(pepper);
+        Ok(copy)
+    }
+
+    async fn rewrap_dek(
+        &self,
+        wrapped: &WrappedDek,
+        new_kek_id: &str,
+    ) -> Result<WrappedDek, KeyProviderError> {
+        // ReEncrypt decrypts and re-encrypts inside KMS, so the DEK never leaves it
+        let response = self
+            .client
+            .re_encrypt()
+            .source_key_id(&wrapped.kek_id)
+            .destination_key_id(new_kek_id)
+            .ciphertext_blob(aws_sdk_kms::primitives::Blob::new(wrapped.encrypted_dek.clone()))
+            .send()
+            .await
+            .map_err(|e| kms_error(&e, "re-encrypt", KeyProviderError::WrapFailed))?;
+
+        let ciphertext_blob = response
+            .ciphertext_blob()
+            .ok_or_else(|| KeyProviderError::WrapFailed("No ciphertext returned".to_string()))?;
+
+        Ok(WrappedDek {
+            kek_id: new_kek_id.to_string(),
+            encrypted_dek: ciphertext_blob.as_ref().to_vec(),
+        })
+    }
+
+    async fn health_check(&self) -> Result<(), KeyProviderError> {
+        let key_id = self.current_kek_id().await?;
+
+        // DescribeKey touches no key material, so it is cheap enough for probes
+        let response = self
+            .client
+            .describe_key()
+            .key_id(&key_id)
+            .send()
+            .await
+            .map_err(|e| kms_error(&e, "describe key", KeyProviderError::Unavailable))?;
+
+        let metadata = response
+            .key_metadata()
+            .ok_or_else(|| KeyProviderError::Unavailable("No key metadata returned".to_string()))?;
+
+        if !metadata.enabled() {
+            return Err(KeyProviderError::Unavailable(format!("KMS key {key_id} is disabled")));
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks whether a key ID names an alias (`alias/name` or an alias ARN).
+fn is_alias(key_id: &str) -> bool {
+    key_id.starts_with("alias/") || (key_id.starts_with("arn:") && key_id.contains(":alias/"))
+}
+
+/// Maps a KMS SDK error to a [`KeyProviderError`] that classifies retryability.
+///
+/// Timeouts and connection failures become `Unavailable`; service errors are
+/// classified by their error code, falling back to `fallback` for codes with
+/// no special meaning.
+fn kms_error<E, R>(
+    err: &SdkError<E, R>,
+    operation: &str,
+    fallback: fn(String) -> KeyProviderError,
+) -> KeyProviderError
+where
+    E: ProvideErrorMetadata + std::error::Error + 'static,
+    R: std::fmt::Debug,
+{
+    let message = format!("KMS {operation} failed: {err}");
+
+    match err {
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) => {
+            KeyProviderError::Unavailable(message)
+        }
+        _ => classify_kms_code(err.code(), message, fallback),
+    }
+}
+
+/// Classifies a KMS service error code.
+///
+/// Codes that aren't transient, such as `AccessDeniedException`, map to the
+/// operation's own `fallback` error.
+fn classify_kms_code(
+    code: Option<&str>,
+    message: String,
+    fallback: fn(String) -> KeyProviderError,
+) -> KeyProviderError {
+    match code {
+        Some("ThrottlingException") => KeyProviderError::Throttled(message),
+        Some("KMSInternalException" | "DependencyTimeoutException") => {
+            KeyProviderError::Unavailable(message)
+        }
+        _ => fallback(message),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aws_sdk_kms::config::{Credentials, Region};
+    use aws_sdk_kms::error::ErrorMetadata;
+    use aws_sdk_kms::operation::encrypt::EncryptError;
+    use wiremock::matchers::{body_partial_json, header, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn service_error(code: &str) -> SdkError<EncryptError, ()> {
+        let meta = ErrorMetadata::builder().code(code).message("test").build();
+        SdkError::service_error(EncryptError::generic(meta), ())
+    }
+
+    #[test]
+    fn test_throttling_is_retryable() {
+        let err = kms_error(
+            &service_error("ThrottlingException"),
+            "encrypt",
+            KeyProviderError::WrapFailed,
+        );
+        assert!(matches!(err, KeyProviderError::Throttled(_)));
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn test_access_denied_is_not_retryable() {
+        let err = kms_error(
+            &service_error("AccessDeniedException"),
+            "encrypt",
+            KeyProviderError::WrapFailed,
+        );
+        assert!(matches!(err, KeyProviderError::WrapFailed(_)));
+        assert!(!err.is_retryable());
+
+        let err = kms_error(
+            &service_error("AccessDeniedException"),
+            "decrypt",
+            KeyProviderError::UnwrapFailed,
+        );
+        assert!(matches!(err, KeyProviderError::UnwrapFailed(_)));
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn test_internal_errors_are_retryable() {
+        for code in ["KMSInternalException", "DependencyTimeoutException"] {
+            let err = kms_error(&service_error(code), "decrypt", KeyProviderError::UnwrapFailed);
+            assert!(matches!(err, KeyProviderError::Unavailable(_)), "{code}");
+            assert!(err.is_retryable(), "{code}");
+        }
+    }
+
+    #[test]
+    fn test_timeout_is_retryable() {
+        let err: SdkError<EncryptError, ()> = SdkError::timeout_error("request timed out");
+        let err = kms_error(&err, "encrypt", KeyProviderError::WrapFailed);
+        assert!(matches!(err, KeyProviderError::Unavailable(_)));
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn test_other_errors_use_fallback() {
+        let err = kms_error(
+            &service_error("InvalidCiphertextException"),
+            "decrypt",
+            KeyProviderError::UnwrapFailed,
+        );
+        assert!(
+            matches!(&err, KeyProviderError::UnwrapFailed(msg) if msg.starts_with("KMS decrypt failed"))
+        );
+        assert!(!err.is_retryable());
+    }
 
     #[tokio::test]
     async fn test_provider_creation() {
@@ -247,26 +558,213 @@ mod tests {
     async fn test_set_key_id() {
         let provider = AwsKmsProvider::new().await.unwrap();
         let key_id = "arn:aws:kms:us-east-1:123456789012:key/test";
-        
+
         provider.set_current_key_id(key_id).await;
-        
+
         let current = provider.current_kek_id().await.unwrap();
         assert_eq!(current, key_id);
     }
 
+    const KEY_ARN: &str =
+        "arn:aws:kms:us-east-1:123456789012:key/12345678-1234-1234-1234-123456789012";
+
+    /// A provider whose KMS client talks to `server`.
+    fn mock_provider(server: &MockServer, key_id: &str) -> AwsKmsProvider {
+        let config = aws_sdk_kms::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("test", "test", None, None, "test"))
+            .endpoint_url(server.uri())
+            .build();
+        AwsKmsProvider::from_client(KmsClient::from_conf(config), key_id)
+    }
+
+    fn describe_key_response(arn: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200)
+            .insert_header("content-type", "application/x-amz-json-1.1")
+            .set_body_json(serde_json::json!({
+                "KeyMetadata": { "KeyId": arn.rsplit('/').next(), "Arn": arn, "Enabled": true }
+            }))
+    }
+
+    #[tokio::test]
+    async fn test_alias_resolved_and_cached() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("x-amz-target", "TrentService.DescribeKey"))
+            .respond_with(describe_key_response(KEY_ARN))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = mock_provider(&server, "alias/sifredb-kek");
+
+        // Both calls within the TTL share one DescribeKey
+        assert_eq!(provider.current_kek_id().await.unwrap(), KEY_ARN);
+        assert_eq!(provider.current_kek_id().await.unwrap(), KEY_ARN);
+    }
+
+    #[tokio::test]
+    async fn test_alias_re_resolved_after_ttl() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("x-amz-target", "TrentService.DescribeKey"))
+            .respond_with(describe_key_response(KEY_ARN))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let provider = mock_provider(&server, "alias/sifredb-kek").with_alias_ttl(Duration::ZERO);
+
+        assert_eq!(provider.current_kek_id().await.unwrap(), KEY_ARN);
+        assert_eq!(provider.current_kek_id().await.unwrap(), KEY_ARN);
+    }
+
+    #[tokio::test]
+    async fn test_changing_alias_bypasses_cache() {
+        let other_arn = "arn:aws:kms:us-east-1:123456789012:key/other";
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("x-amz-target", "TrentService.DescribeKey"))
+            .and(body_partial_json(serde_json::json!({ "KeyId": "alias/other" })))
+            .respond_with(describe_key_response(other_arn))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(header("x-amz-target", "TrentService.DescribeKey"))
+            .respond_with(describe_key_response(KEY_ARN))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = mock_provider(&server, "alias/sifredb-kek");
+        assert_eq!(provider.current_kek_id().await.unwrap(), KEY_ARN);
+
+        provider.set_current_key_id("alias/other").await;
+        assert_eq!(provider.current_kek_id().await.unwrap(), other_arn);
+    }
+
+    #[tokio::test]
+    async fn test_wrap_pins_resolved_key() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("x-amz-target", "TrentService.DescribeKey"))
+            .respond_with(describe_key_response(KEY_ARN))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(header("x-amz-target", "TrentService.Encrypt"))
+            .and(body_partial_json(serde_json::json!({ "KeyId": KEY_ARN })))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "application/x-amz-json-1.1")
+                    .set_body_json(
+                        serde_json::json!({ "CiphertextBlob": "d3JhcHBlZA==", "KeyId": KEY_ARN }),
+                    ),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = mock_provider(&server, "alias/sifredb-kek");
+        let kek_id = provider.current_kek_id().await.unwrap();
+        let wrapped = provider.wrap_dek(&SecretVec::new(vec![7; 32]), &kek_id).await.unwrap();
+
+        assert_eq!(wrapped.kek_id, KEY_ARN);
+        assert_eq!(wrapped.encrypted_dek, b"wrapped");
+    }
+
+    #[tokio::test]
+    async fn test_key_arn_not_resolved() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let provider = mock_provider(&server, KEY_ARN);
+        assert_eq!(provider.current_kek_id().await.unwrap(), KEY_ARN);
+    }
+
+    #[test]
+    fn test_is_alias() {
+        assert!(is_alias("alias/sifredb-kek"));
+        assert!(is_alias("arn:aws:kms:us-east-1:123456789012:alias/sifredb-kek"));
+        assert!(!is_alias(KEY_ARN));
+        assert!(!is_alias("12345678-1234-1234-1234-123456789012"));
+    }
+
+    fn kms_response(body: &serde_json::Value) -> ResponseTemplate {
+        ResponseTemplate::new(200)
+            .insert_header("content-type", "application/x-amz-json-1.1")
+            .set_body_json(body)
+    }
+
     #[tokio::test]
-    async fn test_pepper_generation() {
-        let provider1 = AwsKmsProvider::new().await.unwrap();
-        let provider2 = AwsKmsProvider::new().await.unwrap();
+    async fn test_pepper_shared_across_instances() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("x-amz-target", "TrentService.Decrypt"))
+            .and(body_partial_json(serde_json::json!({
+                "CiphertextBlob": "d3JhcHBlZC1wZXBwZXI=",
+                "EncryptionContext": { "sifredb-purpose": "pepper" }
+            })))
+            .respond_with(kms_response(&serde_json::json!({
+                "KeyId": KEY_ARN,
+                "Plaintext": "cGVwcGVyLXBlcHBlci1wZXBwZXItcGVwcGVyLTA="
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let provider1 = mock_provider(&server, KEY_ARN).with_wrapped_pepper(*b"wrapped-pepper");
+        let provider2 = mock_provider(&server, KEY_ARN).with_wrapped_pepper(*b"wrapped-pepper");
 
         let pepper1 = provider1.get_pepper().await.unwrap();
         let pepper2 = provider2.get_pepper().await.unwrap();
+        assert_eq!(pepper1.expose_secret(), pepper2.expose_secret());
+        assert_eq!(pepper1.expose_secret(), b"pepper-pepper-pepper-pepper-0");
 
-        // Different providers should have different peppers
-        assert_ne!(
-            pepper1.expose_secret(),
-            pepper2.expose_secret(),
-            "Each provider should have unique pepper"
-        );
+        // Unwrapped once per instance, then cached
+        let again = provider1.get_pepper().await.unwrap();
+        assert_eq!(again.expose_secret(), pepper1.expose_secret());
+    }
+
+    #[tokio::test]
+    async fn test_pepper_requires_wrapped_pepper() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let provider = mock_provider(&server, KEY_ARN);
+        let result = provider.get_pepper().await;
+        assert!(matches!(result, Err(KeyProviderError::PepperUnavailable(_))));
+    }
+
+    #[tokio::test]
+    async fn test_generate_wrapped_pepper() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("x-amz-target", "TrentService.GenerateDataKeyWithoutPlaintext"))
+            .and(body_partial_json(serde_json::json!({
+                "KeyId": KEY_ARN,
+                "KeySpec": "AES_256",
+                "EncryptionContext": { "sifredb-purpose": "pepper" }
+            })))
+            .respond_with(kms_response(&serde_json::json!({
+                "CiphertextBlob": "d3JhcHBlZC1wZXBwZXI=",
+                "KeyId": KEY_ARN
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = mock_provider(&server, KEY_ARN);
+        assert_eq!(provider.generate_wrapped_pepper().await.unwrap(), b"wrapped-pepper");
     }
 }
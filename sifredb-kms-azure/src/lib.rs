@@ -0,0 +1,491 @@
+//! Azure Key Vault key provider for `SifreDB`.
+//!
+//! This module provides integration with Azure Key Vault (and Managed HSM)
+//! for managed key storage.
+//!
+//! # Features
+//!
+//! - KEK storage in Key Vault (RSA keys) or Managed HSM (RSA or AES keys)
+//! - Wrap/unwrap via the Key Vault `wrapKey`/`unwrapKey` REST operations
+//! - Wrapped DEKs record the exact key version used
+//! - Access control via Azure RBAC or vault access policies
+//! - Audit logging via Azure Monitor
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use sifredb_kms_azure::{AzureKeyVaultProvider, KeyWrapAlgorithm};
+//! use sifredb::prelude::*;
+//!
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! // Create provider with DefaultAzureCredential
+//! let provider = AzureKeyVaultProvider::new("https://my-vault.vault.azure.net/keys/kek")?;
+//!
+//! // AES key wrap for a Managed HSM key
+//! let provider = AzureKeyVaultProvider::new("https://my-hsm.managedhsm.azure.net/keys/kek")?
+//!     .with_algorithm(KeyWrapAlgorithm::A256Kw);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Azure Configuration
+//!
+//! The provider uses `DefaultAzureCredential`, which tries in order:
+//! - Environment variables (`AZURE_TENANT_ID`, `AZURE_CLIENT_ID`, `AZURE_CLIENT_SECRET`)
+//! - Workload identity (AKS)
+//! - Managed identity (VMs, App Service, Container Apps)
+//! - The Azure CLI (`az login`)
+
+#![warn(clippy::pedantic, clippy::nursery)]
+#![allow(clippy::module_name_repetitions)]
+
+use azure_core::auth::TokenCredential;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use secrecy::{ExposeSecret, SecretVec};
+use serde::{Deserialize, Serialize};
+use sifredb::{
+    error::KeyProviderError,
+    key_provider::{AsyncKeyProvider, WrappedDek},
+};
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::RwLock;
+use zeroize::Zeroize;
+
+/// Key Vault REST API version.
+const API_VERSION: &str = "7.4";
+
+/// OAuth scope required for Key Vault data-plane operations.
+const KEY_VAULT_SCOPE: &str = "https://vault.azure.net/.default";
+
+/// Errors specific to Azure Key Vault operations.
+#[derive(Debug, Error)]
+pub enum AzureKeyVaultError {
+    /// Key Vault API error
+    #[error("Key Vault error: {0}")]
+    KeyVaultError(String),
+
+    /// Key not found in Key Vault
+    #[error("Key Vault key not found: {0}")]
+    KeyNotFound(String),
+
+    /// Credential resolution failed or the request was rejected (401/403)
+    #[error("authentication failed: {0}")]
+    Auth(String),
+
+    /// Key Vault is rate limiting requests (429)
+    #[error("Key Vault throttled: {0}")]
+    Throttled(String),
+
+    /// Key Vault couldn't be reached or returned a server error
+    #[error("Key Vault unavailable: {0}")]
+    Unavailable(String),
+
+    /// Encryption/decryption failed
+    #[error("Key Vault operation failed: {0}")]
+    OperationFailed(String),
+
+    /// Base64 decoding error
+    #[error("base64 decode error: {0}")]
+    Base64Error(#[from] base64::DecodeError),
+}
+
+impl From<AzureKeyVaultError> for KeyProviderError {
+    fn from(err: AzureKeyVaultError) -> Self {
+        match err {
+            AzureKeyVaultError::KeyNotFound(id) => Self::KekNotFound(id),
+            AzureKeyVaultError::KeyVaultError(msg) | AzureKeyVaultError::OperationFailed(msg) => {
+                Self::UnwrapFailed(msg)
+            }
+            AzureKeyVaultError::Auth(msg) => Self::CreationFailed(msg),
+            AzureKeyVaultError::Throttled(msg) => Self::Throttled(msg),
+            AzureKeyVaultError::Unavailable(msg) => Self::Unavailable(msg),
+            AzureKeyVaultError::Base64Error(e) => Self::UnwrapFailed(format!("Base64: {e}")),
+        }
+    }
+}
+
+/// Key wrapping algorithm passed to `wrapKey`/`unwrapKey`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub enum KeyWrapAlgorithm {
+    /// RSA-OAEP with SHA-256 (RSA keys)
+    #[default]
+    #[serde(rename = "RSA-OAEP-256")]
+    RsaOaep256,
+    /// RSA-OAEP with SHA-1 (RSA keys, for compatibility)
+    #[serde(rename = "RSA-OAEP")]
+    RsaOaep,
+    /// AES key wrap with a 256-bit key (Managed HSM `oct-HSM` keys)
+    #[serde(rename = "A256KW")]
+    A256Kw,
+}
+
+/// Source of OAuth access tokens for Key Vault requests.
+enum Credentials {
+    /// `DefaultAzureCredential`
+    Default(Arc<dyn TokenCredential>),
+    /// A caller-supplied bearer token
+    Static(String),
+}
+
+#[derive(Serialize)]
+struct KeyOperationRequest<'a> {
+    alg: KeyWrapAlgorithm,
+    value: &'a str,
+}
+
+#[derive(Deserialize)]
+struct KeyOperationResponse {
+    kid: Option<String>,
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct ErrorDetail {
+    code: String,
+    message: String,
+}
+
+/// Azure Key Vault key provider implementation.
+///
+/// This provider uses Key Vault to:
+/// - Store and manage KEKs in a vault or Managed HSM
+/// - Wrap/unwrap DEKs using envelope encryption
+/// - Track key versions for rotation (the `kek_id` of a wrapped DEK is the
+///   full versioned key URL, `https://{vault}/keys/{name}/{version}`)
+/// - Provide audit trails via Azure Monitor
+pub struct AzureKeyVaultProvider {
+    /// HTTP client for the Key Vault REST API
+    http: reqwest::Client,
+    /// OAuth token source
+    credentials: Credentials,
+    /// Current key URL (versioned or unversioned)
+    current_key_url: Arc<RwLock<String>>,
+    /// Algorithm used for wrapping and unwrapping
+    algorithm: KeyWrapAlgorithm,
+    /// Pepper for blind indexes (stored separately, not in Key Vault)
+    pepper: SecretVec<u8>,
+}
+
+impl AzureKeyVaultProvider {
+    /// Creates a provider for a Key Vault key using `DefaultAzureCredential`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_url` - Key identifier (`https://{vault}/keys/{name}`), optionally
+    ///   with a version to pin wrapping to it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the default credential chain can't be built.
+    pub fn new(key_url: impl Into<String>) -> Result<Self, AzureKeyVaultError> {
+        let credential = azure_identity::create_default_credential()
+            .map_err(|e| AzureKeyVaultError::Auth(e.to_string()))?;
+
+        Ok(Self::with_credentials(Credentials::Default(credential), key_url.into()))
+    }
+
+    /// Creates a provider for a Key Vault key using an explicit bearer token.
+    ///
+    /// Useful when tokens are obtained out of band or when targeting a test
+    /// endpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_url` - Key identifier
+    /// * `access_token` - OAuth 2.0 access token for the Key Vault audience
+    #[must_use]
+    pub fn with_access_token(key_url: impl Into<String>, access_token: impl Into<String>) -> Self {
+        Self::with_credentials(Credentials::Static(access_token.into()), key_url.into())
+    }
+
+    /// Sets the key wrapping algorithm.
+    ///
+    /// The algorithm must match the key type: RSA algorithms for `RSA`/`RSA-HSM`
+    /// keys, [`KeyWrapAlgorithm::A256Kw`] for Managed HSM `oct-HSM` keys.
+    #[must_use]
+    pub const fn with_algorithm(mut self, algorithm: KeyWrapAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Sets the current key URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_url` - Key identifier
+    pub async fn set_current_key_url(&self, key_url: impl Into<String>) {
+        let mut current = self.current_key_url.write().await;
+        *current = key_url.into();
+    }
+
+    fn with_credentials(credentials: Credentials, key_url: String) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            credentials,
+            current_key_url: Arc::new(RwLock::new(key_url)),
+            algorithm: KeyWrapAlgorithm::default(),
+            pepper: SecretVec::new(Self::generate_pepper()),
+        }
+    }
+
+    /// Returns a bearer token for the next request.
+    async fn access_token(&self) -> Result<String, AzureKeyVaultError> {
+        match &self.credentials {
+            Credentials::Default(credential) => credential
+                .get_token(&[KEY_VAULT_SCOPE])
+                .await
+                .map(|token| token.token.secret().to_string())
+                .map_err(|e| AzureKeyVaultError::Auth(e.to_string())),
+            Credentials::Static(token) => Ok(token.clone()),
+        }
+    }
+
+    /// Issues a key operation (`{key_url}/{operation}`) and decodes the response.
+    async fn call(
+        &self,
+        key_url: &str,
+        operation: &str,
+        value: &str,
+    ) -> Result<KeyOperationResponse, AzureKeyVaultError> {
+        let token = self.access_token().await?;
+        let url = format!("{}/{operation}", key_url.trim_end_matches('/'));
+
+        let response = self
+            .http
+            .post(&url)
+            .query(&[("api-version", API_VERSION)])
+            .bearer_auth(token)
+            .json(&KeyOperationRequest { alg: self.algorithm, value })
+            .send()
+            .await
+            .map_err(|e| AzureKeyVaultError::Unavailable(format!("request failed: {e}")))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<ErrorResponse>(&body)
+                .map_or(body, |e| format!("{}: {}", e.error.code, e.error.message));
+            let message = format!("{operation} returned {status}: {message}");
+
+            return Err(match status.as_u16() {
+                401 | 403 => AzureKeyVaultError::Auth(message),
+                404 => AzureKeyVaultError::KeyNotFound(key_url.to_string()),
+                429 => AzureKeyVaultError::Throttled(message),
+                500..=599 => AzureKeyVaultError::Unavailable(message),
+                _ => AzureKeyVaultError::KeyVaultError(message),
+            });
+        }
+
+        response.json::<KeyOperationResponse>().await.map_err(|e| {
+            AzureKeyVaultError::OperationFailed(format!("invalid {operation} response: {e}"))
+        })
+    }
+
+    /// Generates a random pepper for blind indexes.
+    fn generate_pepper() -> Vec<u8> {
+        use rand::RngCore;
+
+        let mut pepper = vec![0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut pepper);
+        pepper
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncKeyProvider for AzureKeyVaultProvider {
+    async fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+        let key_url = self.current_key_url.read().await;
+        if key_url.is_empty() {
+            return Err(KeyProviderError::NoActiveKek);
+        }
+        Ok(key_url.clone())
+    }
+
+    async fn wrap_dek(
+        &self,
+        dek: &SecretVec<u8>,
+        kek_id: &str,
+    ) -> Result<WrappedDek, KeyProviderError> {
+        let mut value = URL_SAFE_NO_PAD.encode(dek.expose_secret());
+        let result = self.call(kek_id, "wrapkey", &value).await;
+        value.zeroize();
+
+        let response = result.map_err(|e| match e {
+            AzureKeyVaultError::OperationFailed(msg) | AzureKeyVaultError::KeyVaultError(msg) => {
+                KeyProviderError::WrapFailed(format!("Key Vault wrapKey failed: {msg}"))
+            }
+            e => e.into(),
+        })?;
+
+        let encrypted_dek = URL_SAFE_NO_PAD
+            .decode(&response.value)
+            .map_err(|e| KeyProviderError::WrapFailed(format!("Base64: {e}")))?;
+
+        // The response names the key version that performed the wrap
+        let kek_id = response.kid.unwrap_or_else(|| kek_id.to_string());
+        Ok(WrappedDek { kek_id, encrypted_dek })
+    }
+
+    async fn unwrap_dek(&self, wrapped: &WrappedDek) -> Result<SecretVec<u8>, KeyProviderError> {
+        let value = URL_SAFE_NO_PAD.encode(&wrapped.encrypted_dek);
+        let mut response = self.call(&wrapped.kek_id, "unwrapkey", &value).await?;
+
+        let plaintext = URL_SAFE_NO_PAD.decode(&response.value).map_err(AzureKeyVaultError::from);
+        response.value.zeroize();
+
+        Ok(SecretVec::new(plaintext?))
+    }
+
+    async fn get_pepper(&self) -> Result<SecretVec<u8>, KeyProviderError> {
+        Ok(SecretVec::new(self.pepper.expose_secret().clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    /// Mock Key Vault that "wraps" by XOR-ing with a fixed byte.
+    struct XorKeyVault {
+        key_url: String,
+    }
+
+    impl Respond for XorKeyVault {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(body["alg"], "RSA-OAEP-256");
+
+            let bytes = URL_SAFE_NO_PAD.decode(body["value"].as_str().unwrap()).unwrap();
+            let value = URL_SAFE_NO_PAD.encode(bytes.iter().map(|b| b ^ 0x5a).collect::<Vec<u8>>());
+
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "kid": format!("{}/v3", self.key_url),
+                "value": value,
+            }))
+        }
+    }
+
+    async fn mock_key_vault() -> (MockServer, String) {
+        let server = MockServer::start().await;
+        let key_url = format!("{}/keys/kek", server.uri());
+        Mock::given(method("POST"))
+            .and(header("authorization", "Bearer test-token"))
+            .and(query_param("api-version", API_VERSION))
+            .respond_with(XorKeyVault { key_url: key_url.clone() })
+            .mount(&server)
+            .await;
+        (server, key_url)
+    }
+
+    #[tokio::test]
+    async fn test_wrap_unwrap_round_trip() {
+        let (_server, key_url) = mock_key_vault().await;
+        let provider = AzureKeyVaultProvider::with_access_token(key_url, "test-token");
+
+        let dek = SecretVec::new(vec![7u8; 32]);
+        let kek_id = provider.current_kek_id().await.unwrap();
+        let wrapped = provider.wrap_dek(&dek, &kek_id).await.unwrap();
+
+        assert_ne!(wrapped.encrypted_dek, dek.expose_secret().clone());
+
+        let unwrapped = provider.unwrap_dek(&wrapped).await.unwrap();
+        assert_eq!(unwrapped.expose_secret(), dek.expose_secret());
+    }
+
+    #[tokio::test]
+    async fn test_wrapped_kek_id_is_versioned_key_url() {
+        let (_server, key_url) = mock_key_vault().await;
+        let provider = AzureKeyVaultProvider::with_access_token(&key_url, "test-token");
+
+        let dek = SecretVec::new(vec![1u8; 32]);
+        let wrapped = provider.wrap_dek(&dek, &key_url).await.unwrap();
+
+        assert_eq!(wrapped.kek_id, format!("{key_url}/v3"));
+    }
+
+    #[tokio::test]
+    async fn test_unwrap_targets_key_version() {
+        let server = MockServer::start().await;
+        let key_url = format!("{}/keys/kek", server.uri());
+        Mock::given(method("POST"))
+            .and(path("/keys/kek/v3/unwrapkey"))
+            .respond_with(XorKeyVault { key_url: key_url.clone() })
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = AzureKeyVaultProvider::with_access_token(&key_url, "test-token");
+        let wrapped = WrappedDek { kek_id: format!("{key_url}/v3"), encrypted_dek: vec![0x5a; 32] };
+
+        let unwrapped = provider.unwrap_dek(&wrapped).await.unwrap();
+        assert_eq!(unwrapped.expose_secret(), &vec![0u8; 32]);
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized_maps_to_auth_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(serde_json::json!({
+                "error": { "code": "Unauthorized", "message": "AKV10000: Request is missing a Bearer token" }
+            })))
+            .mount(&server)
+            .await;
+
+        let key_url = format!("{}/keys/kek", server.uri());
+        let provider = AzureKeyVaultProvider::with_access_token(&key_url, "bad-token");
+        let dek = SecretVec::new(vec![1u8; 32]);
+
+        let result = provider.wrap_dek(&dek, &key_url).await;
+        assert!(
+            matches!(result, Err(KeyProviderError::CreationFailed(ref msg)) if msg.contains("AKV10000"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_key_maps_to_kek_not_found() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+
+        let key_url = format!("{}/keys/kek", server.uri());
+        let provider = AzureKeyVaultProvider::with_access_token(&key_url, "test-token");
+        let dek = SecretVec::new(vec![1u8; 32]);
+
+        let result = provider.wrap_dek(&dek, &key_url).await;
+        assert!(matches!(result, Err(KeyProviderError::KekNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_throttling_is_retryable() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(429)).mount(&server).await;
+
+        let key_url = format!("{}/keys/kek", server.uri());
+        let provider = AzureKeyVaultProvider::with_access_token(&key_url, "test-token");
+        let wrapped = WrappedDek { kek_id: format!("{key_url}/v3"), encrypted_dek: vec![0; 32] };
+
+        let result = provider.unwrap_dek(&wrapped).await;
+        assert!(matches!(result, Err(ref e @ KeyProviderError::Throttled(_)) if e.is_retryable()));
+    }
+
+    #[tokio::test]
+    async fn test_no_active_key() {
+        let provider = AzureKeyVaultProvider::with_access_token("", "test-token");
+
+        let result = provider.current_kek_id().await;
+        assert!(matches!(result, Err(KeyProviderError::NoActiveKek)));
+
+        provider.set_current_key_url("https://vault.vault.azure.net/keys/kek").await;
+        assert_eq!(
+            provider.current_kek_id().await.unwrap(),
+            "https://vault.vault.azure.net/keys/kek"
+        );
+    }
+}
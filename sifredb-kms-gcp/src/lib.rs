@@ -0,0 +1,481 @@
+//! Google Cloud KMS key provider for `SifreDB`.
+//!
+//! This module provides integration with Google Cloud Key Management Service
+//! for managed key storage backed by Google's HSM and software key rings.
+//!
+//! # Features
+//!
+//! - KEK storage in Cloud KMS
+//! - Wrap/unwrap via the Cloud KMS `encrypt`/`decrypt` REST methods
+//! - Wrapped DEKs record the exact `CryptoKeyVersion` used
+//! - Fine-grained access control via IAM
+//! - Audit logging via Cloud Audit Logs
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use sifredb_kms_gcp::GcpKmsProvider;
+//! use sifredb::prelude::*;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! // Create provider with Application Default Credentials
+//! let provider = GcpKmsProvider::new().await?;
+//!
+//! // Or specify a CryptoKey resource name
+//! let provider = GcpKmsProvider::with_key_name(
+//!     "projects/my-project/locations/global/keyRings/sifredb/cryptoKeys/kek"
+//! ).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # GCP Configuration
+//!
+//! The provider uses Application Default Credentials (ADC):
+//! - `GOOGLE_APPLICATION_CREDENTIALS` pointing to a service account key file
+//! - User credentials from `gcloud auth application-default login`
+//! - The metadata server (GCE, GKE, Cloud Run)
+
+#![warn(clippy::pedantic, clippy::nursery)]
+#![allow(clippy::module_name_repetitions)]
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use gcp_auth::TokenProvider;
+use secrecy::{ExposeSecret, SecretVec};
+use serde::{Deserialize, Serialize};
+use sifredb::{
+    error::KeyProviderError,
+    key_provider::{AsyncKeyProvider, WrappedDek},
+};
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::RwLock;
+use zeroize::Zeroize;
+
+/// Default Cloud KMS REST endpoint.
+const DEFAULT_ENDPOINT: &str = "https://cloudkms.googleapis.com";
+
+/// OAuth scope required for Cloud KMS cryptographic operations.
+const KMS_SCOPE: &str = "https://www.googleapis.com/auth/cloudkms";
+
+/// Errors specific to Google Cloud KMS operations.
+#[derive(Debug, Error)]
+pub enum GcpKmsError {
+    /// Cloud KMS API error
+    #[error("Cloud KMS error: {0}")]
+    KmsError(String),
+
+    /// Key not found in Cloud KMS
+    #[error("Cloud KMS key not found: {0}")]
+    KeyNotFound(String),
+
+    /// Credential resolution failed
+    #[error("authentication failed: {0}")]
+    Auth(String),
+
+    /// Encryption/decryption failed
+    #[error("Cloud KMS operation failed: {0}")]
+    OperationFailed(String),
+
+    /// Base64 decoding error
+    #[error("base64 decode error: {0}")]
+    Base64Error(#[from] base64::DecodeError),
+}
+
+impl From<GcpKmsError> for KeyProviderError {
+    fn from(err: GcpKmsError) -> Self {
+        match err {
+            GcpKmsError::KeyNotFound(name) => Self::KekNotFound(name),
+            GcpKmsError::KmsError(msg) | GcpKmsError::OperationFailed(msg) => {
+                Self::UnwrapFailed(msg)
+            }
+            GcpKmsError::Auth(msg) => Self::CreationFailed(msg),
+            GcpKmsError::Base64Error(e) => Self::UnwrapFailed(format!("Base64: {e}")),
+        }
+    }
+}
+
+/// Source of OAuth access tokens for Cloud KMS requests.
+enum Credentials {
+    /// Application Default Credentials
+    Adc(Arc<dyn TokenProvider>),
+    /// A caller-supplied bearer token
+    Static(String),
+}
+
+#[derive(Serialize)]
+struct EncryptRequest<'a> {
+    plaintext: &'a str,
+}
+
+#[derive(Deserialize)]
+struct EncryptResponse {
+    name: String,
+    ciphertext: String,
+}
+
+#[derive(Serialize)]
+struct DecryptRequest<'a> {
+    ciphertext: &'a str,
+}
+
+#[derive(Deserialize)]
+struct DecryptResponse {
+    plaintext: String,
+}
+
+/// Google Cloud KMS key provider implementation.
+///
+/// This provider uses Cloud KMS to:
+/// - Store and manage KEKs in a key ring
+/// - Wrap/unwrap DEKs using envelope encryption
+/// - Track key versions for rotation (the `kek_id` of a wrapped DEK is the
+///   full `CryptoKeyVersion` resource name)
+/// - Provide audit trails via Cloud Audit Logs
+pub struct GcpKmsProvider {
+    /// HTTP client for the Cloud KMS REST API
+    http: reqwest::Client,
+    /// Cloud KMS endpoint (overridable for emulators and tests)
+    endpoint: String,
+    /// OAuth token source
+    credentials: Credentials,
+    /// Current `CryptoKey` resource name
+    current_key_name: Arc<RwLock<String>>,
+    /// Pepper for blind indexes (stored separately, not in KMS)
+    pepper: SecretVec<u8>,
+}
+
+impl GcpKmsProvider {
+    /// Creates a new Cloud KMS provider using Application Default Credentials.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no default credentials can be found.
+    pub async fn new() -> Result<Self, GcpKmsError> {
+        let provider = gcp_auth::provider().await.map_err(|e| GcpKmsError::Auth(e.to_string()))?;
+
+        Ok(Self::with_credentials(Credentials::Adc(provider), String::new()))
+    }
+
+    /// Creates a provider for a specific `CryptoKey` using Application Default Credentials.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_name` - `CryptoKey` resource name
+    ///   (`projects/{p}/locations/{l}/keyRings/{r}/cryptoKeys/{k}`)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no default credentials can be found.
+    pub async fn with_key_name(key_name: impl Into<String>) -> Result<Self, GcpKmsError> {
+        let provider = gcp_auth::provider().await.map_err(|e| GcpKmsError::Auth(e.to_string()))?;
+
+        Ok(Self::with_credentials(Credentials::Adc(provider), key_name.into()))
+    }
+
+    /// Creates a provider for a specific `CryptoKey` using an explicit bearer token.
+    ///
+    /// Useful when tokens are obtained out of band (e.g. workload identity
+    /// federation handled by the caller) or when targeting an emulator.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_name` - `CryptoKey` resource name
+    /// * `access_token` - OAuth 2.0 access token with the Cloud KMS scope
+    #[must_use]
+    pub fn with_access_token(key_name: impl Into<String>, access_token: impl Into<String>) -> Self {
+        Self::with_credentials(Credentials::Static(access_token.into()), key_name.into())
+    }
+
+    /// Overrides the Cloud KMS REST endpoint.
+    #[must_use]
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sets the current `CryptoKey` resource name.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_name` - `CryptoKey` resource name
+    pub async fn set_current_key_name(&self, key_name: impl Into<String>) {
+        let mut current = self.current_key_name.write().await;
+        *current = key_name.into();
+    }
+
+    fn with_credentials(credentials: Credentials, key_name: String) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+            credentials,
+            current_key_name: Arc::new(RwLock::new(key_name)),
+            pepper: SecretVec::new(Self::generate_pepper()),
+        }
+    }
+
+    /// Returns a bearer token for the next request.
+    async fn access_token(&self) -> Result<String, GcpKmsError> {
+        match &self.credentials {
+            Credentials::Adc(provider) => provider
+                .token(&[KMS_SCOPE])
+                .await
+                .map(|token| token.as_str().to_string())
+                .map_err(|e| GcpKmsError::Auth(e.to_string())),
+            Credentials::Static(token) => Ok(token.clone()),
+        }
+    }
+
+    /// Issues a Cloud KMS REST call (`{key_name}:{method}`) and decodes the response.
+    async fn call<Req, Resp>(
+        &self,
+        key_name: &str,
+        method: &str,
+        body: &Req,
+    ) -> Result<Resp, GcpKmsError>
+    where
+        Req: Serialize + Sync,
+        Resp: for<'de> Deserialize<'de>,
+    {
+        let token = self.access_token().await?;
+        let url = format!("{}/v1/{key_name}:{method}", self.endpoint);
+
+        let response = self
+            .http
+            .post(&url)
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| GcpKmsError::KmsError(format!("request failed: {e}")))?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Err(GcpKmsError::KeyNotFound(key_name.to_string()));
+        }
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(GcpKmsError::KmsError(format!("{method} returned {status}: {message}")));
+        }
+
+        response
+            .json::<Resp>()
+            .await
+            .map_err(|e| GcpKmsError::OperationFailed(format!("invalid {method} response: {e}")))
+    }
+
+    /// Generates a random pepper for blind indexes.
+    fn generate_pepper() -> Vec<u8> {
+        use rand::RngCore;
+
+        let mut pepper = vec![0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut pepper);
+        pepper
+    }
+}
+
+/// Returns the `CryptoKey` name for a `CryptoKey` or `CryptoKeyVersion` name.
+///
+/// Cloud KMS `decrypt` is addressed to the `CryptoKey`; the ciphertext itself
+/// identifies the version that produced it.
+fn crypto_key_name(kek_id: &str) -> &str {
+    kek_id.find("/cryptoKeyVersions/").map_or(kek_id, |pos| &kek_id[..pos])
+}
+
+#[async_trait::async_trait]
+impl AsyncKeyProvider for GcpKmsProvider {
+    async fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+        let key_name = self.current_key_name.read().await;
+        if key_name.is_empty() {
+            return Err(KeyProviderError::NoActiveKek);
+        }
+        Ok(key_name.clone())
+    }
+
+    async fn wrap_dek(
+        &self,
+        dek: &SecretVec<u8>,
+        kek_id: &str,
+    ) -> Result<WrappedDek, KeyProviderError> {
+        let mut plaintext = STANDARD.encode(dek.expose_secret());
+        let result = self
+            .call::<_, EncryptResponse>(
+                crypto_key_name(kek_id),
+                "encrypt",
+                &EncryptRequest { plaintext: &plaintext },
+            )
+            .await;
+        plaintext.zeroize();
+
+        let response = result.map_err(|e| match e {
+            GcpKmsError::KeyNotFound(name) => KeyProviderError::KekNotFound(name),
+            e => KeyProviderError::WrapFailed(format!("Cloud KMS encrypt failed: {e}")),
+        })?;
+
+        let encrypted_dek = STANDARD
+            .decode(&response.ciphertext)
+            .map_err(|e| KeyProviderError::WrapFailed(format!("Base64: {e}")))?;
+
+        // The response names the CryptoKeyVersion that performed the encryption
+        Ok(WrappedDek { kek_id: response.name, encrypted_dek })
+    }
+
+    async fn unwrap_dek(&self, wrapped: &WrappedDek) -> Result<SecretVec<u8>, KeyProviderError> {
+        let ciphertext = STANDARD.encode(&wrapped.encrypted_dek);
+        let mut response = self
+            .call::<_, DecryptResponse>(
+                crypto_key_name(&wrapped.kek_id),
+                "decrypt",
+                &DecryptRequest { ciphertext: &ciphertext },
+            )
+            .await?;
+
+        let plaintext = STANDARD.decode(&response.plaintext).map_err(GcpKmsError::from);
+        response.plaintext.zeroize();
+
+        Ok(SecretVec::new(plaintext?))
+    }
+
+    async fn get_pepper(&self) -> Result<SecretVec<u8>, KeyProviderError> {
+        Ok(SecretVec::new(self.pepper.expose_secret().clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    const KEY_NAME: &str = "projects/p/locations/global/keyRings/r/cryptoKeys/kek";
+
+    /// Mock Cloud KMS that "encrypts" by XOR-ing with a fixed byte.
+    struct XorKms;
+
+    impl Respond for XorKms {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let xor = |field: &str| {
+                let bytes = STANDARD.decode(body[field].as_str().unwrap()).unwrap();
+                STANDARD.encode(bytes.iter().map(|b| b ^ 0x5a).collect::<Vec<u8>>())
+            };
+
+            if request.url.path().ends_with(":encrypt") {
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "name": format!("{KEY_NAME}/cryptoKeyVersions/3"),
+                    "ciphertext": xor("plaintext"),
+                }))
+            } else {
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "plaintext": xor("ciphertext"),
+                }))
+            }
+        }
+    }
+
+    async fn mock_kms() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("authorization", "Bearer test-token"))
+            .respond_with(XorKms)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_wrap_unwrap_round_trip() {
+        let server = mock_kms().await;
+        let provider =
+            GcpKmsProvider::with_access_token(KEY_NAME, "test-token").with_endpoint(server.uri());
+
+        let dek = SecretVec::new(vec![7u8; 32]);
+        let kek_id = provider.current_kek_id().await.unwrap();
+        let wrapped = provider.wrap_dek(&dek, &kek_id).await.unwrap();
+
+        assert_ne!(wrapped.encrypted_dek, dek.expose_secret().clone());
+
+        let unwrapped = provider.unwrap_dek(&wrapped).await.unwrap();
+        assert_eq!(unwrapped.expose_secret(), dek.expose_secret());
+    }
+
+    #[tokio::test]
+    async fn test_wrapped_kek_id_is_key_version() {
+        let server = mock_kms().await;
+        let provider =
+            GcpKmsProvider::with_access_token(KEY_NAME, "test-token").with_endpoint(server.uri());
+
+        let dek = SecretVec::new(vec![1u8; 32]);
+        let wrapped = provider.wrap_dek(&dek, KEY_NAME).await.unwrap();
+
+        assert_eq!(wrapped.kek_id, format!("{KEY_NAME}/cryptoKeyVersions/3"));
+    }
+
+    #[tokio::test]
+    async fn test_unwrap_targets_crypto_key_endpoint() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(format!("/v1/{KEY_NAME}:decrypt")))
+            .respond_with(XorKms)
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider =
+            GcpKmsProvider::with_access_token(KEY_NAME, "test-token").with_endpoint(server.uri());
+        let wrapped = WrappedDek {
+            kek_id: format!("{KEY_NAME}/cryptoKeyVersions/3"),
+            encrypted_dek: vec![0x5a; 32],
+        };
+
+        let unwrapped = provider.unwrap_dek(&wrapped).await.unwrap();
+        assert_eq!(unwrapped.expose_secret(), &vec![0u8; 32]);
+    }
+
+    #[tokio::test]
+    async fn test_missing_key_maps_to_kek_not_found() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+
+        let provider =
+            GcpKmsProvider::with_access_token(KEY_NAME, "test-token").with_endpoint(server.uri());
+        let dek = SecretVec::new(vec![1u8; 32]);
+
+        let result = provider.wrap_dek(&dek, KEY_NAME).await;
+        assert!(matches!(result, Err(KeyProviderError::KekNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_api_error_maps_to_wrap_failed() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(403).set_body_string("permission denied"))
+            .mount(&server)
+            .await;
+
+        let provider =
+            GcpKmsProvider::with_access_token(KEY_NAME, "test-token").with_endpoint(server.uri());
+        let dek = SecretVec::new(vec![1u8; 32]);
+
+        let result = provider.wrap_dek(&dek, KEY_NAME).await;
+        assert!(matches!(result, Err(KeyProviderError::WrapFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_no_active_key() {
+        let provider = GcpKmsProvider::with_access_token("", "test-token");
+
+        let result = provider.current_kek_id().await;
+        assert!(matches!(result, Err(KeyProviderError::NoActiveKek)));
+
+        provider.set_current_key_name(KEY_NAME).await;
+        assert_eq!(provider.current_kek_id().await.unwrap(), KEY_NAME);
+    }
+
+    #[test]
+    fn test_crypto_key_name() {
+        let version = format!("{KEY_NAME}/cryptoKeyVersions/12");
+        assert_eq!(crypto_key_name(&version), KEY_NAME);
+        assert_eq!(crypto_key_name(KEY_NAME), KEY_NAME);
+    }
+}
@@ -0,0 +1,571 @@
+//! PKCS#11 HSM key provider for `SifreDB`.
+//!
+//! This module provides integration with hardware security modules and
+//! software tokens (`SoftHSM`, Luna, `nShield`, ...) through the PKCS#11 API,
+//! for on-premises deployments that can't use a cloud KMS.
+//!
+//! # Features
+//!
+//! - KEKs are AES-256 secret keys that never leave the token
+//! - Wrap/unwrap via `C_Encrypt`/`C_Decrypt` with `CKM_AES_GCM`
+//! - The `kek_id` is the `CKA_LABEL` of the key object and is bound to the
+//!   wrapped DEK as associated data, together with the tenant for
+//!   [`KeyProvider::wrap_dek_aad`]
+//! - PIN from configuration or the `SIFREDB_PKCS11_PIN` environment variable
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use sifredb_kms_pkcs11::{Pkcs11Config, Pkcs11Provider};
+//! use sifredb::prelude::*;
+//!
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let config = Pkcs11Config::new("/usr/lib/softhsm/libsofthsm2.so", "sifredb")
+//!     .with_kek_label("sifredb-kek-1");
+//! let provider = Pkcs11Provider::open(config)?;
+//!
+//! let vault = Vault::new(provider, CipherMode::default());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Configuration
+//!
+//! [`Pkcs11Config::from_env`] reads:
+//! - `SIFREDB_PKCS11_MODULE` - path to the PKCS#11 module (`.so`/`.dll`)
+//! - `SIFREDB_PKCS11_TOKEN` - label of the token to use
+//! - `SIFREDB_PKCS11_PIN` - user PIN (also used when the config has no PIN)
+//! - `SIFREDB_PKCS11_KEK` - label of the current KEK (optional)
+
+#![warn(clippy::pedantic, clippy::nursery)]
+#![allow(clippy::module_name_repetitions)]
+
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::error::{Error as CryptokiError, RvError};
+use cryptoki::mechanism::aead::GcmParams;
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::slot::Slot;
+use rand::RngCore;
+use secrecy::{ExposeSecret, SecretString, SecretVec};
+use sifredb::error::KeyProviderError;
+use sifredb::key_provider::KeyProvider;
+use std::fmt;
+use std::os::raw::c_ulong;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, RwLock};
+use zeroize::Zeroize;
+
+/// Environment variable holding the PKCS#11 module path.
+pub const MODULE_ENV: &str = "SIFREDB_PKCS11_MODULE";
+
+/// Environment variable holding the token label.
+pub const TOKEN_ENV: &str = "SIFREDB_PKCS11_TOKEN";
+
+/// Environment variable holding the user PIN.
+pub const PIN_ENV: &str = "SIFREDB_PKCS11_PIN";
+
+/// Environment variable holding the label of the current KEK.
+pub const KEK_ENV: &str = "SIFREDB_PKCS11_KEK";
+
+const KEK_SIZE: c_ulong = 32; // AES-256
+const NONCE_SIZE: usize = 12; // 96 bits for AES-GCM
+const TAG_BITS: c_ulong = 128;
+
+/// Connection settings for a [`Pkcs11Provider`].
+#[derive(Debug, Clone)]
+pub struct Pkcs11Config {
+    /// Path to the PKCS#11 module
+    module_path: PathBuf,
+    /// Label of the token holding the KEKs
+    token_label: String,
+    /// User PIN; falls back to `SIFREDB_PKCS11_PIN` when unset
+    pin: Option<SecretString>,
+    /// Label of the current KEK
+    kek_label: Option<String>,
+}
+
+impl Pkcs11Config {
+    /// Creates a configuration for the token labelled `token_label`.
+    ///
+    /// # Arguments
+    ///
+    /// * `module_path` - Path to the PKCS#11 module (e.g. `libsofthsm2.so`)
+    /// * `token_label` - Label of the token holding the KEKs
+    pub fn new(module_path: impl Into<PathBuf>, token_label: impl Into<String>) -> Self {
+        Self {
+            module_path: module_path.into(),
+            token_label: token_label.into(),
+            pin: None,
+            kek_label: None,
+        }
+    }
+
+    /// Reads the configuration from `SIFREDB_PKCS11_*` environment variables.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::Unavailable` if the module path or token
+    /// label is not set.
+    pub fn from_env() -> Result<Self, KeyProviderError> {
+        let var = |name: &str| {
+            std::env::var(name)
+                .map_err(|_| KeyProviderError::Unavailable(format!("{name} is not set")))
+        };
+
+        let mut config = Self::new(var(MODULE_ENV)?, var(TOKEN_ENV)?);
+        config.pin = std::env::var(PIN_ENV).ok().map(SecretString::new);
+        config.kek_label = std::env::var(KEK_ENV).ok();
+        Ok(config)
+    }
+
+    /// Sets the user PIN.
+    #[must_use]
+    pub fn with_pin(mut self, pin: impl Into<String>) -> Self {
+        self.pin = Some(SecretString::new(pin.into()));
+        self
+    }
+
+    /// Sets the label of the current KEK.
+    #[must_use]
+    pub fn with_kek_label(mut self, kek_label: impl Into<String>) -> Self {
+        self.kek_label = Some(kek_label.into());
+        self
+    }
+}
+
+/// PKCS#11 key provider implementation.
+///
+/// The provider keeps one logged-in read/write session open for its lifetime.
+/// PKCS#11 sessions can't be used from several threads at once, so operations
+/// are serialized on that session.
+///
+/// Dropping a provider finalizes the PKCS#11 module if no other handle to it
+/// is alive, which closes every session on it. Share one provider per module
+/// rather than opening several.
+pub struct Pkcs11Provider {
+    /// Loaded PKCS#11 module
+    pkcs11: Pkcs11,
+    /// Slot holding the configured token
+    slot: Slot,
+    /// Logged-in user session
+    session: Mutex<Session>,
+    /// Label of the current KEK
+    current_kek: RwLock<Option<String>>,
+    /// Pepper for blind indexes (stored separately, not on the token)
+    pepper: Option<SecretVec<u8>>,
+}
+
+impl fmt::Debug for Pkcs11Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pkcs11Provider")
+            .field("slot", &self.slot)
+            .field("current_kek", &self.current_kek)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Pkcs11Provider {
+    /// Loads the PKCS#11 module, finds the token and logs in as the user.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - The module can't be loaded or initialized
+    /// - No token with the configured label is present
+    /// - No PIN is configured or in `SIFREDB_PKCS11_PIN`
+    /// - Login fails
+    pub fn open(config: Pkcs11Config) -> Result<Self, KeyProviderError> {
+        let pkcs11 = Pkcs11::new(&config.module_path)
+            .map_err(|e| pkcs11_error(&e, "load module", KeyProviderError::Unavailable))?;
+
+        // Another provider in this process may already have initialized the module
+        match pkcs11.initialize(CInitializeArgs::OsThreads) {
+            Ok(())
+            | Err(
+                CryptokiError::AlreadyInitialized
+                | CryptokiError::Pkcs11(RvError::CryptokiAlreadyInitialized, _),
+            ) => {}
+            Err(e) => return Err(pkcs11_error(&e, "initialize", KeyProviderError::Unavailable)),
+        }
+
+        let slot = find_token(&pkcs11, &config.token_label)?;
+
+        let pin = match config.pin {
+            Some(pin) => pin,
+            None => std::env::var(PIN_ENV).map(SecretString::new).map_err(|_| {
+                KeyProviderError::CreationFailed(format!(
+                    "no PIN configured and {PIN_ENV} is not set"
+                ))
+            })?,
+        };
+
+        let session = pkcs11
+            .open_rw_session(slot)
+            .map_err(|e| pkcs11_error(&e, "open session", KeyProviderError::Unavailable))?;
+        match session.login(UserType::User, Some(&pin)) {
+            Ok(()) | Err(CryptokiError::Pkcs11(RvError::UserAlreadyLoggedIn, _)) => {}
+            Err(e) => return Err(pkcs11_error(&e, "login", KeyProviderError::CreationFailed)),
+        }
+
+        Ok(Self {
+            pkcs11,
+            slot,
+            session: Mutex::new(session),
+            current_kek: RwLock::new(config.kek_label),
+            pepper: None,
+        })
+    }
+
+    /// Sets the pepper returned for blind index generation.
+    ///
+    /// Without a pepper the provider doesn't support blind indexes.
+    #[must_use]
+    pub fn with_pepper(mut self, pepper: SecretVec<u8>) -> Self {
+        self.pepper = Some(pepper);
+        self
+    }
+
+    /// Sets the label of the current KEK.
+    ///
+    /// # Arguments
+    ///
+    /// * `kek_label` - `CKA_LABEL` of an AES key on the token
+    pub fn set_current_kek(&self, kek_label: impl Into<String>) {
+        let mut current =
+            self.current_kek.write().unwrap_or_else(std::sync::PoisonError::into_inner);
+        *current = Some(kek_label.into());
+    }
+
+    /// Encrypts `dek` under `kek_id` with `aad`, as `nonce || ciphertext`.
+    fn seal_wrapped(
+        &self,
+        kek_id: &str,
+        dek: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        let mut nonce = [0u8; NONCE_SIZE];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+        let session = self.session()?;
+        let kek = find_kek(&session, kek_id)?;
+
+        let params = GcmParams::new(&nonce, aad, TAG_BITS.into());
+        let ciphertext = session
+            .encrypt(&Mechanism::AesGcm(params), kek, dek)
+            .map_err(|e| pkcs11_error(&e, "encrypt", KeyProviderError::WrapFailed))?;
+        drop(session);
+
+        let mut wrapped = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+        wrapped.extend_from_slice(&nonce);
+        wrapped.extend_from_slice(&ciphertext);
+        Ok(wrapped)
+    }
+
+    /// Decrypts a wrapped DEK written by [`Self::seal_wrapped`] with the same `aad`.
+    fn open_wrapped(
+        &self,
+        kek_id: &str,
+        wrapped_dek: &[u8],
+        aad: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        if wrapped_dek.len() <= NONCE_SIZE {
+            return Err(KeyProviderError::UnwrapFailed("Wrapped DEK too short".to_string()));
+        }
+        let (nonce, ciphertext) = wrapped_dek.split_at(NONCE_SIZE);
+
+        let session = self.session()?;
+        let kek = find_kek(&session, kek_id)?;
+
+        let params = GcmParams::new(nonce, aad, TAG_BITS.into());
+        let mut dek = session
+            .decrypt(&Mechanism::AesGcm(params), kek, ciphertext)
+            .map_err(|e| pkcs11_error(&e, "decrypt", KeyProviderError::UnwrapFailed))?;
+        drop(session);
+
+        let secret = SecretVec::new(dek.clone());
+        dek.zeroize();
+        Ok(secret)
+    }
+
+    fn session(&self) -> Result<MutexGuard<'_, Session>, KeyProviderError> {
+        self.session
+            .lock()
+            .map_err(|_| KeyProviderError::Unavailable("PKCS#11 session lock poisoned".to_string()))
+    }
+}
+
+/// Returns the slot holding the token labelled `label`.
+fn find_token(pkcs11: &Pkcs11, label: &str) -> Result<Slot, KeyProviderError> {
+    let slots = pkcs11
+        .get_slots_with_token()
+        .map_err(|e| pkcs11_error(&e, "list slots", KeyProviderError::Unavailable))?;
+
+    for slot in slots {
+        let info = pkcs11
+            .get_token_info(slot)
+            .map_err(|e| pkcs11_error(&e, "read token info", KeyProviderError::Unavailable))?;
+        if info.label() == label {
+            return Ok(slot);
+        }
+    }
+
+    Err(KeyProviderError::Unavailable(format!("no PKCS#11 token labelled '{label}'")))
+}
+
+/// Finds the AES secret key whose `CKA_LABEL` is `kek_id`.
+fn find_kek(session: &Session, kek_id: &str) -> Result<ObjectHandle, KeyProviderError> {
+    let template = [
+        Attribute::Class(ObjectClass::SECRET_KEY),
+        Attribute::KeyType(KeyType::AES),
+        Attribute::Label(kek_id.as_bytes().to_vec()),
+    ];
+
+    let handles = session
+        .find_objects(&template)
+        .map_err(|e| pkcs11_error(&e, "find KEK", KeyProviderError::Unavailable))?;
+
+    match handles.as_slice() {
+        [handle] => Ok(*handle),
+        [] => Err(KeyProviderError::KekNotFound(kek_id.to_string())),
+        _ => Err(KeyProviderError::Unavailable(format!(
+            "{} keys share the label '{kek_id}'",
+            handles.len()
+        ))),
+    }
+}
+
+/// Converts a PKCS#11 failure into a `KeyProviderError`.
+///
+/// Token and session failures become `Unavailable`, `CKR_SESSION_COUNT`
+/// becomes `Throttled`, PIN and login failures become `CreationFailed` (the
+/// caller lacks access) and invalid key handles become `KekNotFound`.
+/// Anything else is wrapped with `fallback`.
+fn pkcs11_error(
+    err: &CryptokiError,
+    op: &str,
+    fallback: fn(String) -> KeyProviderError,
+) -> KeyProviderError {
+    let message = format!("PKCS#11 {op} failed: {err}");
+
+    let CryptokiError::Pkcs11(rv, _) = err else {
+        return match err {
+            CryptokiError::LibraryLoading(_) => KeyProviderError::Unavailable(message),
+            _ => fallback(message),
+        };
+    };
+
+    match rv {
+        RvError::DeviceError
+        | RvError::DeviceMemory
+        | RvError::DeviceRemoved
+        | RvError::TokenNotPresent
+        | RvError::TokenNotRecognized
+        | RvError::SessionClosed
+        | RvError::SessionHandleInvalid
+        | RvError::CryptokiNotInitialized
+        | RvError::FunctionCanceled => KeyProviderError::Unavailable(message),
+        RvError::SessionCount => KeyProviderError::Throttled(message),
+        RvError::PinIncorrect
+        | RvError::PinInvalid
+        | RvError::PinExpired
+        | RvError::PinLocked
+        | RvError::PinLenRange
+        | RvError::UserNotLoggedIn
+        | RvError::UserPinNotInitialized => KeyProviderError::CreationFailed(message),
+        RvError::KeyHandleInvalid | RvError::ObjectHandleInvalid => {
+            KeyProviderError::KekNotFound(message)
+        }
+        _ => fallback(message),
+    }
+}
+
+impl KeyProvider for Pkcs11Provider {
+    fn create_kek(&self) -> Result<String, KeyProviderError> {
+        let kek_id = format!("sifredb-kek-{:016x}", rand::rngs::OsRng.next_u64());
+
+        let template = [
+            Attribute::Class(ObjectClass::SECRET_KEY),
+            Attribute::KeyType(KeyType::AES),
+            Attribute::ValueLen(KEK_SIZE.into()),
+            Attribute::Label(kek_id.as_bytes().to_vec()),
+            Attribute::Token(true),
+            Attribute::Private(true),
+            Attribute::Sensitive(true),
+            Attribute::Extractable(false),
+            Attribute::Encrypt(true),
+            Attribute::Decrypt(true),
+        ];
+
+        self.session()?
+            .generate_key(&Mechanism::AesKeyGen, &template)
+            .map_err(|e| pkcs11_error(&e, "generate KEK", KeyProviderError::CreationFailed))?;
+
+        self.set_current_kek(kek_id.clone());
+        Ok(kek_id)
+    }
+
+    fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+        self.current_kek
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone()
+            .ok_or(KeyProviderError::NoActiveKek)
+    }
+
+    fn wrap_dek(&self, kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+        // Bind the kek_id so a wrapped DEK can't be replayed under another label
+        self.seal_wrapped(kek_id, dek, kek_id.as_bytes())
+    }
+
+    fn unwrap_dek(
+        &self,
+        kek_id: &str,
+        wrapped_dek: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        self.open_wrapped(kek_id, wrapped_dek, kek_id.as_bytes())
+    }
+
+    fn wrap_dek_aad(
+        &self,
+        kek_id: &str,
+        dek: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        if aad.is_empty() {
+            return self.wrap_dek(kek_id, dek);
+        }
+        self.seal_wrapped(kek_id, dek, &bound_aad(kek_id, aad))
+    }
+
+    fn unwrap_dek_aad(
+        &self,
+        kek_id: &str,
+        wrapped_dek: &[u8],
+        aad: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        if aad.is_empty() {
+            return self.unwrap_dek(kek_id, wrapped_dek);
+        }
+
+        // Wrapped DEKs carry no format marker, so one wrapped before tenants
+        // were bound is tried with the kek_id alone. A tenant-bound DEK never
+        // verifies that way, so this can't strip the binding.
+        match self.open_wrapped(kek_id, wrapped_dek, &bound_aad(kek_id, aad)) {
+            Err(KeyProviderError::UnwrapFailed(_)) => self.unwrap_dek(kek_id, wrapped_dek),
+            result => result,
+        }
+    }
+
+    fn rewrap_dek_aad(
+        &self,
+        old_kek_id: &str,
+        new_kek_id: &str,
+        wrapped_dek: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        let dek = self.unwrap_dek_aad(old_kek_id, wrapped_dek, aad)?;
+        self.wrap_dek_aad(new_kek_id, dek.expose_secret(), aad)
+    }
+
+    fn get_pepper(&self) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+        Ok(self.pepper.as_ref().map(|pepper| SecretVec::new(pepper.expose_secret().clone())))
+    }
+
+    fn health_check(&self) -> Result<(), KeyProviderError> {
+        self.pkcs11
+            .get_token_info(self.slot)
+            .map_err(|e| pkcs11_error(&e, "read token info", KeyProviderError::Unavailable))?;
+
+        let kek_id = self.current_kek_id()?;
+        find_kek(&*self.session()?, &kek_id).map(|_| ())
+    }
+}
+
+/// Returns the GCM associated data of a DEK wrapped with `aad`, as
+/// `kek_id || 0x00 || aad`.
+fn bound_aad(kek_id: &str, aad: &[u8]) -> Vec<u8> {
+    let mut bound = Vec::with_capacity(kek_id.len() + 1 + aad.len());
+    bound.extend_from_slice(kek_id.as_bytes());
+    bound.push(0);
+    bound.extend_from_slice(aad);
+    bound
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cryptoki::context::Function;
+
+    fn rv(rv: RvError) -> CryptokiError {
+        CryptokiError::Pkcs11(rv, Function::Decrypt)
+    }
+
+    #[test]
+    fn test_token_failures_map_to_unavailable() {
+        for code in [RvError::DeviceRemoved, RvError::TokenNotPresent, RvError::SessionClosed] {
+            let err = pkcs11_error(&rv(code), "decrypt", KeyProviderError::UnwrapFailed);
+            assert!(matches!(err, KeyProviderError::Unavailable(_)), "{code:?}");
+            assert!(err.is_retryable());
+        }
+    }
+
+    #[test]
+    fn test_session_count_maps_to_throttled() {
+        let err =
+            pkcs11_error(&rv(RvError::SessionCount), "open session", KeyProviderError::Unavailable);
+        assert!(matches!(err, KeyProviderError::Throttled(_)));
+    }
+
+    #[test]
+    fn test_pin_failures_map_to_creation_failed() {
+        for code in [RvError::PinIncorrect, RvError::PinLocked, RvError::UserNotLoggedIn] {
+            let err = pkcs11_error(&rv(code), "login", KeyProviderError::Unavailable);
+            assert!(matches!(err, KeyProviderError::CreationFailed(_)), "{code:?}");
+            assert!(!err.is_retryable());
+        }
+    }
+
+    #[test]
+    fn test_invalid_key_handle_maps_to_kek_not_found() {
+        let err =
+            pkcs11_error(&rv(RvError::KeyHandleInvalid), "encrypt", KeyProviderError::WrapFailed);
+        assert!(matches!(err, KeyProviderError::KekNotFound(_)));
+    }
+
+    #[test]
+    fn test_other_errors_use_fallback() {
+        let err = pkcs11_error(
+            &rv(RvError::EncryptedDataInvalid),
+            "decrypt",
+            KeyProviderError::UnwrapFailed,
+        );
+        assert!(matches!(err, KeyProviderError::UnwrapFailed(msg) if msg.contains("decrypt")));
+
+        let err =
+            pkcs11_error(&CryptokiError::NotSupported, "encrypt", KeyProviderError::WrapFailed);
+        assert!(matches!(err, KeyProviderError::WrapFailed(_)));
+    }
+
+    #[test]
+    fn test_missing_module_is_unavailable() {
+        let config = Pkcs11Config::new("/nonexistent/libpkcs11.so", "sifredb").with_pin("1234");
+        let result = Pkcs11Provider::open(config);
+        assert!(matches!(result, Err(KeyProviderError::Unavailable(_))));
+    }
+
+    #[test]
+    fn test_bound_aad_separates_kek_id_and_tenant() {
+        assert_eq!(bound_aad("kek", b"acme"), b"kek\0acme");
+        assert_ne!(bound_aad("kek", b"acme"), bound_aad("kek\0ac", b"me"));
+    }
+
+    #[test]
+    fn test_config_debug_redacts_pin() {
+        let config = Pkcs11Config::new("libsofthsm2.so", "sifredb").with_pin("123456");
+        let debug = format!("{config:?}");
+        assert!(!debug.contains("123456"));
+        assert!(debug.contains("sifredb"));
+    }
+}
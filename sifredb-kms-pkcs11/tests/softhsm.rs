@@ -0,0 +1,107 @@
+//! Tests against a real PKCS#11 token, normally SoftHSM.
+//!
+//! Enabled by the `softhsm-tests` feature and skipped unless the
+//! `SIFREDB_PKCS11_*` variables point at an initialized token (see README).
+
+#![cfg(feature = "softhsm-tests")]
+
+use secrecy::ExposeSecret;
+use sifredb::prelude::*;
+use sifredb_kms_pkcs11::{Pkcs11Config, Pkcs11Provider};
+use std::sync::Mutex;
+
+/// Serializes access to the token.
+///
+/// Dropping the last handle to a module finalizes it for the whole process,
+/// so two providers must never be alive at the same time.
+static TOKEN: Mutex<()> = Mutex::new(());
+
+/// Runs `test` with a provider holding a fresh current KEK.
+///
+/// Does nothing if no token is configured in the environment.
+fn with_provider(test: impl FnOnce(Pkcs11Provider)) {
+    let _guard = TOKEN.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+
+    let Ok(config) = Pkcs11Config::from_env() else {
+        eprintln!("SIFREDB_PKCS11_MODULE/SIFREDB_PKCS11_TOKEN not set, skipping");
+        return;
+    };
+    let provider = Pkcs11Provider::open(config).expect("Failed to open token");
+    provider.create_kek().expect("Failed to create KEK");
+
+    test(provider);
+}
+
+#[test]
+fn test_wrap_unwrap_round_trip() {
+    with_provider(|provider| {
+        let kek_id = provider.current_kek_id().unwrap();
+        let dek = vec![7u8; 32];
+
+        let wrapped = provider.wrap_dek(&kek_id, &dek).unwrap();
+        assert_ne!(&wrapped[12..], dek.as_slice());
+
+        let unwrapped = provider.unwrap_dek(&kek_id, &wrapped).unwrap();
+        assert_eq!(unwrapped.expose_secret(), &dek);
+    });
+}
+
+#[test]
+fn test_wrap_is_randomized() {
+    with_provider(|provider| {
+        let kek_id = provider.current_kek_id().unwrap();
+        let first = provider.wrap_dek(&kek_id, &[1u8; 32]).unwrap();
+        let second = provider.wrap_dek(&kek_id, &[1u8; 32]).unwrap();
+
+        assert_ne!(first, second);
+    });
+}
+
+#[test]
+fn test_tampered_wrapped_dek_fails() {
+    with_provider(|provider| {
+        let kek_id = provider.current_kek_id().unwrap();
+        let mut wrapped = provider.wrap_dek(&kek_id, &[2u8; 32]).unwrap();
+        let last = wrapped.len() - 1;
+        wrapped[last] ^= 0x01;
+
+        let result = provider.unwrap_dek(&kek_id, &wrapped);
+        assert!(matches!(result, Err(KeyProviderError::UnwrapFailed(_))));
+    });
+}
+
+#[test]
+fn test_wrapped_dek_bound_to_kek() {
+    with_provider(|provider| {
+        let old_kek = provider.current_kek_id().unwrap();
+        let wrapped = provider.wrap_dek(&old_kek, &[4u8; 32]).unwrap();
+
+        let new_kek = provider.create_kek().unwrap();
+        assert_eq!(provider.current_kek_id().unwrap(), new_kek);
+
+        let result = provider.unwrap_dek(&new_kek, &wrapped);
+        assert!(matches!(result, Err(KeyProviderError::UnwrapFailed(_))));
+        assert!(provider.unwrap_dek(&old_kek, &wrapped).is_ok());
+    });
+}
+
+#[test]
+fn test_unknown_kek_label() {
+    with_provider(|provider| {
+        let result = provider.wrap_dek("sifredb-kek-missing", &[3u8; 32]);
+        assert!(matches!(result, Err(KeyProviderError::KekNotFound(_))));
+    });
+}
+
+#[test]
+fn test_vault_round_trip_and_health_check() {
+    with_provider(|provider| {
+        provider.health_check().unwrap();
+
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+        assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), b"alice@example.com");
+    });
+}
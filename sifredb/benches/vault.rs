@@ -0,0 +1,50 @@
+//! Encrypt/decrypt throughput across payload sizes.
+//!
+//! Run with `cargo bench -p sifredb --bench vault`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sifredb::prelude::*;
+use sifredb_key_file::FileKeyProvider;
+use tempfile::TempDir;
+
+const PAYLOAD_SIZES: [usize; 4] = [16, 256, 4096, 65536];
+
+fn setup() -> (TempDir, Vault<FileKeyProvider>) {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    FileKeyProvider::init(temp_dir.path()).expect("Failed to initialize keys");
+    let provider = FileKeyProvider::new(temp_dir.path()).expect("Failed to create provider");
+    (temp_dir, Vault::new(provider, CipherMode::default()))
+}
+
+fn bench_encrypt(c: &mut Criterion) {
+    let (_temp_dir, vault) = setup();
+    let context = EncryptionContext::new("users", "email").with_tenant("tenant_1");
+
+    let mut group = c.benchmark_group("encrypt");
+    for size in PAYLOAD_SIZES {
+        let plaintext = vec![0x42u8; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &plaintext, |b, plaintext| {
+            b.iter(|| vault.encrypt(black_box(plaintext), &context).unwrap());
+        });
+    }
+    group.finish();
+}
+
+fn bench_decrypt(c: &mut Criterion) {
+    let (_temp_dir, vault) = setup();
+    let context = EncryptionContext::new("users", "email").with_tenant("tenant_1");
+
+    let mut group = c.benchmark_group("decrypt");
+    for size in PAYLOAD_SIZES {
+        let ciphertext = vault.encrypt(&vec![0x42u8; size], &context).unwrap();
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &ciphertext, |b, ciphertext| {
+            b.iter(|| vault.decrypt(black_box(ciphertext), &context).unwrap());
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_encrypt, bench_decrypt);
+criterion_main!(benches);
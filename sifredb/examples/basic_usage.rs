@@ -43,7 +43,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Encrypt the data
     let ciphertext = vault.encrypt(plaintext, &context)?;
-    println!("✓ Encrypted ({} bytes)", ciphertext.len());
+    println!("✓ Encrypted ({} bytes)", ciphertext.as_bytes().len());
 
     // Decrypt the data
     let decrypted = vault.decrypt(&ciphertext, &context)?;
@@ -48,7 +48,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     let mut ciphertexts = Vec::new();
     for email in &emails {
-        let ct = vault.encrypt(*email, &email_context)?;
+        let ct = vault.encrypt(email, &email_context)?;
         ciphertexts.push(ct);
         println!("Encrypted: {}", String::from_utf8_lossy(email));
     }
@@ -135,7 +135,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     let mut all_success = true;
     for email in &test_emails {
-        let ciphertext = vault.encrypt(*email, &context)?;
+        let ciphertext = vault.encrypt(email, &context)?;
         let decrypted = vault.decrypt(&ciphertext, &context)?;
         
         let success = decrypted == *email;
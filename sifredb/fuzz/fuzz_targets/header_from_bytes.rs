@@ -0,0 +1,16 @@
+//! Feeds arbitrary bytes to the header parser, which must never panic.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sifredb::header::EncryptionHeader;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((header, header_len)) = EncryptionHeader::from_bytes(data) {
+        assert!(header_len <= data.len());
+
+        // Anything that parses must serialize back to the bytes it came from
+        let bytes = header.to_bytes().expect("parsed header failed to serialize");
+        assert_eq!(bytes, &data[..header_len]);
+    }
+});
@@ -0,0 +1,43 @@
+//! Feeds arbitrary bytes to `Vault::decrypt_bytes`, which must never panic.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use secrecy::SecretVec;
+use sifredb::context::EncryptionContext;
+use sifredb::error::KeyProviderError;
+use sifredb::key_provider::KeyProvider;
+use sifredb::vault::{CipherMode, Vault};
+
+/// Provider that "unwraps" by XOR with a fixed key, so any wrapped DEK of
+/// any length reaches the cipher.
+struct XorKeyProvider;
+
+impl KeyProvider for XorKeyProvider {
+    fn create_kek(&self) -> Result<String, KeyProviderError> {
+        Ok("fuzz_kek".to_string())
+    }
+
+    fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+        Ok("fuzz_kek".to_string())
+    }
+
+    fn wrap_dek(&self, _kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+        Ok(dek.iter().map(|b| b ^ 0x5A).collect())
+    }
+
+    fn unwrap_dek(
+        &self,
+        _kek_id: &str,
+        wrapped_dek: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        Ok(SecretVec::new(wrapped_dek.iter().map(|b| b ^ 0x5A).collect()))
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let vault = Vault::new(XorKeyProvider, CipherMode::default());
+    let context = EncryptionContext::new("fuzz", "field");
+
+    let _ = vault.decrypt_bytes(data, &context);
+});
@@ -0,0 +1,130 @@
+//! Provider-free inspection of stored ciphertext.
+//!
+//! Before a rewrap campaign it helps to know what is actually stored: which
+//! KEKs the data is wrapped under, which header versions are still around,
+//! and which values don't parse at all. [`scan_headers`] answers that from
+//! the headers alone, without a key provider or any decryption.
+
+use crate::ciphertext::check_payload_len;
+use crate::error::Error;
+use crate::header::EncryptionHeader;
+use std::collections::HashMap;
+
+/// The outcome of parsing one blob's header.
+#[derive(Debug)]
+pub struct HeaderAudit {
+    /// Position of the blob in the scanned slice
+    pub index: usize,
+    /// The parsed header, or why the blob isn't valid ciphertext
+    pub result: Result<EncryptionHeader, Error>,
+}
+
+/// Parses the header of every blob in `blobs`.
+///
+/// A blob is valid when it starts with a header this version can read and,
+/// if the header records a payload length, the rest of the blob matches it.
+/// Nothing is decrypted, so a valid header doesn't mean the payload
+/// authenticates. Results are in the same order as `blobs`.
+#[must_use]
+pub fn scan_headers(blobs: &[&[u8]]) -> Vec<HeaderAudit> {
+    blobs
+        .iter()
+        .enumerate()
+        .map(|(index, blob)| HeaderAudit { index, result: parse_header(blob) })
+        .collect()
+}
+
+/// Counts the valid headers in `audits` by primary KEK id.
+///
+/// Blobs that failed to parse are not counted.
+#[must_use]
+pub fn kek_histogram(audits: &[HeaderAudit]) -> HashMap<String, usize> {
+    let mut histogram = HashMap::new();
+    for header in audits.iter().filter_map(|audit| audit.result.as_ref().ok()) {
+        *histogram.entry(header.kek_id().to_string()).or_insert(0) += 1;
+    }
+    histogram
+}
+
+/// Counts the valid headers in `audits` by protocol version.
+///
+/// Blobs that failed to parse are not counted.
+#[must_use]
+pub fn version_histogram(audits: &[HeaderAudit]) -> HashMap<u8, usize> {
+    let mut histogram = HashMap::new();
+    for header in audits.iter().filter_map(|audit| audit.result.as_ref().ok()) {
+        *histogram.entry(header.version()).or_insert(0) += 1;
+    }
+    histogram
+}
+
+fn parse_header(blob: &[u8]) -> Result<EncryptionHeader, Error> {
+    let (header, header_len) = EncryptionHeader::from_bytes(blob)?;
+    if header_len > blob.len() {
+        return Err(Error::InvalidHeader("Header overruns ciphertext".to_string()));
+    }
+    check_payload_len(&header, blob.len() - header_len)?;
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::{HeaderFlags, PROTOCOL_VERSION};
+
+    fn blob(kek_id: &str) -> Vec<u8> {
+        let header = EncryptionHeader::new(kek_id, vec![1; 32], HeaderFlags::empty(), vec![2; 12])
+            .with_payload_len(20);
+        let mut bytes = header.to_bytes().unwrap();
+        bytes.extend_from_slice(&[3; 20]);
+        bytes
+    }
+
+    #[test]
+    fn test_scan_mixed_blobs() {
+        let v1 = blob("kek_v1");
+        let v2 = blob("kek_v2");
+        let mut truncated = blob("kek_v2");
+        truncated.truncate(truncated.len() - 1);
+        let garbage = [0xFF; 16];
+
+        let blobs: [&[u8]; 6] = [&v1, &garbage, &v2, &[], &truncated, &v1];
+        let audits = scan_headers(&blobs);
+
+        assert_eq!(audits.len(), 6);
+        for (i, audit) in audits.iter().enumerate() {
+            assert_eq!(audit.index, i);
+        }
+        let valid: Vec<usize> =
+            audits.iter().filter(|audit| audit.result.is_ok()).map(|audit| audit.index).collect();
+        assert_eq!(valid, [0, 2, 5]);
+        assert_eq!(audits[0].result.as_ref().unwrap().kek_id(), "kek_v1");
+        assert!(matches!(audits[4].result, Err(Error::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn test_histograms_count_valid_headers_only() {
+        let v1 = blob("kek_v1");
+        let v2 = blob("kek_v2");
+        let garbage = [0x00; 4];
+
+        let blobs: [&[u8]; 5] = [&v1, &v2, &v1, &garbage, &v1];
+        let audits = scan_headers(&blobs);
+
+        let keks = kek_histogram(&audits);
+        assert_eq!(keks.len(), 2);
+        assert_eq!(keks["kek_v1"], 3);
+        assert_eq!(keks["kek_v2"], 1);
+
+        let versions = version_histogram(&audits);
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[&PROTOCOL_VERSION], 4);
+    }
+
+    #[test]
+    fn test_scan_empty_slice() {
+        let audits = scan_headers(&[]);
+        assert!(audits.is_empty());
+        assert!(kek_histogram(&audits).is_empty());
+    }
+}
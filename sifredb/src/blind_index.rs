@@ -6,19 +6,82 @@
 use crate::context::IndexContext;
 use crate::error::Error;
 use crate::key_provider::KeyProvider;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
 use hmac::{Hmac, Mac};
 use secrecy::ExposeSecret;
-use sha2::Sha256;
+use sha2::{Sha256, Sha512_256};
+use subtle::ConstantTimeEq;
 
 type HmacSha256 = Hmac<Sha256>;
+type HmacSha512_256 = Hmac<Sha512_256>;
 
 /// Standard blind index output size (16 bytes).
 pub const BLIND_INDEX_SIZE: usize = 16;
 
+/// HMAC construction used to compute blind indexes.
+///
+/// Selected per column with [`IndexContext::with_algo`]. Changing the
+/// algorithm of an existing column changes every index, so it must be
+/// reindexed like after a pepper rotation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlindIndexAlgo {
+    /// HMAC-SHA256 (default, compatible with existing indexes)
+    #[default]
+    HmacSha256,
+    /// HMAC-SHA512/256, often faster on 64-bit hardware
+    HmacSha512_256,
+}
+
+/// A keyed HMAC for one of the [`BlindIndexAlgo`] variants.
+#[derive(Clone)]
+enum IndexMac {
+    Sha256(HmacSha256),
+    Sha512_256(HmacSha512_256),
+}
+
+impl IndexMac {
+    fn new(algo: BlindIndexAlgo, key: &[u8]) -> Result<Self, Error> {
+        let mac = match algo {
+            BlindIndexAlgo::HmacSha256 => HmacSha256::new_from_slice(key).map(Self::Sha256),
+            BlindIndexAlgo::HmacSha512_256 => {
+                HmacSha512_256::new_from_slice(key).map(Self::Sha512_256)
+            }
+        };
+        mac.map_err(|e| Error::IndexGenerationFailed(format!("Invalid pepper: {e}")))
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha256(mac) => mac.update(data),
+            Self::Sha512_256(mac) => mac.update(data),
+        }
+    }
+
+    /// Returns the MAC truncated to [`BLIND_INDEX_SIZE`].
+    fn finalize_index(self) -> Vec<u8> {
+        match self {
+            Self::Sha256(mac) => mac.finalize().into_bytes()[..BLIND_INDEX_SIZE].to_vec(),
+            Self::Sha512_256(mac) => mac.finalize().into_bytes()[..BLIND_INDEX_SIZE].to_vec(),
+        }
+    }
+
+    /// Checks in constant time that `index` is a prefix of the MAC.
+    fn verify_index(self, index: &[u8]) -> bool {
+        match self {
+            Self::Sha256(mac) => mac.verify_truncated_left(index).is_ok(),
+            Self::Sha512_256(mac) => mac.verify_truncated_left(index).is_ok(),
+        }
+    }
+}
+
 /// Generates a blind index for searchable encryption.
 ///
 /// The blind index is computed as:
-/// `HMAC-SHA256(pepper, value || context)[..16]`
+/// `HMAC-SHA256(pepper, value || context)[..16]`, using the HMAC selected
+/// by the context's [`BlindIndexAlgo`].
 ///
 /// # Arguments
 ///
@@ -52,27 +115,262 @@ pub fn generate_blind_index<P: KeyProvider>(
     value: &[u8],
     context: &IndexContext,
 ) -> Result<Vec<u8>, Error> {
+    let mac = pepper_mac(provider, context)?;
+    Ok(finalize_index(mac, value, &context.to_string()))
+}
+
+/// Generates blind indexes for many values, fetching the pepper only once.
+///
+/// Produces exactly the same indexes as calling [`generate_blind_index`] for
+/// each value, but retrieves the pepper a single time and clones the keyed
+/// HMAC per value. Use this when the pepper lives in a remote KMS.
+///
+/// # Returns
+///
+/// One 16-byte blind index per value, in the same order as `values`.
+///
+/// # Errors
+///
+/// Returns error if:
+/// - Pepper is not available from the provider
+/// - HMAC computation fails
+pub fn generate_blind_indexes<P: KeyProvider>(
+    provider: &P,
+    values: &[&[u8]],
+    context: &IndexContext,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let mac = pepper_mac(provider, context)?;
+    let context_str = context.to_string();
+
+    Ok(values.iter().map(|value| finalize_index(mac.clone(), value, &context_str)).collect())
+}
+
+/// Checks in constant time whether `stored` is the blind index of `value`.
+///
+/// Recomputes the index as [`generate_blind_index`] does and compares it
+/// with `stored`, for integrity checks that catch corrupted indexes or
+/// indexes written for another value. An index shorter than
+/// [`BLIND_INDEX_SIZE`] is compared against that many leading bytes, so
+/// columns storing truncated indexes can be checked too; an empty or longer
+/// one never matches.
+///
+/// # Errors
+///
+/// Returns error only if the pepper is not available from the provider or
+/// HMAC computation fails; a mismatch is `Ok(false)`.
+pub fn verify_index<P: KeyProvider>(
+    provider: &P,
+    value: &[u8],
+    context: &IndexContext,
+    stored: &[u8],
+) -> Result<bool, Error> {
+    let mut mac = pepper_mac(provider, context)?;
+    mac.update(value);
+    mac.update(context.to_string().as_bytes());
+
+    Ok(stored.len() <= BLIND_INDEX_SIZE && mac.verify_index(stored))
+}
+
+/// Generates blind indexes over increasing prefixes of `value`, for range
+/// queries.
+///
+/// One index is returned per entry of `prefix_lengths`, computed like
+/// [`generate_blind_index`] over `value[..len]` with the prefix length bound
+/// into the context, so prefix indexes never equal each other's or the
+/// column's equality index. Store them all with the row.
+///
+/// Values must be encoded so that byte order is value order and all values
+/// have the same width, e.g. zero-padded decimal (`b"037"`) or big-endian
+/// integers. A range is then split into the prefixes that cover it: ages
+/// 30 to 47 in three digits are the prefix `03` plus `040` to `047`. The
+/// query side indexes each prefix with this function, passing the prefix
+/// as `value` and its length as the only entry of `prefix_lengths`, and
+/// matches rows holding any of the results.
+///
+/// # Security
+///
+/// This leaks much more than an equality index. Anyone who can read the
+/// index columns sees which rows share each prefix, which groups rows into
+/// ordered buckets and, with some known values or a frequency profile,
+/// narrows down the plaintext of the rest. Shorter prefixes are shared by
+/// more rows and leak more. Only index columns whose approximate order may
+/// be revealed, and prefer the coarsest `prefix_lengths` the queries allow.
+///
+/// # Errors
+///
+/// Returns `Error::IndexGenerationFailed` if `prefix_lengths` is not
+/// strictly increasing, contains 0 or exceeds the length of `value`, or if
+/// the pepper is not available from the provider.
+pub fn generate_prefix_indexes<P: KeyProvider>(
+    provider: &P,
+    value: &[u8],
+    context: &IndexContext,
+    prefix_lengths: &[usize],
+) -> Result<Vec<Vec<u8>>, Error> {
+    let increasing = prefix_lengths.windows(2).all(|pair| pair[0] < pair[1]);
+    let in_bounds = prefix_lengths.first().map_or(true, |&len| len > 0)
+        && prefix_lengths.last().map_or(true, |&len| len <= value.len());
+    if !increasing || !in_bounds {
+        return Err(Error::IndexGenerationFailed(format!(
+            "Prefix lengths must be strictly increasing and within 1..={}",
+            value.len()
+        )));
+    }
+
+    let mac = pepper_mac(provider, context)?;
+    Ok(prefix_lengths
+        .iter()
+        .map(|&len| finalize_index(mac.clone(), &value[..len], &format!("{context}|prefix:{len}")))
+        .collect())
+}
+
+/// Creates an HMAC keyed with the pepper version `context` is tagged with,
+/// or the provider's current pepper when it has none.
+fn pepper_mac<P: KeyProvider>(provider: &P, context: &IndexContext) -> Result<IndexMac, Error> {
     // Get pepper from provider
-    let pepper = provider
-        .get_pepper()?
-        .ok_or_else(|| Error::IndexGenerationFailed("Pepper not available".to_string()))?;
+    let pepper = match context.pepper_version() {
+        Some(version) => provider.get_pepper_version(version)?.ok_or_else(|| {
+            Error::IndexGenerationFailed(format!("Pepper version {version} not available"))
+        })?,
+        None => provider
+            .get_pepper()?
+            .ok_or_else(|| Error::IndexGenerationFailed("Pepper not available".to_string()))?,
+    };
 
     // Create HMAC instance with pepper as key
-    let mut mac = HmacSha256::new_from_slice(pepper.expose_secret())
-        .map_err(|e| Error::IndexGenerationFailed(format!("Invalid pepper: {e}")))?;
+    IndexMac::new(context.algo(), pepper.expose_secret())
+}
 
+/// Computes `HMAC(value || context)` and truncates it to [`BLIND_INDEX_SIZE`].
+fn finalize_index(mut mac: IndexMac, value: &[u8], context_str: &str) -> Vec<u8> {
     // Include value
     mac.update(value);
 
     // Include context for domain separation (tenant|table|column)
-    let context_str = context.to_string();
     mac.update(context_str.as_bytes());
 
     // Compute HMAC and truncate to BLIND_INDEX_SIZE
-    let result = mac.finalize();
-    let bytes = result.into_bytes();
+    mac.finalize_index()
+}
+
+/// Computes blind indexes within an application-wide domain.
+///
+/// The free functions in this module key every index by the pepper and
+/// separate columns only by their context, so two unrelated databases that
+/// share a pepper and reuse context names produce correlatable indexes.
+/// A `BlindIndexer` mixes a domain tag, configured once, into every index:
+/// `HMAC(pepper, len(domain) || domain || value || context)[..16]`, with
+/// the length as 8 big-endian bytes.
+///
+/// The default empty domain mixes in nothing, so its output equals
+/// [`generate_blind_index`] and existing indexes stay valid. Changing the
+/// domain of a deployment changes every index, which must then be rebuilt
+/// like after a pepper rotation.
+///
+/// # Example
+///
+/// ```ignore
+/// use sifredb::blind_index::BlindIndexer;
+/// use sifredb::context::IndexContext;
+/// use sifredb_key_file::FileKeyProvider;
+///
+/// let indexer = BlindIndexer::new(FileKeyProvider::new("./keys")?).with_domain("billing-eu");
+/// let context = IndexContext::new("users", "email");
+/// let index = indexer.index(b"alice@example.com", &context)?;
+/// ```
+pub struct BlindIndexer<P: KeyProvider> {
+    provider: P,
+    domain: Vec<u8>,
+}
+
+impl<P: KeyProvider> fmt::Debug for BlindIndexer<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The provider may hold key material, so it is never formatted
+        f.debug_struct("BlindIndexer").field("domain", &self.domain).finish_non_exhaustive()
+    }
+}
+
+impl<P: KeyProvider> BlindIndexer<P> {
+    /// Creates an indexer with the empty domain, matching [`generate_blind_index`].
+    pub const fn new(provider: P) -> Self {
+        Self { provider, domain: Vec::new() }
+    }
+
+    /// Sets the domain tag mixed into every index.
+    #[must_use]
+    pub fn with_domain(mut self, domain: impl Into<Vec<u8>>) -> Self {
+        self.domain = domain.into();
+        self
+    }
 
-    Ok(bytes[..BLIND_INDEX_SIZE].to_vec())
+    /// Returns the domain tag.
+    #[must_use]
+    pub fn domain(&self) -> &[u8] {
+        &self.domain
+    }
+
+    /// Returns the key provider.
+    pub const fn provider(&self) -> &P {
+        &self.provider
+    }
+
+    /// Generates the blind index of `value` within this domain.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the pepper is not available from the provider or
+    /// HMAC computation fails.
+    pub fn index(&self, value: &[u8], context: &IndexContext) -> Result<Vec<u8>, Error> {
+        let mac = self.domain_mac(context)?;
+        Ok(finalize_index(mac, value, &context.to_string()))
+    }
+
+    /// Generates blind indexes for many values, fetching the pepper only
+    /// once; see [`generate_blind_indexes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the pepper is not available from the provider or
+    /// HMAC computation fails.
+    pub fn indexes(&self, values: &[&[u8]], context: &IndexContext) -> Result<Vec<Vec<u8>>, Error> {
+        let mac = self.domain_mac(context)?;
+        let context_str = context.to_string();
+
+        Ok(values.iter().map(|value| finalize_index(mac.clone(), value, &context_str)).collect())
+    }
+
+    /// Checks in constant time whether `stored` is the blind index of
+    /// `value` within this domain; see [`verify_index`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error only if the pepper is not available from the provider
+    /// or HMAC computation fails; a mismatch is `Ok(false)`.
+    pub fn verify(
+        &self,
+        value: &[u8],
+        context: &IndexContext,
+        stored: &[u8],
+    ) -> Result<bool, Error> {
+        let mut mac = self.domain_mac(context)?;
+        mac.update(value);
+        mac.update(context.to_string().as_bytes());
+
+        Ok(stored.len() <= BLIND_INDEX_SIZE && mac.verify_index(stored))
+    }
+
+    /// Creates the pepper-keyed HMAC with the domain already absorbed.
+    fn domain_mac(&self, context: &IndexContext) -> Result<IndexMac, Error> {
+        let mut mac = pepper_mac(&self.provider, context)?;
+
+        // Length-prefixed so the domain can't run into the value
+        if !self.domain.is_empty() {
+            mac.update(&(self.domain.len() as u64).to_be_bytes());
+            mac.update(&self.domain);
+        }
+
+        Ok(mac)
+    }
 }
 
 /// Generates a deterministic blind index suitable for equality queries.
@@ -117,24 +415,196 @@ pub fn generate_deterministic_index<P: KeyProvider>(
     generate_blind_index(provider, value, context)
 }
 
+/// A blind index tagged with the version of the pepper that produced it.
+///
+/// Storing the version next to the index lets a reindex job find entries made
+/// with an old pepper after [`KeyProvider::rotate_pepper`], and lets lookups
+/// keep verifying them until they are recomputed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionedBlindIndex {
+    /// Pepper version used to compute the index
+    pub pepper_version: u32,
+    /// The blind index bytes
+    pub index: Vec<u8>,
+}
+
+impl VersionedBlindIndex {
+    /// Serializes as `[pepper_version:4 BE][index]` for storage in one column.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(4 + self.index.len());
+        bytes.extend_from_slice(&self.pepper_version.to_be_bytes());
+        bytes.extend_from_slice(&self.index);
+        bytes
+    }
+
+    /// Parses bytes produced by [`VersionedBlindIndex::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Decoding` if the input is not 4 + [`BLIND_INDEX_SIZE`] bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != 4 + BLIND_INDEX_SIZE {
+            return Err(Error::Decoding(format!(
+                "Versioned blind index must be {} bytes, got {}",
+                4 + BLIND_INDEX_SIZE,
+                bytes.len()
+            )));
+        }
+
+        let (version, index) = bytes.split_at(4);
+        let pepper_version = u32::from_be_bytes([version[0], version[1], version[2], version[3]]);
+        Ok(Self { pepper_version, index: index.to_vec() })
+    }
+}
+
+/// Generates a blind index with the provider's current pepper version.
+///
+/// The HMAC input is prefixed with the pepper version, so indexes from
+/// different versions never collide:
+/// `HMAC-SHA256(pepper_v, version || value || context)[..16]`
+///
+/// # Errors
+///
+/// Returns error if:
+/// - The current pepper version is not available from the provider
+/// - HMAC computation fails
+pub fn generate_blind_index_versioned<P: KeyProvider>(
+    provider: &P,
+    value: &[u8],
+    context: &IndexContext,
+) -> Result<VersionedBlindIndex, Error> {
+    let version = provider.current_pepper_version()?;
+    generate_blind_index_with_pepper_version(provider, value, context, version)
+}
+
+/// Generates a versioned blind index with a specific pepper version.
+///
+/// # Errors
+///
+/// Returns error if:
+/// - This pepper version is not available from the provider
+/// - `context` is tagged with another pepper version (see
+///   [`IndexContext::with_pepper_version`])
+/// - HMAC computation fails
+pub fn generate_blind_index_with_pepper_version<P: KeyProvider>(
+    provider: &P,
+    value: &[u8],
+    context: &IndexContext,
+    pepper_version: u32,
+) -> Result<VersionedBlindIndex, Error> {
+    let mac = versioned_mac(provider, value, context, pepper_version)?;
+
+    Ok(VersionedBlindIndex { pepper_version, index: mac.finalize_index() })
+}
+
+/// Checks in constant time whether `value` produces `expected` under its pepper version.
+///
+/// # Errors
+///
+/// Returns error if the index's pepper version is not available from the
+/// provider, or `context` is tagged with another pepper version.
+pub fn verify_blind_index_versioned<P: KeyProvider>(
+    provider: &P,
+    value: &[u8],
+    context: &IndexContext,
+    expected: &VersionedBlindIndex,
+) -> Result<bool, Error> {
+    let mac = versioned_mac(provider, value, context, expected.pepper_version)?;
+    Ok(mac.verify_index(&expected.index))
+}
+
+/// Builds the HMAC over `version || value || context` keyed by the versioned pepper.
+fn versioned_mac<P: KeyProvider>(
+    provider: &P,
+    value: &[u8],
+    context: &IndexContext,
+    pepper_version: u32,
+) -> Result<IndexMac, Error> {
+    if context.pepper_version().is_some_and(|version| version != pepper_version) {
+        return Err(Error::IndexGenerationFailed(format!(
+            "Context is tagged with another pepper version than {pepper_version}"
+        )));
+    }
+
+    let pepper = provider.get_pepper_version(pepper_version)?.ok_or_else(|| {
+        Error::IndexGenerationFailed(format!("Pepper version {pepper_version} not available"))
+    })?;
+
+    let mut mac = IndexMac::new(context.algo(), pepper.expose_secret())?;
+
+    mac.update(&pepper_version.to_be_bytes());
+    mac.update(value);
+    mac.update(context.to_string().as_bytes());
+
+    Ok(mac)
+}
+
+/// Compares two blind indexes in constant time.
+///
+/// Equality lookups normally happen in the database, but any in-process
+/// comparison of indexes should use this rather than `==`, which can leak
+/// through timing how many leading bytes match. Only a length mismatch
+/// returns early.
+#[must_use]
+pub fn indexes_equal(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && bool::from(a.ct_eq(b))
+}
+
+/// Encodes a blind index as lowercase hex for storage in a text column.
+#[must_use]
+pub fn blind_index_to_hex(index: &[u8]) -> String {
+    hex::encode(index)
+}
+
+/// Decodes a hex-encoded blind index, checking it is [`BLIND_INDEX_SIZE`] bytes.
+///
+/// # Errors
+///
+/// Returns `Error::Decoding` if the input is not valid hex or has the wrong length.
+pub fn blind_index_from_hex(encoded: &str) -> Result<Vec<u8>, Error> {
+    let index = hex::decode(encoded).map_err(|e| Error::Decoding(format!("Invalid hex: {e}")))?;
+
+    if index.len() != BLIND_INDEX_SIZE {
+        return Err(Error::Decoding(format!(
+            "Blind index must be {BLIND_INDEX_SIZE} bytes, got {}",
+            index.len()
+        )));
+    }
+
+    Ok(index)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::error::KeyProviderError;
     use secrecy::SecretVec;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     // Mock key provider for testing
     struct MockKeyProvider {
         pepper: Option<SecretVec<u8>>,
+        /// The version 1 pepper once `pepper` was rotated to version 2
+        previous: Option<SecretVec<u8>>,
+        pepper_calls: AtomicUsize,
     }
 
     impl MockKeyProvider {
         fn with_pepper(pepper: Vec<u8>) -> Self {
-            Self { pepper: Some(SecretVec::new(pepper)) }
+            Self {
+                pepper: Some(SecretVec::new(pepper)),
+                previous: None,
+                pepper_calls: AtomicUsize::new(0),
+            }
         }
 
         fn without_pepper() -> Self {
-            Self { pepper: None }
+            Self { pepper: None, previous: None, pepper_calls: AtomicUsize::new(0) }
+        }
+
+        fn rotated(previous: Vec<u8>, pepper: Vec<u8>) -> Self {
+            Self { previous: Some(SecretVec::new(previous)), ..Self::with_pepper(pepper) }
         }
     }
 
@@ -160,8 +630,121 @@ mod tests {
         }
 
         fn get_pepper(&self) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+            self.pepper_calls.fetch_add(1, Ordering::SeqCst);
             Ok(self.pepper.as_ref().map(|p| SecretVec::new(p.expose_secret().clone())))
         }
+
+        fn current_pepper_version(&self) -> Result<u32, KeyProviderError> {
+            Ok(if self.previous.is_some() { 2 } else { 1 })
+        }
+
+        fn get_pepper_version(
+            &self,
+            version: u32,
+        ) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+            match (version, &self.previous) {
+                (1, Some(previous)) => Ok(Some(SecretVec::new(previous.expose_secret().clone()))),
+                (v, _) if v == self.current_pepper_version()? => self.get_pepper(),
+                _ => Ok(None),
+            }
+        }
+    }
+
+    #[test]
+    fn test_blind_index_algorithms_differ() {
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let sha256 = IndexContext::new("users", "email");
+        let sha512_256 =
+            IndexContext::new("users", "email").with_algo(BlindIndexAlgo::HmacSha512_256);
+        let value = b"alice@example.com";
+
+        let index_sha256 = generate_blind_index(&provider, value, &sha256).unwrap();
+        let index_sha512_256 = generate_blind_index(&provider, value, &sha512_256).unwrap();
+
+        assert_ne!(index_sha256, index_sha512_256);
+        assert_eq!(index_sha512_256.len(), BLIND_INDEX_SIZE);
+        assert_eq!(index_sha512_256, generate_blind_index(&provider, value, &sha512_256).unwrap());
+        assert_eq!(
+            generate_blind_indexes(&provider, &[value], &sha512_256).unwrap(),
+            vec![index_sha512_256]
+        );
+    }
+
+    #[test]
+    fn test_blind_index_default_algo_is_sha256() {
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let context = IndexContext::new("users", "email");
+        assert_eq!(context.algo(), BlindIndexAlgo::HmacSha256);
+
+        let mut mac = HmacSha256::new_from_slice(&[42u8; 32]).unwrap();
+        mac.update(b"alice@example.com");
+        mac.update(b"default|users|email");
+        let expected = mac.finalize().into_bytes()[..BLIND_INDEX_SIZE].to_vec();
+
+        assert_eq!(
+            generate_blind_index(&provider, b"alice@example.com", &context).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_blind_index_pepper_version_in_context() {
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let value = b"alice@example.com";
+        let unversioned = IndexContext::new("users", "email");
+        let v1 = IndexContext::new("users", "email").with_pepper_version(1);
+
+        let index = generate_blind_index(&provider, value, &unversioned).unwrap();
+        let index_v1 = generate_blind_index(&provider, value, &v1).unwrap();
+        assert_ne!(index, index_v1);
+        assert_eq!(index_v1, generate_blind_index(&provider, value, &v1).unwrap());
+
+        // A version the provider doesn't have is an error, not the current pepper
+        let v2 = IndexContext::new("users", "email").with_pepper_version(2);
+        let result = generate_blind_index(&provider, value, &v2);
+        assert!(matches!(result, Err(Error::IndexGenerationFailed(_))));
+        assert!(verify_index(&provider, value, &v2, &index_v1).is_err());
+    }
+
+    #[test]
+    fn test_blind_index_pepper_version_survives_rotation() {
+        let value = b"alice@example.com";
+        let v1 = IndexContext::new("users", "email").with_pepper_version(1);
+        let v2 = IndexContext::new("users", "email").with_pepper_version(2);
+        let before = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let index_v1 = generate_blind_index(&before, value, &v1).unwrap();
+
+        // After the rotation the v1 context still uses the v1 pepper
+        let after = MockKeyProvider::rotated(vec![42u8; 32], vec![43u8; 32]);
+        assert_eq!(generate_blind_index(&after, value, &v1).unwrap(), index_v1);
+        assert!(verify_index(&after, value, &v1, &index_v1).unwrap());
+        assert!(!verify_index(&after, value, &v2, &index_v1).unwrap());
+
+        // The v2 context is keyed by the new pepper
+        let index_v2 = generate_blind_index(&after, value, &v2).unwrap();
+        let mut mac = HmacSha256::new_from_slice(&[43u8; 32]).unwrap();
+        mac.update(value);
+        mac.update(b"default|users|email|pepper:v2");
+        assert_eq!(index_v2, mac.finalize().into_bytes()[..BLIND_INDEX_SIZE].to_vec());
+
+        // An explicit version must agree with the context's
+        let result = generate_blind_index_with_pepper_version(&after, value, &v1, 2);
+        assert!(matches!(result, Err(Error::IndexGenerationFailed(_))));
+        let versioned = generate_blind_index_with_pepper_version(&after, value, &v1, 1).unwrap();
+        assert!(verify_blind_index_versioned(&after, value, &v1, &versioned).unwrap());
+    }
+
+    #[test]
+    fn test_versioned_blind_index_honors_algo() {
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let sha256 = IndexContext::new("users", "email");
+        let sha512_256 =
+            IndexContext::new("users", "email").with_algo(BlindIndexAlgo::HmacSha512_256);
+
+        let index = generate_blind_index_versioned(&provider, b"alice", &sha512_256).unwrap();
+
+        assert!(verify_blind_index_versioned(&provider, b"alice", &sha512_256, &index).unwrap());
+        assert!(!verify_blind_index_versioned(&provider, b"alice", &sha256, &index).unwrap());
     }
 
     #[test]
@@ -265,4 +848,267 @@ mod tests {
         let index = generate_blind_index(&provider, &large_value, &context).unwrap();
         assert_eq!(index.len(), BLIND_INDEX_SIZE);
     }
+
+    #[test]
+    fn test_blind_index_hex_round_trip() {
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let context = IndexContext::new("users", "email");
+
+        let index = generate_blind_index(&provider, b"alice@example.com", &context).unwrap();
+        let encoded = blind_index_to_hex(&index);
+
+        assert_eq!(encoded.len(), BLIND_INDEX_SIZE * 2);
+        assert_eq!(blind_index_from_hex(&encoded).unwrap(), index);
+    }
+
+    #[test]
+    fn test_blind_index_from_hex_rejects_invalid() {
+        assert!(matches!(blind_index_from_hex("not hex"), Err(Error::Decoding(_))));
+
+        // Valid hex, wrong length
+        assert!(matches!(blind_index_from_hex("00ff"), Err(Error::Decoding(_))));
+    }
+
+    #[test]
+    fn test_blind_indexes_fetch_pepper_once() {
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let context = IndexContext::new("users", "email");
+        let owned: Vec<Vec<u8>> =
+            (0..10).map(|i| format!("user{i}@example.com").into_bytes()).collect();
+        let values: Vec<&[u8]> = owned.iter().map(Vec::as_slice).collect();
+
+        let indexes = generate_blind_indexes(&provider, &values, &context).unwrap();
+        assert_eq!(provider.pepper_calls.load(Ordering::SeqCst), 1);
+
+        // Same indexes, in input order, as the per-value function
+        assert_eq!(indexes.len(), values.len());
+        for (value, index) in values.iter().zip(&indexes) {
+            assert_eq!(index, &generate_blind_index(&provider, value, &context).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_blind_indexes_empty_and_no_pepper() {
+        let context = IndexContext::new("users", "email");
+
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        assert!(generate_blind_indexes(&provider, &[], &context).unwrap().is_empty());
+
+        let provider = MockKeyProvider::without_pepper();
+        let result = generate_blind_indexes(&provider, &[b"value"], &context);
+        assert!(matches!(result, Err(Error::IndexGenerationFailed(_))));
+    }
+
+    #[test]
+    fn test_verify_index() {
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let context = IndexContext::new("users", "email");
+        let index = generate_blind_index(&provider, b"alice@example.com", &context).unwrap();
+
+        assert!(verify_index(&provider, b"alice@example.com", &context, &index).unwrap());
+        assert!(!verify_index(&provider, b"bob@example.com", &context, &index).unwrap());
+
+        let other = IndexContext::new("users", "phone");
+        assert!(!verify_index(&provider, b"alice@example.com", &other, &index).unwrap());
+
+        let mut corrupted = index;
+        corrupted[0] ^= 1;
+        assert!(!verify_index(&provider, b"alice@example.com", &context, &corrupted).unwrap());
+    }
+
+    #[test]
+    fn test_verify_index_lengths() {
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let context = IndexContext::new("users", "email");
+        let index = generate_blind_index(&provider, b"alice@example.com", &context).unwrap();
+
+        // A truncated index is checked against its own length
+        assert!(verify_index(&provider, b"alice@example.com", &context, &index[..8]).unwrap());
+
+        let mut longer = index;
+        longer.push(0);
+        assert!(!verify_index(&provider, b"alice@example.com", &context, &longer).unwrap());
+        assert!(!verify_index(&provider, b"alice@example.com", &context, &[]).unwrap());
+    }
+
+    #[test]
+    fn test_verify_index_no_pepper() {
+        let provider = MockKeyProvider::without_pepper();
+        let context = IndexContext::new("users", "email");
+
+        let result = verify_index(&provider, b"alice@example.com", &context, &[0; 16]);
+        assert!(matches!(result, Err(Error::IndexGenerationFailed(_))));
+    }
+
+    #[test]
+    fn test_prefix_indexes_match_range_query() {
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let context = IndexContext::new("users", "age");
+        let stored = |age: &[u8]| generate_prefix_indexes(&provider, age, &context, &[1, 2, 3]);
+
+        // Ages 30 to 47 are the prefix "03" plus "040" to "047"
+        let mut query = generate_prefix_indexes(&provider, b"03", &context, &[2]).unwrap();
+        for age in 40..=47 {
+            let age = format!("{age:03}");
+            query.extend(
+                generate_prefix_indexes(&provider, age.as_bytes(), &context, &[3]).unwrap(),
+            );
+        }
+        let in_range = |age: &[u8]| stored(age).unwrap().iter().any(|index| query.contains(index));
+
+        assert!(in_range(b"030"));
+        assert!(in_range(b"037"));
+        assert!(in_range(b"047"));
+        assert!(!in_range(b"029"));
+        assert!(!in_range(b"048"));
+        assert!(!in_range(b"130"));
+    }
+
+    #[test]
+    fn test_prefix_indexes_are_domain_separated() {
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let context = IndexContext::new("users", "age");
+
+        let indexes = generate_prefix_indexes(&provider, b"037", &context, &[1, 2, 3]).unwrap();
+        assert_eq!(indexes.len(), 3);
+        assert!(indexes.iter().all(|index| index.len() == BLIND_INDEX_SIZE));
+
+        // The full-length prefix is not the equality index
+        let equality = generate_blind_index(&provider, b"037", &context).unwrap();
+        assert_ne!(indexes[2], equality);
+
+        let other = IndexContext::new("users", "zip");
+        let other_indexes = generate_prefix_indexes(&provider, b"037", &other, &[1, 2, 3]).unwrap();
+        assert!(indexes.iter().all(|index| !other_indexes.contains(index)));
+    }
+
+    #[test]
+    fn test_prefix_indexes_reject_bad_lengths() {
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let context = IndexContext::new("users", "age");
+
+        for lengths in [&[0, 1][..], &[2, 1], &[1, 1], &[1, 4]] {
+            let result = generate_prefix_indexes(&provider, b"037", &context, lengths);
+            assert!(matches!(result, Err(Error::IndexGenerationFailed(_))));
+        }
+        assert!(generate_prefix_indexes(&provider, b"037", &context, &[]).unwrap().is_empty());
+
+        let provider = MockKeyProvider::without_pepper();
+        let result = generate_prefix_indexes(&provider, b"037", &context, &[1]);
+        assert!(matches!(result, Err(Error::IndexGenerationFailed(_))));
+    }
+
+    #[test]
+    fn test_indexer_empty_domain_matches_free_functions() {
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let indexer = BlindIndexer::new(MockKeyProvider::with_pepper(vec![42u8; 32]));
+        let context = IndexContext::new("users", "email");
+
+        let index = generate_blind_index(&provider, b"alice@example.com", &context).unwrap();
+        assert!(indexer.domain().is_empty());
+        assert_eq!(indexer.index(b"alice@example.com", &context).unwrap(), index);
+        assert_eq!(
+            indexer.indexes(&[b"alice@example.com"], &context).unwrap(),
+            generate_blind_indexes(&provider, &[b"alice@example.com"], &context).unwrap()
+        );
+        assert!(indexer.verify(b"alice@example.com", &context, &index).unwrap());
+    }
+
+    #[test]
+    fn test_indexer_domains_separate_indexes() {
+        let context = IndexContext::new("users", "email");
+        let billing =
+            BlindIndexer::new(MockKeyProvider::with_pepper(vec![42u8; 32])).with_domain("billing");
+        let support =
+            BlindIndexer::new(MockKeyProvider::with_pepper(vec![42u8; 32])).with_domain("support");
+        let default = BlindIndexer::new(MockKeyProvider::with_pepper(vec![42u8; 32]));
+
+        let index = billing.index(b"alice@example.com", &context).unwrap();
+        assert_eq!(index.len(), BLIND_INDEX_SIZE);
+        assert_eq!(index, billing.index(b"alice@example.com", &context).unwrap());
+        assert_ne!(index, support.index(b"alice@example.com", &context).unwrap());
+        assert_ne!(index, default.index(b"alice@example.com", &context).unwrap());
+
+        // Verification and batches stay within the domain
+        assert!(billing.verify(b"alice@example.com", &context, &index).unwrap());
+        assert!(!support.verify(b"alice@example.com", &context, &index).unwrap());
+        assert_eq!(billing.indexes(&[b"alice@example.com"], &context).unwrap(), vec![index]);
+        assert_eq!(billing.provider().pepper_calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_indexer_no_pepper() {
+        let indexer = BlindIndexer::new(MockKeyProvider::without_pepper()).with_domain("billing");
+        let context = IndexContext::new("users", "email");
+
+        let result = indexer.index(b"alice@example.com", &context);
+        assert!(matches!(result, Err(Error::IndexGenerationFailed(_))));
+    }
+
+    #[test]
+    fn test_indexes_equal() {
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let context = IndexContext::new("users", "email");
+
+        let index1 = generate_blind_index(&provider, b"alice@example.com", &context).unwrap();
+        let index2 = generate_blind_index(&provider, b"alice@example.com", &context).unwrap();
+        assert!(indexes_equal(&index1, &index2));
+    }
+
+    #[test]
+    fn test_indexes_equal_same_length_mismatch() {
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let context = IndexContext::new("users", "email");
+
+        let index1 = generate_blind_index(&provider, b"alice@example.com", &context).unwrap();
+        let index2 = generate_blind_index(&provider, b"bob@example.com", &context).unwrap();
+        assert_eq!(index1.len(), index2.len());
+        assert!(!indexes_equal(&index1, &index2));
+    }
+
+    #[test]
+    fn test_indexes_equal_length_mismatch() {
+        let index = vec![7u8; BLIND_INDEX_SIZE];
+        assert!(!indexes_equal(&index, &index[..BLIND_INDEX_SIZE - 1]));
+        assert!(!indexes_equal(&index, &[]));
+        assert!(indexes_equal(&[], &[]));
+    }
+
+    #[test]
+    fn test_versioned_index_default_version() {
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let context = IndexContext::new("users", "email");
+
+        let versioned =
+            generate_blind_index_versioned(&provider, b"alice@example.com", &context).unwrap();
+        assert_eq!(versioned.pepper_version, 1);
+        assert_eq!(versioned.index.len(), BLIND_INDEX_SIZE);
+
+        // The version tag keeps it distinct from the unversioned index
+        let plain = generate_blind_index(&provider, b"alice@example.com", &context).unwrap();
+        assert_ne!(versioned.index, plain);
+
+        assert!(verify_blind_index_versioned(
+            &provider,
+            b"alice@example.com",
+            &context,
+            &versioned
+        )
+        .unwrap());
+        assert!(!verify_blind_index_versioned(&provider, b"bob@example.com", &context, &versioned)
+            .unwrap());
+
+        let parsed = VersionedBlindIndex::from_bytes(&versioned.to_bytes()).unwrap();
+        assert_eq!(parsed, versioned);
+    }
+
+    #[test]
+    fn test_versioned_index_unknown_version() {
+        let provider = MockKeyProvider::with_pepper(vec![42u8; 32]);
+        let context = IndexContext::new("users", "email");
+
+        let result = generate_blind_index_with_pepper_version(&provider, b"x", &context, 2);
+        assert!(matches!(result, Err(Error::IndexGenerationFailed(_))));
+        assert!(matches!(provider.rotate_pepper(), Err(KeyProviderError::Unsupported(_))));
+    }
 }
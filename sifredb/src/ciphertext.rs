@@ -0,0 +1,401 @@
+//! Typed wrapper for encrypted blobs.
+
+use crate::error::Error;
+use crate::header::{ByteCount, EncryptionHeader};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use std::fmt;
+
+/// An encrypted value: a parsed [`EncryptionHeader`] followed by the AEAD payload.
+///
+/// Constructing a `Ciphertext` always validates that the header parses, so a
+/// `Ciphertext` can't be confused with plaintext or an arbitrary byte buffer.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use sifredb::ciphertext::Ciphertext;
+///
+/// // Bytes loaded from the database
+/// let ciphertext = Ciphertext::from_bytes(row.get("email"))?;
+/// println!("encrypted under {}", ciphertext.kek_id());
+///
+/// let plaintext = vault.decrypt(&ciphertext, &context)?;
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct Ciphertext {
+    bytes: Vec<u8>,
+    header: EncryptionHeader,
+    header_len: usize,
+}
+
+impl Ciphertext {
+    /// Wraps raw ciphertext bytes, validating the header.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the bytes don't start with a valid header, or if the
+    /// header records a payload length that doesn't match the bytes.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, Error> {
+        let (header, header_len) = EncryptionHeader::from_bytes(&bytes)?;
+        if header_len > bytes.len() {
+            return Err(Error::InvalidHeader("Header overruns ciphertext".to_string()));
+        }
+        check_payload_len(&header, bytes.len() - header_len)?;
+        Ok(Self { bytes, header, header_len })
+    }
+
+    /// Reads one ciphertext from the front of `data`, returning it and the
+    /// bytes that follow.
+    ///
+    /// For blobs holding several ciphertexts back to back. Each record's
+    /// extent comes from the payload length in its header; a record written
+    /// before payload lengths were recorded can only be the last one, as its
+    /// payload is taken to run to the end of `data`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if `data` doesn't start with a valid header or is
+    /// shorter than the recorded payload length.
+    pub fn parse_one(data: &[u8]) -> Result<(Self, &[u8]), Error> {
+        let (header, header_len) = EncryptionHeader::from_bytes(data)?;
+        let end = payload_end(&header, header_len, data.len())?;
+
+        let (record, rest) = data.split_at(end);
+        Ok((Self { bytes: record.to_vec(), header, header_len }, rest))
+    }
+
+    /// Splits back-to-back ciphertexts, yielding each in order.
+    ///
+    /// Iteration stops after the first error, as the records after a
+    /// malformed one can't be located.
+    pub fn parse_all(data: &[u8]) -> impl Iterator<Item = Result<Self, Error>> + '_ {
+        let mut rest = Some(data);
+        core::iter::from_fn(move || {
+            let data = rest.take().filter(|data| !data.is_empty())?;
+            match Self::parse_one(data) {
+                Ok((ciphertext, remaining)) => {
+                    rest = Some(remaining);
+                    Some(Ok(ciphertext))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        })
+    }
+
+    /// Assembles a ciphertext by serializing `header` straight into a buffer
+    /// sized for the header and payload.
+    pub(crate) fn from_parts(header: EncryptionHeader, payload: &[u8]) -> Result<Self, Error> {
+        let mut bytes = Vec::with_capacity(header.encoded_len() + payload.len());
+        header.write_to(&mut bytes)?;
+        let header_len = bytes.len();
+        bytes.extend_from_slice(payload);
+        Ok(Self { bytes, header, header_len })
+    }
+
+    /// Returns the full serialized ciphertext (header and payload).
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Consumes the ciphertext and returns its serialized bytes.
+    #[must_use]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Returns the parsed header.
+    #[must_use]
+    pub const fn header(&self) -> &EncryptionHeader {
+        &self.header
+    }
+
+    /// Returns the identifier of the KEK that wraps this ciphertext's DEK.
+    #[must_use]
+    pub fn kek_id(&self) -> &str {
+        self.header.kek_id()
+    }
+
+    /// Returns the header's protocol version.
+    #[must_use]
+    pub const fn version(&self) -> u8 {
+        self.header.version()
+    }
+
+    /// Encodes the ciphertext as standard (padded) base64 for text columns.
+    #[must_use]
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(&self.bytes)
+    }
+
+    /// Decodes base64 produced by [`Ciphertext::to_base64`], validating the header.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Decoding` if the input is not valid base64, or a header
+    /// error if the decoded bytes aren't a ciphertext.
+    pub fn from_base64(encoded: &str) -> Result<Self, Error> {
+        let bytes = STANDARD
+            .decode(encoded)
+            .map_err(|e| Error::Decoding(format!("Invalid base64: {e}")))?;
+        Self::from_bytes(bytes)
+    }
+
+    /// Encodes the ciphertext as lowercase hex.
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.bytes)
+    }
+
+    /// Decodes hex produced by [`Ciphertext::to_hex`], validating the header.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Decoding` if the input is not valid hex, or a header
+    /// error if the decoded bytes aren't a ciphertext.
+    pub fn from_hex(encoded: &str) -> Result<Self, Error> {
+        let bytes =
+            hex::decode(encoded).map_err(|e| Error::Decoding(format!("Invalid hex: {e}")))?;
+        Self::from_bytes(bytes)
+    }
+
+    /// Returns the encrypted payload following the header.
+    pub(crate) fn payload(&self) -> &[u8] {
+        &self.bytes[self.header_len..]
+    }
+
+    /// Consumes the ciphertext and splits it into serialized header and payload.
+    pub(crate) fn into_parts(self) -> (Vec<u8>, Vec<u8>) {
+        let mut header_bytes = self.bytes;
+        let payload = header_bytes.split_off(self.header_len);
+        (header_bytes, payload)
+    }
+}
+
+impl fmt::Debug for Ciphertext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ciphertext")
+            .field("header", &self.header)
+            .field("payload", &ByteCount(self.bytes.len() - self.header_len))
+            .finish()
+    }
+}
+
+impl AsRef<[u8]> for Ciphertext {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl TryFrom<Vec<u8>> for Ciphertext {
+    type Error = Error;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for Ciphertext {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_bytes(bytes.to_vec())
+    }
+}
+
+impl From<Ciphertext> for Vec<u8> {
+    fn from(ciphertext: Ciphertext) -> Self {
+        ciphertext.bytes
+    }
+}
+
+/// Returns where the payload that follows a `header_len`-byte header ends
+/// within `data_len` bytes.
+///
+/// Bounded by the recorded payload length when the header has one, otherwise
+/// the payload runs to the end of the data.
+pub(crate) fn payload_end(
+    header: &EncryptionHeader,
+    header_len: usize,
+    data_len: usize,
+) -> Result<usize, Error> {
+    if header_len > data_len {
+        return Err(Error::InvalidHeader("Header overruns ciphertext".to_string()));
+    }
+    let Some(len) = header.payload_len() else {
+        return Ok(data_len);
+    };
+    usize::try_from(len)
+        .ok()
+        .and_then(|len| header_len.checked_add(len))
+        .filter(|&end| end <= data_len)
+        .ok_or_else(|| {
+            Error::InvalidHeader(format!(
+                "Payload truncated: declared {len} bytes, {} available",
+                data_len - header_len
+            ))
+        })
+}
+
+/// Checks a payload against the length recorded in its header, if any.
+pub(crate) fn check_payload_len(
+    header: &EncryptionHeader,
+    payload_len: usize,
+) -> Result<(), Error> {
+    match header.payload_len() {
+        Some(expected) if usize::try_from(expected).ok() != Some(payload_len) => {
+            Err(Error::InvalidHeader(format!(
+                "Payload length mismatch: header records {expected} bytes, found {payload_len}"
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::{HeaderFlags, PROTOCOL_VERSION};
+
+    fn sample_bytes() -> Vec<u8> {
+        let header =
+            EncryptionHeader::new("kek_v1", vec![1, 2, 3, 4], HeaderFlags::empty(), vec![7; 12]);
+        let mut bytes = header.to_bytes().unwrap();
+        bytes.extend_from_slice(b"payload");
+        bytes
+    }
+
+    #[test]
+    fn test_from_bytes_exposes_header() {
+        let bytes = sample_bytes();
+        let ciphertext = Ciphertext::from_bytes(bytes.clone()).unwrap();
+
+        assert_eq!(ciphertext.kek_id(), "kek_v1");
+        assert_eq!(ciphertext.version(), PROTOCOL_VERSION);
+        assert_eq!(ciphertext.header().wrapped_dek(), &[1, 2, 3, 4]);
+        assert_eq!(ciphertext.header().nonce(), &[7; 12]);
+        assert_eq!(ciphertext.payload(), b"payload");
+        assert_eq!(ciphertext.as_bytes(), bytes.as_slice());
+        assert_eq!(ciphertext.into_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_from_bytes_rejects_malformed() {
+        assert!(matches!(Ciphertext::from_bytes(Vec::new()), Err(Error::InvalidHeader(_))));
+        assert!(matches!(
+            Ciphertext::from_bytes(b"alice@example.com".to_vec()),
+            Err(Error::UnsupportedVersion { .. })
+        ));
+
+        // Truncated inside the header
+        let bytes = sample_bytes();
+        assert!(Ciphertext::try_from(bytes[..5].to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_debug_redacts_bytes() {
+        let ciphertext = Ciphertext::from_bytes(sample_bytes()).unwrap();
+
+        let debug = format!("{ciphertext:?}");
+        assert!(debug.contains("kek_v1"));
+        assert!(debug.contains("payload: [7 bytes]"));
+        assert!(!debug.contains("1, 2, 3, 4"));
+    }
+
+    #[test]
+    fn test_base64_round_trip() {
+        let ciphertext = Ciphertext::from_bytes(sample_bytes()).unwrap();
+
+        let encoded = ciphertext.to_base64();
+        assert_eq!(Ciphertext::from_base64(&encoded).unwrap(), ciphertext);
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        let ciphertext = Ciphertext::from_bytes(sample_bytes()).unwrap();
+
+        let encoded = ciphertext.to_hex();
+        assert!(encoded.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(Ciphertext::from_hex(&encoded).unwrap(), ciphertext);
+    }
+
+    #[test]
+    fn test_from_base64_rejects_malformed() {
+        assert!(matches!(Ciphertext::from_base64("not*base64!"), Err(Error::Decoding(_))));
+        assert!(matches!(Ciphertext::from_hex("zz"), Err(Error::Decoding(_))));
+
+        // Well-formed encoding of something that isn't a ciphertext
+        let encoded = STANDARD.encode(b"alice@example.com");
+        assert!(matches!(Ciphertext::from_base64(&encoded), Err(Error::UnsupportedVersion { .. })));
+    }
+
+    fn record(kek_id: &str, payload: &[u8]) -> Vec<u8> {
+        let header = EncryptionHeader::new(kek_id, vec![1; 4], HeaderFlags::empty(), vec![7; 12])
+            .with_payload_len(u32::try_from(payload.len()).unwrap());
+        let mut bytes = header.to_bytes().unwrap();
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    #[test]
+    fn test_parse_one_splits_concatenation() {
+        let records = [record("kek_a", b"first"), record("kek_b", b""), record("kek_c", b"third!")];
+        let packed = records.concat();
+
+        let (first, rest) = Ciphertext::parse_one(&packed).unwrap();
+        assert_eq!(first.kek_id(), "kek_a");
+        assert_eq!(first.payload(), b"first");
+        assert_eq!(first.as_bytes(), records[0].as_slice());
+
+        let (second, rest) = Ciphertext::parse_one(rest).unwrap();
+        assert_eq!(second.kek_id(), "kek_b");
+        assert_eq!(second.payload(), b"");
+
+        let (third, rest) = Ciphertext::parse_one(rest).unwrap();
+        assert_eq!(third.kek_id(), "kek_c");
+        assert_eq!(third.payload(), b"third!");
+        assert!(rest.is_empty());
+
+        let all: Vec<Ciphertext> =
+            Ciphertext::parse_all(&packed).collect::<Result<_, _>>().unwrap();
+        assert_eq!(all, [first, second, third]);
+        assert_eq!(Ciphertext::parse_all(&[]).count(), 0);
+    }
+
+    #[test]
+    fn test_parse_one_truncated_payload() {
+        let packed = [record("kek_a", b"first"), record("kek_b", b"second")].concat();
+
+        let result = Ciphertext::parse_one(&packed[..packed.len() - 1]);
+        assert!(result.is_ok());
+        let (_, rest) = result.unwrap();
+        assert!(matches!(Ciphertext::parse_one(rest), Err(Error::InvalidHeader(_))));
+
+        // The iterator reports the error once, then stops
+        let results: Vec<_> = Ciphertext::parse_all(&packed[..packed.len() - 1]).collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[1].is_err());
+    }
+
+    #[test]
+    fn test_parse_one_legacy_record_runs_to_end() {
+        let bytes = sample_bytes();
+        let (ciphertext, rest) = Ciphertext::parse_one(&bytes).unwrap();
+        assert_eq!(ciphertext.payload(), b"payload");
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_from_bytes_checks_payload_len() {
+        let bytes = record("kek_a", b"payload");
+        assert!(Ciphertext::try_from(bytes.as_slice()).is_ok());
+
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(matches!(Ciphertext::from_bytes(longer), Err(Error::InvalidHeader(_))));
+        assert!(matches!(
+            Ciphertext::try_from(&bytes[..bytes.len() - 1]),
+            Err(Error::InvalidHeader(_))
+        ));
+    }
+}
@@ -0,0 +1,221 @@
+//! Per-column encryption helper.
+//!
+//! An [`EncryptedColumn`] bundles the [`Vault`], [`EncryptionContext`] and
+//! (optionally) the [`IndexContext`] for one database column, so callers don't
+//! have to keep pairing them by hand.
+
+use crate::blind_index::generate_blind_index;
+use crate::ciphertext::Ciphertext;
+use crate::context::{EncryptionContext, IndexContext};
+use crate::error::Error;
+use crate::key_provider::KeyProvider;
+use crate::vault::Vault;
+use std::fmt;
+
+/// Encrypts and decrypts the values of a single column.
+///
+/// If the column is indexed, every encrypted value comes with a blind index
+/// computed under an [`IndexContext`] derived from the column's encryption
+/// context, so equality lookups can be made without decrypting.
+///
+/// # Example
+///
+/// ```ignore
+/// use sifredb::prelude::*;
+/// use sifredb_key_file::FileKeyProvider;
+///
+/// let vault = Vault::new(FileKeyProvider::new("./keys")?, CipherMode::default());
+/// let email = EncryptedColumn::builder(vault, EncryptionContext::new("users", "email"))
+///     .indexed()
+///     .build();
+///
+/// let (ciphertext, index) = email.encrypt_value(b"alice@example.com")?;
+/// let plaintext = email.decrypt_value(&ciphertext)?;
+/// ```
+pub struct EncryptedColumn<P: KeyProvider> {
+    vault: Vault<P>,
+    context: EncryptionContext,
+    index_context: Option<IndexContext>,
+}
+
+impl<P: KeyProvider> fmt::Debug for EncryptedColumn<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedColumn")
+            .field("vault", &self.vault)
+            .field("context", &self.context)
+            .field("index_context", &self.index_context)
+            .finish()
+    }
+}
+
+impl<P: KeyProvider> EncryptedColumn<P> {
+    /// Creates a column without a blind index.
+    #[must_use]
+    pub fn new(vault: Vault<P>, context: EncryptionContext) -> Self {
+        Self::builder(vault, context).build()
+    }
+
+    /// Returns a builder for a column encrypted by `vault` under `context`.
+    #[must_use]
+    pub const fn builder(vault: Vault<P>, context: EncryptionContext) -> EncryptedColumnBuilder<P> {
+        EncryptedColumnBuilder { vault, context, indexed: false }
+    }
+
+    /// Encrypts a value, returning the ciphertext and, for indexed columns, its blind index.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if encryption fails or the provider has no pepper for
+    /// the blind index.
+    pub fn encrypt_value(&self, plaintext: &[u8]) -> Result<(Ciphertext, Option<Vec<u8>>), Error> {
+        let index = self.blind_index(plaintext)?;
+        let ciphertext = self.vault.encrypt(plaintext, &self.context)?;
+        Ok((ciphertext, index))
+    }
+
+    /// Decrypts a value encrypted by this column.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the key provider fails or authentication fails.
+    pub fn decrypt_value(&self, ciphertext: &Ciphertext) -> Result<Vec<u8>, Error> {
+        self.vault.decrypt(ciphertext, &self.context)
+    }
+
+    /// Computes the blind index to search for `value`, or `None` if the column is not indexed.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the provider has no pepper.
+    pub fn blind_index(&self, value: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        self.index_context
+            .as_ref()
+            .map(|index_context| generate_blind_index(self.vault.provider(), value, index_context))
+            .transpose()
+    }
+
+    /// Returns the encryption context of this column.
+    #[must_use]
+    pub const fn context(&self) -> &EncryptionContext {
+        &self.context
+    }
+
+    /// Returns the index context, if the column is indexed.
+    #[must_use]
+    pub const fn index_context(&self) -> Option<&IndexContext> {
+        self.index_context.as_ref()
+    }
+
+    /// Returns the vault used by this column.
+    #[must_use]
+    pub const fn vault(&self) -> &Vault<P> {
+        &self.vault
+    }
+}
+
+/// Builder for an [`EncryptedColumn`], created with [`EncryptedColumn::builder`].
+pub struct EncryptedColumnBuilder<P: KeyProvider> {
+    vault: Vault<P>,
+    context: EncryptionContext,
+    indexed: bool,
+}
+
+impl<P: KeyProvider> EncryptedColumnBuilder<P> {
+    /// Generates a blind index for every encrypted value.
+    ///
+    /// The index context is derived from the encryption context, dropping
+    /// its version so indexes survive key rotation.
+    #[must_use]
+    pub const fn indexed(mut self) -> Self {
+        self.indexed = true;
+        self
+    }
+
+    /// Builds the configured column.
+    #[must_use]
+    pub fn build(self) -> EncryptedColumn<P> {
+        let index_context = self.indexed.then(|| IndexContext::from(&self.context));
+        EncryptedColumn { vault: self.vault, context: self.context, index_context }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::KeyProviderError;
+    use crate::vault::CipherMode;
+    use secrecy::SecretVec;
+
+    struct PepperProvider;
+
+    // Identity DEK wrapping, for testing only.
+    impl KeyProvider for PepperProvider {
+        fn create_kek(&self) -> Result<String, KeyProviderError> {
+            Ok("test_kek".to_string())
+        }
+
+        fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+            Ok("test_kek".to_string())
+        }
+
+        fn wrap_dek(&self, _kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+            Ok(dek.to_vec())
+        }
+
+        fn unwrap_dek(
+            &self,
+            _kek_id: &str,
+            wrapped_dek: &[u8],
+        ) -> Result<SecretVec<u8>, KeyProviderError> {
+            Ok(SecretVec::new(wrapped_dek.to_vec()))
+        }
+
+        fn get_pepper(&self) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+            Ok(Some(SecretVec::new(vec![0x24; 32])))
+        }
+    }
+
+    fn context() -> EncryptionContext {
+        EncryptionContext::new("users", "email").with_tenant("tenant_a").with_version(3)
+    }
+
+    #[test]
+    fn test_indexed_column_round_trip() {
+        let vault = Vault::new(PepperProvider, CipherMode::default());
+        let column = EncryptedColumn::builder(vault, context()).indexed().build();
+
+        let (ciphertext, index) = column.encrypt_value(b"alice@example.com").unwrap();
+        assert_eq!(column.decrypt_value(&ciphertext).unwrap(), b"alice@example.com");
+
+        let expected = generate_blind_index(
+            &PepperProvider,
+            b"alice@example.com",
+            &IndexContext::new("users", "email").with_tenant("tenant_a"),
+        )
+        .unwrap();
+        assert_eq!(index, Some(expected.clone()));
+        assert_eq!(column.blind_index(b"alice@example.com").unwrap(), Some(expected));
+    }
+
+    #[test]
+    fn test_unindexed_column() {
+        let column =
+            EncryptedColumn::new(Vault::new(PepperProvider, CipherMode::default()), context());
+
+        let (ciphertext, index) = column.encrypt_value(b"alice@example.com").unwrap();
+        assert!(index.is_none());
+        assert!(column.index_context().is_none());
+        assert_eq!(column.decrypt_value(&ciphertext).unwrap(), b"alice@example.com");
+    }
+
+    #[test]
+    fn test_column_rejects_other_context() {
+        let vault = Vault::new(PepperProvider, CipherMode::default());
+        let email = EncryptedColumn::new(vault, context());
+        let (ciphertext, _) = email.encrypt_value(b"alice@example.com").unwrap();
+
+        let other =
+            EncryptionContext::new("users", "phone").with_tenant("tenant_a").with_version(3);
+        assert!(email.vault().decrypt(&ciphertext, &other).is_err());
+    }
+}
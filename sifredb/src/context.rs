@@ -1,6 +1,12 @@
 //! Context types for encryption and indexing operations.
 
-use std::fmt;
+use crate::blind_index::BlindIndexAlgo;
+use crate::error::Error;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Write as _};
 
 /// Context for encryption operations, used for key derivation and domain separation.
 ///
@@ -8,6 +14,7 @@ use std::fmt;
 /// - Different tenants produce different ciphertexts
 /// - Different tables/columns produce different ciphertexts
 /// - Key rotation is supported via versioning
+/// - Extra attributes (e.g. a purpose or partition key) separate domains further
 ///
 /// # Example
 ///
@@ -16,7 +23,8 @@ use std::fmt;
 ///
 /// let ctx = EncryptionContext::new("users", "email")
 ///     .with_tenant("tenant_123")
-///     .with_version(1);
+///     .with_version(1)
+///     .with_attribute("purpose", "billing");
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct EncryptionContext {
@@ -24,32 +32,87 @@ pub struct EncryptionContext {
     table_name: String,
     column_name: String,
     version: u32,
+    schema_fingerprint: Option<[u8; 8]>,
+    attributes: BTreeMap<String, String>,
 }
 
 impl EncryptionContext {
     /// Creates a new encryption context.
     ///
+    /// Names must be non-empty and must not contain `|`, the separator of the
+    /// canonical encoding. Debug builds panic on invalid names; release builds
+    /// accept them. Use [`EncryptionContext::try_new`] for names that come
+    /// from configuration or a schema mapping.
+    ///
     /// # Arguments
     ///
     /// * `table_name` - Database table name
     /// * `column_name` - Database column name
     #[must_use]
     pub fn new(table_name: impl Into<String>, column_name: impl Into<String>) -> Self {
+        let table_name = table_name.into();
+        let column_name = column_name.into();
+        debug_assert!(
+            check_name("table", &table_name).is_ok() && check_name("column", &column_name).is_ok(),
+            "invalid encryption context names: table {table_name:?}, column {column_name:?}"
+        );
+
         Self {
             tenant_id: None,
-            table_name: table_name.into(),
-            column_name: column_name.into(),
+            table_name,
+            column_name,
             version: 1,
+            schema_fingerprint: None,
+            attributes: BTreeMap::new(),
         }
     }
 
+    /// Creates a new encryption context, rejecting invalid names.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidContext` if `table_name` or `column_name` is
+    /// empty or contains `|`.
+    pub fn try_new(
+        table_name: impl Into<String>,
+        column_name: impl Into<String>,
+    ) -> Result<Self, Error> {
+        let table_name = table_name.into();
+        let column_name = column_name.into();
+        check_name("table", &table_name)?;
+        check_name("column", &column_name)?;
+
+        Ok(Self::new(table_name, column_name))
+    }
+
     /// Sets the tenant ID for multi-tenant applications.
+    ///
+    /// Like table and column names, the tenant must be non-empty and must not
+    /// contain `|`. Debug builds panic on an invalid tenant; use
+    /// [`EncryptionContext::try_with_tenant`] for tenants that come from
+    /// requests or configuration.
     #[must_use]
     pub fn with_tenant(mut self, tenant_id: impl Into<String>) -> Self {
-        self.tenant_id = Some(tenant_id.into());
+        let tenant_id = tenant_id.into();
+        debug_assert!(
+            check_name("tenant", &tenant_id).is_ok(),
+            "invalid encryption context tenant {tenant_id:?}"
+        );
+        self.tenant_id = Some(tenant_id);
         self
     }
 
+    /// Sets the tenant ID, rejecting an invalid tenant.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidContext` if `tenant_id` is empty or contains `|`.
+    pub fn try_with_tenant(self, tenant_id: impl Into<String>) -> Result<Self, Error> {
+        let tenant_id = tenant_id.into();
+        check_name("tenant", &tenant_id)?;
+        Ok(self.with_tenant(tenant_id))
+    }
+
     /// Sets the version for key rotation support.
     #[must_use]
     pub const fn with_version(mut self, version: u32) -> Self {
@@ -57,6 +120,31 @@ impl EncryptionContext {
         self
     }
 
+    /// Binds the context to a fingerprint of the column's schema.
+    ///
+    /// The fingerprint is part of the key derivation and AAD, so ciphertext
+    /// fed to decryption configured with a different fingerprint fails with
+    /// `Error::AuthenticationFailed` instead of decrypting under the wrong
+    /// schema (e.g. after a migration renamed columns but data moved).
+    /// Computing it is up to the caller, e.g. a truncated hash of the column DDL.
+    #[must_use]
+    pub const fn with_schema_fingerprint(mut self, fingerprint: [u8; 8]) -> Self {
+        self.schema_fingerprint = Some(fingerprint);
+        self
+    }
+
+    /// Adds an extra attribute for finer domain separation, replacing any
+    /// previous value for `key`.
+    ///
+    /// Attributes are part of the key derivation and AAD, so data encrypted
+    /// with an attribute only decrypts with the same attribute. Insertion
+    /// order doesn't matter.
+    #[must_use]
+    pub fn with_attribute(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.insert(key.into(), value.into());
+        self
+    }
+
     /// Returns the tenant ID, if set.
     #[must_use]
     pub fn tenant_id(&self) -> Option<&str> {
@@ -75,11 +163,152 @@ impl EncryptionContext {
         &self.column_name
     }
 
+    /// Returns the schema fingerprint, if set.
+    #[must_use]
+    pub const fn schema_fingerprint(&self) -> Option<[u8; 8]> {
+        self.schema_fingerprint
+    }
+
+    /// Returns the value of an extra attribute, if set.
+    #[must_use]
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(String::as_str)
+    }
+
+    /// Returns the extra attributes, sorted by key.
+    pub fn attributes(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.attributes.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
     /// Returns the version.
     #[must_use]
     pub const fn version(&self) -> u32 {
         self.version
     }
+
+    /// Returns the canonical byte encoding used for key derivation and as AEAD
+    /// associated data (`tenant|table|column|vN`).
+    ///
+    /// A schema fingerprint follows as `|schema={hex}`, then extra attributes
+    /// in key order as `|{len}:{key}={len}:{value}`; the lengths keep
+    /// attributes containing `|` or `=` unambiguous. Without either the
+    /// encoding is exactly the `Display` output.
+    #[must_use]
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.to_string();
+        if let Some(fingerprint) = self.schema_fingerprint {
+            bytes.push_str("|schema=");
+            bytes.push_str(&hex::encode(fingerprint));
+        }
+        for (key, value) in &self.attributes {
+            // Writing to a String can't fail
+            let _ = write!(bytes, "|{}:{key}={}:{value}", key.len(), value.len());
+        }
+        bytes.into_bytes()
+    }
+
+    /// Parses a context from its canonical encoding, the inverse of
+    /// [`EncryptionContext::canonical_bytes`] and of `Display`.
+    ///
+    /// Accepts `tenant|table|column|vN` with the optional schema fingerprint
+    /// and attributes that follow it. The tenant `default` parses as no
+    /// tenant, as both encode the same. Attribute keys and values are read by
+    /// their length prefixes, so they may contain `|` or `=`; tenants can't
+    /// contain `|` (see [`EncryptionContext::try_with_tenant`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidContext` if `canonical` is malformed or not in
+    /// canonical form, e.g. a version with leading zeros, an uppercase
+    /// fingerprint, or attributes out of key order, as those would encode
+    /// differently from the input.
+    pub fn from_canonical(canonical: &str) -> Result<Self, Error> {
+        let invalid = |what: &str| Error::InvalidContext(format!("{what} in {canonical:?}"));
+
+        let mut fields = canonical.splitn(5, '|');
+        let (Some(tenant), Some(table), Some(column), Some(version)) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return Err(invalid("missing fields"));
+        };
+        let version = version
+            .strip_prefix('v')
+            .and_then(|digits| digits.parse().ok())
+            .ok_or_else(|| invalid("invalid version"))?;
+
+        let mut context = Self::try_new(table, column)?.with_version(version);
+        if tenant != "default" {
+            context = context.try_with_tenant(tenant)?;
+        }
+
+        // Everything after the version: `schema={hex}` and attributes, each
+        // preceded by `|`, which `splitn` already consumed for the first
+        let mut rest = fields.next();
+        if let Some(hex) = rest.and_then(|rest| rest.strip_prefix("schema=")) {
+            let mut fingerprint = [0u8; 8];
+            hex.get(..16)
+                .and_then(|digits| hex::decode_to_slice(digits, &mut fingerprint).ok())
+                .ok_or_else(|| invalid("invalid schema fingerprint"))?;
+            context = context.with_schema_fingerprint(fingerprint);
+            rest = next_segment(&hex[16..]).map_err(|()| invalid("trailing data"))?;
+        }
+        while let Some(mut attribute) = rest {
+            let key =
+                take_len_prefixed(&mut attribute).ok_or_else(|| invalid("invalid attribute"))?;
+            attribute = attribute.strip_prefix('=').ok_or_else(|| invalid("invalid attribute"))?;
+            let value =
+                take_len_prefixed(&mut attribute).ok_or_else(|| invalid("invalid attribute"))?;
+            context = context.with_attribute(key, value);
+            rest = next_segment(attribute).map_err(|()| invalid("trailing data"))?;
+        }
+
+        // Anything accepted above but spelled differently would not round-trip
+        if context.canonical_bytes() != canonical.as_bytes() {
+            return Err(invalid("non-canonical encoding"));
+        }
+        Ok(context)
+    }
+}
+
+impl TryFrom<&str> for EncryptionContext {
+    type Error = Error;
+
+    /// Parses a canonical context string; see [`EncryptionContext::from_canonical`].
+    fn try_from(canonical: &str) -> Result<Self, Self::Error> {
+        Self::from_canonical(canonical)
+    }
+}
+
+/// Returns the segment after the leading `|` of `rest`, or `None` at the
+/// end of the input.
+fn next_segment(rest: &str) -> Result<Option<&str>, ()> {
+    if rest.is_empty() {
+        return Ok(None);
+    }
+    rest.strip_prefix('|').map(Some).ok_or(())
+}
+
+/// Splits a `{len}:{data}` field off the front of `rest`.
+fn take_len_prefixed<'a>(rest: &mut &'a str) -> Option<&'a str> {
+    let (len, tail) = rest.split_once(':')?;
+    if len.is_empty() || !len.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let len: usize = len.parse().ok()?;
+    let data = tail.get(..len)?;
+    *rest = &tail[len..];
+    Some(data)
+}
+
+/// Checks that a table or column name is non-empty and free of `|`.
+fn check_name(kind: &str, name: &str) -> Result<(), Error> {
+    if name.is_empty() {
+        return Err(Error::InvalidContext(format!("{kind} name is empty")));
+    }
+    if name.contains('|') {
+        return Err(Error::InvalidContext(format!("{kind} name {name:?} contains '|'")));
+    }
+    Ok(())
 }
 
 impl fmt::Display for EncryptionContext {
@@ -97,25 +326,81 @@ impl fmt::Display for EncryptionContext {
 
 /// Context for blind index generation.
 ///
-/// Similar to `EncryptionContext` but without versioning (indexes are immutable).
+/// Similar to `EncryptionContext` but without a schema version: an index
+/// depends only on the value, the pepper and this context. Pepper rotation
+/// is the exception, so a context can carry an optional pepper version (see
+/// [`IndexContext::with_pepper_version`]) to keep indexes computed under
+/// different peppers apart.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IndexContext {
     tenant_id: Option<String>,
     table_name: String,
     column_name: String,
+    algo: BlindIndexAlgo,
+    pepper_version: Option<u32>,
 }
 
 impl IndexContext {
     /// Creates a new index context.
     #[must_use]
     pub fn new(table_name: impl Into<String>, column_name: impl Into<String>) -> Self {
-        Self { tenant_id: None, table_name: table_name.into(), column_name: column_name.into() }
+        Self {
+            tenant_id: None,
+            table_name: table_name.into(),
+            column_name: column_name.into(),
+            algo: BlindIndexAlgo::default(),
+            pepper_version: None,
+        }
     }
 
     /// Sets the tenant ID.
+    ///
+    /// The tenant must be non-empty and must not contain `|`. Debug builds
+    /// panic on an invalid tenant; use [`IndexContext::try_with_tenant`] for
+    /// tenants that come from requests or configuration.
     #[must_use]
     pub fn with_tenant(mut self, tenant_id: impl Into<String>) -> Self {
-        self.tenant_id = Some(tenant_id.into());
+        let tenant_id = tenant_id.into();
+        debug_assert!(
+            check_name("tenant", &tenant_id).is_ok(),
+            "invalid index context tenant {tenant_id:?}"
+        );
+        self.tenant_id = Some(tenant_id);
+        self
+    }
+
+    /// Sets the tenant ID, rejecting an invalid tenant.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidContext` if `tenant_id` is empty or contains `|`.
+    pub fn try_with_tenant(self, tenant_id: impl Into<String>) -> Result<Self, Error> {
+        let tenant_id = tenant_id.into();
+        check_name("tenant", &tenant_id)?;
+        Ok(self.with_tenant(tenant_id))
+    }
+
+    /// Sets the HMAC used for blind indexes. Defaults to HMAC-SHA256.
+    #[must_use]
+    pub const fn with_algo(mut self, algo: BlindIndexAlgo) -> Self {
+        self.algo = algo;
+        self
+    }
+
+    /// Tags the context with the pepper version its indexes are computed
+    /// under.
+    ///
+    /// Indexes for such a context are keyed by that pepper version (see
+    /// [`KeyProvider::get_pepper_version`](crate::key_provider::KeyProvider::get_pepper_version))
+    /// rather than the current pepper, and the version is mixed into the HMAC
+    /// input (the context string gains a `|pepper:v{version}` suffix). A
+    /// reindex job can then write indexes for a new pepper under a new
+    /// version while rows indexed under the old one stay queryable with the
+    /// old context. Without a version the current pepper is used and the
+    /// output is unchanged.
+    #[must_use]
+    pub const fn with_pepper_version(mut self, version: u32) -> Self {
+        self.pepper_version = Some(version);
         self
     }
 
@@ -136,6 +421,18 @@ impl IndexContext {
     pub fn column_name(&self) -> &str {
         &self.column_name
     }
+
+    /// Returns the HMAC used for blind indexes.
+    #[must_use]
+    pub const fn algo(&self) -> BlindIndexAlgo {
+        self.algo
+    }
+
+    /// Returns the pepper version, if set.
+    #[must_use]
+    pub const fn pepper_version(&self) -> Option<u32> {
+        self.pepper_version
+    }
 }
 
 impl fmt::Display for IndexContext {
@@ -146,7 +443,11 @@ impl fmt::Display for IndexContext {
             self.tenant_id.as_deref().unwrap_or("default"),
             self.table_name,
             self.column_name
-        )
+        )?;
+        if let Some(version) = self.pepper_version {
+            write!(f, "|pepper:v{version}")?;
+        }
+        Ok(())
     }
 }
 
@@ -156,6 +457,8 @@ impl From<&EncryptionContext> for IndexContext {
             tenant_id: ctx.tenant_id.clone(),
             table_name: ctx.table_name.clone(),
             column_name: ctx.column_name.clone(),
+            algo: BlindIndexAlgo::default(),
+            pepper_version: None,
         }
     }
 }
@@ -178,12 +481,211 @@ mod tests {
         assert_eq!(ctx.to_string(), "default|users|email|v1");
     }
 
+    #[test]
+    fn test_encryption_context_canonical_bytes() {
+        let ctx = EncryptionContext::new("users", "email").with_tenant("tenant_123");
+        assert_eq!(ctx.canonical_bytes(), b"tenant_123|users|email|v1");
+    }
+
+    #[test]
+    fn test_encryption_context_attributes_canonical_bytes() {
+        let ctx = EncryptionContext::new("users", "email")
+            .with_attribute("purpose", "billing")
+            .with_attribute("partition", "eu");
+
+        assert_eq!(
+            ctx.canonical_bytes(),
+            b"default|users|email|v1|9:partition=2:eu|7:purpose=7:billing"
+        );
+        assert_eq!(ctx.attribute("purpose"), Some("billing"));
+        assert_eq!(
+            ctx.attributes().collect::<Vec<_>>(),
+            [("partition", "eu"), ("purpose", "billing")]
+        );
+    }
+
+    #[test]
+    fn test_encryption_context_schema_fingerprint_canonical_bytes() {
+        let ctx = EncryptionContext::new("users", "email")
+            .with_schema_fingerprint([0xde, 0xad, 0xbe, 0xef, 0, 1, 2, 3])
+            .with_attribute("purpose", "billing");
+
+        assert_eq!(ctx.schema_fingerprint(), Some([0xde, 0xad, 0xbe, 0xef, 0, 1, 2, 3]));
+        assert_eq!(
+            ctx.canonical_bytes(),
+            b"default|users|email|v1|schema=deadbeef00010203|7:purpose=7:billing"
+        );
+    }
+
+    #[test]
+    fn test_encryption_context_attribute_order_independent() {
+        let a = EncryptionContext::new("users", "email")
+            .with_attribute("x", "1")
+            .with_attribute("y", "2");
+        let b = EncryptionContext::new("users", "email")
+            .with_attribute("y", "2")
+            .with_attribute("x", "1");
+
+        assert_eq!(a, b);
+        assert_eq!(a.canonical_bytes(), b.canonical_bytes());
+    }
+
+    #[test]
+    fn test_encryption_context_attributes_unambiguous() {
+        let joined = EncryptionContext::new("users", "email").with_attribute("a", "b|1:c=1:d");
+        let split = EncryptionContext::new("users", "email")
+            .with_attribute("a", "b")
+            .with_attribute("c", "d");
+
+        assert_ne!(joined.canonical_bytes(), split.canonical_bytes());
+    }
+
+    #[test]
+    fn test_from_canonical_round_trips() {
+        let base = EncryptionContext::new("users", "email");
+        let mut contexts = Vec::new();
+        for tenant in [None, Some("tenant_123"), Some("a=b:c")] {
+            for version in [0, 1, 2, 10, u32::MAX] {
+                let mut context = base.clone().with_version(version);
+                if let Some(tenant) = tenant {
+                    context = context.with_tenant(tenant);
+                }
+                contexts.push(context.clone());
+                contexts.push(context.clone().with_schema_fingerprint([0xab; 8]));
+                contexts.push(
+                    context
+                        .with_attribute("purpose", "billing")
+                        .with_attribute("a|b", "1:c=1:d|")
+                        .with_attribute("empty", ""),
+                );
+            }
+        }
+
+        for context in contexts {
+            assert_eq!(
+                EncryptionContext::from_canonical(&context.to_string()).unwrap().to_string(),
+                context.to_string()
+            );
+            let canonical = String::from_utf8(context.canonical_bytes()).unwrap();
+            assert_eq!(EncryptionContext::from_canonical(&canonical).unwrap(), context);
+            assert_eq!(EncryptionContext::try_from(canonical.as_str()).unwrap(), context);
+        }
+    }
+
+    #[test]
+    fn test_from_canonical_default_tenant_is_none() {
+        let context = EncryptionContext::from_canonical("default|users|email|v3").unwrap();
+        assert_eq!(context, EncryptionContext::new("users", "email").with_version(3));
+        assert_eq!(context.tenant_id(), None);
+    }
+
+    #[test]
+    fn test_from_canonical_rejects_malformed() {
+        for input in [
+            "",
+            "default|users|email",
+            "default|users|email|1",
+            "default|users|email|v",
+            "default|users|email|v-1",
+            "default|users|email|v01",
+            "default|users|email|v+1",
+            "default|users|email|v4294967296",
+            "default||email|v1",
+            "default|users||v1",
+            "default|users|email|v1|",
+            "default|users|email|v1|schema=abcd",
+            "default|users|email|v1|schema=ABABABABABABABAB",
+            "default|users|email|v1|schema=abababababababab|",
+            "default|users|email|v1|7:purpose=7:billin",
+            "default|users|email|v1|7:purpose=7:billingx",
+            "default|users|email|v1|7:purpose7:billing",
+            "default|users|email|v1|+7:purpose=7:billing",
+            "default|users|email|v1|7:purpose=7:billing|1:a=1:b",
+            "default|users|email|v1|1:a=1:b|1:a=1:c",
+        ] {
+            let result = EncryptionContext::from_canonical(input);
+            assert!(matches!(result, Err(Error::InvalidContext(_))), "{input:?} parsed");
+        }
+    }
+
+    #[test]
+    fn test_try_new_accepts_valid_names() {
+        let ctx = EncryptionContext::try_new("users", "email").unwrap();
+        assert_eq!(ctx, EncryptionContext::new("users", "email"));
+    }
+
+    #[test]
+    fn test_try_new_rejects_empty_names() {
+        for (table, column) in [("", "email"), ("users", ""), ("", "")] {
+            let result = EncryptionContext::try_new(table, column);
+            assert!(matches!(result, Err(Error::InvalidContext(_))));
+        }
+    }
+
+    #[test]
+    fn test_try_new_rejects_pipe() {
+        // "a|b", "c" would otherwise encode like "a", "b|c"
+        for (table, column) in [("a|b", "c"), ("a", "b|c"), ("|", "email")] {
+            let result = EncryptionContext::try_new(table, column);
+            assert!(matches!(result, Err(Error::InvalidContext(_))));
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "invalid encryption context names")]
+    fn test_new_panics_on_empty_name_in_debug() {
+        let _ = EncryptionContext::new("", "");
+    }
+
+    #[test]
+    fn test_try_with_tenant_rejects_invalid_tenants() {
+        let ctx = EncryptionContext::new("users", "email").try_with_tenant("acme").unwrap();
+        assert_eq!(ctx.tenant_id(), Some("acme"));
+        let ctx = IndexContext::new("users", "email").try_with_tenant("acme").unwrap();
+        assert_eq!(ctx.tenant_id(), Some("acme"));
+
+        // "a|b" on table "c" would otherwise encode like tenant "a", table "b|c"
+        for tenant in ["", "a|b", "|"] {
+            let result = EncryptionContext::new("c", "email").try_with_tenant(tenant);
+            assert!(matches!(result, Err(Error::InvalidContext(_))), "{tenant:?}");
+            let result = IndexContext::new("c", "email").try_with_tenant(tenant);
+            assert!(matches!(result, Err(Error::InvalidContext(_))), "{tenant:?}");
+        }
+
+        // Nor can such a tenant be parsed back in
+        let result = EncryptionContext::from_canonical("|users|email|v1");
+        assert!(matches!(result, Err(Error::InvalidContext(_))));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "invalid encryption context tenant")]
+    fn test_with_tenant_panics_on_pipe_in_debug() {
+        let _ = EncryptionContext::new("c", "email").with_tenant("a|b");
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "invalid index context tenant")]
+    fn test_index_with_tenant_panics_on_empty_tenant_in_debug() {
+        let _ = IndexContext::new("users", "email").with_tenant("");
+    }
+
     #[test]
     fn test_index_context_display() {
         let ctx = IndexContext::new("users", "email").with_tenant("tenant_123");
         assert_eq!(ctx.to_string(), "tenant_123|users|email");
     }
 
+    #[test]
+    fn test_index_context_display_pepper_version() {
+        let ctx = IndexContext::new("users", "email").with_pepper_version(2);
+        assert_eq!(ctx.pepper_version(), Some(2));
+        assert_eq!(ctx.to_string(), "default|users|email|pepper:v2");
+        assert_eq!(IndexContext::new("users", "email").pepper_version(), None);
+    }
+
     #[test]
     fn test_index_context_from_encryption_context() {
         let enc_ctx =
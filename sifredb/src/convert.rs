@@ -0,0 +1,138 @@
+//! Conversion between AEAD and deterministic encodings of a field.
+//!
+//! A field sometimes has to change scheme: an AEAD-encrypted column becomes
+//! searchable, or a deterministic one stops needing equality queries and
+//! should stop leaking equality. [`reencode`] does the conversion in one
+//! place, so the plaintext never outlives the call.
+
+use crate::ciphertext::Ciphertext;
+use crate::context::EncryptionContext;
+use crate::deterministic::DeterministicVault;
+use crate::error::Error;
+use crate::key_provider::KeyProvider;
+use crate::vault::Vault;
+use zeroize::Zeroizing;
+
+/// Which scheme a field is encrypted with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodingMode {
+    /// Randomized envelope encryption with [`Vault`].
+    Aead,
+    /// Deterministic AES-SIV encryption with [`DeterministicVault`].
+    Deterministic,
+}
+
+/// A field ciphertext tagged with the scheme that produced it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodedValue {
+    /// Output of [`Vault::encrypt`].
+    Aead(Ciphertext),
+    /// Output of [`DeterministicVault::encrypt`].
+    Deterministic(Vec<u8>),
+}
+
+impl EncodedValue {
+    /// Returns the scheme this value is encrypted with.
+    #[must_use]
+    pub const fn mode(&self) -> EncodingMode {
+        match self {
+            Self::Aead(_) => EncodingMode::Aead,
+            Self::Deterministic(_) => EncodingMode::Deterministic,
+        }
+    }
+
+    /// Returns the serialized ciphertext.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Aead(ciphertext) => ciphertext.as_bytes(),
+            Self::Deterministic(bytes) => bytes,
+        }
+    }
+}
+
+/// Re-encrypts `src` under `dst_mode`, keeping the same context.
+///
+/// The value is decrypted with its own scheme and encrypted with the
+/// destination one; the plaintext is held only in a buffer that is zeroized
+/// before returning. Converting to the same mode still re-encrypts, which
+/// for [`EncodingMode::Aead`] yields a fresh DEK under the current KEK.
+///
+/// # Errors
+///
+/// Returns error if `src` doesn't decrypt under its vault and `context`, or
+/// if encryption under the destination scheme fails.
+pub fn reencode<P: KeyProvider>(
+    src: EncodedValue,
+    dst_mode: EncodingMode,
+    vault: &Vault<P>,
+    det_vault: &DeterministicVault,
+    context: &EncryptionContext,
+) -> Result<EncodedValue, Error> {
+    let plaintext = Zeroizing::new(match src {
+        EncodedValue::Aead(ciphertext) => vault.decrypt(&ciphertext, context)?,
+        EncodedValue::Deterministic(bytes) => det_vault.decrypt(&bytes, context)?,
+    });
+
+    match dst_mode {
+        EncodingMode::Aead => vault.encrypt(&plaintext, context).map(EncodedValue::Aead),
+        EncodingMode::Deterministic => {
+            det_vault.encrypt(&plaintext, context).map(EncodedValue::Deterministic)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::XorKeyProvider;
+    use crate::vault::CipherMode;
+    use secrecy::SecretVec;
+
+    fn vaults() -> (Vault<XorKeyProvider>, DeterministicVault) {
+        let vault = Vault::new(XorKeyProvider, CipherMode::default());
+        let det_vault = DeterministicVault::new(SecretVec::new(vec![7u8; 64])).unwrap();
+        (vault, det_vault)
+    }
+
+    #[test]
+    fn test_reencode_aead_to_deterministic_is_searchable() {
+        let (vault, det_vault) = vaults();
+        let context = EncryptionContext::new("users", "email");
+
+        let aead = EncodedValue::Aead(vault.encrypt(b"alice@example.com", &context).unwrap());
+        let converted =
+            reencode(aead, EncodingMode::Deterministic, &vault, &det_vault, &context).unwrap();
+        assert_eq!(converted.mode(), EncodingMode::Deterministic);
+
+        // Equality search: the query encrypts the needle the same way
+        let needle = det_vault.encrypt(b"alice@example.com", &context).unwrap();
+        assert_eq!(converted.as_bytes(), needle);
+        let other = det_vault.encrypt(b"bob@example.com", &context).unwrap();
+        assert_ne!(converted.as_bytes(), other);
+    }
+
+    #[test]
+    fn test_reencode_deterministic_to_aead() {
+        let (vault, det_vault) = vaults();
+        let context = EncryptionContext::new("users", "email");
+
+        let det = EncodedValue::Deterministic(det_vault.encrypt(b"secret", &context).unwrap());
+        let converted = reencode(det, EncodingMode::Aead, &vault, &det_vault, &context).unwrap();
+
+        let EncodedValue::Aead(ciphertext) = &converted else {
+            panic!("expected an AEAD value, got {converted:?}");
+        };
+        assert_eq!(vault.decrypt(ciphertext, &context).unwrap(), b"secret");
+    }
+
+    #[test]
+    fn test_reencode_rejects_wrong_context() {
+        let (vault, det_vault) = vaults();
+        let context = EncryptionContext::new("users", "email");
+        let other = EncryptionContext::new("users", "phone");
+
+        let aead = EncodedValue::Aead(vault.encrypt(b"secret", &context).unwrap());
+        assert!(reencode(aead, EncodingMode::Deterministic, &vault, &det_vault, &other).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! Bounded LRU cache of unwrapped Data Encryption Keys.
+//!
+//! Entries are keyed by a SHA-256 digest of the KEK identifier, the wrapped
+//! DEK bytes and the associated data the DEK was unwrapped with, so the
+//! wrapped material itself is never kept as a map key. Cached
+//! DEKs are held in `SecretVec`, which zeroizes them when they are evicted or
+//! the cache is cleared.
+
+use lru::LruCache;
+use secrecy::{ExposeSecret, SecretVec};
+use sha2::{Digest, Sha256};
+use std::num::NonZeroUsize;
+use std::sync::{Mutex, PoisonError};
+
+/// Thread-safe LRU cache of unwrapped DEKs.
+pub struct DekCache {
+    entries: Mutex<LruCache<[u8; 32], SecretVec<u8>>>,
+}
+
+impl DekCache {
+    /// Creates a cache holding at most `capacity` DEKs.
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self { entries: Mutex::new(LruCache::new(capacity)) }
+    }
+
+    /// Returns a copy of the cached DEK for this wrapped key and unwrap
+    /// associated data, if present.
+    pub fn get(&self, kek_id: &str, wrapped_dek: &[u8], aad: &[u8]) -> Option<SecretVec<u8>> {
+        let key = cache_key(kek_id, wrapped_dek, aad);
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.get(&key).map(|dek| SecretVec::new(dek.expose_secret().clone()))
+    }
+
+    /// Stores a DEK unwrapped with `aad`, evicting the least recently used
+    /// entry if full.
+    pub fn insert(&self, kek_id: &str, wrapped_dek: &[u8], aad: &[u8], dek: &SecretVec<u8>) {
+        let key = cache_key(kek_id, wrapped_dek, aad);
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.put(key, SecretVec::new(dek.expose_secret().clone()));
+    }
+
+    /// Returns a copy of the cached DEK derived for `context` from this
+    /// wrapped root key, if present.
+    pub fn get_derived(
+        &self,
+        kek_id: &str,
+        wrapped_root: &[u8],
+        context: &[u8],
+    ) -> Option<SecretVec<u8>> {
+        let key = derived_cache_key(kek_id, wrapped_root, context);
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.get(&key).map(|dek| SecretVec::new(dek.expose_secret().clone()))
+    }
+
+    /// Stores a DEK derived for `context` from a wrapped root key, evicting
+    /// the least recently used entry if full.
+    pub fn insert_derived(
+        &self,
+        kek_id: &str,
+        wrapped_root: &[u8],
+        context: &[u8],
+        dek: &SecretVec<u8>,
+    ) {
+        let key = derived_cache_key(kek_id, wrapped_root, context);
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.put(key, SecretVec::new(dek.expose_secret().clone()));
+    }
+
+    /// Removes (and zeroizes) every cached DEK.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+}
+
+/// Computes the cache key:
+/// `SHA-256(0x01 || kek_id_len || kek_id || wrapped_dek_len || wrapped_dek || aad_len || aad)`.
+///
+/// The unwrap associated data (the tenant) is part of the key, so a wrapped
+/// DEK moved into another tenant's header misses the cache and the provider
+/// checks the binding again.
+fn cache_key(kek_id: &str, wrapped_dek: &[u8], aad: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update((kek_id.len() as u64).to_be_bytes());
+    hasher.update(kek_id.as_bytes());
+    hasher.update((wrapped_dek.len() as u64).to_be_bytes());
+    hasher.update(wrapped_dek);
+    hasher.update((aad.len() as u64).to_be_bytes());
+    hasher.update(aad);
+    hasher.finalize().into()
+}
+
+/// Computes the key of a derived DEK:
+/// `SHA-256(0x02 || kek_id_len || kek_id || wrapped_root_len || wrapped_root || context)`,
+/// where `context` is the canonical context bytes. The leading byte keeps
+/// it apart from [`cache_key`].
+fn derived_cache_key(kek_id: &str, wrapped_root: &[u8], context: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0x02]);
+    hasher.update((kek_id.len() as u64).to_be_bytes());
+    hasher.update(kek_id.as_bytes());
+    hasher.update((wrapped_root.len() as u64).to_be_bytes());
+    hasher.update(wrapped_root);
+    hasher.update(context);
+    hasher.finalize().into()
+}
@@ -22,15 +22,52 @@
 //!
 //! Deterministic encryption reveals equality patterns. Use only for fields
 //! requiring equality queries. For other fields, use AEAD encryption.
+//!
+//! Ciphertext length also reveals plaintext length. Use
+//! [`DeterministicVault::encrypt_padded`] to round lengths up to a block size,
+//! or [`DeterministicVault::encrypt_fixed`] to hide it entirely.
+//! [`DeterministicVault::index_key`] gives a short, fixed-length equality key
+//! to index on instead of the ciphertext itself.
 
 use aes_siv::{
-    aead::{Aead, KeyInit, Payload},
+    aead::{generic_array::GenericArray, Aead, KeyInit, Payload},
     Aes256SivAead,
 };
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use core::fmt;
 use secrecy::{ExposeSecret, SecretVec};
-use zeroize::Zeroizing;
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{
+    context::{EncryptionContext, IndexContext},
+    error::{Error, KeyProviderError},
+    kdf::derive_siv_key,
+    key_provider::KeyProvider,
+};
+
+/// Size of the AES-SIV synthetic IV that prefixes every ciphertext.
+pub(crate) const SIV_TAG_SIZE: usize = 16;
+
+/// Leading byte of [`DeterministicVault::encrypt_tagged`] output.
+///
+/// Never a valid protocol version, so [`crate::scheme::detect_scheme`] can
+/// tell tagged deterministic ciphertext from ciphertext with a header.
+pub const DETERMINISTIC_TAG: u8 = 0xD5;
+
+/// Size of the big-endian plaintext length prefix used by
+/// [`DeterministicVault::encrypt_fixed`].
+const FIXED_LEN_PREFIX_SIZE: usize = 4;
+
+/// Largest equality key [`DeterministicVault::index_key`] can produce (the
+/// full SHA-256 output).
+pub const MAX_INDEX_KEY_LEN: usize = 32;
 
-use crate::{context::EncryptionContext, error::Error};
+/// Domain separation label for [`DeterministicVault::index_key`].
+const INDEX_KEY_LABEL: &[u8] = b"sifredb-siv-index\0";
 
 /// Deterministic encryption using AES-256-SIV.
 ///
@@ -54,6 +91,12 @@ pub struct DeterministicVault {
     key: SecretVec<u8>,
 }
 
+impl fmt::Debug for DeterministicVault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeterministicVault").field("key", &"[REDACTED]").finish()
+    }
+}
+
 impl DeterministicVault {
     /// Creates a new deterministic vault with the provided key.
     ///
@@ -74,6 +117,42 @@ impl DeterministicVault {
         Ok(Self { key })
     }
 
+    /// Creates a deterministic vault whose key is derived from the provider's pepper.
+    ///
+    /// The 64-byte SIV key is derived with [`derive_siv_key`] from the current
+    /// pepper and `context`, so deterministic encryption shares the provider's
+    /// key management instead of needing a key distributed out of band. Vaults
+    /// built from the same provider and context always use the same key.
+    ///
+    /// Rotating the pepper changes the derived key; existing deterministic
+    /// ciphertext must be re-encrypted afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider has no pepper or key derivation fails.
+    pub fn from_provider<P: KeyProvider>(
+        provider: &P,
+        context: &IndexContext,
+    ) -> Result<Self, Error> {
+        let pepper = provider.get_pepper()?.ok_or_else(|| {
+            KeyProviderError::PepperUnavailable("Provider has no pepper".to_string())
+        })?;
+
+        Self::new(derive_siv_key(&pepper, context)?)
+    }
+
+    /// Returns the exact length of [`DeterministicVault::encrypt`] output for a
+    /// plaintext of `plaintext_len` bytes.
+    ///
+    /// AES-SIV output is the 16-byte synthetic IV (which doubles as the
+    /// authentication tag) followed by the plaintext-sized ciphertext; there is
+    /// no header. For [`DeterministicVault::encrypt_padded`], pass the padded
+    /// length.
+    #[must_use]
+    pub const fn ciphertext_len(plaintext_len: usize) -> usize {
+        SIV_TAG_SIZE + plaintext_len
+    }
+
     /// Encrypts plaintext deterministically using the given context.
     ///
     /// The context is used as Additional Associated Data (AAD), ensuring
@@ -93,20 +172,33 @@ impl DeterministicVault {
     ///
     /// Returns an error if encryption fails.
     pub fn encrypt(&self, plaintext: &[u8], context: &EncryptionContext) -> Result<Vec<u8>, Error> {
-        let cipher = Aes256SivAead::new_from_slice(self.key.expose_secret())
-            .map_err(|e| Error::Encryption(format!("Failed to create AES-SIV cipher: {e}")))?;
+        siv_encrypt(&self.encryption_cipher()?, plaintext, context)
+    }
 
-        // Use context as AAD for domain separation
-        let aad = Zeroizing::new(context.to_string().into_bytes());
-        let payload = Payload {
-            msg: plaintext,
-            aad: &aad,
-        };
+    /// Encrypts a batch of values, each under its own context.
+    ///
+    /// Output is identical to calling [`DeterministicVault::encrypt`] on each
+    /// item in order, but the AES-SIV key schedule runs once for the whole
+    /// batch instead of once per item, which dominates the cost of short
+    /// values such as bulk tokenization of a column.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if encrypting any item fails; no partial output is
+    /// returned.
+    pub fn encrypt_all(
+        &self,
+        items: &[(&[u8], &EncryptionContext)],
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let cipher = self.encryption_cipher()?;
 
-        // AES-SIV is deterministic - uses empty nonce
-        cipher
-            .encrypt(&Default::default(), payload)
-            .map_err(|e| Error::Encryption(format!("AES-SIV encryption failed: {e}")))
+        items.iter().map(|(plaintext, context)| siv_encrypt(&cipher, plaintext, context)).collect()
+    }
+
+    /// Builds the AES-SIV cipher for encryption.
+    fn encryption_cipher(&self) -> Result<Aes256SivAead, Error> {
+        Aes256SivAead::new_from_slice(self.key.expose_secret())
+            .map_err(|e| Error::Encryption(format!("Failed to create AES-SIV cipher: {e}")))
     }
 
     /// Decrypts ciphertext that was encrypted with the same key and context.
@@ -126,30 +218,351 @@ impl DeterministicVault {
     /// - The ciphertext is corrupted
     /// - The context doesn't match
     /// - Authentication fails
-    pub fn decrypt(&self, ciphertext: &[u8], context: &EncryptionContext) -> Result<Vec<u8>, Error> {
+    pub fn decrypt(
+        &self,
+        ciphertext: &[u8],
+        context: &EncryptionContext,
+    ) -> Result<Vec<u8>, Error> {
         let cipher = Aes256SivAead::new_from_slice(self.key.expose_secret())
             .map_err(|e| Error::Decryption(format!("Failed to create AES-SIV cipher: {e}")))?;
 
         // Use same context as AAD
-        let aad = Zeroizing::new(context.to_string().into_bytes());
-        let payload = Payload {
-            msg: ciphertext,
-            aad: &aad,
-        };
+        let aad = Zeroizing::new(context.canonical_bytes());
+        let payload = Payload { msg: ciphertext, aad: &aad };
 
         // AES-SIV uses empty nonce
         cipher
-            .decrypt(&Default::default(), payload)
+            .decrypt(&GenericArray::default(), payload)
             .map_err(|e| Error::Decryption(format!("AES-SIV decryption failed: {e}")))
     }
 }
 
+impl DeterministicVault {
+    /// Encrypts plaintext deterministically after padding it to a multiple of `block`.
+    ///
+    /// Padding is PKCS#7-style: `n` bytes of value `n` are appended, where
+    /// `1 <= n <= block`, so every plaintext in the same block-sized bucket
+    /// produces a ciphertext of the same length. Output is still deterministic
+    /// for a given plaintext, context, and `block`.
+    ///
+    /// Decrypt with [`DeterministicVault::decrypt_padded`] using the same `block`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `block` is 0 or greater than 255, or if encryption fails.
+    pub fn encrypt_padded(
+        &self,
+        plaintext: &[u8],
+        context: &EncryptionContext,
+        block: usize,
+    ) -> Result<Vec<u8>, Error> {
+        let pad_len = padding_len(plaintext.len(), block).map_err(Error::Encryption)?;
+
+        let mut padded = Zeroizing::new(Vec::with_capacity(plaintext.len() + pad_len));
+        padded.extend_from_slice(plaintext);
+        // Safe cast: pad_len <= block <= 255
+        #[allow(clippy::cast_possible_truncation)]
+        padded.resize(plaintext.len() + pad_len, pad_len as u8);
+
+        self.encrypt(&padded, context)
+    }
+
+    /// Decrypts ciphertext from [`DeterministicVault::encrypt_padded`] and strips the padding.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `block` is 0 or greater than 255
+    /// - Decryption or authentication fails
+    /// - The padding is malformed
+    pub fn decrypt_padded(
+        &self,
+        ciphertext: &[u8],
+        context: &EncryptionContext,
+        block: usize,
+    ) -> Result<Vec<u8>, Error> {
+        validate_block(block).map_err(Error::Decryption)?;
+
+        let mut plaintext = self.decrypt(ciphertext, context)?;
+
+        let pad_len = plaintext.last().map_or(0, |&last| usize::from(last));
+        let valid = plaintext.len() % block == 0
+            && (1..=block).contains(&pad_len)
+            && plaintext[plaintext.len() - pad_len..].iter().all(|&b| usize::from(b) == pad_len);
+
+        if !valid {
+            plaintext.zeroize();
+            return Err(Error::Decryption("Invalid padding".to_string()));
+        }
+
+        let len = plaintext.len() - pad_len;
+        plaintext.truncate(len);
+        Ok(plaintext)
+    }
+
+    /// Encrypts plaintext deterministically into exactly `total_len` bytes.
+    ///
+    /// For fixed-width columns (e.g. `BINARY(n)`): every plaintext that fits
+    /// produces a ciphertext of the same length, so unlike
+    /// [`DeterministicVault::encrypt_padded`] nothing about the plaintext
+    /// length is revealed. The plaintext is prefixed with its length as a
+    /// 4-byte big-endian integer and zero-filled, leaving room for
+    /// `total_len - 20` bytes of plaintext. Output is still deterministic for
+    /// a given plaintext, context, and `total_len`.
+    ///
+    /// Decrypt with [`DeterministicVault::decrypt_fixed`] using the same `total_len`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `total_len` can't hold the SIV tag and length
+    /// prefix, if the plaintext doesn't fit, or if encryption fails.
+    pub fn encrypt_fixed(
+        &self,
+        plaintext: &[u8],
+        context: &EncryptionContext,
+        total_len: usize,
+    ) -> Result<Vec<u8>, Error> {
+        let capacity = fixed_capacity(total_len).map_err(Error::Encryption)?;
+        if plaintext.len() > capacity {
+            return Err(Error::Encryption(format!(
+                "Plaintext of {} bytes exceeds fixed-width capacity of {capacity} bytes",
+                plaintext.len()
+            )));
+        }
+
+        let len = u32::try_from(plaintext.len())
+            .map_err(|_| Error::Encryption("Plaintext too long".to_string()))?;
+        let mut padded = Zeroizing::new(Vec::with_capacity(total_len - SIV_TAG_SIZE));
+        padded.extend_from_slice(&len.to_be_bytes());
+        padded.extend_from_slice(plaintext);
+        padded.resize(total_len - SIV_TAG_SIZE, 0);
+
+        self.encrypt(&padded, context)
+    }
+
+    /// Decrypts ciphertext from [`DeterministicVault::encrypt_fixed`], returning
+    /// the exact original plaintext.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `total_len` is too small or doesn't match the ciphertext length
+    /// - Decryption or authentication fails
+    /// - The length prefix or zero fill is malformed
+    pub fn decrypt_fixed(
+        &self,
+        ciphertext: &[u8],
+        context: &EncryptionContext,
+        total_len: usize,
+    ) -> Result<Vec<u8>, Error> {
+        let capacity = fixed_capacity(total_len).map_err(Error::Decryption)?;
+        if ciphertext.len() != total_len {
+            return Err(Error::Decryption(format!(
+                "Expected {total_len} bytes of fixed-width ciphertext, got {}",
+                ciphertext.len()
+            )));
+        }
+
+        let mut plaintext = self.decrypt(ciphertext, context)?;
+
+        let (prefix, body) = plaintext.split_at(FIXED_LEN_PREFIX_SIZE);
+        let len = usize::try_from(u32::from_be_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]))
+            .unwrap_or(usize::MAX);
+        let valid = len <= capacity && body[len..].iter().all(|&b| b == 0);
+
+        if !valid {
+            plaintext.zeroize();
+            return Err(Error::Decryption("Invalid fixed-width padding".to_string()));
+        }
+
+        plaintext.truncate(FIXED_LEN_PREFIX_SIZE + len);
+        plaintext.drain(..FIXED_LEN_PREFIX_SIZE);
+        Ok(plaintext)
+    }
+
+    /// Encrypts plaintext deterministically into an unpadded base64url token.
+    ///
+    /// The token is URL-safe, so it can be embedded directly in paths and
+    /// query strings as an opaque lookup key. The same plaintext and context
+    /// always yield the same token string.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if encryption fails.
+    pub fn encrypt_token(
+        &self,
+        plaintext: &[u8],
+        context: &EncryptionContext,
+    ) -> Result<String, Error> {
+        Ok(URL_SAFE_NO_PAD.encode(self.encrypt(plaintext, context)?))
+    }
+
+    /// Decodes and decrypts a token from [`DeterministicVault::encrypt_token`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Decryption` if the token is not valid unpadded
+    /// base64url, or if decryption or authentication fails.
+    pub fn decrypt_token(
+        &self,
+        token: &str,
+        context: &EncryptionContext,
+    ) -> Result<Vec<u8>, Error> {
+        let ciphertext = URL_SAFE_NO_PAD
+            .decode(token)
+            .map_err(|e| Error::Decryption(format!("Invalid token encoding: {e}")))?;
+
+        self.decrypt(&ciphertext, context)
+    }
+
+    /// Encrypts plaintext deterministically, prefixed with [`DETERMINISTIC_TAG`].
+    ///
+    /// The tag makes the output self-describing, so a column mixing AEAD and
+    /// deterministic values can route each value with
+    /// [`crate::scheme::detect_scheme`]. It costs one byte and stays
+    /// deterministic. Decrypt with [`DeterministicVault::decrypt_tagged`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if encryption fails.
+    pub fn encrypt_tagged(
+        &self,
+        plaintext: &[u8],
+        context: &EncryptionContext,
+    ) -> Result<Vec<u8>, Error> {
+        let ciphertext = self.encrypt(plaintext, context)?;
+
+        let mut tagged = Vec::with_capacity(1 + ciphertext.len());
+        tagged.push(DETERMINISTIC_TAG);
+        tagged.extend_from_slice(&ciphertext);
+        Ok(tagged)
+    }
+
+    /// Decrypts ciphertext from [`DeterministicVault::encrypt_tagged`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Decryption` if the tag is missing, or if decryption or
+    /// authentication fails.
+    pub fn decrypt_tagged(
+        &self,
+        ciphertext: &[u8],
+        context: &EncryptionContext,
+    ) -> Result<Vec<u8>, Error> {
+        let Some((&DETERMINISTIC_TAG, ciphertext)) = ciphertext.split_first() else {
+            return Err(Error::Decryption("Missing deterministic scheme tag".to_string()));
+        };
+
+        self.decrypt(ciphertext, context)
+    }
+
+    /// Re-encrypts a ciphertext from this vault's key under `new_vault`'s key.
+    ///
+    /// AES-SIV has no wrapped key to rewrap: rotating a deterministic column's
+    /// key means decrypting and encrypting again. This does both in one step,
+    /// holding the plaintext only in a buffer that is zeroized as soon as the
+    /// new ciphertext exists. The context stays the same.
+    ///
+    /// Every row must be re-encrypted before the old key is retired, and
+    /// equality lookups must use `new_vault` once the rotation is done.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `old_ct` doesn't decrypt under this vault and
+    /// `context`, or if encryption under `new_vault` fails.
+    pub fn reencrypt(
+        &self,
+        old_ct: &[u8],
+        context: &EncryptionContext,
+        new_vault: &Self,
+    ) -> Result<Vec<u8>, Error> {
+        let plaintext = Zeroizing::new(self.decrypt(old_ct, context)?);
+        new_vault.encrypt(&plaintext, context)
+    }
+
+    /// Computes a compact, fixed-length equality key for `plaintext`.
+    ///
+    /// The AES-SIV ciphertext is hashed with SHA-256 and truncated to `len`
+    /// bytes. Index the column on this key and store the full ciphertext from
+    /// [`DeterministicVault::encrypt`] separately for retrieval: the key takes
+    /// less space and, unlike the ciphertext, doesn't reveal the plaintext
+    /// length. It inherits the ciphertext's keying and context binding, so it
+    /// can't be computed without this vault's key.
+    ///
+    /// Truncation trades space for collisions. With `n` distinct values in a
+    /// column, two of them share a key with probability about
+    /// `n² / 2^(8·len + 1)`: at 8 bytes that is about one in 30 million for a
+    /// million rows, while at 4 bytes a collision is likely past ~77,000 rows.
+    /// A lookup by key can therefore return extra rows, so confirm matches by
+    /// comparing the full ciphertext. Use 16 bytes or more to make collisions
+    /// negligible.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `len` is 0 or greater than [`MAX_INDEX_KEY_LEN`],
+    /// or if encryption fails.
+    pub fn index_key(
+        &self,
+        plaintext: &[u8],
+        context: &EncryptionContext,
+        len: usize,
+    ) -> Result<Vec<u8>, Error> {
+        if len == 0 || len > MAX_INDEX_KEY_LEN {
+            return Err(Error::Encryption(format!(
+                "Index key length must be between 1 and {MAX_INDEX_KEY_LEN}, got {len}"
+            )));
+        }
+
+        let ciphertext = self.encrypt(plaintext, context)?;
+        let digest = Sha256::new_with_prefix(INDEX_KEY_LABEL).chain_update(&ciphertext).finalize();
+        Ok(digest[..len].to_vec())
+    }
+}
+
+/// Encrypts `plaintext` under `cipher` with `context` as AAD.
+fn siv_encrypt(
+    cipher: &Aes256SivAead,
+    plaintext: &[u8],
+    context: &EncryptionContext,
+) -> Result<Vec<u8>, Error> {
+    // Use context as AAD for domain separation
+    let aad = Zeroizing::new(context.canonical_bytes());
+    let payload = Payload { msg: plaintext, aad: &aad };
+
+    // AES-SIV is deterministic - uses empty nonce
+    cipher
+        .encrypt(&GenericArray::default(), payload)
+        .map_err(|e| Error::Encryption(format!("AES-SIV encryption failed: {e}")))
+}
+
+/// Checks a padding block size is in `1..=255`.
+fn validate_block(block: usize) -> Result<(), String> {
+    if block == 0 || block > 255 {
+        return Err(format!("Padding block size must be between 1 and 255, got {block}"));
+    }
+    Ok(())
+}
+
+/// Returns how many plaintext bytes fit in a `total_len`-byte fixed-width ciphertext.
+fn fixed_capacity(total_len: usize) -> Result<usize, String> {
+    total_len.checked_sub(SIV_TAG_SIZE + FIXED_LEN_PREFIX_SIZE).ok_or_else(|| {
+        format!(
+            "Fixed-width length must be at least {} bytes, got {total_len}",
+            SIV_TAG_SIZE + FIXED_LEN_PREFIX_SIZE
+        )
+    })
+}
+
+/// Returns how many padding bytes bring `len` to the next multiple of `block`.
+fn padding_len(len: usize, block: usize) -> Result<usize, String> {
+    validate_block(block)?;
+    Ok(block - len % block)
+}
+
 impl Clone for DeterministicVault {
     fn clone(&self) -> Self {
         // Safe to clone since we're cloning the SecretVec wrapper
-        Self {
-            key: SecretVec::new(self.key.expose_secret().to_vec()),
-        }
+        Self { key: SecretVec::new(self.key.expose_secret().clone()) }
     }
 }
 
@@ -162,6 +575,36 @@ mod tests {
         DeterministicVault::new(key).unwrap()
     }
 
+    struct PepperProvider {
+        pepper: Option<Vec<u8>>,
+    }
+
+    impl KeyProvider for PepperProvider {
+        fn create_kek(&self) -> Result<String, KeyProviderError> {
+            Ok("kek_v1".to_string())
+        }
+
+        fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+            Ok("kek_v1".to_string())
+        }
+
+        fn wrap_dek(&self, _kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+            Ok(dek.to_vec())
+        }
+
+        fn unwrap_dek(
+            &self,
+            _kek_id: &str,
+            wrapped_dek: &[u8],
+        ) -> Result<SecretVec<u8>, KeyProviderError> {
+            Ok(SecretVec::new(wrapped_dek.to_vec()))
+        }
+
+        fn get_pepper(&self) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+            Ok(self.pepper.clone().map(SecretVec::new))
+        }
+    }
+
     #[test]
     fn test_deterministic_encryption() {
         let vault = create_test_vault();
@@ -175,6 +618,41 @@ mod tests {
         assert_eq!(ciphertext1, ciphertext2, "Encryption must be deterministic");
     }
 
+    #[test]
+    fn test_ciphertext_len_matches_encrypt() {
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+
+        for len in [0, 1, 16, 17, 1000] {
+            let ciphertext = vault.encrypt(&vec![b'x'; len], &context).unwrap();
+            assert_eq!(DeterministicVault::ciphertext_len(len), ciphertext.len());
+        }
+
+        let padded = vault.encrypt_padded(b"alice", &context, 16).unwrap();
+        assert_eq!(DeterministicVault::ciphertext_len(16), padded.len());
+    }
+
+    #[test]
+    fn test_encrypt_all_matches_encrypt() {
+        let vault = create_test_vault();
+        let email = EncryptionContext::new("users", "email");
+        let phone = EncryptionContext::new("users", "phone").with_tenant("tenant_a");
+
+        let items: [(&[u8], &EncryptionContext); 4] = [
+            (b"alice@example.com", &email),
+            (b"bob@example.com", &email),
+            (b"alice@example.com", &phone),
+            (b"", &phone),
+        ];
+        let batch = vault.encrypt_all(&items).unwrap();
+
+        assert_eq!(batch.len(), items.len());
+        for ((plaintext, context), ciphertext) in items.iter().zip(&batch) {
+            assert_eq!(ciphertext, &vault.encrypt(plaintext, context).unwrap());
+        }
+        assert!(vault.encrypt_all(&[]).unwrap().is_empty());
+    }
+
     #[test]
     fn test_deterministic_decrypt() {
         let vault = create_test_vault();
@@ -203,11 +681,11 @@ mod tests {
         let vault = create_test_vault();
         let plaintext = b"alice@example.com";
 
-        let ctx1 = EncryptionContext::new("users", "email");
-        let ctx2 = EncryptionContext::new("users", "phone");
+        let context1 = EncryptionContext::new("users", "email");
+        let context2 = EncryptionContext::new("users", "phone");
 
-        let ct1 = vault.encrypt(plaintext, &ctx1).unwrap();
-        let ct2 = vault.encrypt(plaintext, &ctx2).unwrap();
+        let ct1 = vault.encrypt(plaintext, &context1).unwrap();
+        let ct2 = vault.encrypt(plaintext, &context2).unwrap();
 
         assert_ne!(ct1, ct2, "Different contexts must produce different ciphertexts");
     }
@@ -233,7 +711,7 @@ mod tests {
         let plaintext = b"alice@example.com";
 
         let mut ciphertext = vault.encrypt(plaintext, &context).unwrap();
-        
+
         // Corrupt the ciphertext
         if let Some(byte) = ciphertext.first_mut() {
             *byte ^= 0xFF;
@@ -319,4 +797,346 @@ mod tests {
         assert_eq!(pt1, pt2);
         assert_eq!(plaintext, pt1.as_slice());
     }
+
+    #[test]
+    fn test_padded_equal_length_within_block() {
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+
+        let short = b"al@example.com";
+        let long = b"alice.smith@example.com";
+
+        let ct_short = vault.encrypt_padded(short, &context, 32).unwrap();
+        let ct_long = vault.encrypt_padded(long, &context, 32).unwrap();
+
+        assert_eq!(ct_short.len(), ct_long.len());
+        assert_eq!(vault.decrypt_padded(&ct_short, &context, 32).unwrap(), short);
+        assert_eq!(vault.decrypt_padded(&ct_long, &context, 32).unwrap(), long);
+    }
+
+    #[test]
+    fn test_padded_is_deterministic() {
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+
+        let ct1 = vault.encrypt_padded(b"alice@example.com", &context, 16).unwrap();
+        let ct2 = vault.encrypt_padded(b"alice@example.com", &context, 16).unwrap();
+        assert_eq!(ct1, ct2);
+
+        // A different block size is a different encoding
+        let ct3 = vault.encrypt_padded(b"alice@example.com", &context, 8).unwrap();
+        assert_ne!(ct1, ct3);
+    }
+
+    #[test]
+    fn test_padded_exact_multiple_adds_full_block() {
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+
+        let plaintext = [7u8; 16];
+        let ciphertext = vault.encrypt_padded(&plaintext, &context, 16).unwrap();
+
+        // 32 padded bytes plus the 16-byte SIV tag
+        assert_eq!(ciphertext.len(), 48);
+        assert_eq!(vault.decrypt_padded(&ciphertext, &context, 16).unwrap(), plaintext);
+
+        let empty = vault.encrypt_padded(b"", &context, 16).unwrap();
+        assert_eq!(vault.decrypt_padded(&empty, &context, 16).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_padded_rejects_invalid_block() {
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+
+        assert!(matches!(vault.encrypt_padded(b"x", &context, 0), Err(Error::Encryption(_))));
+        assert!(matches!(vault.encrypt_padded(b"x", &context, 256), Err(Error::Encryption(_))));
+        assert!(matches!(vault.decrypt_padded(b"x", &context, 0), Err(Error::Decryption(_))));
+    }
+
+    #[test]
+    fn test_decrypt_padded_rejects_unpadded() {
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+
+        // Last byte 'm' (109) is not a valid pad length for block 16
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+        let result = vault.decrypt_padded(&ciphertext, &context, 16);
+        assert!(matches!(result, Err(Error::Decryption(_))));
+    }
+
+    #[test]
+    fn test_fixed_constant_length() {
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+        let plaintexts: [&[u8]; 3] = [b"", b"al@example.com", b"alice.smith@example.com"];
+
+        let ciphertexts: Vec<Vec<u8>> = plaintexts
+            .iter()
+            .map(|plaintext| vault.encrypt_fixed(plaintext, &context, 64).unwrap())
+            .collect();
+
+        for (plaintext, ciphertext) in plaintexts.iter().zip(&ciphertexts) {
+            assert_eq!(ciphertext.len(), 64);
+            assert_eq!(vault.decrypt_fixed(ciphertext, &context, 64).unwrap(), *plaintext);
+        }
+
+        // Trailing zeros in the plaintext itself survive the round trip
+        let zeros = vault.encrypt_fixed(&[0, 0], &context, 64).unwrap();
+        assert_eq!(vault.decrypt_fixed(&zeros, &context, 64).unwrap(), [0, 0]);
+
+        // Still deterministic
+        assert_eq!(vault.encrypt_fixed(b"al@example.com", &context, 64).unwrap(), ciphertexts[1]);
+    }
+
+    #[test]
+    fn test_fixed_capacity_limits() {
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+
+        // 16-byte tag plus 4-byte length prefix leaves 44 bytes of room in 64
+        assert!(vault.encrypt_fixed(&[7; 44], &context, 64).is_ok());
+        assert!(matches!(vault.encrypt_fixed(&[7; 45], &context, 64), Err(Error::Encryption(_))));
+
+        // Too small to hold the tag and length prefix
+        assert!(matches!(vault.encrypt_fixed(b"", &context, 19), Err(Error::Encryption(_))));
+        let empty = vault.encrypt_fixed(b"", &context, 20).unwrap();
+        assert_eq!(vault.decrypt_fixed(&empty, &context, 20).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_decrypt_fixed_rejects_mismatch() {
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+
+        let ciphertext = vault.encrypt_fixed(b"alice", &context, 64).unwrap();
+        assert!(matches!(
+            vault.decrypt_fixed(&ciphertext, &context, 48),
+            Err(Error::Decryption(_))
+        ));
+
+        // Plain deterministic output of the right length has no valid prefix
+        let plain = vault.encrypt(&[0xFF; 48], &context).unwrap();
+        assert!(matches!(vault.decrypt_fixed(&plain, &context, 64), Err(Error::Decryption(_))));
+    }
+
+    #[test]
+    fn test_cipher_state_zeroizes_on_drop() {
+        use zeroize::ZeroizeOnDrop;
+
+        // AES-SIV zeroizes its CTR key itself; the CMAC and AES key schedules
+        // only do with their zeroize features
+        fn assert_zeroize_on_drop<T: ZeroizeOnDrop>() {}
+        assert_zeroize_on_drop::<aes::Aes256>();
+        assert_zeroize_on_drop::<cmac::CmacCore<aes::Aes256>>();
+
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+        assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), b"alice@example.com");
+    }
+
+    #[test]
+    fn test_reencrypt_rotates_key() {
+        let old_vault = create_test_vault();
+        let new_vault = DeterministicVault::new(SecretVec::new(vec![0x24; 64])).unwrap();
+        let context = EncryptionContext::new("users", "email");
+
+        let old_ct = old_vault.encrypt(b"alice@example.com", &context).unwrap();
+        let new_ct = old_vault.reencrypt(&old_ct, &context, &new_vault).unwrap();
+
+        assert_ne!(new_ct, old_ct);
+        assert_eq!(new_vault.decrypt(&new_ct, &context).unwrap(), b"alice@example.com");
+        assert!(new_vault.decrypt(&old_ct, &context).is_err());
+        assert!(old_vault.decrypt(&new_ct, &context).is_err());
+
+        // Equality lookups under the new key match the re-encrypted value
+        assert_eq!(new_vault.encrypt(b"alice@example.com", &context).unwrap(), new_ct);
+    }
+
+    #[test]
+    fn test_reencrypt_rejects_foreign_ciphertext() {
+        let old_vault = create_test_vault();
+        let new_vault = DeterministicVault::new(SecretVec::new(vec![0x24; 64])).unwrap();
+        let context = EncryptionContext::new("users", "email");
+
+        // Already rotated, or encrypted under a different context
+        let new_ct = new_vault.encrypt(b"alice@example.com", &context).unwrap();
+        let result = old_vault.reencrypt(&new_ct, &context, &new_vault);
+        assert!(matches!(result, Err(Error::Decryption(_))));
+
+        let other = old_vault.encrypt(b"alice", &EncryptionContext::new("users", "name")).unwrap();
+        assert!(old_vault.reencrypt(&other, &context, &new_vault).is_err());
+    }
+
+    #[test]
+    fn test_index_key_is_deterministic() {
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+
+        let key1 = vault.index_key(b"alice@example.com", &context, 8).unwrap();
+        let key2 = vault.index_key(b"alice@example.com", &context, 8).unwrap();
+        assert_eq!(key1, key2);
+        assert_eq!(key1.len(), 8);
+
+        // Shorter keys are prefixes of longer ones
+        let full = vault.index_key(b"alice@example.com", &context, MAX_INDEX_KEY_LEN).unwrap();
+        assert_eq!(full[..8], key1);
+
+        // Same length regardless of plaintext length
+        let long = vault.index_key(&[b'a'; 1000], &context, 8).unwrap();
+        assert_eq!(long.len(), 8);
+
+        // Bound to the context and the key
+        let other_context = EncryptionContext::new("users", "name");
+        assert_ne!(vault.index_key(b"alice@example.com", &other_context, 8).unwrap(), key1);
+        let other_vault = DeterministicVault::new(SecretVec::new(vec![0x24; 64])).unwrap();
+        assert_ne!(other_vault.index_key(b"alice@example.com", &context, 8).unwrap(), key1);
+    }
+
+    #[test]
+    fn test_index_key_collisions_match_length() {
+        use std::collections::HashSet;
+
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+        let values: Vec<Vec<u8>> = (0..2000u32).map(|i| format!("user{i}").into_bytes()).collect();
+
+        let distinct = |len: usize| {
+            values
+                .iter()
+                .map(|v| vault.index_key(v, &context, len).unwrap())
+                .collect::<HashSet<_>>()
+                .len()
+        };
+
+        // 2000 values in 2^16 buckets: ~30 colliding pairs expected
+        let colliding = values.len() - distinct(2);
+        assert!((1..100).contains(&colliding), "{colliding} collisions at 2 bytes");
+
+        // At 8 bytes a collision would be a ~1 in 10^13 event
+        assert_eq!(distinct(8), values.len());
+    }
+
+    #[test]
+    fn test_index_key_rejects_bad_length() {
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+
+        assert!(matches!(vault.index_key(b"x", &context, 0), Err(Error::Encryption(_))));
+        assert!(matches!(
+            vault.index_key(b"x", &context, MAX_INDEX_KEY_LEN + 1),
+            Err(Error::Encryption(_))
+        ));
+    }
+
+    #[test]
+    fn test_tagged_round_trip() {
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+
+        let tagged = vault.encrypt_tagged(b"alice@example.com", &context).unwrap();
+        let untagged = vault.encrypt(b"alice@example.com", &context).unwrap();
+        assert_eq!(tagged[0], DETERMINISTIC_TAG);
+        assert_eq!(tagged[1..], untagged);
+        assert_eq!(vault.encrypt_tagged(b"alice@example.com", &context).unwrap(), tagged);
+
+        assert_eq!(vault.decrypt_tagged(&tagged, &context).unwrap(), b"alice@example.com");
+        assert!(matches!(vault.decrypt_tagged(&untagged, &context), Err(Error::Decryption(_))));
+        assert!(matches!(vault.decrypt_tagged(&[], &context), Err(Error::Decryption(_))));
+    }
+
+    #[test]
+    fn test_token_round_trip() {
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+
+        let token = vault.encrypt_token(b"alice@example.com", &context).unwrap();
+        assert!(token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+
+        let decrypted = vault.decrypt_token(&token, &context).unwrap();
+        assert_eq!(decrypted, b"alice@example.com");
+    }
+
+    #[test]
+    fn test_token_is_deterministic() {
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+
+        let token1 = vault.encrypt_token(b"alice@example.com", &context).unwrap();
+        let token2 = vault.encrypt_token(b"alice@example.com", &context).unwrap();
+        assert_eq!(token1, token2);
+
+        let other = vault.encrypt_token(b"bob@example.com", &context).unwrap();
+        assert_ne!(token1, other);
+    }
+
+    #[test]
+    fn test_tampered_token_fails() {
+        let vault = create_test_vault();
+        let context = EncryptionContext::new("users", "email");
+
+        let token = vault.encrypt_token(b"alice@example.com", &context).unwrap();
+
+        // Swap the first character for a different valid base64url character
+        let replacement = if token.starts_with('A') { "B" } else { "A" };
+        let tampered = format!("{replacement}{}", &token[1..]);
+        assert!(matches!(vault.decrypt_token(&tampered, &context), Err(Error::Decryption(_))));
+
+        // Not base64url at all
+        assert!(matches!(vault.decrypt_token("not a token!", &context), Err(Error::Decryption(_))));
+        // Padded base64 is rejected
+        assert!(matches!(
+            vault.decrypt_token(&format!("{token}="), &context),
+            Err(Error::Decryption(_))
+        ));
+    }
+
+    #[test]
+    fn test_debug_redacts_key() {
+        let vault = DeterministicVault::new(SecretVec::new(vec![0x5A; 64])).unwrap();
+
+        let debug = format!("{vault:?}");
+        assert!(debug.contains("[REDACTED]"));
+        assert!(!debug.contains("90"));
+    }
+
+    #[test]
+    fn test_from_provider_is_stable() {
+        let provider = PepperProvider { pepper: Some(vec![0x11; 32]) };
+        let index_context = IndexContext::new("users", "email").with_tenant("tenant_a");
+        let context = EncryptionContext::new("users", "email");
+
+        let vault1 = DeterministicVault::from_provider(&provider, &index_context).unwrap();
+        let vault2 = DeterministicVault::from_provider(&provider, &index_context).unwrap();
+
+        let ct1 = vault1.encrypt(b"alice@example.com", &context).unwrap();
+        let ct2 = vault2.encrypt(b"alice@example.com", &context).unwrap();
+        assert_eq!(ct1, ct2);
+        assert_eq!(vault2.decrypt(&ct1, &context).unwrap(), b"alice@example.com");
+    }
+
+    #[test]
+    fn test_from_provider_separates_contexts() {
+        let provider = PepperProvider { pepper: Some(vec![0x11; 32]) };
+        let context = EncryptionContext::new("users", "email");
+
+        let email = IndexContext::new("users", "email");
+        let phone = IndexContext::new("users", "phone");
+        let vault1 = DeterministicVault::from_provider(&provider, &email).unwrap();
+        let vault2 = DeterministicVault::from_provider(&provider, &phone).unwrap();
+
+        let ct1 = vault1.encrypt(b"alice@example.com", &context).unwrap();
+        let ct2 = vault2.encrypt(b"alice@example.com", &context).unwrap();
+        assert_ne!(ct1, ct2);
+    }
+
+    #[test]
+    fn test_from_provider_requires_pepper() {
+        let provider = PepperProvider { pepper: None };
+        let result =
+            DeterministicVault::from_provider(&provider, &IndexContext::new("users", "email"));
+
+        assert!(matches!(result, Err(Error::KeyProvider(KeyProviderError::PepperUnavailable(_)))));
+    }
 }
@@ -1,36 +1,38 @@
 //! Error types for `SifreDB` operations.
 
-use std::fmt;
+use alloc::string::String;
+use core::fmt;
 
 /// Main error type for `SifreDB` operations.
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug)]
 pub enum Error {
     /// Encryption operation failed
-    #[error("encryption failed: {0}")]
     EncryptionFailed(String),
 
     /// Decryption operation failed
-    #[error("decryption failed: {0}")]
     DecryptionFailed(String),
 
     /// Authentication tag verification failed (data may be corrupted or tampered)
-    #[error("authentication failed: ciphertext may be corrupted or tampered")]
     AuthenticationFailed,
 
+    /// The encryption context version differs from the one recorded in the header
+    ContextVersionMismatch {
+        /// The context version recorded in the ciphertext header
+        expected: u32,
+        /// The version of the context supplied for decryption
+        actual: u32,
+    },
+
     /// Key provider operation failed
-    #[error("key provider error: {0}")]
-    KeyProvider(#[from] KeyProviderError),
+    KeyProvider(KeyProviderError),
 
     /// Encryption header parsing failed
-    #[error("invalid header: {0}")]
     InvalidHeader(String),
 
     /// Key derivation failed
-    #[error("key derivation failed")]
     KeyDerivation,
 
     /// Unsupported protocol version
-    #[error("unsupported version: {version} (supported: {supported})")]
     UnsupportedVersion {
         /// The version found in the ciphertext
         version: u8,
@@ -38,12 +40,29 @@ pub enum Error {
         supported: String,
     },
 
+    /// The header sets flags this reader doesn't understand, written by a
+    /// newer version of the format
+    UnsupportedFeature {
+        /// The unknown flag bits
+        flags: u8,
+    },
+
+    /// The header names a cipher id this reader doesn't know
+    UnsupportedCipher(u8),
+
+    /// Plaintext too large for the header's 4-byte payload length field, or
+    /// for the cipher's limit on data sealed under one DEK
+    PayloadTooLarge {
+        /// Length of the rejected plaintext in bytes
+        len: usize,
+        /// Largest plaintext length that can be encrypted
+        max: usize,
+    },
+
     /// Blind index generation failed
-    #[error("blind index generation failed: {0}")]
     IndexGenerationFailed(String),
 
     /// Invalid key length
-    #[error("invalid key length: expected {expected} bytes, got {actual} bytes")]
     InvalidKeyLength {
         /// Expected key length
         expected: usize,
@@ -52,16 +71,94 @@ pub enum Error {
     },
 
     /// Encryption operation failed (generic)
-    #[error("encryption error: {0}")]
     Encryption(String),
 
     /// Decryption operation failed (generic)
-    #[error("decryption error: {0}")]
     Decryption(String),
 
+    /// Text decoding (base64 or hex) of stored data failed
+    Decoding(String),
+
+    /// An encryption context was built from invalid names
+    InvalidContext(String),
+
     /// I/O operation failed
-    #[error("I/O error: {0}")]
-    Io(#[from] std::io::Error),
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EncryptionFailed(msg) => write!(f, "encryption failed: {msg}"),
+            Self::DecryptionFailed(msg) => write!(f, "decryption failed: {msg}"),
+            Self::AuthenticationFailed => {
+                write!(f, "authentication failed: ciphertext may be corrupted or tampered")
+            }
+            Self::ContextVersionMismatch { expected, actual } => write!(
+                f,
+                "context version mismatch: ciphertext was encrypted with version {expected}, got {actual}"
+            ),
+            Self::KeyProvider(err) => write!(f, "key provider error: {err}"),
+            Self::InvalidHeader(msg) => write!(f, "invalid header: {msg}"),
+            Self::KeyDerivation => write!(f, "key derivation failed"),
+            Self::UnsupportedVersion { version, supported } => {
+                write!(f, "unsupported version: {version} (supported: {supported})")
+            }
+            Self::UnsupportedCipher(id) => write!(f, "unsupported cipher id: {id}"),
+            Self::PayloadTooLarge { len, max } => {
+                write!(f, "payload too large: {len} bytes (max: {max})")
+            }
+            Self::UnsupportedFeature { flags } => {
+                write!(f, "unsupported header flags: {flags:#04x}")
+            }
+            Self::IndexGenerationFailed(msg) => write!(f, "blind index generation failed: {msg}"),
+            Self::InvalidKeyLength { expected, actual } => {
+                write!(f, "invalid key length: expected {expected} bytes, got {actual} bytes")
+            }
+            Self::Encryption(msg) => write!(f, "encryption error: {msg}"),
+            Self::Decryption(msg) => write!(f, "decryption error: {msg}"),
+            Self::Decoding(msg) => write!(f, "decoding failed: {msg}"),
+            Self::InvalidContext(msg) => write!(f, "invalid context: {msg}"),
+            #[cfg(feature = "std")]
+            Self::Io(err) => write!(f, "I/O error: {err}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::KeyProvider(err) => Some(err),
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<KeyProviderError> for Error {
+    fn from(err: KeyProviderError) -> Self {
+        Self::KeyProvider(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl Error {
+    /// Returns true if the operation may succeed when retried with backoff.
+    ///
+    /// Only key provider failures are ever retryable; see
+    /// [`KeyProviderError::is_retryable`].
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::KeyProvider(err) if err.is_retryable())
+    }
 }
 
 /// Errors specific to key provider operations.
@@ -76,16 +173,53 @@ pub enum KeyProviderError {
     /// No active KEK configured
     NoActiveKek,
 
+    /// Key directory exists but holds no keys, e.g. it was never initialized
+    #[cfg(feature = "std")]
+    NotInitialized(std::path::PathBuf),
+
     /// DEK wrapping failed
     WrapFailed(String),
 
     /// DEK unwrapping failed
     UnwrapFailed(String),
 
+    /// DEK wrapping failed because of an underlying error, returned by
+    /// [`source`](std::error::Error::source); displays like `WrapFailed`
+    #[cfg(feature = "std")]
+    WrapFailedWithSource {
+        /// Description of the failure
+        message: String,
+        /// The error that caused it
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// DEK unwrapping failed because of an underlying error, returned by
+    /// [`source`](std::error::Error::source); displays like `UnwrapFailed`
+    #[cfg(feature = "std")]
+    UnwrapFailedWithSource {
+        /// Description of the failure
+        message: String,
+        /// The error that caused it
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
     /// Pepper not available
     PepperUnavailable(String),
 
+    /// Operation not supported by this provider
+    Unsupported(String),
+
+    /// Backend unreachable or not ready to serve requests
+    Unavailable(String),
+
+    /// Backend rejected the request due to rate limiting
+    Throttled(String),
+
+    /// Another process holds the lock needed to change the keys
+    Locked(String),
+
     /// I/O operation failed
+    #[cfg(feature = "std")]
     Io(std::io::Error),
 }
 
@@ -95,25 +229,151 @@ impl fmt::Display for KeyProviderError {
             Self::KekNotFound(id) => write!(f, "KEK not found: {id}"),
             Self::CreationFailed(msg) => write!(f, "KEK creation failed: {msg}"),
             Self::NoActiveKek => write!(f, "no active KEK configured"),
+            #[cfg(feature = "std")]
+            Self::NotInitialized(path) => {
+                write!(f, "key directory not initialized: {}", path.display())
+            }
             Self::WrapFailed(msg) => write!(f, "DEK wrap failed: {msg}"),
             Self::UnwrapFailed(msg) => write!(f, "DEK unwrap failed: {msg}"),
+            #[cfg(feature = "std")]
+            Self::WrapFailedWithSource { message, .. } => write!(f, "DEK wrap failed: {message}"),
+            #[cfg(feature = "std")]
+            Self::UnwrapFailedWithSource { message, .. } => {
+                write!(f, "DEK unwrap failed: {message}")
+            }
             Self::PepperUnavailable(msg) => write!(f, "pepper not available: {msg}"),
+            Self::Unsupported(op) => write!(f, "operation not supported: {op}"),
+            Self::Unavailable(msg) => write!(f, "key provider unavailable: {msg}"),
+            Self::Throttled(msg) => write!(f, "key provider throttled: {msg}"),
+            Self::Locked(msg) => write!(f, "key store locked: {msg}"),
+            #[cfg(feature = "std")]
             Self::Io(err) => write!(f, "I/O error: {err}"),
         }
     }
 }
 
+impl KeyProviderError {
+    /// Builds a `WrapFailedWithSource` error whose message ends with the
+    /// source's own, as `WrapFailed(format!("{context}: {source}"))` would.
+    #[cfg(feature = "std")]
+    pub fn wrap_failed(
+        context: &str,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        Self::WrapFailedWithSource {
+            message: format!("{context}: {source}"),
+            source: Box::new(source),
+        }
+    }
+
+    /// Builds an `UnwrapFailedWithSource` error whose message ends with the
+    /// source's own, as `UnwrapFailed(format!("{context}: {source}"))` would.
+    #[cfg(feature = "std")]
+    pub fn unwrap_failed(
+        context: &str,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        Self::UnwrapFailedWithSource {
+            message: format!("{context}: {source}"),
+            source: Box::new(source),
+        }
+    }
+
+    /// Returns true if the operation may succeed when retried with backoff.
+    ///
+    /// Throttling, an unavailable backend, and transient I/O failures are
+    /// retryable; missing keys, denied access, and malformed data are not.
+    #[must_use]
+    // Could only be const without the `Io` variant
+    #[cfg_attr(not(feature = "std"), allow(clippy::missing_const_for_fn))]
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Throttled(_) | Self::Unavailable(_) => true,
+            #[cfg(feature = "std")]
+            Self::Io(err) => matches!(
+                err.kind(),
+                std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::WouldBlock
+            ),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
 impl std::error::Error for KeyProviderError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Self::Io(err) => Some(err),
+            Self::WrapFailedWithSource { source, .. }
+            | Self::UnwrapFailedWithSource { source, .. } => Some(source.as_ref()),
             _ => None,
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl From<std::io::Error> for KeyProviderError {
     fn from(err: std::io::Error) -> Self {
         Self::Io(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_provider_error_retryable() {
+        assert!(KeyProviderError::Throttled("rate exceeded".to_string()).is_retryable());
+        assert!(KeyProviderError::Unavailable("connection refused".to_string()).is_retryable());
+
+        assert!(!KeyProviderError::KekNotFound("kek_v1".to_string()).is_retryable());
+        assert!(!KeyProviderError::CreationFailed("access denied".to_string()).is_retryable());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_io_error_retryable() {
+        assert!(KeyProviderError::Io(std::io::ErrorKind::TimedOut.into()).is_retryable());
+        assert!(!KeyProviderError::Io(std::io::ErrorKind::NotFound.into()).is_retryable());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_wrap_failure_keeps_source() {
+        use chacha20poly1305::{ChaCha20Poly1305, KeyInit};
+        use std::error::Error as _;
+
+        // A 16-byte KEK is too short for ChaCha20-Poly1305
+        let cause = ChaCha20Poly1305::new_from_slice(&[0u8; 16]).err().unwrap();
+        let err = Error::from(KeyProviderError::wrap_failed("Invalid KEK", cause));
+        assert_eq!(
+            err.to_string(),
+            "key provider error: DEK wrap failed: Invalid KEK: Invalid Length"
+        );
+        assert!(!err.is_retryable());
+
+        let chain: Vec<&(dyn std::error::Error + 'static)> =
+            std::iter::successors(err.source(), |&err| err.source()).collect();
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain[1].to_string(), "Invalid Length");
+
+        let cause = ChaCha20Poly1305::new_from_slice(&[0u8; 16]).err().unwrap();
+        let err = KeyProviderError::unwrap_failed("Invalid KEK", cause);
+        assert_eq!(err.to_string(), "DEK unwrap failed: Invalid KEK: Invalid Length");
+        assert!(err.source().is_some());
+        assert!(KeyProviderError::UnwrapFailed("Invalid KEK".to_string()).source().is_none());
+    }
+
+    #[test]
+    fn test_error_forwards_retryable() {
+        let throttled = Error::from(KeyProviderError::Throttled("rate exceeded".to_string()));
+        assert!(throttled.is_retryable());
+
+        let missing = Error::from(KeyProviderError::NoActiveKek);
+        assert!(!missing.is_retryable());
+        assert!(!Error::AuthenticationFailed.is_retryable());
+    }
+}
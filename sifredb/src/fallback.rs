@@ -0,0 +1,388 @@
+//! Primary/fallback key provider for migrating between backends.
+//!
+//! [`FallbackProvider`] pairs the provider being migrated to with the one
+//! being migrated from. New data is always wrapped by the primary, while DEKs
+//! wrapped under a KEK the primary doesn't know are unwrapped by the
+//! secondary, so old rows keep decrypting until they are rewrapped.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use sifredb::fallback::FallbackProvider;
+//!
+//! let provider = FallbackProvider::new(kms_provider, FileKeyProvider::new("./keys")?);
+//! let vault = Vault::new(provider, CipherMode::default());
+//!
+//! // Moves a row's DEK from a file KEK to the KMS key
+//! let migrated = vault.rewrap(&ciphertext, &kms_key_arn)?;
+//! ```
+
+use crate::error::KeyProviderError;
+#[cfg(feature = "std")]
+use crate::key_provider::KekMetadata;
+use crate::key_provider::KeyProvider;
+use alloc::string::String;
+use alloc::vec::Vec;
+use secrecy::{ExposeSecret, SecretVec};
+
+/// Key provider that falls back to a secondary provider for unknown KEKs.
+///
+/// [`KeyProvider::create_kek`], [`KeyProvider::current_kek_id`] and
+/// [`KeyProvider::wrap_dek`] always use the primary, so everything encrypted
+/// or rotated through this provider ends up under a primary KEK.
+/// [`KeyProvider::unwrap_dek`] tries the primary first and, only when it
+/// fails with [`KeyProviderError::KekNotFound`], the secondary; any other
+/// primary error is returned as is. [`KeyProvider::rewrap_dek`] unwraps the
+/// same way and wraps with the primary.
+///
+/// The pepper comes from the primary if it has one, otherwise from the
+/// secondary. Blind indexes only keep matching if both serve the same pepper
+/// or the primary has none, so migrate the pepper together with the KEKs.
+#[derive(Debug, Clone)]
+pub struct FallbackProvider<P, S> {
+    primary: P,
+    secondary: S,
+}
+
+impl<P, S> FallbackProvider<P, S> {
+    /// Combines the provider to migrate to with the one to migrate from.
+    #[must_use]
+    pub const fn new(primary: P, secondary: S) -> Self {
+        Self { primary, secondary }
+    }
+
+    /// Returns the primary provider.
+    #[must_use]
+    pub const fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    /// Returns the secondary provider.
+    #[must_use]
+    pub const fn secondary(&self) -> &S {
+        &self.secondary
+    }
+
+    /// Splits the combinator into its primary and secondary providers.
+    #[must_use]
+    pub fn into_parts(self) -> (P, S) {
+        (self.primary, self.secondary)
+    }
+}
+
+impl<P: KeyProvider, S: KeyProvider> FallbackProvider<P, S> {
+    /// Returns the provider that serves the pepper.
+    fn pepper_provider(&self) -> Result<&dyn KeyProvider, KeyProviderError> {
+        if self.primary.get_pepper()?.is_some() {
+            Ok(&self.primary)
+        } else {
+            Ok(&self.secondary)
+        }
+    }
+}
+
+impl<P: KeyProvider, S: KeyProvider> KeyProvider for FallbackProvider<P, S> {
+    fn create_kek(&self) -> Result<String, KeyProviderError> {
+        self.primary.create_kek()
+    }
+
+    fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+        self.primary.current_kek_id()
+    }
+
+    fn current_kek_id_for_tenant(&self, tenant: &str) -> Result<String, KeyProviderError> {
+        self.primary.current_kek_id_for_tenant(tenant)
+    }
+
+    fn wrap_dek(&self, kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+        self.primary.wrap_dek(kek_id, dek)
+    }
+
+    fn unwrap_dek(
+        &self,
+        kek_id: &str,
+        wrapped_dek: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        match self.primary.unwrap_dek(kek_id, wrapped_dek) {
+            Err(KeyProviderError::KekNotFound(_)) => self.secondary.unwrap_dek(kek_id, wrapped_dek),
+            result => result,
+        }
+    }
+
+    fn wrap_dek_aad(
+        &self,
+        kek_id: &str,
+        dek: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        self.primary.wrap_dek_aad(kek_id, dek, aad)
+    }
+
+    fn unwrap_dek_aad(
+        &self,
+        kek_id: &str,
+        wrapped_dek: &[u8],
+        aad: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        match self.primary.unwrap_dek_aad(kek_id, wrapped_dek, aad) {
+            Err(KeyProviderError::KekNotFound(_)) => {
+                self.secondary.unwrap_dek_aad(kek_id, wrapped_dek, aad)
+            }
+            result => result,
+        }
+    }
+
+    fn get_pepper(&self) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+        let Some(pepper) = self.primary.get_pepper()? else {
+            return self.secondary.get_pepper();
+        };
+        Ok(Some(pepper))
+    }
+
+    fn current_pepper_version(&self) -> Result<u32, KeyProviderError> {
+        self.pepper_provider()?.current_pepper_version()
+    }
+
+    fn get_pepper_version(&self, version: u32) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+        self.pepper_provider()?.get_pepper_version(version)
+    }
+
+    fn rotate_pepper(&self) -> Result<(), KeyProviderError> {
+        self.pepper_provider()?.rotate_pepper()
+    }
+
+    fn rewrap_dek(
+        &self,
+        old_kek_id: &str,
+        new_kek_id: &str,
+        wrapped_dek: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        // The primary can rewrap server-side when it holds the old KEK too
+        match self.primary.rewrap_dek(old_kek_id, new_kek_id, wrapped_dek) {
+            Err(KeyProviderError::KekNotFound(_)) => {
+                let dek = self.secondary.unwrap_dek(old_kek_id, wrapped_dek)?;
+                self.primary.wrap_dek(new_kek_id, dek.expose_secret())
+            }
+            result => result,
+        }
+    }
+
+    fn rewrap_dek_aad(
+        &self,
+        old_kek_id: &str,
+        new_kek_id: &str,
+        wrapped_dek: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        match self.primary.rewrap_dek_aad(old_kek_id, new_kek_id, wrapped_dek, aad) {
+            Err(KeyProviderError::KekNotFound(_)) => {
+                let dek = self.secondary.unwrap_dek_aad(old_kek_id, wrapped_dek, aad)?;
+                self.primary.wrap_dek_aad(new_kek_id, dek.expose_secret(), aad)
+            }
+            result => result,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn kek_metadata(&self, kek_id: &str) -> Result<KekMetadata, KeyProviderError> {
+        match self.primary.kek_metadata(kek_id) {
+            Err(KeyProviderError::KekNotFound(_)) => self.secondary.kek_metadata(kek_id),
+            result => result,
+        }
+    }
+
+    fn health_check(&self) -> Result<(), KeyProviderError> {
+        // Both are needed: the primary for writes, the secondary for old reads
+        self.primary.health_check()?;
+        self.secondary.health_check()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Provider holding a fixed set of KEKs that "wrap" by XOR with a
+    /// per-KEK byte.
+    struct MapProvider {
+        current: &'static str,
+        keks: HashMap<&'static str, u8>,
+        pepper: Option<Vec<u8>>,
+        unwrap_calls: AtomicUsize,
+    }
+
+    impl MapProvider {
+        fn new(keks: &[(&'static str, u8)]) -> Self {
+            Self {
+                current: keks[0].0,
+                keks: keks.iter().copied().collect(),
+                pepper: None,
+                unwrap_calls: AtomicUsize::new(0),
+            }
+        }
+
+        fn with_pepper(mut self, pepper: &[u8]) -> Self {
+            self.pepper = Some(pepper.to_vec());
+            self
+        }
+
+        fn kek(&self, kek_id: &str) -> Result<u8, KeyProviderError> {
+            self.keks
+                .get(kek_id)
+                .copied()
+                .ok_or_else(|| KeyProviderError::KekNotFound(kek_id.to_string()))
+        }
+    }
+
+    impl KeyProvider for MapProvider {
+        fn create_kek(&self) -> Result<String, KeyProviderError> {
+            Ok(self.current.to_string())
+        }
+
+        fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+            Ok(self.current.to_string())
+        }
+
+        fn wrap_dek(&self, kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+            let kek = self.kek(kek_id)?;
+            Ok(dek.iter().map(|b| b ^ kek).collect())
+        }
+
+        fn unwrap_dek(
+            &self,
+            kek_id: &str,
+            wrapped_dek: &[u8],
+        ) -> Result<SecretVec<u8>, KeyProviderError> {
+            self.unwrap_calls.fetch_add(1, Ordering::SeqCst);
+            let kek = self.kek(kek_id)?;
+            Ok(SecretVec::new(wrapped_dek.iter().map(|b| b ^ kek).collect()))
+        }
+
+        fn get_pepper(&self) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+            Ok(self.pepper.clone().map(SecretVec::new))
+        }
+    }
+
+    fn migrating() -> FallbackProvider<MapProvider, MapProvider> {
+        FallbackProvider::new(
+            MapProvider::new(&[("kms_v1", 0x11)]),
+            MapProvider::new(&[("file_v2", 0x22), ("file_v1", 0x33)]),
+        )
+    }
+
+    #[test]
+    fn test_unwrap_resolves_from_either_provider() {
+        let provider = migrating();
+        let dek = [7u8; 32];
+
+        let new = provider.primary().wrap_dek("kms_v1", &dek).unwrap();
+        let old = provider.secondary().wrap_dek("file_v1", &dek).unwrap();
+
+        assert_eq!(provider.unwrap_dek("kms_v1", &new).unwrap().expose_secret(), &dek);
+        assert_eq!(provider.secondary().unwrap_calls.load(Ordering::SeqCst), 0);
+
+        assert_eq!(provider.unwrap_dek("file_v1", &old).unwrap().expose_secret(), &dek);
+        assert_eq!(provider.primary().unwrap_calls.load(Ordering::SeqCst), 2);
+        assert_eq!(provider.secondary().unwrap_calls.load(Ordering::SeqCst), 1);
+
+        let result = provider.unwrap_dek("unknown", &old);
+        assert!(matches!(result, Err(KeyProviderError::KekNotFound(_))));
+    }
+
+    #[test]
+    fn test_writes_use_primary() {
+        let provider = migrating();
+
+        assert_eq!(provider.current_kek_id().unwrap(), "kms_v1");
+        assert_eq!(provider.current_kek_id_for_tenant("acme").unwrap(), "kms_v1");
+        assert_eq!(provider.create_kek().unwrap(), "kms_v1");
+
+        // A secondary KEK is never used to wrap
+        let result = provider.wrap_dek("file_v2", &[7u8; 32]);
+        assert!(matches!(result, Err(KeyProviderError::KekNotFound(_))));
+    }
+
+    #[test]
+    fn test_rewrap_moves_dek_to_primary() {
+        let provider = migrating();
+        let dek = [7u8; 32];
+        let old = provider.secondary().wrap_dek("file_v2", &dek).unwrap();
+
+        let rewrapped = provider.rewrap_dek("file_v2", "kms_v1", &old).unwrap();
+        let unwrapped = provider.primary().unwrap_dek("kms_v1", &rewrapped).unwrap();
+        assert_eq!(unwrapped.expose_secret(), &dek);
+    }
+
+    #[test]
+    fn test_non_kek_errors_do_not_fall_back() {
+        struct Unavailable;
+
+        impl KeyProvider for Unavailable {
+            fn create_kek(&self) -> Result<String, KeyProviderError> {
+                Err(KeyProviderError::Unavailable("down".to_string()))
+            }
+
+            fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+                Err(KeyProviderError::Unavailable("down".to_string()))
+            }
+
+            fn wrap_dek(&self, _kek_id: &str, _dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+                Err(KeyProviderError::Unavailable("down".to_string()))
+            }
+
+            fn unwrap_dek(
+                &self,
+                _kek_id: &str,
+                _wrapped_dek: &[u8],
+            ) -> Result<SecretVec<u8>, KeyProviderError> {
+                Err(KeyProviderError::Unavailable("down".to_string()))
+            }
+        }
+
+        let provider = FallbackProvider::new(Unavailable, MapProvider::new(&[("file_v1", 0x33)]));
+        let old = provider.secondary().wrap_dek("file_v1", &[7u8; 32]).unwrap();
+
+        let result = provider.unwrap_dek("file_v1", &old);
+        assert!(matches!(result, Err(KeyProviderError::Unavailable(_))));
+        assert_eq!(provider.secondary().unwrap_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_pepper_prefers_primary() {
+        let provider = migrating();
+        assert!(provider.get_pepper().unwrap().is_none());
+
+        let provider = FallbackProvider::new(
+            MapProvider::new(&[("kms_v1", 0x11)]),
+            MapProvider::new(&[("file_v1", 0x33)]).with_pepper(b"old pepper"),
+        );
+        assert_eq!(provider.get_pepper().unwrap().unwrap().expose_secret(), b"old pepper");
+        assert_eq!(provider.get_pepper_version(1).unwrap().unwrap().expose_secret(), b"old pepper");
+
+        let provider = FallbackProvider::new(
+            MapProvider::new(&[("kms_v1", 0x11)]).with_pepper(b"new pepper"),
+            MapProvider::new(&[("file_v1", 0x33)]).with_pepper(b"old pepper"),
+        );
+        assert_eq!(provider.get_pepper().unwrap().unwrap().expose_secret(), b"new pepper");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_vault_decrypts_unmigrated_data() {
+        use crate::context::EncryptionContext;
+        use crate::vault::{CipherMode, Vault};
+
+        let context = EncryptionContext::new("users", "email");
+        let old_vault = Vault::new(MapProvider::new(&[("file_v1", 0x33)]), CipherMode::default());
+        let old = old_vault.encrypt(b"alice@example.com", &context).unwrap();
+
+        let vault = Vault::new(migrating(), CipherMode::default());
+        assert_eq!(vault.decrypt(&old, &context).unwrap(), b"alice@example.com");
+
+        let new = vault.encrypt(b"bob@example.com", &context).unwrap();
+        assert_eq!(new.kek_id(), "kms_v1");
+        assert_eq!(vault.decrypt(&new, &context).unwrap(), b"bob@example.com");
+    }
+}
@@ -5,12 +5,60 @@
 //! - KEK identifier
 //! - Wrapped DEK
 //! - Flags
+//! - Cipher identifier (optional, protocol version 2)
+//! - Creation timestamp (optional, protocol version 2)
+//! - Encryption context version (optional, protocol version 2)
+//! - Payload length (optional, protocol version 3)
+//! - Key commitment (optional, protocol version 4)
+//! - Additional recipients (optional, protocol version 2)
 //! - Nonce
+//! - Header MAC (optional, protocol version 4)
 
 use crate::error::Error;
+use crate::key_provider::WrappedDek;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
 
 /// Protocol version for the encryption format.
-pub const PROTOCOL_VERSION: u8 = 1;
+///
+/// Version 3 added the payload length field; version 2 the optional
+/// cipher id, timestamp, context version, and recipient fields.
+pub const PROTOCOL_VERSION: u8 = 3;
+
+/// Newest protocol version this reader accepts.
+///
+/// Version 4 adds an extension flags byte after `flags`, marking a trailing
+/// header MAC, a DEK derived per context or a key commitment. It is written
+/// only for headers that use one of those, so everything else stays on
+/// [`PROTOCOL_VERSION`] and readable by releases that predate it.
+pub const MAX_PROTOCOL_VERSION: u8 = 4;
+
+/// Size of the key commitment field in bytes.
+pub const COMMITMENT_SIZE: usize = 32;
+
+/// Size of the header MAC field in bytes.
+pub const HEADER_MAC_SIZE: usize = 32;
+
+/// Extension flag (protocol version 4) marking a trailing header MAC.
+const EXT_HEADER_MAC: u8 = 0x01;
+
+/// Extension flag (protocol version 4) marking a wrapped key that the
+/// payload DEK is derived from per context, rather than the DEK itself.
+const EXT_DERIVED_DEK: u8 = 0x02;
+
+/// Extension flag (protocol version 4) marking a key commitment field.
+const EXT_COMMITTED: u8 = 0x04;
+
+/// Every extension flag bit this version of the format understands.
+const EXT_KNOWN_MASK: u8 = EXT_HEADER_MAC | EXT_DERIVED_DEK | EXT_COMMITTED;
+
+/// Oldest protocol version this reader still accepts.
+///
+/// Version 1 headers have no creation timestamp field.
+pub const MIN_PROTOCOL_VERSION: u8 = 1;
 
 /// Header flags for encryption options.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -36,6 +84,93 @@ impl HeaderFlags {
         self
     }
 
+    /// Checks if the payload was compressed before encryption.
+    #[must_use]
+    pub const fn is_compressed(self) -> bool {
+        (self.0 & 0x02) != 0
+    }
+
+    /// Sets compressed payload flag.
+    #[must_use]
+    pub const fn with_compressed(mut self) -> Self {
+        self.0 |= 0x02;
+        self
+    }
+
+    /// Checks if the header carries a creation timestamp.
+    #[must_use]
+    pub const fn has_timestamp(self) -> bool {
+        (self.0 & 0x04) != 0
+    }
+
+    /// Sets creation timestamp flag.
+    #[must_use]
+    pub const fn with_timestamp(mut self) -> Self {
+        self.0 |= 0x04;
+        self
+    }
+
+    /// Checks if the DEK is also wrapped for additional recipients.
+    #[must_use]
+    pub const fn has_multiple_recipients(self) -> bool {
+        (self.0 & 0x08) != 0
+    }
+
+    /// Sets multiple recipients flag.
+    #[must_use]
+    pub const fn with_multiple_recipients(mut self) -> Self {
+        self.0 |= 0x08;
+        self
+    }
+
+    /// Checks if the header names the AEAD cipher explicitly.
+    #[must_use]
+    pub const fn has_cipher_id(self) -> bool {
+        (self.0 & 0x10) != 0
+    }
+
+    /// Sets cipher identifier flag.
+    #[must_use]
+    pub const fn with_cipher_id(mut self) -> Self {
+        self.0 |= 0x10;
+        self
+    }
+
+    /// Checks if the header records the encryption context version.
+    #[must_use]
+    pub const fn has_context_version(self) -> bool {
+        (self.0 & 0x20) != 0
+    }
+
+    /// Sets context version flag.
+    #[must_use]
+    pub const fn with_context_version(mut self) -> Self {
+        self.0 |= 0x20;
+        self
+    }
+
+    /// Checks if the header records the payload length.
+    #[must_use]
+    pub const fn has_payload_len(self) -> bool {
+        (self.0 & 0x40) != 0
+    }
+
+    /// Sets payload length flag.
+    #[must_use]
+    pub const fn with_payload_len(mut self) -> Self {
+        self.0 |= 0x40;
+        self
+    }
+
+    /// Clears the flags that describe optional header fields.
+    ///
+    /// Those flags are derived from the fields themselves when a header is built.
+    #[must_use]
+    const fn without_field_flags(mut self) -> Self {
+        self.0 &= !(0x04 | 0x08 | 0x10 | 0x20 | 0x40);
+        self
+    }
+
     /// Returns the raw flags value.
     #[must_use]
     pub const fn as_u8(self) -> u8 {
@@ -43,25 +178,132 @@ impl HeaderFlags {
     }
 
     /// Creates flags from a raw value.
+    ///
+    /// Any bits are accepted here; [`EncryptionHeader::from_bytes`] rejects
+    /// bits outside [`HeaderFlags::known_mask`].
     #[must_use]
     pub const fn from_u8(value: u8) -> Self {
         Self(value)
     }
+
+    /// Returns every flag bit this version of the format understands.
+    ///
+    /// A bit outside the mask was set by a newer writer and may change how
+    /// the header or payload must be read, so such headers are refused
+    /// rather than misread.
+    #[must_use]
+    pub const fn known_mask() -> u8 {
+        Self::empty()
+            .with_deterministic()
+            .with_compressed()
+            .with_timestamp()
+            .with_multiple_recipients()
+            .with_cipher_id()
+            .with_context_version()
+            .with_payload_len()
+            .as_u8()
+    }
+
+    /// Returns the set bits outside [`HeaderFlags::known_mask`].
+    #[must_use]
+    pub const fn unknown_bits(self) -> u8 {
+        self.0 & !Self::known_mask()
+    }
 }
 
 /// Encryption header containing metadata for decryption.
 ///
 /// Format:
 /// ```text
-/// [version:1][kek_id_len:1][kek_id:N][wrapped_dek_len:2][wrapped_dek:M][flags:1][nonce_len:1][nonce:L]
+/// [version:1][kek_id_len:1][kek_id:N][wrapped_dek_len:2][wrapped_dek:M][flags:1][ext_flags:1]?[cipher_id:1]?[created_at:8]?[context_version:4]?[payload_len:4]?[commitment:32]?[recipients]?[nonce_len:1][nonce:L][header_mac:32]?
 /// ```
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// `ext_flags` is present in protocol version 4 and later only. Bit `0x01`
+/// marks the trailing `header_mac`; bit `0x02` means the wrapped DEK is a
+/// root key the payload DEK is derived from with the encryption context (see
+/// [`Vault::deriving`](crate::vault::Vault::deriving)); bit `0x04` marks the
+/// `commitment` field.
+///
+/// `cipher_id` identifies the AEAD cipher, present only when the cipher id
+/// flag is set (protocol version 2 and later). Headers without it were
+/// written with ChaCha20-Poly1305.
+///
+/// `created_at` is a big-endian count of Unix milliseconds, present only when
+/// the timestamp flag is set (protocol version 2 and later).
+///
+/// `context_version` is the big-endian [`EncryptionContext`](crate::context::EncryptionContext) version used at
+/// encryption time, present only when the context version flag is set
+/// (protocol version 2 and later). It lets decryption report a version
+/// mismatch instead of a bare authentication failure.
+///
+/// `payload_len` is the big-endian length of the AEAD payload that follows
+/// the header, present only when the payload length flag is set (protocol
+/// version 3 and later). It lets several ciphertexts be stored back to back
+/// and split again with [`Ciphertext::parse_one`](crate::ciphertext::Ciphertext::parse_one).
+///
+/// `commitment` commits the header to the DEK its payload was sealed under,
+/// present only when the key commitment extension flag is set (protocol
+/// version 4 and later). The AEAD ciphers are not key-committing, so without it a payload
+/// can be crafted to authenticate under two DEKs, e.g. each wrapped for a
+/// different recipient.
+///
+/// `recipients` is present only when the multiple recipients flag is set
+/// (protocol version 2 and later): a 1-byte count followed by that many
+/// `[kek_id_len:1][kek_id][wrapped_dek_len:2][wrapped_dek]` entries, each
+/// wrapping the same DEK as the primary `kek_id`.
+///
+/// `header_mac` authenticates the header itself, present only when the
+/// header MAC extension flag is set. It covers every field except the
+/// recipient entries, which are bound anyway because each must unwrap to the
+/// DEK the MAC is keyed by; leaving them out lets a header be rewrapped
+/// without the DEK. See [`EncryptionHeader::mac_input`].
+///
+/// The `Debug` output shows the lengths of the wrapped DEK and nonce but
+/// never their contents, so headers are safe to log.
+#[derive(Clone, PartialEq, Eq)]
 pub struct EncryptionHeader {
     version: u8,
-    kek_id: String,
-    wrapped_dek: Vec<u8>,
+    primary: WrappedDek,
     flags: HeaderFlags,
+    cipher_id: Option<u8>,
+    created_at: Option<u64>,
+    context_version: Option<u32>,
+    payload_len: Option<u32>,
+    commitment: Option<[u8; COMMITMENT_SIZE]>,
+    additional_recipients: Vec<WrappedDek>,
     nonce: Vec<u8>,
+    // Boxed so headers without one (the default) don't grow by its size
+    header_mac: Option<Box<[u8; HEADER_MAC_SIZE]>>,
+    derived_dek: bool,
+}
+
+impl fmt::Debug for EncryptionHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptionHeader")
+            .field("version", &self.version)
+            .field("kek_id", &self.primary.kek_id)
+            .field("wrapped_dek", &ByteCount(self.primary.encrypted_dek.len()))
+            .field("flags", &self.flags)
+            .field("cipher_id", &self.cipher_id)
+            .field("created_at", &self.created_at)
+            .field("context_version", &self.context_version)
+            .field("payload_len", &self.payload_len)
+            .field("commitment", &self.commitment.map(|commitment| ByteCount(commitment.len())))
+            .field("additional_recipients", &self.additional_recipients)
+            .field("nonce", &ByteCount(self.nonce.len()))
+            .field("header_mac", &self.header_mac.as_ref().map(|mac| ByteCount(mac.len())))
+            .field("derived_dek", &self.derived_dek)
+            .finish()
+    }
+}
+
+/// Formats as `[N bytes]`, standing in for redacted byte contents in `Debug` output.
+pub(crate) struct ByteCount(pub(crate) usize);
+
+impl fmt::Debug for ByteCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{} bytes]", self.0)
+    }
 }
 
 impl EncryptionHeader {
@@ -80,7 +322,138 @@ impl EncryptionHeader {
         flags: HeaderFlags,
         nonce: Vec<u8>,
     ) -> Self {
-        Self { version: PROTOCOL_VERSION, kek_id: kek_id.into(), wrapped_dek, flags, nonce }
+        Self::from_wrapped_dek(
+            WrappedDek { kek_id: kek_id.into(), encrypted_dek: wrapped_dek },
+            flags,
+            nonce,
+        )
+    }
+
+    /// Creates a new encryption header for a DEK wrapped by a key provider.
+    ///
+    /// Equivalent to [`EncryptionHeader::new`] with the wrapped DEK's
+    /// `kek_id` and `encrypted_dek`.
+    #[must_use]
+    pub const fn from_wrapped_dek(
+        wrapped_dek: WrappedDek,
+        flags: HeaderFlags,
+        nonce: Vec<u8>,
+    ) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            primary: wrapped_dek,
+            flags: flags.without_field_flags(),
+            cipher_id: None,
+            created_at: None,
+            context_version: None,
+            payload_len: None,
+            commitment: None,
+            additional_recipients: Vec::new(),
+            nonce,
+            header_mac: None,
+            derived_dek: false,
+        }
+    }
+
+    /// Records the AEAD cipher identifier and sets the cipher id flag.
+    #[must_use]
+    pub const fn with_cipher_id(mut self, cipher_id: u8) -> Self {
+        self.cipher_id = Some(cipher_id);
+        self.flags = self.flags.with_cipher_id();
+        self
+    }
+
+    /// Records the creation time (Unix milliseconds) and sets the timestamp flag.
+    #[must_use]
+    pub const fn with_created_at(mut self, created_at: u64) -> Self {
+        self.created_at = Some(created_at);
+        self.flags = self.flags.with_timestamp();
+        self
+    }
+
+    /// Records the encryption context version and sets the context version flag.
+    #[must_use]
+    pub const fn with_context_version(mut self, context_version: u32) -> Self {
+        self.context_version = Some(context_version);
+        self.flags = self.flags.with_context_version();
+        self
+    }
+
+    /// Records the length of the payload following the header and sets the
+    /// payload length flag.
+    #[must_use]
+    pub const fn with_payload_len(mut self, payload_len: u32) -> Self {
+        self.payload_len = Some(payload_len);
+        self.flags = self.flags.with_payload_len();
+        self
+    }
+
+    /// Records the key commitment, moving the header to protocol version 4.
+    #[must_use]
+    pub const fn with_commitment(mut self, commitment: [u8; COMMITMENT_SIZE]) -> Self {
+        self.commitment = Some(commitment);
+        self.version = MAX_PROTOCOL_VERSION;
+        self
+    }
+
+    /// Records the header MAC, moving the header to protocol version 4.
+    ///
+    /// The MAC is computed over [`EncryptionHeader::mac_input`], which
+    /// already accounts for the MAC being present, so setting a placeholder
+    /// first is not needed.
+    #[must_use]
+    pub fn with_header_mac(mut self, header_mac: [u8; HEADER_MAC_SIZE]) -> Self {
+        self.header_mac = Some(Box::new(header_mac));
+        self.version = MAX_PROTOCOL_VERSION;
+        self
+    }
+
+    /// Marks the wrapped DEK as a root key the payload DEK is derived from
+    /// per context, moving the header to protocol version 4.
+    #[must_use]
+    pub const fn with_derived_dek(mut self) -> Self {
+        self.derived_dek = true;
+        self.version = MAX_PROTOCOL_VERSION;
+        self
+    }
+
+    /// Adds KEKs that the same DEK is also wrapped under.
+    ///
+    /// Any recipient can decrypt the ciphertext. An empty list keeps the
+    /// compact single-recipient format.
+    #[must_use]
+    pub fn with_additional_recipients(mut self, recipients: Vec<WrappedDek>) -> Self {
+        self.flags = if recipients.is_empty() {
+            HeaderFlags::from_u8(self.flags.as_u8() & !0x08)
+        } else {
+            self.flags.with_multiple_recipients()
+        };
+        self.additional_recipients = recipients;
+        self
+    }
+
+    /// Returns a copy of this header with the primary DEK wrapped under another KEK.
+    ///
+    /// Every other field (flags, cipher, timestamp, additional recipients,
+    /// nonce) is kept, so the payload it describes stays decryptable.
+    ///
+    /// A header MAC stays valid, since it doesn't cover recipient entries.
+    #[must_use]
+    pub fn rewrapped(&self, kek_id: impl Into<String>, wrapped_dek: Vec<u8>) -> Self {
+        Self {
+            version: if self.ext_flags() == 0 { PROTOCOL_VERSION } else { MAX_PROTOCOL_VERSION },
+            primary: WrappedDek { kek_id: kek_id.into(), encrypted_dek: wrapped_dek },
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of this header with a different nonce, e.g. one derived
+    /// per chunk.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub(crate) fn with_nonce(mut self, nonce: Vec<u8>) -> Self {
+        self.nonce = nonce;
+        self
     }
 
     /// Returns the protocol version.
@@ -92,13 +465,19 @@ impl EncryptionHeader {
     /// Returns the KEK identifier.
     #[must_use]
     pub fn kek_id(&self) -> &str {
-        &self.kek_id
+        &self.primary.kek_id
     }
 
     /// Returns the wrapped DEK.
     #[must_use]
     pub fn wrapped_dek(&self) -> &[u8] {
-        &self.wrapped_dek
+        &self.primary.encrypted_dek
+    }
+
+    /// Returns the primary KEK identifier and wrapped DEK together.
+    #[must_use]
+    pub const fn primary_recipient(&self) -> &WrappedDek {
+        &self.primary
     }
 
     /// Returns the header flags.
@@ -107,6 +486,90 @@ impl EncryptionHeader {
         self.flags
     }
 
+    /// Returns the AEAD cipher identifier, if recorded.
+    #[must_use]
+    pub const fn cipher_id(&self) -> Option<u8> {
+        self.cipher_id
+    }
+
+    /// Returns the creation time in Unix milliseconds, if recorded.
+    #[must_use]
+    pub const fn created_at(&self) -> Option<u64> {
+        self.created_at
+    }
+
+    /// Returns the encryption context version, if recorded.
+    #[must_use]
+    pub const fn context_version(&self) -> Option<u32> {
+        self.context_version
+    }
+
+    /// Returns the length of the payload following the header, if recorded.
+    #[must_use]
+    pub const fn payload_len(&self) -> Option<u32> {
+        self.payload_len
+    }
+
+    /// Returns the key commitment, if recorded.
+    #[must_use]
+    pub const fn commitment(&self) -> Option<&[u8; COMMITMENT_SIZE]> {
+        self.commitment.as_ref()
+    }
+
+    /// Returns the header MAC, if recorded.
+    #[must_use]
+    pub fn header_mac(&self) -> Option<&[u8; HEADER_MAC_SIZE]> {
+        self.header_mac.as_deref()
+    }
+
+    /// Returns the bytes the header MAC is computed over.
+    ///
+    /// This is the serialized header as if it carried a header MAC, with the
+    /// recipient entries (primary KEK id and wrapped DEK, and any additional
+    /// recipients) and the MAC itself left out. The number of additional
+    /// recipients is still covered.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`to_bytes`](Self::to_bytes).
+    pub fn mac_input(&self) -> Result<Vec<u8>, Error> {
+        let mut authenticated = self.clone();
+        authenticated.header_mac = Some(Box::new([0; HEADER_MAC_SIZE]));
+        authenticated.version = MAX_PROTOCOL_VERSION;
+
+        let mut bytes = Vec::with_capacity(authenticated.encoded_len());
+        authenticated.write_fields(&mut bytes, false)?;
+        Ok(bytes)
+    }
+
+    /// Checks if the payload DEK is derived from the wrapped key and the
+    /// encryption context.
+    #[must_use]
+    pub const fn derives_dek(&self) -> bool {
+        self.derived_dek
+    }
+
+    /// Returns the extension flags byte written in protocol version 4.
+    const fn ext_flags(&self) -> u8 {
+        let mut ext_flags = 0;
+        if self.header_mac.is_some() {
+            ext_flags |= EXT_HEADER_MAC;
+        }
+        if self.derived_dek {
+            ext_flags |= EXT_DERIVED_DEK;
+        }
+        if self.commitment.is_some() {
+            ext_flags |= EXT_COMMITTED;
+        }
+        ext_flags
+    }
+
+    /// Returns the recipients other than the primary `kek_id`.
+    #[must_use]
+    pub fn additional_recipients(&self) -> &[WrappedDek] {
+        &self.additional_recipients
+    }
+
     /// Returns the nonce.
     #[must_use]
     pub fn nonce(&self) -> &[u8] {
@@ -117,21 +580,75 @@ impl EncryptionHeader {
     ///
     /// # Errors
     ///
-    /// Returns error if the KEK ID is too long (> 255 bytes) or if
-    /// the wrapped DEK is too long (> 65535 bytes).
+    /// Returns error if a KEK ID is too long (> 255 bytes), if a wrapped
+    /// DEK is too long (> 65535 bytes), or if there are more than 255
+    /// additional recipients.
     pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write_to(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Returns the number of bytes [`to_bytes`](Self::to_bytes) produces.
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        let mut len = 1 + self.primary.encoded_len() + 1;
+        if self.version >= 4 {
+            len += 1;
+        }
+        if self.cipher_id.is_some() {
+            len += 1;
+        }
+        if self.created_at.is_some() {
+            len += 8;
+        }
+        if self.context_version.is_some() {
+            len += 4;
+        }
+        if self.payload_len.is_some() {
+            len += 4;
+        }
+        if self.commitment.is_some() {
+            len += COMMITMENT_SIZE;
+        }
+        if !self.additional_recipients.is_empty() {
+            len +=
+                1 + self.additional_recipients.iter().map(WrappedDek::encoded_len).sum::<usize>();
+        }
+        if self.header_mac.is_some() {
+            len += HEADER_MAC_SIZE;
+        }
+        len + 1 + self.nonce.len()
+    }
+
+    /// Appends the serialized header to `bytes`.
+    ///
+    /// Produces exactly the bytes of [`to_bytes`](Self::to_bytes), letting
+    /// callers serialize straight into a larger buffer. Nothing is written
+    /// if validation fails.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`to_bytes`](Self::to_bytes).
+    pub fn write_to(&self, bytes: &mut Vec<u8>) -> Result<(), Error> {
+        self.write_fields(bytes, true)
+    }
+
+    /// Appends the serialized header to `bytes`, leaving out the recipient
+    /// entries and header MAC unless `recipients` is set (see
+    /// [`mac_input`](Self::mac_input)).
+    fn write_fields(&self, bytes: &mut Vec<u8>, recipients: bool) -> Result<(), Error> {
         // Validate lengths
-        if self.kek_id.len() > 255 {
-            return Err(Error::InvalidHeader(format!(
-                "KEK ID too long: {} bytes (max: 255)",
-                self.kek_id.len()
-            )));
+        validate_recipient(&self.primary)?;
+
+        for recipient in &self.additional_recipients {
+            validate_recipient(recipient)?;
         }
 
-        if self.wrapped_dek.len() > 65535 {
+        if self.additional_recipients.len() > 255 {
             return Err(Error::InvalidHeader(format!(
-                "Wrapped DEK too long: {} bytes (max: 65535)",
-                self.wrapped_dek.len()
+                "Too many recipients: {} (max: 255)",
+                self.additional_recipients.len()
             )));
         }
 
@@ -142,36 +659,77 @@ impl EncryptionHeader {
             )));
         }
 
-        let mut bytes = Vec::new();
+        // Never write a header this reader would refuse
+        if self.flags.unknown_bits() != 0 {
+            return Err(Error::UnsupportedFeature { flags: self.flags.unknown_bits() });
+        }
 
         // Version (1 byte)
         bytes.push(self.version);
 
-        // KEK ID length (1 byte) + KEK ID
-        // Safe cast: length validated above (line 124-128, max 255)
-        #[allow(clippy::cast_possible_truncation)]
-        let kek_id_len = self.kek_id.len() as u8;
-        bytes.push(kek_id_len);
-        bytes.extend_from_slice(self.kek_id.as_bytes());
-
-        // Wrapped DEK length (2 bytes, big-endian) + wrapped DEK
-        // Safe cast: length validated above (line 130-135, max 65535)
-        #[allow(clippy::cast_possible_truncation)]
-        let wrapped_dek_len = self.wrapped_dek.len() as u16;
-        bytes.extend_from_slice(&wrapped_dek_len.to_be_bytes());
-        bytes.extend_from_slice(&self.wrapped_dek);
+        // KEK ID + wrapped DEK
+        if recipients {
+            write_recipient(bytes, &self.primary);
+        }
 
         // Flags (1 byte)
         bytes.push(self.flags.as_u8());
 
+        // Extension flags (1 byte), protocol version 4 and later
+        if self.version >= 4 {
+            bytes.push(self.ext_flags());
+        }
+
+        // Cipher identifier (1 byte), only when flagged
+        if let Some(cipher_id) = self.cipher_id {
+            bytes.push(cipher_id);
+        }
+
+        // Creation timestamp (8 bytes, big-endian), only when flagged
+        if let Some(created_at) = self.created_at {
+            bytes.extend_from_slice(&created_at.to_be_bytes());
+        }
+
+        // Context version (4 bytes, big-endian), only when flagged
+        if let Some(context_version) = self.context_version {
+            bytes.extend_from_slice(&context_version.to_be_bytes());
+        }
+
+        // Payload length (4 bytes, big-endian), only when flagged
+        if let Some(payload_len) = self.payload_len {
+            bytes.extend_from_slice(&payload_len.to_be_bytes());
+        }
+
+        // Key commitment (32 bytes), only when extension flagged
+        if let Some(commitment) = &self.commitment {
+            bytes.extend_from_slice(commitment);
+        }
+
+        // Additional recipients (count + entries), only when flagged
+        if !self.additional_recipients.is_empty() {
+            // Safe cast: count validated above (max 255)
+            #[allow(clippy::cast_possible_truncation)]
+            bytes.push(self.additional_recipients.len() as u8);
+            if recipients {
+                for recipient in &self.additional_recipients {
+                    write_recipient(bytes, recipient);
+                }
+            }
+        }
+
         // Nonce length (1 byte) + nonce
-        // Safe cast: length validated above (line 137-142, max 255)
+        // Safe cast: length validated above (max 255)
         #[allow(clippy::cast_possible_truncation)]
         let nonce_len = self.nonce.len() as u8;
         bytes.push(nonce_len);
         bytes.extend_from_slice(&self.nonce);
 
-        Ok(bytes)
+        // Header MAC (32 bytes), only when flagged
+        if let (Some(header_mac), true) = (&self.header_mac, recipients) {
+            bytes.extend_from_slice(header_mac.as_slice());
+        }
+
+        Ok(())
     }
 
     /// Deserializes a header from bytes.
@@ -181,6 +739,7 @@ impl EncryptionHeader {
     /// Returns error if:
     /// - The data is too short
     /// - The version is not supported
+    /// - A flag bit unknown to this reader is set
     /// - The data is malformed
     pub fn from_bytes(data: &[u8]) -> Result<(Self, usize), Error> {
         if data.is_empty() {
@@ -193,46 +752,86 @@ impl EncryptionHeader {
         let version = data[pos];
         pos += 1;
 
-        if version != PROTOCOL_VERSION {
+        if !(MIN_PROTOCOL_VERSION..=MAX_PROTOCOL_VERSION).contains(&version) {
             return Err(Error::UnsupportedVersion {
                 version,
-                supported: PROTOCOL_VERSION.to_string(),
+                supported: format!("{MIN_PROTOCOL_VERSION}-{MAX_PROTOCOL_VERSION}"),
             });
         }
 
-        // KEK ID
+        // KEK ID + wrapped DEK
+        let primary = read_recipient(data, &mut pos)?;
+
+        // Flags
         if pos >= data.len() {
-            return Err(Error::InvalidHeader("Missing KEK ID length".to_string()));
+            return Err(Error::InvalidHeader("Missing flags".to_string()));
         }
-        let kek_id_len = data[pos] as usize;
+        let flags = HeaderFlags::from_u8(data[pos]);
         pos += 1;
 
-        if pos + kek_id_len > data.len() {
-            return Err(Error::InvalidHeader("KEK ID truncated".to_string()));
+        // Unknown bits could change how the rest must be read, so fail loudly
+        if flags.unknown_bits() != 0 {
+            return Err(Error::UnsupportedFeature { flags: flags.unknown_bits() });
         }
-        let kek_id = String::from_utf8(data[pos..pos + kek_id_len].to_vec())
-            .map_err(|e| Error::InvalidHeader(format!("Invalid KEK ID UTF-8: {e}")))?;
-        pos += kek_id_len;
 
-        // Wrapped DEK
-        if pos + 2 > data.len() {
-            return Err(Error::InvalidHeader("Missing wrapped DEK length".to_string()));
-        }
-        let wrapped_dek_len = u16::from_be_bytes([data[pos], data[pos + 1]]) as usize;
-        pos += 2;
+        // Extension flags
+        let ext_flags = read_ext_flags(data, &mut pos, version)?;
 
-        if pos + wrapped_dek_len > data.len() {
-            return Err(Error::InvalidHeader("Wrapped DEK truncated".to_string()));
-        }
-        let wrapped_dek = data[pos..pos + wrapped_dek_len].to_vec();
-        pos += wrapped_dek_len;
+        // Cipher identifier
+        let cipher_id = flags
+            .has_cipher_id()
+            .then(|| read_optional_field(data, &mut pos, version, 2, "Cipher id"))
+            .transpose()?
+            .map(|[cipher_id]| cipher_id);
 
-        // Flags
-        if pos >= data.len() {
-            return Err(Error::InvalidHeader("Missing flags".to_string()));
+        // Creation timestamp
+        let created_at = flags
+            .has_timestamp()
+            .then(|| read_optional_field(data, &mut pos, version, 2, "Timestamp"))
+            .transpose()?
+            .map(u64::from_be_bytes);
+
+        // Context version
+        let context_version = flags
+            .has_context_version()
+            .then(|| read_optional_field(data, &mut pos, version, 2, "Context version"))
+            .transpose()?
+            .map(u32::from_be_bytes);
+
+        // Payload length
+        let payload_len = flags
+            .has_payload_len()
+            .then(|| read_optional_field(data, &mut pos, version, 3, "Payload length"))
+            .transpose()?
+            .map(u32::from_be_bytes);
+
+        // Key commitment
+        let commitment = (ext_flags & EXT_COMMITTED != 0)
+            .then(|| read_optional_field(data, &mut pos, version, 4, "Key commitment"))
+            .transpose()?;
+
+        // Additional recipients
+        let mut additional_recipients = Vec::new();
+        if flags.has_multiple_recipients() {
+            if version < 2 {
+                return Err(Error::InvalidHeader(format!(
+                    "Multiple recipients flag not valid in protocol version {version}"
+                )));
+            }
+            let count = *data
+                .get(pos)
+                .ok_or_else(|| Error::InvalidHeader("Missing recipient count".to_string()))?;
+            pos += 1;
+
+            // The flag is only written alongside at least one recipient
+            if count == 0 {
+                return Err(Error::InvalidHeader("Recipient count is zero".to_string()));
+            }
+
+            for _ in 0..count {
+                additional_recipients.push(read_recipient(data, &mut pos)?);
+            }
         }
-        let flags = HeaderFlags::from_u8(data[pos]);
-        pos += 1;
 
         // Nonce
         if pos >= data.len() {
@@ -242,17 +841,186 @@ impl EncryptionHeader {
         pos += 1;
 
         if pos + nonce_len > data.len() {
-            return Err(Error::InvalidHeader("Nonce truncated".to_string()));
+            return Err(Error::InvalidHeader(format!(
+                "Nonce truncated: declared {nonce_len} bytes, {} available",
+                data.len() - pos
+            )));
         }
         let nonce = data[pos..pos + nonce_len].to_vec();
         pos += nonce_len;
 
-        let header = Self { version, kek_id, wrapped_dek, flags, nonce };
+        // Header MAC
+        let header_mac = (ext_flags & EXT_HEADER_MAC != 0)
+            .then(|| read_optional_field(data, &mut pos, version, 4, "Header MAC"))
+            .transpose()?
+            .map(Box::new);
+        let derived_dek = ext_flags & EXT_DERIVED_DEK != 0;
+
+        let header = Self {
+            version,
+            primary,
+            flags,
+            cipher_id,
+            created_at,
+            context_version,
+            payload_len,
+            commitment,
+            additional_recipients,
+            nonce,
+            header_mac,
+            derived_dek,
+        };
 
         Ok((header, pos))
     }
 }
 
+/// Parses the header of a serialized ciphertext without touching a key provider.
+///
+/// Useful for tooling that needs a ciphertext's KEK ID or protocol version,
+/// e.g. to bucket blobs by KEK before a rewrap. No DEK is unwrapped and the
+/// payload is not authenticated, so the result must not be trusted as proof
+/// that the ciphertext is genuine.
+///
+/// # Errors
+///
+/// Returns `Error::InvalidHeader` or `Error::UnsupportedVersion` if the
+/// header is malformed.
+pub fn peek_header(ciphertext: &[u8]) -> Result<EncryptionHeader, Error> {
+    EncryptionHeader::from_bytes(ciphertext).map(|(header, _)| header)
+}
+
+/// Reads the extension flags byte of a protocol version 4 header, or
+/// returns none set for older versions, which lack it.
+fn read_ext_flags(data: &[u8], pos: &mut usize, version: u8) -> Result<u8, Error> {
+    if version < 4 {
+        return Ok(0);
+    }
+    let [ext_flags] = read_optional_field(data, pos, version, 4, "Extension flags")?;
+
+    // Same as unknown flag bits: refuse rather than misread
+    if ext_flags & !EXT_KNOWN_MASK != 0 {
+        return Err(Error::UnsupportedFeature { flags: ext_flags & !EXT_KNOWN_MASK });
+    }
+    Ok(ext_flags)
+}
+
+/// Reads a fixed-size optional field, which only protocol version `since`
+/// and later carry.
+fn read_optional_field<const N: usize>(
+    data: &[u8],
+    pos: &mut usize,
+    version: u8,
+    since: u8,
+    name: &str,
+) -> Result<[u8; N], Error> {
+    if version < since {
+        return Err(Error::InvalidHeader(format!(
+            "{name} flag not valid in protocol version {version}"
+        )));
+    }
+    let bytes: [u8; N] = data
+        .get(*pos..*pos + N)
+        .and_then(|slice| slice.try_into().ok())
+        .ok_or_else(|| Error::InvalidHeader(format!("{name} truncated")))?;
+    *pos += N;
+    Ok(bytes)
+}
+
+/// Checks that a KEK ID and wrapped DEK are non-empty and fit their length prefixes.
+pub(crate) fn validate_recipient(recipient: &WrappedDek) -> Result<(), Error> {
+    let WrappedDek { kek_id, encrypted_dek: wrapped_dek } = recipient;
+
+    if kek_id.is_empty() {
+        return Err(Error::InvalidHeader("KEK ID is empty".to_string()));
+    }
+
+    if wrapped_dek.is_empty() {
+        return Err(Error::InvalidHeader("Wrapped DEK is empty".to_string()));
+    }
+
+    if kek_id.len() > 255 {
+        return Err(Error::InvalidHeader(format!(
+            "KEK ID too long: {} bytes (max: 255)",
+            kek_id.len()
+        )));
+    }
+
+    if wrapped_dek.len() > 65535 {
+        return Err(Error::InvalidHeader(format!(
+            "Wrapped DEK too long: {} bytes (max: 65535)",
+            wrapped_dek.len()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Writes `[kek_id_len:1][kek_id][wrapped_dek_len:2][wrapped_dek]`.
+///
+/// Lengths must already have been checked with [`validate_recipient`].
+pub(crate) fn write_recipient(bytes: &mut Vec<u8>, recipient: &WrappedDek) {
+    let WrappedDek { kek_id, encrypted_dek: wrapped_dek } = recipient;
+
+    // KEK ID length (1 byte) + KEK ID
+    // Safe cast: length validated (max 255)
+    #[allow(clippy::cast_possible_truncation)]
+    let kek_id_len = kek_id.len() as u8;
+    bytes.push(kek_id_len);
+    bytes.extend_from_slice(kek_id.as_bytes());
+
+    // Wrapped DEK length (2 bytes, big-endian) + wrapped DEK
+    // Safe cast: length validated (max 65535)
+    #[allow(clippy::cast_possible_truncation)]
+    let wrapped_dek_len = wrapped_dek.len() as u16;
+    bytes.extend_from_slice(&wrapped_dek_len.to_be_bytes());
+    bytes.extend_from_slice(wrapped_dek);
+}
+
+/// Reads a `[kek_id_len:1][kek_id][wrapped_dek_len:2][wrapped_dek]` entry at `pos`.
+pub(crate) fn read_recipient(data: &[u8], pos: &mut usize) -> Result<WrappedDek, Error> {
+    // KEK ID
+    if *pos >= data.len() {
+        return Err(Error::InvalidHeader("Missing KEK ID length".to_string()));
+    }
+    let kek_id_len = data[*pos] as usize;
+    *pos += 1;
+
+    if kek_id_len == 0 {
+        return Err(Error::InvalidHeader("KEK ID length is zero".to_string()));
+    }
+    if *pos + kek_id_len > data.len() {
+        return Err(Error::InvalidHeader(format!(
+            "KEK ID truncated: declared {kek_id_len} bytes, {} available",
+            data.len() - *pos
+        )));
+    }
+    let kek_id = String::from_utf8(data[*pos..*pos + kek_id_len].to_vec())
+        .map_err(|e| Error::InvalidHeader(format!("Invalid KEK ID UTF-8: {e}")))?;
+    *pos += kek_id_len;
+
+    // Wrapped DEK
+    if *pos + 2 > data.len() {
+        return Err(Error::InvalidHeader("Missing wrapped DEK length".to_string()));
+    }
+    let wrapped_dek_len = u16::from_be_bytes([data[*pos], data[*pos + 1]]) as usize;
+    *pos += 2;
+
+    if wrapped_dek_len == 0 {
+        return Err(Error::InvalidHeader("Wrapped DEK length is zero".to_string()));
+    }
+    if *pos + wrapped_dek_len > data.len() {
+        return Err(Error::InvalidHeader(format!(
+            "Wrapped DEK truncated: declared {wrapped_dek_len} bytes, {} available",
+            data.len() - *pos
+        )));
+    }
+    let encrypted_dek = data[*pos..*pos + wrapped_dek_len].to_vec();
+    *pos += wrapped_dek_len;
+
+    Ok(WrappedDek { kek_id, encrypted_dek })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -266,6 +1034,398 @@ mod tests {
         let flags = flags.with_deterministic();
         assert!(flags.is_deterministic());
         assert_eq!(flags.as_u8(), 1);
+
+        let flags = HeaderFlags::empty().with_compressed();
+        assert!(flags.is_compressed());
+        assert!(!flags.is_deterministic());
+        assert_eq!(flags.as_u8(), 0x02);
+
+        let flags = flags.with_deterministic();
+        assert!(flags.is_compressed() && flags.is_deterministic());
+        assert_eq!(flags.as_u8(), 0x03);
+
+        let flags = HeaderFlags::empty().with_timestamp();
+        assert!(flags.has_timestamp());
+        assert_eq!(flags.as_u8(), 0x04);
+
+        let flags = HeaderFlags::empty().with_multiple_recipients();
+        assert!(flags.has_multiple_recipients());
+        assert_eq!(flags.as_u8(), 0x08);
+
+        let flags = HeaderFlags::empty().with_cipher_id();
+        assert!(flags.has_cipher_id());
+        assert_eq!(flags.as_u8(), 0x10);
+
+        let flags = HeaderFlags::empty().with_context_version();
+        assert!(flags.has_context_version());
+        assert_eq!(flags.as_u8(), 0x20);
+    }
+
+    #[test]
+    fn test_header_cipher_id_round_trip() {
+        let header = EncryptionHeader::new("kek_v1", vec![1; 4], HeaderFlags::empty(), vec![3; 12])
+            .with_cipher_id(2)
+            .with_created_at(7);
+
+        let bytes = header.to_bytes().unwrap();
+        let (parsed, pos) = EncryptionHeader::from_bytes(&bytes).unwrap();
+
+        assert_eq!(parsed, header);
+        assert_eq!(parsed.cipher_id(), Some(2));
+        assert_eq!(parsed.created_at(), Some(7));
+        assert_eq!(pos, bytes.len());
+
+        let plain = EncryptionHeader::new("kek_v1", vec![1; 4], HeaderFlags::empty(), vec![3; 12]);
+        assert_eq!(plain.cipher_id(), None);
+        assert!(!plain.flags().has_cipher_id());
+    }
+
+    #[test]
+    fn test_header_rewrapped_keeps_fields() {
+        let header = EncryptionHeader::new("kek_a", vec![1; 4], HeaderFlags::empty(), vec![3; 12])
+            .with_cipher_id(2)
+            .with_created_at(7)
+            .with_additional_recipients(vec![WrappedDek {
+                kek_id: "kek_b".to_string(),
+                encrypted_dek: vec![2; 4],
+            }]);
+
+        let rewrapped = header.rewrapped("kek_c", vec![9; 4]);
+
+        assert_eq!(rewrapped.kek_id(), "kek_c");
+        assert_eq!(rewrapped.wrapped_dek(), &[9; 4]);
+        assert_eq!(rewrapped.flags(), header.flags());
+        assert_eq!(rewrapped.cipher_id(), Some(2));
+        assert_eq!(rewrapped.created_at(), Some(7));
+        assert_eq!(rewrapped.additional_recipients(), header.additional_recipients());
+        assert_eq!(rewrapped.nonce(), header.nonce());
+    }
+
+    #[test]
+    fn test_header_additional_recipients_round_trip() {
+        let recipients = vec![
+            WrappedDek { kek_id: "tenant_b".to_string(), encrypted_dek: vec![5; 40] },
+            WrappedDek { kek_id: "tenant_c".to_string(), encrypted_dek: vec![6; 40] },
+        ];
+        let header =
+            EncryptionHeader::new("tenant_a", vec![4; 40], HeaderFlags::empty(), vec![9; 12])
+                .with_created_at(1_700_000_000_000)
+                .with_additional_recipients(recipients.clone());
+
+        assert!(header.flags().has_multiple_recipients());
+
+        let bytes = header.to_bytes().unwrap();
+        let (parsed, pos) = EncryptionHeader::from_bytes(&bytes).unwrap();
+
+        assert_eq!(parsed, header);
+        assert_eq!(parsed.kek_id(), "tenant_a");
+        assert_eq!(parsed.additional_recipients(), recipients.as_slice());
+        assert_eq!(parsed.created_at(), Some(1_700_000_000_000));
+        assert_eq!(parsed.nonce(), &[9; 12]);
+        assert_eq!(pos, bytes.len());
+    }
+
+    #[test]
+    fn test_header_single_recipient_is_compact() {
+        let header = EncryptionHeader::new("kek_v1", vec![1; 4], HeaderFlags::empty(), vec![0; 12]);
+        let with_empty = header.clone().with_additional_recipients(Vec::new());
+
+        assert!(!with_empty.flags().has_multiple_recipients());
+        assert_eq!(with_empty.to_bytes().unwrap(), header.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn test_header_recipients_truncated() {
+        let header = EncryptionHeader::new("kek_a", vec![1; 4], HeaderFlags::empty(), vec![])
+            .with_additional_recipients(vec![WrappedDek {
+                kek_id: "kek_b".to_string(),
+                encrypted_dek: vec![2; 4],
+            }]);
+        let bytes = header.to_bytes().unwrap();
+
+        // Cut inside the additional recipient's wrapped DEK
+        let result = EncryptionHeader::from_bytes(&bytes[..bytes.len() - 3]);
+        assert!(matches!(result, Err(Error::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn test_header_created_at_round_trip() {
+        let header =
+            EncryptionHeader::new("kek_v1", vec![1, 2, 3, 4], HeaderFlags::empty(), vec![9; 12])
+                .with_created_at(1_700_000_000_123);
+
+        assert!(header.flags().has_timestamp());
+
+        let bytes = header.to_bytes().unwrap();
+        let (parsed, pos) = EncryptionHeader::from_bytes(&bytes).unwrap();
+
+        assert_eq!(parsed, header);
+        assert_eq!(parsed.created_at(), Some(1_700_000_000_123));
+        assert_eq!(parsed.nonce(), &[9; 12]);
+        assert_eq!(pos, bytes.len());
+    }
+
+    #[test]
+    fn test_header_context_version_round_trip() {
+        let header = EncryptionHeader::new("kek_v1", vec![1; 4], HeaderFlags::empty(), vec![9; 12])
+            .with_created_at(5)
+            .with_context_version(7);
+
+        assert!(header.flags().has_context_version());
+
+        let bytes = header.to_bytes().unwrap();
+        let (parsed, pos) = EncryptionHeader::from_bytes(&bytes).unwrap();
+
+        assert_eq!(parsed, header);
+        assert_eq!(parsed.context_version(), Some(7));
+        assert_eq!(parsed.created_at(), Some(5));
+        assert_eq!(pos, bytes.len());
+
+        // Cut inside the 4-byte context version
+        let cut = bytes.len() - 12 - 1 - 2;
+        assert!(matches!(
+            EncryptionHeader::from_bytes(&bytes[..cut]),
+            Err(Error::InvalidHeader(_))
+        ));
+    }
+
+    #[test]
+    fn test_header_payload_len_round_trip() {
+        let header = EncryptionHeader::new("kek_v1", vec![1; 4], HeaderFlags::empty(), vec![9; 12])
+            .with_context_version(7)
+            .with_payload_len(1234);
+
+        assert!(header.flags().has_payload_len());
+
+        let bytes = header.to_bytes().unwrap();
+        let (parsed, pos) = EncryptionHeader::from_bytes(&bytes).unwrap();
+
+        assert_eq!(parsed, header);
+        assert_eq!(parsed.payload_len(), Some(1234));
+        assert_eq!(parsed.context_version(), Some(7));
+        assert_eq!(pos, bytes.len());
+        assert_eq!(header.encoded_len(), bytes.len());
+
+        // Version 1 and 2 headers can't carry it
+        for version in [1, 2] {
+            let mut older = bytes.clone();
+            older[0] = version;
+            assert!(matches!(EncryptionHeader::from_bytes(&older), Err(Error::InvalidHeader(_))));
+        }
+    }
+
+    #[test]
+    fn test_header_commitment_round_trip() {
+        let header = EncryptionHeader::new("kek_v1", vec![1; 4], HeaderFlags::empty(), vec![9; 12])
+            .with_payload_len(1234)
+            .with_commitment([5; COMMITMENT_SIZE])
+            .with_additional_recipients(vec![WrappedDek {
+                kek_id: "kek_v2".to_string(),
+                encrypted_dek: vec![2; 4],
+            }]);
+
+        assert_eq!(header.version(), MAX_PROTOCOL_VERSION);
+        assert_eq!(header.flags().unknown_bits(), 0);
+
+        let bytes = header.to_bytes().unwrap();
+        let (parsed, pos) = EncryptionHeader::from_bytes(&bytes).unwrap();
+
+        assert_eq!(bytes[1 + 1 + 6 + 2 + 4 + 1], 0x04);
+        assert_eq!(parsed, header);
+        assert_eq!(parsed.commitment(), Some(&[5; COMMITMENT_SIZE]));
+        assert_eq!(parsed.additional_recipients().len(), 1);
+        assert_eq!(pos, bytes.len());
+        assert_eq!(header.encoded_len(), bytes.len());
+
+        // Rewrapping keeps it, and the version it needs
+        let rewrapped = header.rewrapped("kek_v2", vec![2; 4]);
+        assert_eq!(rewrapped.version(), MAX_PROTOCOL_VERSION);
+        assert_eq!(rewrapped.commitment(), Some(&[5; COMMITMENT_SIZE]));
+
+        // Nor can the flag be set without the field
+        let uncommitted =
+            EncryptionHeader::new("kek_v1", vec![1; 4], HeaderFlags::empty(), vec![9; 12])
+                .with_derived_dek();
+        assert_eq!(uncommitted.commitment(), None);
+        let mut bytes = uncommitted.to_bytes().unwrap();
+        bytes[1 + 1 + 6 + 2 + 4 + 1] |= 0x04;
+        assert!(matches!(
+            EncryptionHeader::from_bytes(&bytes),
+            Err(Error::InvalidHeader(msg)) if msg == "Key commitment truncated"
+        ));
+    }
+
+    #[test]
+    fn test_header_mac_round_trip() {
+        let header = EncryptionHeader::new("kek_v1", vec![1; 4], HeaderFlags::empty(), vec![9; 12])
+            .with_payload_len(1234);
+        assert_eq!(header.version(), PROTOCOL_VERSION);
+
+        let mac_input = header.mac_input().unwrap();
+        let header = header.with_header_mac([0x42; HEADER_MAC_SIZE]);
+        assert_eq!(header.version(), MAX_PROTOCOL_VERSION);
+        assert_eq!(header.mac_input().unwrap(), mac_input);
+
+        let bytes = header.to_bytes().unwrap();
+        let (parsed, pos) = EncryptionHeader::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, header);
+        assert_eq!(parsed.header_mac(), Some(&[0x42; HEADER_MAC_SIZE]));
+        assert_eq!(pos, bytes.len());
+        assert_eq!(header.encoded_len(), bytes.len());
+        assert_eq!(&bytes[bytes.len() - HEADER_MAC_SIZE..], &[0x42; HEADER_MAC_SIZE]);
+
+        // The MAC covers everything but the recipients and itself
+        let rewrapped = header.rewrapped("kek_v2", vec![2; 8]);
+        assert_eq!(rewrapped.version(), MAX_PROTOCOL_VERSION);
+        assert_eq!(rewrapped.mac_input().unwrap(), mac_input);
+        let retimed = header.with_created_at(1);
+        assert_ne!(retimed.mac_input().unwrap(), mac_input);
+
+        // Truncating the MAC is caught while parsing
+        let result = EncryptionHeader::from_bytes(&bytes[..bytes.len() - 1]);
+        assert!(matches!(result, Err(Error::InvalidHeader(msg)) if msg == "Header MAC truncated"));
+    }
+
+    #[test]
+    fn test_header_unknown_extension_flag_rejected() {
+        let header = EncryptionHeader::new("kek_v1", vec![1; 4], HeaderFlags::empty(), vec![9; 12])
+            .with_header_mac([0; HEADER_MAC_SIZE]);
+        let mut bytes = header.to_bytes().unwrap();
+        let ext_flags_pos = 1 + 1 + 6 + 2 + 4 + 1;
+        assert_eq!(bytes[ext_flags_pos], 0x01);
+
+        bytes[ext_flags_pos] |= 0x08;
+        let result = EncryptionHeader::from_bytes(&bytes);
+        assert!(matches!(result, Err(Error::UnsupportedFeature { flags: 0x08 })));
+    }
+
+    #[test]
+    fn test_header_derived_dek_round_trip() {
+        let header = EncryptionHeader::new("kek_v1", vec![1; 4], HeaderFlags::empty(), vec![9; 12]);
+        assert!(!header.derives_dek());
+
+        let header = header.with_derived_dek();
+        assert_eq!(header.version(), MAX_PROTOCOL_VERSION);
+
+        let bytes = header.to_bytes().unwrap();
+        assert_eq!(bytes[1 + 1 + 6 + 2 + 4 + 1], 0x02);
+        let (parsed, pos) = EncryptionHeader::from_bytes(&bytes).unwrap();
+        assert!(parsed.derives_dek());
+        assert_eq!(parsed.header_mac(), None);
+        assert_eq!(parsed, header);
+        assert_eq!(pos, bytes.len());
+
+        // Rewrapping keeps the flag and so the version
+        let rewrapped = parsed.rewrapped("kek_v2", vec![2; 4]);
+        assert!(rewrapped.derives_dek());
+        assert_eq!(rewrapped.version(), MAX_PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn test_header_v2_without_payload_len_still_parses() {
+        let mut bytes =
+            EncryptionHeader::new("kek_v1", vec![1; 4], HeaderFlags::empty(), vec![9; 12])
+                .with_context_version(7)
+                .to_bytes()
+                .unwrap();
+        bytes[0] = 2;
+
+        let (parsed, pos) = EncryptionHeader::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.version(), 2);
+        assert_eq!(parsed.context_version(), Some(7));
+        assert_eq!(parsed.payload_len(), None);
+        assert_eq!(pos, bytes.len());
+    }
+
+    /// Header parsing is part of the `no_std` core: nothing here may need
+    /// the clock or IO (timestamps are caller-supplied).
+    #[test]
+    fn test_header_round_trip_no_std() {
+        let header = EncryptionHeader::new("kek_v1", vec![1; 4], HeaderFlags::empty(), vec![9; 12])
+            .with_cipher_id(2)
+            .with_created_at(1_700_000_000_000)
+            .with_context_version(3);
+
+        let mut bytes = header.to_bytes().unwrap();
+        bytes.extend_from_slice(b"payload");
+
+        assert_eq!(peek_header(&bytes).unwrap(), header);
+
+        let (parsed, pos) = EncryptionHeader::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, header);
+        assert_eq!(&bytes[pos..], b"payload");
+    }
+
+    #[test]
+    fn test_header_v1_rejects_context_version_flag() {
+        let mut bytes = vec![1]; // Legacy version
+        bytes.extend_from_slice(&[6]);
+        bytes.extend_from_slice(b"kek_v1");
+        bytes.extend_from_slice(&[0, 4]);
+        bytes.extend_from_slice(&[1, 2, 3, 4]);
+        bytes.push(0x20); // context version flag
+        bytes.extend_from_slice(&[0, 0, 0, 1]);
+        bytes.push(12);
+        bytes.extend_from_slice(&[0; 12]);
+
+        let result = EncryptionHeader::from_bytes(&bytes);
+        assert!(matches!(result, Err(Error::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn test_header_without_created_at() {
+        let header =
+            EncryptionHeader::new("kek_v1", vec![1, 2, 3, 4], HeaderFlags::empty(), vec![9; 12]);
+
+        let bytes = header.to_bytes().unwrap();
+        let (parsed, _) = EncryptionHeader::from_bytes(&bytes).unwrap();
+
+        assert!(!parsed.flags().has_timestamp());
+        assert_eq!(parsed.created_at(), None);
+    }
+
+    #[test]
+    fn test_header_v1_still_parses() {
+        let mut bytes = vec![1]; // Legacy version
+        bytes.extend_from_slice(&[6]);
+        bytes.extend_from_slice(b"kek_v1");
+        bytes.extend_from_slice(&[0, 4]);
+        bytes.extend_from_slice(&[1, 2, 3, 4]);
+        bytes.push(0); // flags
+        bytes.push(12);
+        bytes.extend_from_slice(&[0; 12]);
+
+        let (parsed, pos) = EncryptionHeader::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.version(), 1);
+        assert_eq!(parsed.created_at(), None);
+        assert_eq!(pos, bytes.len());
+    }
+
+    #[test]
+    fn test_header_v1_rejects_timestamp_flag() {
+        let mut bytes = vec![1]; // Legacy version
+        bytes.extend_from_slice(&[6]);
+        bytes.extend_from_slice(b"kek_v1");
+        bytes.extend_from_slice(&[0, 4]);
+        bytes.extend_from_slice(&[1, 2, 3, 4]);
+        bytes.push(0x04); // timestamp flag
+        bytes.extend_from_slice(&[0; 8]);
+        bytes.push(12);
+        bytes.extend_from_slice(&[0; 12]);
+
+        let result = EncryptionHeader::from_bytes(&bytes);
+        assert!(matches!(result, Err(Error::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn test_header_created_at_truncated() {
+        let header = EncryptionHeader::new("kek_v1", vec![1], HeaderFlags::empty(), vec![])
+            .with_created_at(42);
+        let bytes = header.to_bytes().unwrap();
+
+        // Cut inside the 8-byte timestamp
+        let result = EncryptionHeader::from_bytes(&bytes[..bytes.len() - 5]);
+        assert!(matches!(result, Err(Error::InvalidHeader(_))));
     }
 
     #[test]
@@ -324,6 +1484,119 @@ mod tests {
         assert!(matches!(result, Err(Error::InvalidHeader(_))));
     }
 
+    #[test]
+    fn test_header_every_truncation_fails() {
+        let header = EncryptionHeader::new("kek_a", vec![1; 8], HeaderFlags::empty(), vec![9; 12])
+            .with_cipher_id(1)
+            .with_created_at(1_700_000_000_000)
+            .with_additional_recipients(vec![WrappedDek {
+                kek_id: "kek_b".to_string(),
+                encrypted_dek: vec![2; 8],
+            }]);
+        let bytes = header.to_bytes().unwrap();
+
+        for len in 0..bytes.len() {
+            let result = EncryptionHeader::from_bytes(&bytes[..len]);
+            assert!(
+                matches!(result, Err(Error::InvalidHeader(_))),
+                "truncation to {len} bytes was accepted"
+            );
+        }
+        assert!(EncryptionHeader::from_bytes(&bytes).is_ok());
+    }
+
+    #[test]
+    fn test_header_zero_kek_id_len_rejected() {
+        // version, kek_id_len = 0, wrapped_dek_len = 1, wrapped_dek, flags, nonce_len = 0
+        let bytes = vec![PROTOCOL_VERSION, 0, 0, 1, 7, 0, 0];
+        let result = EncryptionHeader::from_bytes(&bytes);
+        assert!(matches!(result, Err(Error::InvalidHeader(msg)) if msg == "KEK ID length is zero"));
+    }
+
+    #[test]
+    fn test_header_zero_wrapped_dek_len_rejected() {
+        // version, kek_id_len = 1, kek_id, wrapped_dek_len = 0, flags, nonce_len = 0
+        let bytes = vec![PROTOCOL_VERSION, 1, b'k', 0, 0, 0, 0];
+        let result = EncryptionHeader::from_bytes(&bytes);
+        assert!(
+            matches!(result, Err(Error::InvalidHeader(msg)) if msg == "Wrapped DEK length is zero")
+        );
+    }
+
+    #[test]
+    fn test_header_overlong_wrapped_dek_len_rejected() {
+        // Declares a 65535-byte wrapped DEK with only 4 bytes following
+        let bytes = vec![PROTOCOL_VERSION, 1, b'k', 0xFF, 0xFF, 1, 2, 3, 4];
+        let result = EncryptionHeader::from_bytes(&bytes);
+        assert!(matches!(
+            result,
+            Err(Error::InvalidHeader(msg))
+                if msg == "Wrapped DEK truncated: declared 65535 bytes, 4 available"
+        ));
+    }
+
+    #[test]
+    fn test_header_known_mask_covers_every_flag() {
+        let flags = [
+            HeaderFlags::empty().with_deterministic(),
+            HeaderFlags::empty().with_compressed(),
+            HeaderFlags::empty().with_timestamp(),
+            HeaderFlags::empty().with_multiple_recipients(),
+            HeaderFlags::empty().with_cipher_id(),
+            HeaderFlags::empty().with_context_version(),
+            HeaderFlags::empty().with_payload_len(),
+        ];
+        for flag in flags {
+            assert_eq!(flag.as_u8() & HeaderFlags::known_mask(), flag.as_u8());
+            assert_eq!(flag.unknown_bits(), 0);
+        }
+        assert_eq!(HeaderFlags::known_mask(), 0x7F);
+        assert_eq!(HeaderFlags::from_u8(0xFF).unknown_bits(), 0x80);
+    }
+
+    #[test]
+    fn test_header_unknown_flag_rejected() {
+        let header = EncryptionHeader::new("kek_v1", vec![1; 4], HeaderFlags::empty(), vec![9; 12]);
+        let mut bytes = header.to_bytes().unwrap();
+
+        // Flags sit just before the nonce length and nonce
+        let flags_pos = bytes.len() - 14;
+        bytes[flags_pos] |= 0x80;
+
+        let result = EncryptionHeader::from_bytes(&bytes);
+        assert!(matches!(result, Err(Error::UnsupportedFeature { flags: 0x80 })));
+
+        // Nor will the writer produce one
+        let future =
+            EncryptionHeader::new("kek_v1", vec![1; 4], HeaderFlags::from_u8(0x81), vec![9; 12]);
+        assert!(matches!(future.to_bytes(), Err(Error::UnsupportedFeature { flags: 0x80 })));
+    }
+
+    #[test]
+    fn test_header_zero_recipient_count_rejected() {
+        let header = EncryptionHeader::new("kek_v1", vec![1; 4], HeaderFlags::empty(), vec![]);
+        let mut bytes = header.to_bytes().unwrap();
+
+        // Set the recipients flag and insert a zero count before the nonce length
+        let flags_pos = bytes.len() - 2;
+        bytes[flags_pos] |= 0x08;
+        bytes.insert(flags_pos + 1, 0);
+
+        let result = EncryptionHeader::from_bytes(&bytes);
+        assert!(
+            matches!(result, Err(Error::InvalidHeader(msg)) if msg == "Recipient count is zero")
+        );
+    }
+
+    #[test]
+    fn test_header_empty_fields_not_serialized() {
+        let empty_kek_id = EncryptionHeader::new("", vec![1; 4], HeaderFlags::empty(), vec![0; 12]);
+        assert!(matches!(empty_kek_id.to_bytes(), Err(Error::InvalidHeader(_))));
+
+        let empty_dek = EncryptionHeader::new("kek_v1", vec![], HeaderFlags::empty(), vec![0; 12]);
+        assert!(matches!(empty_dek.to_bytes(), Err(Error::InvalidHeader(_))));
+    }
+
     #[test]
     fn test_header_empty_data() {
         let result = EncryptionHeader::from_bytes(&[]);
@@ -359,4 +1632,57 @@ mod tests {
         assert_eq!(parsed.nonce(), &vec![7; 16]);
         assert_eq!(pos, bytes.len());
     }
+
+    #[test]
+    fn test_header_write_to_matches_to_bytes() {
+        let minimal =
+            EncryptionHeader::new("kek_v1", vec![1, 2, 3], HeaderFlags::empty(), vec![0; 12]);
+        let full = EncryptionHeader::new("kek_v1", vec![1; 48], HeaderFlags::empty(), vec![9; 24])
+            .with_cipher_id(3)
+            .with_created_at(1_700_000_000)
+            .with_context_version(7)
+            .with_payload_len(1024)
+            .with_additional_recipients(vec![WrappedDek {
+                kek_id: "tenant_b".to_string(),
+                encrypted_dek: vec![5; 40],
+            }]);
+
+        for header in [minimal, full] {
+            let expected = header.to_bytes().unwrap();
+
+            let mut bytes = b"prefix".to_vec();
+            header.write_to(&mut bytes).unwrap();
+            assert_eq!(&bytes[..6], b"prefix");
+            assert_eq!(&bytes[6..], expected.as_slice());
+            assert_eq!(header.encoded_len(), expected.len());
+        }
+
+        // Nothing is appended when validation fails
+        let invalid =
+            EncryptionHeader::new("k".repeat(256), vec![1], HeaderFlags::empty(), vec![0; 12]);
+        let mut bytes = b"prefix".to_vec();
+        assert!(invalid.write_to(&mut bytes).is_err());
+        assert_eq!(bytes, b"prefix");
+    }
+
+    #[test]
+    fn test_header_debug_redacts_bytes() {
+        let header =
+            EncryptionHeader::new("kek_v1", vec![0xAB; 32], HeaderFlags::empty(), vec![0xCD; 12])
+                .with_additional_recipients(vec![WrappedDek {
+                    kek_id: "kek_v2".to_string(),
+                    encrypted_dek: vec![0xEF; 48],
+                }]);
+
+        let debug = format!("{header:?}");
+        assert!(debug.contains("kek_v1"));
+        assert!(debug.contains("kek_v2"));
+        assert!(debug.contains("wrapped_dek: [32 bytes]"));
+        assert!(debug.contains("nonce: [12 bytes]"));
+        assert!(debug.contains("encrypted_dek: [48 bytes]"));
+
+        for byte in [0xAB, 0xCD, 0xEF] {
+            assert!(!debug.contains(&format!("{byte}, {byte}")));
+        }
+    }
 }
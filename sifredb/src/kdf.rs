@@ -1,10 +1,14 @@
 //! Key derivation using HKDF (HMAC-based Key Derivation Function).
 //!
 //! This module implements key derivation for generating Data Encryption Keys (DEKs)
-//! from a Key Encryption Key (KEK) using HKDF with SHA-256.
+//! from a Key Encryption Key (KEK) using HKDF with SHA-256, and for bootstrapping
+//! a KEK from a human passphrase using the memory-hard Argon2id function.
 
-use crate::context::EncryptionContext;
+use crate::context::{EncryptionContext, IndexContext};
 use crate::error::Error;
+use alloc::string::ToString;
+use alloc::vec;
+use argon2::{Algorithm, Argon2, Params, Version};
 use hkdf::Hkdf;
 use secrecy::{ExposeSecret, SecretVec};
 use sha2::Sha256;
@@ -12,6 +16,37 @@ use sha2::Sha256;
 /// Standard DEK size in bytes (256 bits).
 pub const DEK_SIZE: usize = 32;
 
+/// Size of a KEK derived from a passphrase in bytes (256 bits).
+pub const KEK_SIZE: usize = 32;
+
+/// Cost parameters for Argon2id passphrase derivation.
+///
+/// The defaults follow the OWASP recommendation for Argon2id
+/// (19 MiB of memory, 2 iterations, 1 degree of parallelism).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argon2Params {
+    /// Memory cost in KiB
+    pub memory_kib: u32,
+    /// Number of iterations (time cost)
+    pub iterations: u32,
+    /// Degree of parallelism (lanes)
+    pub parallelism: u32,
+}
+
+impl Argon2Params {
+    /// Creates Argon2id cost parameters.
+    #[must_use]
+    pub const fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> Self {
+        Self { memory_kib, iterations, parallelism }
+    }
+}
+
+impl Default for Argon2Params {
+    fn default() -> Self {
+        Self::new(19 * 1024, 2, 1)
+    }
+}
+
 /// Derives a Data Encryption Key (DEK) from a KEK using HKDF.
 ///
 /// The derivation uses the encryption context as the `info` parameter for domain separation:
@@ -45,20 +80,88 @@ pub fn derive_dek(
     kek: &SecretVec<u8>,
     context: &EncryptionContext,
 ) -> Result<SecretVec<u8>, Error> {
+    derive_dek_sized(kek, context, DEK_SIZE)
+}
+
+/// Derives a DEK of `len` bytes from a KEK using HKDF.
+///
+/// Like [`derive_dek`], for ciphers whose key isn't [`DEK_SIZE`] bytes
+/// (e.g. 16 bytes for AES-128-GCM). Output of different lengths for the same
+/// KEK and context shares a prefix, so use one length per KEK.
+///
+/// # Errors
+///
+/// Returns `Error::KeyDerivation` if `len` is zero or exceeds what HKDF-SHA256
+/// can produce (8160 bytes).
+pub fn derive_dek_sized(
+    kek: &SecretVec<u8>,
+    context: &EncryptionContext,
+    len: usize,
+) -> Result<SecretVec<u8>, Error> {
+    if len == 0 {
+        return Err(Error::KeyDerivation);
+    }
+
     // Create HKDF instance with the KEK as input key material
     let hkdf = Hkdf::<Sha256>::new(None, kek.expose_secret());
 
-    // Use the context string as the info parameter for domain separation
-    let info = context.to_string();
-    let info_bytes = info.as_bytes();
+    // Use the canonical context bytes as the info parameter for domain separation
+    let info = context.canonical_bytes();
 
-    // Derive a DEK of the standard size
-    let mut dek = vec![0u8; DEK_SIZE];
-    hkdf.expand(info_bytes, &mut dek).map_err(|_| Error::KeyDerivation)?;
+    let mut dek = vec![0u8; len];
+    hkdf.expand(&info, &mut dek).map_err(|_| Error::KeyDerivation)?;
 
     Ok(SecretVec::new(dek))
 }
 
+/// Size of an AES-256-SIV key in bytes.
+pub const SIV_KEY_SIZE: usize = 64;
+
+/// Derives a 64-byte AES-256-SIV key for deterministic encryption of a column.
+///
+/// The key is HKDF-SHA256 over `secret` (typically the provider's pepper),
+/// with info `sifredb-siv|` followed by the index context. Index contexts
+/// carry no version, so the key stays stable for the lifetime of a column.
+///
+/// # Errors
+///
+/// Returns `Error::KeyDerivation` if the derivation fails.
+pub fn derive_siv_key(
+    secret: &SecretVec<u8>,
+    context: &IndexContext,
+) -> Result<SecretVec<u8>, Error> {
+    let hkdf = Hkdf::<Sha256>::new(None, secret.expose_secret());
+
+    let mut info = b"sifredb-siv|".to_vec();
+    info.extend_from_slice(context.to_string().as_bytes());
+
+    let mut key = vec![0u8; SIV_KEY_SIZE];
+    hkdf.expand(&info, &mut key).map_err(|_| Error::KeyDerivation)?;
+
+    Ok(SecretVec::new(key))
+}
+
+/// Size of a ciphertext deduplication fingerprint key in bytes.
+pub const FINGERPRINT_KEY_SIZE: usize = 32;
+
+/// Derives the HMAC key for ciphertext deduplication fingerprints.
+///
+/// The key is HKDF-SHA256 over `secret` (typically the provider's pepper)
+/// with info `sifredb-dedup`, so fingerprints are unrelated to blind indexes
+/// keyed by the pepper itself and can't be matched against them.
+///
+/// # Errors
+///
+/// Returns `Error::KeyDerivation` if the derivation fails.
+pub fn derive_fingerprint_key(secret: &SecretVec<u8>) -> Result<SecretVec<u8>, Error> {
+    let hkdf = Hkdf::<Sha256>::new(None, secret.expose_secret());
+
+    let mut key = vec![0u8; FINGERPRINT_KEY_SIZE];
+    hkdf.expand(b"sifredb-dedup", &mut key).map_err(|_| Error::KeyDerivation)?;
+
+    Ok(SecretVec::new(key))
+}
+
 /// Generates a random DEK for envelope encryption.
 ///
 /// This DEK should be wrapped (encrypted) with a KEK before storage.
@@ -78,13 +181,69 @@ pub fn derive_dek(
 /// ```
 #[must_use]
 pub fn generate_dek() -> SecretVec<u8> {
+    generate_dek_sized(DEK_SIZE)
+}
+
+/// Generates a random DEK of `len` bytes.
+///
+/// Use this for ciphers whose key size differs from [`DEK_SIZE`], such as
+/// AES-128-GCM (16 bytes).
+#[must_use]
+pub fn generate_dek_sized(len: usize) -> SecretVec<u8> {
     use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
 
-    let mut dek = vec![0u8; DEK_SIZE];
+    let mut dek = vec![0u8; len];
     OsRng.fill_bytes(&mut dek);
     SecretVec::new(dek)
 }
 
+/// Derives a KEK from a passphrase using Argon2id.
+///
+/// Unlike [`derive_dek`], which expands an existing high-entropy key, this is a
+/// memory-hard function intended for low-entropy human input. The same
+/// passphrase, salt, and parameters always produce the same key.
+///
+/// # Arguments
+///
+/// * `passphrase` - The passphrase to derive from
+/// * `salt` - A random salt (at least 8 bytes, 16 recommended), stored alongside the key
+/// * `params` - Argon2id cost parameters
+///
+/// # Returns
+///
+/// A 32-byte KEK.
+///
+/// # Errors
+///
+/// Returns `Error::KeyDerivation` if the parameters or salt are invalid.
+///
+/// # Example
+///
+/// ```
+/// use sifredb::kdf::{derive_kek_from_passphrase, Argon2Params};
+/// use secrecy::ExposeSecret;
+///
+/// let params = Argon2Params::new(1024, 1, 1);
+/// let kek = derive_kek_from_passphrase(b"correct horse", b"random-salt-1234", params)
+///     .expect("KEK derivation failed");
+/// assert_eq!(kek.expose_secret().len(), 32);
+/// ```
+pub fn derive_kek_from_passphrase(
+    passphrase: &[u8],
+    salt: &[u8],
+    params: Argon2Params,
+) -> Result<SecretVec<u8>, Error> {
+    let params =
+        Params::new(params.memory_kib, params.iterations, params.parallelism, Some(KEK_SIZE))
+            .map_err(|_| Error::KeyDerivation)?;
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+
+    let mut kek = vec![0u8; KEK_SIZE];
+    argon2.hash_password_into(passphrase, salt, &mut kek).map_err(|_| Error::KeyDerivation)?;
+
+    Ok(SecretVec::new(kek))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -115,6 +274,37 @@ mod tests {
         assert_ne!(dek1.expose_secret(), dek2.expose_secret());
     }
 
+    #[test]
+    fn test_derive_dek_attributes() {
+        let kek = SecretVec::new(vec![1u8; 32]);
+        let base = EncryptionContext::new("users", "email");
+        let billing = base.clone().with_attribute("purpose", "billing").with_attribute("row", "42");
+        let reordered =
+            base.clone().with_attribute("row", "42").with_attribute("purpose", "billing");
+        let support = base.clone().with_attribute("purpose", "support").with_attribute("row", "42");
+
+        let dek = |ctx| derive_dek(&kek, ctx).expect("DEK derivation failed");
+
+        assert_eq!(dek(&billing).expose_secret(), dek(&reordered).expose_secret());
+        assert_ne!(dek(&billing).expose_secret(), dek(&support).expose_secret());
+        assert_ne!(dek(&billing).expose_secret(), dek(&base).expose_secret());
+    }
+
+    #[test]
+    fn test_derive_siv_key() {
+        let pepper = SecretVec::new(vec![7u8; 32]);
+        let context = IndexContext::new("users", "email");
+
+        let key1 = derive_siv_key(&pepper, &context).expect("SIV key derivation failed");
+        let key2 = derive_siv_key(&pepper, &context).expect("SIV key derivation failed");
+        let other = derive_siv_key(&pepper, &IndexContext::new("users", "phone"))
+            .expect("SIV key derivation failed");
+
+        assert_eq!(key1.expose_secret().len(), SIV_KEY_SIZE);
+        assert_eq!(key1.expose_secret(), key2.expose_secret());
+        assert_ne!(key1.expose_secret(), other.expose_secret());
+    }
+
     #[test]
     fn test_derive_dek_different_tenants() {
         let kek = SecretVec::new(vec![1u8; 32]);
@@ -151,6 +341,23 @@ mod tests {
         assert_eq!(dek.expose_secret().len(), DEK_SIZE);
     }
 
+    #[test]
+    fn test_derive_dek_sized() {
+        let kek = SecretVec::new(vec![42u8; 32]);
+        let context = EncryptionContext::new("test_table", "test_column");
+
+        let full = derive_dek(&kek, &context).unwrap();
+        let short = derive_dek_sized(&kek, &context, 16).unwrap();
+        assert_eq!(short.expose_secret().len(), 16);
+        assert_eq!(short.expose_secret()[..], full.expose_secret()[..16]);
+
+        assert!(matches!(derive_dek_sized(&kek, &context, 0), Err(Error::KeyDerivation)));
+        assert!(matches!(
+            derive_dek_sized(&kek, &context, 255 * 32 + 1),
+            Err(Error::KeyDerivation)
+        ));
+    }
+
     #[test]
     fn test_generate_dek() {
         let dek1 = generate_dek();
@@ -164,6 +371,13 @@ mod tests {
         assert_eq!(dek2.expose_secret().len(), DEK_SIZE);
     }
 
+    #[test]
+    fn test_generate_dek_sized() {
+        let dek = generate_dek_sized(16);
+        assert_eq!(dek.expose_secret().len(), 16);
+        assert_ne!(dek.expose_secret(), generate_dek_sized(16).expose_secret());
+    }
+
     #[test]
     fn test_derive_dek_with_different_keks() {
         let kek1 = SecretVec::new(vec![1u8; 32]);
@@ -203,4 +417,52 @@ mod tests {
 
         assert_eq!(okm, expected_okm);
     }
+
+    // Argon2 reference implementation test vector (argon2id, v=0x13)
+    // https://github.com/P-H-C/phc-winner-argon2/blob/master/src/test.c
+    #[test]
+    fn test_argon2id_known_answer() {
+        const EXPECTED_HEX: &str =
+            "09316115d5cf24ed5a15a31a3ba326e5cf32edc24702987c02b6566f61913cf7";
+
+        let params = Argon2Params::new(1 << 16, 2, 1);
+        let kek = derive_kek_from_passphrase(b"password", b"somesalt", params)
+            .expect("KEK derivation failed");
+
+        assert_eq!(hex::encode(kek.expose_secret()), EXPECTED_HEX);
+    }
+
+    #[test]
+    fn test_derive_kek_different_salts() {
+        let params = Argon2Params::new(1024, 1, 1);
+
+        let kek1 = derive_kek_from_passphrase(b"passphrase", b"salt_one", params).unwrap();
+        let kek2 = derive_kek_from_passphrase(b"passphrase", b"salt_two", params).unwrap();
+
+        // Different salts should produce different KEKs
+        assert_ne!(kek1.expose_secret(), kek2.expose_secret());
+        assert_eq!(kek1.expose_secret().len(), KEK_SIZE);
+    }
+
+    #[test]
+    fn test_derive_kek_deterministic() {
+        let params = Argon2Params::new(1024, 1, 1);
+
+        let kek1 = derive_kek_from_passphrase(b"passphrase", b"somesalt", params).unwrap();
+        let kek2 = derive_kek_from_passphrase(b"passphrase", b"somesalt", params).unwrap();
+
+        assert_eq!(kek1.expose_secret(), kek2.expose_secret());
+    }
+
+    #[test]
+    fn test_derive_kek_invalid_params() {
+        // Salt shorter than the Argon2 minimum of 8 bytes
+        let result = derive_kek_from_passphrase(b"passphrase", b"short", Argon2Params::default());
+        assert!(matches!(result, Err(Error::KeyDerivation)));
+
+        // Zero iterations
+        let result =
+            derive_kek_from_passphrase(b"passphrase", b"somesalt", Argon2Params::new(1024, 0, 1));
+        assert!(matches!(result, Err(Error::KeyDerivation)));
+    }
 }
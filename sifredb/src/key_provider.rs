@@ -1,7 +1,116 @@
 //! Key provider abstraction for key management.
 
-use crate::error::KeyProviderError;
-use secrecy::SecretVec;
+use crate::error::{Error, KeyProviderError};
+use crate::header::{read_recipient, validate_recipient, write_recipient, ByteCount};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use secrecy::{ExposeSecret, SecretVec};
+#[cfg(feature = "std")]
+use std::time::SystemTime;
+
+/// A Data Encryption Key (DEK) wrapped under a specific KEK.
+///
+/// Remote providers may wrap under a more specific key than the one requested
+/// (e.g. a concrete KMS key version), so the KEK identifier needed to unwrap is
+/// carried alongside the ciphertext.
+///
+/// This is also how an [`EncryptionHeader`](crate::header::EncryptionHeader)
+/// records each recipient. For storing wrapped DEKs apart from a ciphertext,
+/// [`WrappedDek::to_bytes`] gives the same compact binary form, and with the
+/// `serde` feature the struct serializes as `kek_id` plus a base64
+/// `encrypted_dek`.
+///
+/// The `Debug` output shows the length of the encrypted DEK, not its contents.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WrappedDek {
+    /// Identifier of the KEK that wrapped the DEK
+    pub kek_id: String,
+    /// The encrypted DEK
+    #[cfg_attr(feature = "serde", serde(with = "base64_bytes"))]
+    pub encrypted_dek: Vec<u8>,
+}
+
+impl WrappedDek {
+    /// Serializes as `[kek_id_len:1][kek_id][encrypted_dek_len:2][encrypted_dek]`,
+    /// the layout each header recipient uses.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidHeader` if either field is empty or the KEK ID
+    /// exceeds 255 bytes or the encrypted DEK 65535 bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        validate_recipient(self)?;
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        write_recipient(&mut bytes, self);
+        Ok(bytes)
+    }
+
+    /// Parses the output of [`WrappedDek::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidHeader` if `data` is malformed or has bytes
+    /// left over.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        let mut pos = 0;
+        let wrapped_dek = read_recipient(data, &mut pos)?;
+        if pos != data.len() {
+            return Err(Error::InvalidHeader(format!(
+                "Unexpected {} trailing bytes after wrapped DEK",
+                data.len() - pos
+            )));
+        }
+        Ok(wrapped_dek)
+    }
+
+    /// Returns the number of bytes [`WrappedDek::to_bytes`] produces.
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        1 + self.kek_id.len() + 2 + self.encrypted_dek.len()
+    }
+}
+
+/// Serializes bytes as a standard base64 string.
+#[cfg(feature = "serde")]
+mod base64_bytes {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD.decode(encoded).map_err(D::Error::custom)
+    }
+}
+
+impl fmt::Debug for WrappedDek {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WrappedDek")
+            .field("kek_id", &self.kek_id)
+            .field("encrypted_dek", &ByteCount(self.encrypted_dek.len()))
+            .finish()
+    }
+}
+
+/// Descriptive information about a KEK, used to enforce rotation policy.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KekMetadata {
+    /// Identifier of the KEK
+    pub id: String,
+    /// When the KEK was created, if the provider knows
+    pub created_at: Option<SystemTime>,
+    /// Whether this is the provider's current (active) KEK
+    pub is_current: bool,
+}
 
 /// Provides key management operations for encryption/decryption.
 ///
@@ -37,6 +146,22 @@ pub trait KeyProvider: Send + Sync {
     /// Returns `KeyProviderError::NoActiveKek` if no KEK is configured.
     fn current_kek_id(&self) -> Result<String, KeyProviderError>;
 
+    /// Returns the identifier of the current KEK for `tenant`.
+    ///
+    /// `Vault` calls this instead of [`KeyProvider::current_kek_id`] when the
+    /// encryption context names a tenant. Providers that keep a separate KEK
+    /// per tenant override it, so revoking one tenant's KEK destroys only that
+    /// tenant's data. The default implementation delegates to
+    /// [`KeyProvider::current_kek_id`], so every tenant shares one KEK and is
+    /// separated only by the context.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::NoActiveKek` if no KEK is configured.
+    fn current_kek_id_for_tenant(&self, _tenant: &str) -> Result<String, KeyProviderError> {
+        self.current_kek_id()
+    }
+
     /// Wraps (encrypts) a Data Encryption Key (DEK) with the specified KEK.
     ///
     /// # Arguments
@@ -69,6 +194,48 @@ pub trait KeyProvider: Send + Sync {
         wrapped_dek: &[u8],
     ) -> Result<SecretVec<u8>, KeyProviderError>;
 
+    /// Wraps a DEK like [`KeyProvider::wrap_dek`], binding `aad` to the
+    /// wrapped DEK as associated data.
+    ///
+    /// `Vault` passes the tenant of the encryption context (empty when there
+    /// is none), so a wrapped DEK pasted into another tenant's header fails
+    /// to unwrap instead of relying on the payload's AAD alone. A DEK wrapped
+    /// with `aad` must be unwrapped with [`KeyProvider::unwrap_dek_aad`] and
+    /// the same `aad`.
+    ///
+    /// The default implementation ignores `aad` and calls `wrap_dek`, so a
+    /// provider that doesn't override this (and [`KeyProvider::unwrap_dek_aad`]
+    /// and [`KeyProvider::rewrap_dek_aad`]) doesn't bind tenants.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::WrapFailed` if wrapping fails.
+    fn wrap_dek_aad(
+        &self,
+        kek_id: &str,
+        dek: &[u8],
+        _aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        self.wrap_dek(kek_id, dek)
+    }
+
+    /// Unwraps a DEK wrapped by [`KeyProvider::wrap_dek_aad`] with the same `aad`.
+    ///
+    /// The default implementation ignores `aad` and calls `unwrap_dek`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::UnwrapFailed` if unwrapping fails, including
+    /// when `aad` differs from the one the DEK was wrapped with.
+    fn unwrap_dek_aad(
+        &self,
+        kek_id: &str,
+        wrapped_dek: &[u8],
+        _aad: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        self.unwrap_dek(kek_id, wrapped_dek)
+    }
+
     /// Returns the pepper value for blind index generation.
     ///
     /// # Returns
@@ -81,4 +248,255 @@ pub trait KeyProvider: Send + Sync {
     fn get_pepper(&self) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
         Ok(None)
     }
+
+    /// Returns the version number of the pepper returned by [`KeyProvider::get_pepper`].
+    ///
+    /// Versions start at 1 and increase with each [`KeyProvider::rotate_pepper`].
+    /// The default implementation always reports version 1.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::PepperUnavailable` if the version can't be determined.
+    fn current_pepper_version(&self) -> Result<u32, KeyProviderError> {
+        Ok(1)
+    }
+
+    /// Returns a specific pepper version, so indexes made before a rotation
+    /// can still be recomputed.
+    ///
+    /// The default implementation serves version 1 from [`KeyProvider::get_pepper`]
+    /// and knows no other versions.
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the provider has no pepper with this version.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::PepperUnavailable` if pepper retrieval fails.
+    fn get_pepper_version(&self, version: u32) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+        if version == 1 {
+            self.get_pepper()
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Generates a new pepper and makes it current, keeping older versions.
+    ///
+    /// Every blind index computed with the old pepper must be recomputed
+    /// afterwards; see [`crate::blind_index::generate_blind_index_versioned`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::Unsupported` unless the provider overrides this.
+    fn rotate_pepper(&self) -> Result<(), KeyProviderError> {
+        Err(KeyProviderError::Unsupported("pepper rotation".to_string()))
+    }
+
+    /// Re-wraps a DEK from one KEK to another.
+    ///
+    /// The default implementation unwraps the DEK with `old_kek_id` and wraps it
+    /// again with `new_kek_id`, so the plaintext DEK briefly exists in process
+    /// memory. Providers whose backend can re-encrypt ciphertext server-side
+    /// should override this to avoid exposing the DEK.
+    ///
+    /// # Arguments
+    ///
+    /// * `old_kek_id` - Identifier of the KEK currently wrapping the DEK
+    /// * `new_kek_id` - Identifier of the KEK to wrap the DEK under
+    /// * `wrapped_dek` - The DEK wrapped under `old_kek_id`
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::UnwrapFailed` or `KeyProviderError::WrapFailed`
+    /// if either step fails.
+    fn rewrap_dek(
+        &self,
+        old_kek_id: &str,
+        new_kek_id: &str,
+        wrapped_dek: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        let dek = self.unwrap_dek(old_kek_id, wrapped_dek)?;
+        self.wrap_dek(new_kek_id, dek.expose_secret())
+    }
+
+    /// Re-wraps a DEK wrapped by [`KeyProvider::wrap_dek_aad`], keeping it
+    /// bound to the same `aad`.
+    ///
+    /// The default implementation ignores `aad` and calls `rewrap_dek`.
+    /// Providers that override `wrap_dek_aad` must override this too.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::UnwrapFailed` or `KeyProviderError::WrapFailed`
+    /// if either step fails.
+    fn rewrap_dek_aad(
+        &self,
+        old_kek_id: &str,
+        new_kek_id: &str,
+        wrapped_dek: &[u8],
+        _aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        self.rewrap_dek(old_kek_id, new_kek_id, wrapped_dek)
+    }
+
+    /// Returns metadata about a KEK, such as its age and whether it is active.
+    ///
+    /// The default implementation reports no creation time and compares
+    /// `kek_id` against [`KeyProvider::current_kek_id`]. Providers that track
+    /// key creation should override this.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::KekNotFound` if the provider knows the KEK
+    /// doesn't exist.
+    #[cfg(feature = "std")]
+    fn kek_metadata(&self, kek_id: &str) -> Result<KekMetadata, KeyProviderError> {
+        let is_current = self.current_kek_id().is_ok_and(|current| current == kek_id);
+        Ok(KekMetadata { id: kek_id.to_string(), created_at: None, is_current })
+    }
+
+    /// Checks that the backend is reachable and the current KEK is usable.
+    ///
+    /// Intended for readiness probes (e.g. a `/healthz` handler), so
+    /// implementations should be cheap and must not wrap or unwrap key
+    /// material. The default implementation always succeeds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing why the provider can't serve requests.
+    fn health_check(&self) -> Result<(), KeyProviderError> {
+        Ok(())
+    }
+}
+
+/// Asynchronous key provider for backends reached over the network (cloud KMS).
+///
+/// This mirrors [`KeyProvider`] for providers whose operations are naturally
+/// async, returning a [`WrappedDek`] that records the exact KEK used.
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+pub trait AsyncKeyProvider: Send + Sync {
+    /// Returns the identifier of the current (active) KEK.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::NoActiveKek` if no KEK is configured.
+    async fn current_kek_id(&self) -> Result<String, KeyProviderError>;
+
+    /// Wraps (encrypts) a DEK with the specified KEK.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::WrapFailed` if wrapping fails.
+    async fn wrap_dek(
+        &self,
+        dek: &SecretVec<u8>,
+        kek_id: &str,
+    ) -> Result<WrappedDek, KeyProviderError>;
+
+    /// Unwraps (decrypts) a DEK using the KEK recorded in `wrapped`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::UnwrapFailed` if unwrapping fails.
+    async fn unwrap_dek(&self, wrapped: &WrappedDek) -> Result<SecretVec<u8>, KeyProviderError>;
+
+    /// Returns the pepper value for blind index generation.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::PepperUnavailable` if pepper retrieval fails.
+    async fn get_pepper(&self) -> Result<SecretVec<u8>, KeyProviderError>;
+
+    /// Re-wraps a DEK under `new_kek_id`.
+    ///
+    /// The default implementation unwraps then wraps client-side. Backends with
+    /// server-side re-encryption should override this so the plaintext DEK never
+    /// leaves the KMS.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyProviderError::UnwrapFailed` or `KeyProviderError::WrapFailed`
+    /// if either step fails.
+    async fn rewrap_dek(
+        &self,
+        wrapped: &WrappedDek,
+        new_kek_id: &str,
+    ) -> Result<WrappedDek, KeyProviderError> {
+        let dek = self.unwrap_dek(wrapped).await?;
+        self.wrap_dek(&dek, new_kek_id).await
+    }
+
+    /// Checks that the backend is reachable and the current KEK is usable.
+    ///
+    /// See [`KeyProvider::health_check`]. The default implementation always
+    /// succeeds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing why the provider can't serve requests.
+    async fn health_check(&self) -> Result<(), KeyProviderError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn sample() -> WrappedDek {
+        WrappedDek { kek_id: "kek_v1".to_string(), encrypted_dek: vec![0xAB; 45] }
+    }
+
+    #[test]
+    fn test_wrapped_dek_binary_round_trip() {
+        let wrapped = sample();
+        let bytes = wrapped.to_bytes().unwrap();
+
+        assert_eq!(bytes.len(), wrapped.encoded_len());
+        assert_eq!(&bytes[..7], b"\x06kek_v1");
+        assert_eq!(&bytes[7..9], &[0, 45]);
+        assert_eq!(WrappedDek::from_bytes(&bytes).unwrap(), wrapped);
+
+        // Same layout as a header recipient
+        let header = crate::header::EncryptionHeader::from_wrapped_dek(
+            wrapped.clone(),
+            crate::header::HeaderFlags::empty(),
+            vec![0; 12],
+        );
+        assert_eq!(header.primary_recipient(), &wrapped);
+        assert_eq!(&header.to_bytes().unwrap()[1..=bytes.len()], bytes.as_slice());
+    }
+
+    #[test]
+    fn test_wrapped_dek_binary_rejects_malformed() {
+        let bytes = sample().to_bytes().unwrap();
+
+        for len in 0..bytes.len() {
+            assert!(matches!(WrappedDek::from_bytes(&bytes[..len]), Err(Error::InvalidHeader(_))));
+        }
+
+        let mut trailing = bytes;
+        trailing.push(0);
+        assert!(matches!(WrappedDek::from_bytes(&trailing), Err(Error::InvalidHeader(_))));
+
+        let empty = WrappedDek { kek_id: String::new(), encrypted_dek: vec![1] };
+        assert!(matches!(empty.to_bytes(), Err(Error::InvalidHeader(_))));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_wrapped_dek_serde_json_round_trip() {
+        let wrapped = WrappedDek { kek_id: "kek_v1".to_string(), encrypted_dek: vec![1, 2, 3] };
+
+        let json = serde_json::to_string(&wrapped).unwrap();
+        assert_eq!(json, r#"{"kek_id":"kek_v1","encrypted_dek":"AQID"}"#);
+        assert_eq!(serde_json::from_str::<WrappedDek>(&json).unwrap(), wrapped);
+
+        let invalid = r#"{"kek_id":"kek_v1","encrypted_dek":"not base64!"}"#;
+        assert!(serde_json::from_str::<WrappedDek>(invalid).is_err());
+    }
 }
@@ -5,9 +5,9 @@
 //!
 //! ## Features
 //!
-//! - AEAD encryption (ChaCha20-Poly1305, AES-GCM)
+//! - AEAD encryption (ChaCha20-Poly1305, AES-GCM, AES-GCM-SIV)
 //! - Deterministic encryption (AES-SIV) for equality queries
-//! - Blind indexes for searchable encryption
+//! - Blind indexes and versioned search tokens for searchable encryption
 //! - Envelope encryption with KEK/DEK separation
 //! - Multi-tenant key isolation
 //! - Key rotation support
@@ -24,19 +24,70 @@
 //! let ciphertext = vault.encrypt(b"alice@example.com", &context)?;
 //! let plaintext = vault.decrypt(&ciphertext, &context)?;
 //! ```
+//!
+//! ## `no_std`
+//!
+//! With `default-features = false` the crate builds under `no_std + alloc`
+//! (e.g. for browser-side tokenization in WASM). Only the clock- and
+//! IO-free core is available then: [`header`], [`context`],
+//! [`deterministic`], [`kdf`], [`blind_index`], [`key_provider`],
+//! [`fallback`], [`scheme`] and [`error`]. Check it with `cargo check -p sifredb --no-default-features`.
+//! On `wasm32-unknown-unknown`, also enable the `js` feature of `getrandom`
+//! so DEK generation can reach the browser's CSPRNG.
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![warn(clippy::pedantic, clippy::nursery)]
 #![allow(clippy::module_name_repetitions)]
 
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod audit;
+pub mod blind_index;
+#[cfg(feature = "std")]
+pub mod ciphertext;
+#[cfg(feature = "std")]
+pub mod column;
 pub mod context;
+#[cfg(feature = "std")]
+pub mod convert;
+#[cfg(feature = "dek-cache")]
+mod dek_cache;
+pub mod deterministic;
 pub mod error;
+pub mod fallback;
+pub mod header;
+pub mod kdf;
 pub mod key_provider;
-pub mod deterministic;
+#[cfg(feature = "std")]
+pub mod retry;
+pub mod scheme;
+#[cfg(feature = "std")]
+pub mod search_token;
+#[cfg(test)]
+mod test_support;
+#[cfg(feature = "std")]
+pub mod vault;
+
+pub use scheme::{detect_scheme, Scheme};
 
 pub mod prelude {
     //! Convenience re-exports for common use.
+    #[cfg(feature = "std")]
+    pub use crate::ciphertext::Ciphertext;
+    #[cfg(feature = "std")]
+    pub use crate::column::EncryptedColumn;
     pub use crate::context::{EncryptionContext, IndexContext};
+    #[cfg(feature = "std")]
+    pub use crate::convert::{reencode, EncodedValue, EncodingMode};
     pub use crate::deterministic::DeterministicVault;
     pub use crate::error::{Error, KeyProviderError};
-    pub use crate::key_provider::KeyProvider;
+    #[cfg(feature = "async")]
+    pub use crate::key_provider::AsyncKeyProvider;
+    #[cfg(feature = "std")]
+    pub use crate::key_provider::KekMetadata;
+    pub use crate::key_provider::{KeyProvider, WrappedDek};
+    pub use crate::scheme::{detect_scheme, Scheme};
+    #[cfg(feature = "std")]
+    pub use crate::vault::{CipherMode, Vault};
 }
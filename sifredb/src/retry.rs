@@ -0,0 +1,452 @@
+//! Retry with exponential backoff for key providers.
+//!
+//! [`RetryingProvider`] wraps any [`KeyProvider`] (or, with the `async`
+//! feature, [`AsyncKeyProvider`]) and retries calls that fail with a
+//! retryable error, as classified by [`KeyProviderError::is_retryable`].
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use sifredb::retry::RetryingProvider;
+//! use std::time::Duration;
+//!
+//! let provider = RetryingProvider::new(kms_provider)
+//!     .with_max_attempts(5)
+//!     .with_base_delay(Duration::from_millis(100));
+//! let vault = Vault::new(provider, CipherMode::default());
+//! ```
+
+use crate::error::KeyProviderError;
+#[cfg(feature = "async")]
+use crate::key_provider::{AsyncKeyProvider, WrappedDek};
+use crate::key_provider::{KekMetadata, KeyProvider};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use secrecy::SecretVec;
+use std::thread;
+use std::time::Duration;
+
+/// Default number of attempts, including the first call.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Default delay before the first retry, before jitter.
+pub const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(50);
+
+/// Default upper bound on a single backoff delay.
+pub const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// Key provider decorator that retries transient failures.
+///
+/// A call failing with a retryable error (throttling, an unavailable backend,
+/// transient I/O) is retried up to the configured number of attempts, sleeping
+/// between attempts with "full jitter" exponential backoff: a random delay
+/// between zero and `min(max_delay, base_delay * 2^(attempt - 1))`.
+/// Non-retryable errors are returned immediately, as is the last error once
+/// attempts run out.
+///
+/// Only idempotent operations are retried. [`KeyProvider::create_kek`] and
+/// [`KeyProvider::rotate_pepper`] are passed through once, since retrying a
+/// call that timed out after succeeding would create an extra key.
+/// [`KeyProvider::health_check`] is also passed through, so readiness probes
+/// report the backend's current state.
+#[derive(Debug, Clone)]
+pub struct RetryingProvider<P> {
+    inner: P,
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl<P> RetryingProvider<P> {
+    /// Wraps `inner` with the default retry policy.
+    #[must_use]
+    pub const fn new(inner: P) -> Self {
+        Self {
+            inner,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+        }
+    }
+
+    /// Sets how many times a call is attempted in total, including the first.
+    ///
+    /// Values below 1 are treated as 1 (no retries).
+    #[must_use]
+    pub const fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the delay before the first retry, before jitter.
+    #[must_use]
+    pub const fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Sets the upper bound on a single backoff delay.
+    #[must_use]
+    pub const fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Returns the wrapped provider.
+    #[must_use]
+    pub const fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// Unwraps the decorator, returning the wrapped provider.
+    #[must_use]
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+
+    /// Returns true if a call that failed with `err` on attempt `attempt`
+    /// (1-based) should be tried again.
+    fn should_retry(&self, err: &KeyProviderError, attempt: u32) -> bool {
+        err.is_retryable() && attempt < self.max_attempts
+    }
+
+    /// Returns the jittered delay to sleep after failed attempt `attempt` (1-based).
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let cap = self.base_delay.saturating_mul(1 << exponent).min(self.max_delay);
+
+        let cap_nanos = u64::try_from(cap.as_nanos()).unwrap_or(u64::MAX);
+        if cap_nanos == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_nanos(OsRng.next_u64() % cap_nanos.saturating_add(1))
+    }
+
+    /// Runs `op` against the inner provider, retrying retryable failures.
+    fn retry<T>(
+        &self,
+        mut op: impl FnMut(&P) -> Result<T, KeyProviderError>,
+    ) -> Result<T, KeyProviderError> {
+        let mut attempt = 1;
+        loop {
+            match op(&self.inner) {
+                Err(err) if self.should_retry(&err, attempt) => {
+                    thread::sleep(self.backoff(attempt));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<P: KeyProvider> KeyProvider for RetryingProvider<P> {
+    fn create_kek(&self) -> Result<String, KeyProviderError> {
+        self.inner.create_kek()
+    }
+
+    fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+        self.retry(KeyProvider::current_kek_id)
+    }
+
+    fn current_kek_id_for_tenant(&self, tenant: &str) -> Result<String, KeyProviderError> {
+        self.retry(|inner| inner.current_kek_id_for_tenant(tenant))
+    }
+
+    fn wrap_dek(&self, kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+        self.retry(|inner| inner.wrap_dek(kek_id, dek))
+    }
+
+    fn unwrap_dek(
+        &self,
+        kek_id: &str,
+        wrapped_dek: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        self.retry(|inner| inner.unwrap_dek(kek_id, wrapped_dek))
+    }
+
+    fn wrap_dek_aad(
+        &self,
+        kek_id: &str,
+        dek: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        self.retry(|inner| inner.wrap_dek_aad(kek_id, dek, aad))
+    }
+
+    fn unwrap_dek_aad(
+        &self,
+        kek_id: &str,
+        wrapped_dek: &[u8],
+        aad: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        self.retry(|inner| inner.unwrap_dek_aad(kek_id, wrapped_dek, aad))
+    }
+
+    fn get_pepper(&self) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+        self.retry(KeyProvider::get_pepper)
+    }
+
+    fn current_pepper_version(&self) -> Result<u32, KeyProviderError> {
+        self.retry(KeyProvider::current_pepper_version)
+    }
+
+    fn get_pepper_version(&self, version: u32) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+        self.retry(|inner| inner.get_pepper_version(version))
+    }
+
+    fn rotate_pepper(&self) -> Result<(), KeyProviderError> {
+        self.inner.rotate_pepper()
+    }
+
+    fn rewrap_dek(
+        &self,
+        old_kek_id: &str,
+        new_kek_id: &str,
+        wrapped_dek: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        self.retry(|inner| inner.rewrap_dek(old_kek_id, new_kek_id, wrapped_dek))
+    }
+
+    fn rewrap_dek_aad(
+        &self,
+        old_kek_id: &str,
+        new_kek_id: &str,
+        wrapped_dek: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        self.retry(|inner| inner.rewrap_dek_aad(old_kek_id, new_kek_id, wrapped_dek, aad))
+    }
+
+    fn kek_metadata(&self, kek_id: &str) -> Result<KekMetadata, KeyProviderError> {
+        self.retry(|inner| inner.kek_metadata(kek_id))
+    }
+
+    fn health_check(&self) -> Result<(), KeyProviderError> {
+        self.inner.health_check()
+    }
+}
+
+#[cfg(feature = "async")]
+impl<P: AsyncKeyProvider> RetryingProvider<P> {
+    /// Async counterpart of `retry`, sleeping with `tokio::time::sleep`.
+    async fn retry_async<'a, T, F, Fut>(&'a self, mut op: F) -> Result<T, KeyProviderError>
+    where
+        F: FnMut(&'a P) -> Fut + Send,
+        Fut: std::future::Future<Output = Result<T, KeyProviderError>> + Send,
+        T: Send,
+    {
+        let mut attempt = 1;
+        loop {
+            match op(&self.inner).await {
+                Err(err) if self.should_retry(&err, attempt) => {
+                    tokio::time::sleep(self.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl<P: AsyncKeyProvider> AsyncKeyProvider for RetryingProvider<P> {
+    async fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+        self.retry_async(AsyncKeyProvider::current_kek_id).await
+    }
+
+    async fn wrap_dek(
+        &self,
+        dek: &SecretVec<u8>,
+        kek_id: &str,
+    ) -> Result<WrappedDek, KeyProviderError> {
+        self.retry_async(|inner| inner.wrap_dek(dek, kek_id)).await
+    }
+
+    async fn unwrap_dek(&self, wrapped: &WrappedDek) -> Result<SecretVec<u8>, KeyProviderError> {
+        self.retry_async(|inner| inner.unwrap_dek(wrapped)).await
+    }
+
+    async fn get_pepper(&self) -> Result<SecretVec<u8>, KeyProviderError> {
+        self.retry_async(AsyncKeyProvider::get_pepper).await
+    }
+
+    async fn rewrap_dek(
+        &self,
+        wrapped: &WrappedDek,
+        new_kek_id: &str,
+    ) -> Result<WrappedDek, KeyProviderError> {
+        self.retry_async(|inner| inner.rewrap_dek(wrapped, new_kek_id)).await
+    }
+
+    async fn health_check(&self) -> Result<(), KeyProviderError> {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::ExposeSecret;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Provider whose wrap fails with `error` for the first `failures` calls.
+    struct FlakyProvider {
+        failures: u32,
+        error: fn() -> KeyProviderError,
+        calls: AtomicU32,
+    }
+
+    impl FlakyProvider {
+        fn new(failures: u32, error: fn() -> KeyProviderError) -> Self {
+            Self { failures, error, calls: AtomicU32::new(0) }
+        }
+
+        fn call(&self) -> Result<(), KeyProviderError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                Err((self.error)())
+            } else {
+                Ok(())
+            }
+        }
+
+        fn calls(&self) -> u32 {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    fn throttled() -> KeyProviderError {
+        KeyProviderError::Throttled("rate exceeded".to_string())
+    }
+
+    impl KeyProvider for FlakyProvider {
+        fn create_kek(&self) -> Result<String, KeyProviderError> {
+            self.call().map(|()| "kek_v2".to_string())
+        }
+
+        fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+            Ok("kek_v1".to_string())
+        }
+
+        fn wrap_dek(&self, _kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+            self.call().map(|()| dek.to_vec())
+        }
+
+        fn unwrap_dek(
+            &self,
+            _kek_id: &str,
+            wrapped_dek: &[u8],
+        ) -> Result<SecretVec<u8>, KeyProviderError> {
+            self.call().map(|()| SecretVec::new(wrapped_dek.to_vec()))
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[async_trait::async_trait]
+    impl AsyncKeyProvider for FlakyProvider {
+        async fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+            Ok("kek_v1".to_string())
+        }
+
+        async fn wrap_dek(
+            &self,
+            dek: &SecretVec<u8>,
+            kek_id: &str,
+        ) -> Result<WrappedDek, KeyProviderError> {
+            self.call().map(|()| WrappedDek {
+                kek_id: kek_id.to_string(),
+                encrypted_dek: dek.expose_secret().clone(),
+            })
+        }
+
+        async fn unwrap_dek(
+            &self,
+            wrapped: &WrappedDek,
+        ) -> Result<SecretVec<u8>, KeyProviderError> {
+            self.call().map(|()| SecretVec::new(wrapped.encrypted_dek.clone()))
+        }
+
+        async fn get_pepper(&self) -> Result<SecretVec<u8>, KeyProviderError> {
+            Ok(SecretVec::new(vec![0; 32]))
+        }
+    }
+
+    fn retrying(inner: FlakyProvider) -> RetryingProvider<FlakyProvider> {
+        RetryingProvider::new(inner).with_base_delay(Duration::from_millis(1))
+    }
+
+    #[test]
+    fn test_retry_succeeds_after_transient_failures() {
+        let provider = retrying(FlakyProvider::new(2, throttled));
+
+        let wrapped = KeyProvider::wrap_dek(&provider, "kek_v1", &[7; 32]).unwrap();
+        assert_eq!(wrapped, [7; 32]);
+        assert_eq!(provider.inner().calls(), 3);
+
+        let dek = KeyProvider::unwrap_dek(&provider, "kek_v1", &wrapped).unwrap();
+        assert_eq!(dek.expose_secret(), &[7; 32]);
+        assert_eq!(provider.inner().calls(), 4);
+    }
+
+    #[test]
+    fn test_retry_respects_max_attempts() {
+        let provider = retrying(FlakyProvider::new(2, throttled)).with_max_attempts(2);
+
+        let result = KeyProvider::wrap_dek(&provider, "kek_v1", &[7; 32]);
+        assert!(matches!(result, Err(KeyProviderError::Throttled(_))));
+        assert_eq!(provider.inner().calls(), 2);
+    }
+
+    #[test]
+    fn test_non_retryable_error_fails_immediately() {
+        let provider =
+            retrying(FlakyProvider::new(2, || KeyProviderError::KekNotFound("kek_v1".to_string())));
+
+        let result = KeyProvider::wrap_dek(&provider, "kek_v1", &[7; 32]);
+        assert!(matches!(result, Err(KeyProviderError::KekNotFound(_))));
+        assert_eq!(provider.inner().calls(), 1);
+    }
+
+    #[test]
+    fn test_create_kek_is_not_retried() {
+        let provider = retrying(FlakyProvider::new(1, throttled));
+
+        assert!(provider.create_kek().is_err());
+        assert_eq!(provider.inner().calls(), 1);
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let provider = RetryingProvider::new(())
+            .with_base_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(250));
+
+        for attempt in 1..40 {
+            assert!(provider.backoff(attempt) <= Duration::from_millis(250));
+        }
+        assert!(provider.backoff(1) <= Duration::from_millis(100));
+        assert_eq!(provider.with_base_delay(Duration::ZERO).backoff(5), Duration::ZERO);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_retry_succeeds_after_transient_failures() {
+        let provider = retrying(FlakyProvider::new(2, throttled));
+        let dek = SecretVec::new(vec![7; 32]);
+
+        let wrapped = AsyncKeyProvider::wrap_dek(&provider, &dek, "kek_v1").await.unwrap();
+        assert_eq!(wrapped.encrypted_dek, [7; 32]);
+        assert_eq!(provider.inner().calls(), 3);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_retry_respects_max_attempts() {
+        let provider = retrying(FlakyProvider::new(5, throttled)).with_max_attempts(3);
+        let wrapped = WrappedDek { kek_id: "kek_v1".to_string(), encrypted_dek: vec![7; 32] };
+
+        let result = AsyncKeyProvider::unwrap_dek(&provider, &wrapped).await;
+        assert!(matches!(result, Err(KeyProviderError::Throttled(_))));
+        assert_eq!(provider.inner().calls(), 3);
+    }
+}
@@ -0,0 +1,119 @@
+//! Detection of the scheme a stored value was encrypted with.
+//!
+//! A column can hold both AEAD ciphertext from `Vault` and deterministic
+//! ciphertext from [`DeterministicVault`], e.g. while it is converted from
+//! one to the other. [`detect_scheme`] tells which decrypt path a value
+//! needs, provided deterministic values were written with
+//! [`DeterministicVault::encrypt_tagged`].
+//!
+//! [`DeterministicVault`]: crate::deterministic::DeterministicVault
+//! [`DeterministicVault::encrypt_tagged`]: crate::deterministic::DeterministicVault::encrypt_tagged
+
+use crate::deterministic::{DETERMINISTIC_TAG, SIV_TAG_SIZE};
+use crate::error::Error;
+use crate::header::EncryptionHeader;
+use alloc::string::ToString;
+
+/// Cipher id of headers written before the cipher id was recorded, which are
+/// always ChaCha20-Poly1305.
+const LEGACY_CIPHER_ID: u8 = 1;
+
+/// The scheme a stored value was encrypted with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    /// Envelope encryption with a `SifreDB` header.
+    Aead {
+        /// The header's cipher id (see `CipherMode::id`)
+        cipher_id: u8,
+    },
+    /// Tagged deterministic AES-SIV ciphertext.
+    Deterministic,
+}
+
+/// Detects the scheme of `data` without decrypting it.
+///
+/// Values starting with [`DETERMINISTIC_TAG`] are deterministic; anything
+/// else must parse as a `SifreDB` header, whose cipher id is reported. Headers
+/// written before the cipher id was recorded report ChaCha20-Poly1305's id.
+/// Untagged output of `DeterministicVault::encrypt` has no marker and can't
+/// be detected.
+///
+/// # Errors
+///
+/// Returns `Error::InvalidHeader` (or another header parsing error) if `data`
+/// is neither tagged deterministic ciphertext nor starts with a valid header.
+pub fn detect_scheme(data: &[u8]) -> Result<Scheme, Error> {
+    match data.first() {
+        None => Err(Error::InvalidHeader("Empty ciphertext".to_string())),
+        Some(&DETERMINISTIC_TAG) if data.len() > SIV_TAG_SIZE => Ok(Scheme::Deterministic),
+        Some(&DETERMINISTIC_TAG) => {
+            Err(Error::InvalidHeader("Truncated deterministic ciphertext".to_string()))
+        }
+        Some(_) => {
+            let (header, _) = EncryptionHeader::from_bytes(data)?;
+            Ok(Scheme::Aead { cipher_id: header.cipher_id().unwrap_or(LEGACY_CIPHER_ID) })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::EncryptionContext;
+    use crate::deterministic::DeterministicVault;
+    use crate::header::HeaderFlags;
+    use secrecy::SecretVec;
+
+    fn context() -> EncryptionContext {
+        EncryptionContext::new("users", "email")
+    }
+
+    #[test]
+    fn test_detect_tagged_deterministic() {
+        let vault = DeterministicVault::new(SecretVec::new(vec![0x42; 64])).unwrap();
+        let tagged = vault.encrypt_tagged(b"alice@example.com", &context()).unwrap();
+        assert_eq!(detect_scheme(&tagged).unwrap(), Scheme::Deterministic);
+
+        // Even an empty plaintext leaves the SIV tag
+        let empty = vault.encrypt_tagged(b"", &context()).unwrap();
+        assert_eq!(detect_scheme(&empty).unwrap(), Scheme::Deterministic);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_detect_aead_cipher() {
+        use crate::test_support::XorKeyProvider;
+        use crate::vault::{CipherMode, Vault};
+
+        for mode in CipherMode::ALL {
+            let vault = Vault::new(XorKeyProvider, mode);
+            let ciphertext = vault.encrypt(b"alice@example.com", &context()).unwrap();
+            assert_eq!(
+                detect_scheme(ciphertext.as_bytes()).unwrap(),
+                Scheme::Aead { cipher_id: mode.to_id() }
+            );
+        }
+    }
+
+    #[test]
+    fn test_detect_header_without_cipher_id() {
+        let header =
+            EncryptionHeader::new("kek_v1", vec![1; 32], HeaderFlags::empty(), vec![2; 12]);
+        let mut data = header.to_bytes().unwrap();
+        data.extend_from_slice(&[3; 20]);
+
+        assert_eq!(detect_scheme(&data).unwrap(), Scheme::Aead { cipher_id: LEGACY_CIPHER_ID });
+    }
+
+    #[test]
+    fn test_detect_rejects_unknown_data() {
+        assert!(matches!(detect_scheme(&[]), Err(Error::InvalidHeader(_))));
+        assert!(matches!(detect_scheme(&[DETERMINISTIC_TAG; 8]), Err(Error::InvalidHeader(_))));
+        assert!(detect_scheme(&[0x00, 0x01, 0x02]).is_err());
+
+        // Untagged deterministic output is not recognized
+        let vault = DeterministicVault::new(SecretVec::new(vec![0x42; 64])).unwrap();
+        let untagged = vault.encrypt(b"alice@example.com", &context()).unwrap();
+        assert!(!matches!(detect_scheme(&untagged), Ok(Scheme::Deterministic)));
+    }
+}
@@ -0,0 +1,222 @@
+//! Versioned equality search tokens.
+//!
+//! A search token is an equality token like a blind index, but always carries
+//! the pepper version that produced it. Rows store the token together with
+//! its version, so lookups keep working across a pepper rotation window:
+//! queries recompute the token under each row's stored version instead of
+//! only the current one.
+
+use crate::context::IndexContext;
+use crate::error::Error;
+use crate::key_provider::KeyProvider;
+use hmac::{Hmac, Mac};
+use secrecy::ExposeSecret;
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Domain separation label keeping search tokens distinct from blind indexes.
+const SEARCH_TOKEN_LABEL: &[u8] = b"sifredb-search-token\0";
+
+/// Search token output size (32 bytes, the full HMAC-SHA256 output).
+pub const SEARCH_TOKEN_SIZE: usize = 32;
+
+/// An equality search token tagged with the pepper version that produced it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchToken {
+    /// Pepper version used to compute the token
+    pub version: u32,
+    /// The token bytes
+    pub bytes: Vec<u8>,
+}
+
+/// Generates a search token with the provider's current pepper version.
+///
+/// The token is computed as:
+/// `HMAC-SHA256(pepper_v, label || version || value || context)`
+///
+/// # Errors
+///
+/// Returns error if:
+/// - The current pepper version is not available from the provider
+/// - HMAC computation fails
+///
+/// # Example
+///
+/// ```ignore
+/// use sifredb::context::IndexContext;
+/// use sifredb::search_token::{generate_search_token, search_token_matches};
+///
+/// let context = IndexContext::new("users", "email");
+/// let token = generate_search_token(&provider, b"alice@example.com", &context)?;
+///
+/// // Later, possibly after a pepper rotation
+/// assert!(search_token_matches(&token, b"alice@example.com", &provider, &context)?);
+/// ```
+pub fn generate_search_token<P: KeyProvider>(
+    provider: &P,
+    value: &[u8],
+    context: &IndexContext,
+) -> Result<SearchToken, Error> {
+    let version = provider.current_pepper_version()?;
+    let mac = token_mac(provider, value, context, version)?;
+
+    Ok(SearchToken { version, bytes: mac.finalize().into_bytes().to_vec() })
+}
+
+/// Checks in constant time whether `value` produces `token` under the token's
+/// pepper version.
+///
+/// # Errors
+///
+/// Returns error if the token's pepper version is not available from the provider.
+pub fn search_token_matches<P: KeyProvider>(
+    token: &SearchToken,
+    value: &[u8],
+    provider: &P,
+    context: &IndexContext,
+) -> Result<bool, Error> {
+    let mac = token_mac(provider, value, context, token.version)?;
+    Ok(mac.verify_slice(&token.bytes).is_ok())
+}
+
+/// Builds the token HMAC keyed by the given pepper version.
+fn token_mac<P: KeyProvider>(
+    provider: &P,
+    value: &[u8],
+    context: &IndexContext,
+    version: u32,
+) -> Result<HmacSha256, Error> {
+    let pepper = provider.get_pepper_version(version)?.ok_or_else(|| {
+        Error::IndexGenerationFailed(format!("Pepper version {version} not available"))
+    })?;
+
+    let mut mac = HmacSha256::new_from_slice(pepper.expose_secret())
+        .map_err(|e| Error::IndexGenerationFailed(format!("Invalid pepper: {e}")))?;
+
+    mac.update(SEARCH_TOKEN_LABEL);
+    mac.update(&version.to_be_bytes());
+    mac.update(value);
+    mac.update(context.to_string().as_bytes());
+
+    Ok(mac)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blind_index::generate_blind_index_versioned;
+    use crate::error::KeyProviderError;
+    use secrecy::SecretVec;
+    use std::sync::Mutex;
+
+    // Mock key provider whose pepper can be rotated
+    struct RotatingPepperProvider {
+        peppers: Mutex<Vec<Vec<u8>>>,
+    }
+
+    impl RotatingPepperProvider {
+        fn new() -> Self {
+            Self { peppers: Mutex::new(vec![vec![1u8; 32]]) }
+        }
+    }
+
+    impl KeyProvider for RotatingPepperProvider {
+        fn create_kek(&self) -> Result<String, KeyProviderError> {
+            Ok("mock_kek".to_string())
+        }
+
+        fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+            Ok("mock_kek".to_string())
+        }
+
+        fn wrap_dek(&self, _kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+            Ok(dek.to_vec())
+        }
+
+        fn unwrap_dek(
+            &self,
+            _kek_id: &str,
+            wrapped_dek: &[u8],
+        ) -> Result<SecretVec<u8>, KeyProviderError> {
+            Ok(SecretVec::new(wrapped_dek.to_vec()))
+        }
+
+        fn current_pepper_version(&self) -> Result<u32, KeyProviderError> {
+            Ok(u32::try_from(self.peppers.lock().unwrap().len()).unwrap())
+        }
+
+        fn get_pepper_version(
+            &self,
+            version: u32,
+        ) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+            let peppers = self.peppers.lock().unwrap();
+            let index = usize::try_from(version).unwrap().checked_sub(1);
+            Ok(index.and_then(|i| peppers.get(i)).map(|p| SecretVec::new(p.clone())))
+        }
+
+        fn rotate_pepper(&self) -> Result<(), KeyProviderError> {
+            let next = u8::try_from(self.current_pepper_version()? + 1).unwrap();
+            self.peppers.lock().unwrap().push(vec![next; 32]);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_search_token_matches_value() {
+        let provider = RotatingPepperProvider::new();
+        let context = IndexContext::new("users", "email");
+
+        let token = generate_search_token(&provider, b"alice@example.com", &context).unwrap();
+        assert_eq!(token.version, 1);
+        assert_eq!(token.bytes.len(), SEARCH_TOKEN_SIZE);
+
+        assert!(search_token_matches(&token, b"alice@example.com", &provider, &context).unwrap());
+        assert!(!search_token_matches(&token, b"bob@example.com", &provider, &context).unwrap());
+
+        let other_context = IndexContext::new("users", "backup_email");
+        assert!(
+            !search_token_matches(&token, b"alice@example.com", &provider, &other_context).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_search_token_survives_pepper_rotation() {
+        let provider = RotatingPepperProvider::new();
+        let context = IndexContext::new("users", "email");
+
+        let v1_token = generate_search_token(&provider, b"alice@example.com", &context).unwrap();
+        provider.rotate_pepper().unwrap();
+
+        let v2_token = generate_search_token(&provider, b"alice@example.com", &context).unwrap();
+        assert_eq!(v2_token.version, 2);
+        assert_ne!(v1_token.bytes, v2_token.bytes);
+
+        // Rows written before the rotation still match
+        assert!(search_token_matches(&v1_token, b"alice@example.com", &provider, &context).unwrap());
+        assert!(search_token_matches(&v2_token, b"alice@example.com", &provider, &context).unwrap());
+        assert!(!search_token_matches(&v1_token, b"bob@example.com", &provider, &context).unwrap());
+    }
+
+    #[test]
+    fn test_search_token_distinct_from_blind_index() {
+        let provider = RotatingPepperProvider::new();
+        let context = IndexContext::new("users", "email");
+
+        let token = generate_search_token(&provider, b"alice@example.com", &context).unwrap();
+        let index =
+            generate_blind_index_versioned(&provider, b"alice@example.com", &context).unwrap();
+
+        assert_ne!(&token.bytes[..index.index.len()], index.index.as_slice());
+    }
+
+    #[test]
+    fn test_search_token_unknown_version() {
+        let provider = RotatingPepperProvider::new();
+        let context = IndexContext::new("users", "email");
+
+        let token = SearchToken { version: 7, bytes: vec![0; SEARCH_TOKEN_SIZE] };
+        let result = search_token_matches(&token, b"alice@example.com", &provider, &context);
+        assert!(matches!(result, Err(Error::IndexGenerationFailed(_))));
+    }
+}
@@ -0,0 +1,32 @@
+//! Fixtures shared by the crate's unit tests.
+
+use crate::error::KeyProviderError;
+use crate::key_provider::KeyProvider;
+use secrecy::SecretVec;
+
+/// Provider with a single fixed KEK that "wraps" by XOR.
+///
+/// Only for tests; it offers no protection.
+pub struct XorKeyProvider;
+
+impl KeyProvider for XorKeyProvider {
+    fn create_kek(&self) -> Result<String, KeyProviderError> {
+        Ok("kek_v1".to_string())
+    }
+
+    fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+        Ok("kek_v1".to_string())
+    }
+
+    fn wrap_dek(&self, _kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+        Ok(dek.iter().map(|b| b ^ 0x5A).collect())
+    }
+
+    fn unwrap_dek(
+        &self,
+        _kek_id: &str,
+        wrapped_dek: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        Ok(SecretVec::new(wrapped_dek.iter().map(|b| b ^ 0x5A).collect()))
+    }
+}
@@ -3,31 +3,206 @@
 //! The Vault provides high-level encryption and decryption operations using
 //! envelope encryption with AEAD ciphers.
 
+use crate::ciphertext::{check_payload_len, payload_end, Ciphertext};
 use crate::context::EncryptionContext;
-use crate::error::Error;
-use crate::header::{EncryptionHeader, HeaderFlags};
-use crate::kdf::generate_dek;
-use crate::key_provider::KeyProvider;
+#[cfg(feature = "dek-cache")]
+use crate::dek_cache::DekCache;
+use crate::error::{Error, KeyProviderError};
+use crate::header::{
+    EncryptionHeader, HeaderFlags, COMMITMENT_SIZE, HEADER_MAC_SIZE, PROTOCOL_VERSION,
+};
+use crate::kdf::{derive_dek_sized, derive_fingerprint_key, DEK_SIZE};
+use crate::key_provider::{KeyProvider, WrappedDek};
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::{Aes128Gcm, Aes256Gcm};
+use aes_gcm_siv::Aes256GcmSiv;
 use chacha20poly1305::{
-    aead::{rand_core::RngCore, Aead, KeyInit, OsRng},
-    ChaCha20Poly1305, Nonce,
+    aead::{
+        rand_core::{CryptoRng, RngCore},
+        Aead, KeyInit, OsRng,
+    },
+    ChaCha20Poly1305, XChaCha20Poly1305,
 };
-use secrecy::ExposeSecret;
-use std::sync::Arc;
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretVec};
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{Read, Write};
+use std::iter;
+#[cfg(feature = "dek-cache")]
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::{Zeroize, Zeroizing};
 
-/// Nonce size for ChaCha20-Poly1305 (96 bits).
+/// Nonce size of every cipher but XChaCha20-Poly1305 (96 bits).
 const NONCE_SIZE: usize = 12;
 
-/// Cipher mode for encryption.
+/// Nonce size of XChaCha20-Poly1305 (192 bits).
+const XNONCE_SIZE: usize = 24;
+
+/// Trailing bytes of a chunk nonce that hold the chunk index, see
+/// [`Vault::encrypt_chunked`].
+const CHUNK_INDEX_SIZE: usize = 4;
+
+/// AEAD authentication tag size in bytes, the same for every [`CipherMode`].
+pub const TAG_SIZE: usize = 16;
+
+/// Largest plaintext one ciphertext can hold, as the header records the
+/// payload (plaintext plus tag) length in 4 bytes.
+pub const MAX_PLAINTEXT_LEN: usize = u32::MAX as usize - TAG_SIZE;
+
+/// Default upper bound on the size of a decompressed payload (64 MiB).
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
+
+/// External data bound to the first chunk by
+/// [`Vault::encrypt_chunked_with_aad`], such as a file's metadata record.
+///
+/// Only the SHA-256 digest of the data enters the chunk's associated data, so
+/// `Bytes(data)` and `Sha256Digest(sha256(data))` are interchangeable: data
+/// too large to buffer can be hashed incrementally by the caller instead.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamAad<'a> {
+    /// The data itself.
+    Bytes(&'a [u8]),
+    /// A SHA-256 digest of the data.
+    Sha256Digest([u8; 32]),
+}
+
+impl StreamAad<'_> {
+    /// Returns the SHA-256 digest of the data.
+    fn digest(self) -> [u8; 32] {
+        match self {
+            Self::Bytes(data) => Sha256::digest(data).into(),
+            Self::Sha256Digest(digest) => digest,
+        }
+    }
+}
+
+/// Cipher mode for encryption.
+///
+/// The mode is recorded in each ciphertext header as a cipher id, so a vault
+/// can decrypt ciphertext produced under any mode.
+///
+/// # Cipher ids
+///
+/// The id assignment is part of the wire format and never changes; readers
+/// in other languages can rely on it:
+///
+/// | Id | Cipher |
+/// |----|--------|
+/// | 1  | ChaCha20-Poly1305 (also assumed for headers without a cipher id) |
+/// | 2  | AES-256-GCM |
+/// | 3  | XChaCha20-Poly1305 |
+/// | 4  | AES-256-GCM-SIV |
+/// | 5  | AES-128-GCM |
+///
+/// Id 0 and ids above 5 are unassigned. New ciphers take the next free id.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum CipherMode {
     /// ChaCha20-Poly1305 AEAD cipher (default).
+    #[default]
     ChaCha20Poly1305,
+    /// AES-256-GCM AEAD cipher, for hardware with AES acceleration.
+    Aes256Gcm,
+    /// XChaCha20-Poly1305, ChaCha20-Poly1305 with a 192-bit nonce.
+    ///
+    /// Random nonces of that size don't realistically collide, so one DEK
+    /// can seal far more messages than with a 96-bit nonce.
+    XChaCha20Poly1305,
+    /// AES-128-GCM AEAD cipher, for systems constrained to 128-bit keys.
+    Aes128Gcm,
+    /// AES-256-GCM-SIV, a nonce-misuse-resistant AEAD cipher.
+    ///
+    /// A repeated nonce under the same DEK only reveals whether two messages
+    /// are identical, instead of breaking confidentiality and authenticity as
+    /// with GCM or ChaCha20-Poly1305. A safer choice for high-volume workloads
+    /// that share DEKs, such as [`Vault::encrypt_batch`] or a DEK cache.
+    Aes256GcmSiv,
 }
 
-impl Default for CipherMode {
-    fn default() -> Self {
-        Self::ChaCha20Poly1305
+impl CipherMode {
+    /// Every cipher mode, in cipher id order.
+    pub const ALL: [Self; 5] = [
+        Self::ChaCha20Poly1305,
+        Self::Aes256Gcm,
+        Self::XChaCha20Poly1305,
+        Self::Aes256GcmSiv,
+        Self::Aes128Gcm,
+    ];
+
+    /// Returns the identifier stored in the header's cipher id field.
+    #[must_use]
+    pub const fn to_id(self) -> u8 {
+        match self {
+            Self::ChaCha20Poly1305 => 1,
+            Self::Aes256Gcm => 2,
+            Self::XChaCha20Poly1305 => 3,
+            Self::Aes256GcmSiv => 4,
+            Self::Aes128Gcm => 5,
+        }
+    }
+
+    /// Looks up a cipher mode by its header identifier.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UnsupportedCipher` if `id` is not assigned.
+    pub const fn from_id(id: u8) -> Result<Self, Error> {
+        match id {
+            1 => Ok(Self::ChaCha20Poly1305),
+            2 => Ok(Self::Aes256Gcm),
+            3 => Ok(Self::XChaCha20Poly1305),
+            4 => Ok(Self::Aes256GcmSiv),
+            5 => Ok(Self::Aes128Gcm),
+            _ => Err(Error::UnsupportedCipher(id)),
+        }
+    }
+
+    /// Returns the DEK size in bytes this cipher requires.
+    #[must_use]
+    pub const fn key_len(self) -> usize {
+        match self {
+            Self::ChaCha20Poly1305
+            | Self::Aes256Gcm
+            | Self::XChaCha20Poly1305
+            | Self::Aes256GcmSiv => DEK_SIZE,
+            Self::Aes128Gcm => 16,
+        }
+    }
+
+    /// Returns the nonce size in bytes this cipher requires.
+    #[must_use]
+    pub const fn nonce_len(self) -> usize {
+        match self {
+            Self::XChaCha20Poly1305 => XNONCE_SIZE,
+            Self::ChaCha20Poly1305 | Self::Aes256Gcm | Self::Aes256GcmSiv | Self::Aes128Gcm => {
+                NONCE_SIZE
+            }
+        }
+    }
+
+    /// Returns the largest plaintext, in bytes, the cipher's specification
+    /// allows in one message.
+    ///
+    /// That is 2^38 - 64 bytes (about 256 GiB) for ChaCha20-Poly1305 and
+    /// XChaCha20-Poly1305 (RFC 8439), 2^36 - 32 bytes (about 64 GiB) for AES-GCM (NIST SP
+    /// 800-38D) and 2^36 bytes for AES-GCM-SIV (RFC 8452). Past it the
+    /// keystream repeats and confidentiality and authenticity are lost.
+    /// A single ciphertext can't get near it (see [`MAX_PLAINTEXT_LEN`]),
+    /// but [`Vault::encrypt_chunked`] seals every chunk under one DEK and
+    /// refuses a plaintext larger than this.
+    #[must_use]
+    pub const fn max_message_bytes(self) -> u64 {
+        match self {
+            Self::ChaCha20Poly1305 | Self::XChaCha20Poly1305 => (1 << 38) - 64,
+            Self::Aes256Gcm | Self::Aes128Gcm => (1 << 36) - 32,
+            Self::Aes256GcmSiv => 1 << 36,
+        }
     }
 }
 
@@ -63,6 +238,35 @@ impl Default for CipherMode {
 pub struct Vault<P: KeyProvider> {
     provider: Arc<P>,
     cipher_mode: CipherMode,
+    max_decompressed_size: usize,
+    clock: Arc<dyn Fn() -> u64 + Send + Sync>,
+    nonce_counter: Option<Arc<AtomicU64>>,
+    rng: RngSource,
+    key_commitment: bool,
+    require_key_commitment: bool,
+    header_mac: bool,
+    root_keys: Option<Arc<RootKeys>>,
+    #[cfg(feature = "dek-cache")]
+    dek_cache: Option<Arc<DekCache>>,
+}
+
+impl<P: KeyProvider> fmt::Debug for Vault<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The provider may hold key material, so it is never formatted
+        let mut debug = f.debug_struct("Vault");
+        debug
+            .field("cipher_mode", &self.cipher_mode)
+            .field("max_decompressed_size", &self.max_decompressed_size)
+            .field("counter_nonces", &self.nonce_counter.is_some())
+            .field("rng", &self.rng)
+            .field("key_commitment", &self.key_commitment)
+            .field("require_key_commitment", &self.require_key_commitment)
+            .field("header_mac", &self.header_mac)
+            .field("deriving", &self.root_keys.is_some());
+        #[cfg(feature = "dek-cache")]
+        debug.field("dek_cache", &self.dek_cache.is_some());
+        debug.finish_non_exhaustive()
+    }
 }
 
 impl<P: KeyProvider> Vault<P> {
@@ -72,8 +276,198 @@ impl<P: KeyProvider> Vault<P> {
     ///
     /// * `provider` - Key provider for KEK management
     /// * `cipher_mode` - Cipher mode to use for encryption
+    ///
+    /// Equivalent to `Vault::builder(provider).cipher(cipher_mode).build()`.
     pub fn new(provider: P, cipher_mode: CipherMode) -> Self {
-        Self { provider: Arc::new(provider), cipher_mode }
+        Self::builder(provider).cipher(cipher_mode).build()
+    }
+
+    /// Creates a Vault that derives each context's DEK from a root key.
+    ///
+    /// A plain vault generates and wraps a fresh DEK per encryption, which is
+    /// one key provider call each time. A deriving vault generates one random
+    /// root key per KEK and tenant, wraps it once, and derives the DEK for
+    /// each context from it with HKDF ([`derive_dek_sized`]) over the
+    /// canonical context bytes. The header records the wrapped root key and
+    /// is marked as derived (protocol version 4), so any vault over the same
+    /// provider can decrypt it.
+    ///
+    /// Every encryption for a context uses the same DEK, so each root key is
+    /// retired after 2^32 messages or [`CipherMode::max_message_bytes`] of
+    /// plaintext across all of its contexts, whichever comes first, and the
+    /// next encryption wraps a new one. Root keys otherwise live as long as
+    /// the vault; after a KEK rotation the next encryption creates a new one.
+    /// [`Vault::encrypt_chunked`] numbers its chunk nonces itself, so chunked
+    /// streams always get a fresh random DEK of their own instead.
+    ///
+    /// With [`Vault::with_dek_cache`], derived DEKs are cached per context
+    /// too, so repeat encryption skips the HKDF step.
+    ///
+    /// Equivalent to `Vault::builder(provider).cipher(cipher_mode).deriving().build()`.
+    pub fn deriving(provider: P, cipher_mode: CipherMode) -> Self {
+        Self::builder(provider).cipher(cipher_mode).deriving().build()
+    }
+
+    /// Starts configuring a Vault for the given key provider.
+    ///
+    /// Every option defaults to the same value [`Vault::new`] uses.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let vault = Vault::builder(provider)
+    ///     .cipher(CipherMode::Aes128Gcm)
+    ///     .counter_nonces()
+    ///     .max_decompressed(1024 * 1024)
+    ///     .build();
+    /// ```
+    pub fn builder(provider: P) -> VaultBuilder<P> {
+        VaultBuilder {
+            provider,
+            cipher_mode: CipherMode::default(),
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+            clock: Arc::new(system_clock),
+            counter_nonces: false,
+            rng: RngSource::default(),
+            key_commitment: false,
+            require_key_commitment: false,
+            header_mac: false,
+            deriving: false,
+            #[cfg(feature = "dek-cache")]
+            dek_cache_capacity: 0,
+        }
+    }
+
+    /// Enables an LRU cache of unwrapped DEKs holding up to `capacity` entries.
+    ///
+    /// On decryption, a cache hit skips the `unwrap_dek` call to the key provider,
+    /// which avoids a KMS round-trip when many ciphertexts share a wrapped DEK
+    /// (e.g. output of [`Vault::encrypt_batch`]). Cached DEKs are zeroized when
+    /// evicted. The cache is shared between clones of this vault.
+    ///
+    /// DEKs derived per context (see [`Vault::deriving`]) are cached as well,
+    /// keyed by the wrapped root key and the canonical context bytes, so
+    /// repeat encryption and decryption for a context skip HKDF.
+    ///
+    /// A `capacity` of zero disables the cache.
+    #[cfg(feature = "dek-cache")]
+    #[must_use]
+    pub fn with_dek_cache(mut self, capacity: usize) -> Self {
+        self.dek_cache = NonZeroUsize::new(capacity).map(|cap| Arc::new(DekCache::new(cap)));
+        self
+    }
+
+    /// Evicts (and zeroizes) every cached DEK.
+    #[cfg(feature = "dek-cache")]
+    pub fn clear_dek_cache(&self) {
+        if let Some(cache) = &self.dek_cache {
+            cache.clear();
+        }
+    }
+
+    /// Sets the maximum size a compressed payload may inflate to on decryption.
+    ///
+    /// Decryption of a compressed ciphertext whose payload would exceed this
+    /// limit fails instead of allocating, guarding against decompression bombs.
+    /// Defaults to [`DEFAULT_MAX_DECOMPRESSED_SIZE`].
+    #[must_use]
+    pub const fn with_max_decompressed_size(mut self, max: usize) -> Self {
+        self.max_decompressed_size = max;
+        self
+    }
+
+    /// Replaces the clock used to stamp each ciphertext's `created_at`.
+    ///
+    /// The clock returns the current time in Unix milliseconds. Defaults to the
+    /// system clock; inject a fixed clock to make header timestamps
+    /// deterministic in tests.
+    #[must_use]
+    pub fn with_clock(mut self, clock: impl Fn() -> u64 + Send + Sync + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Derives nonces from a monotonic counter instead of the RNG.
+    ///
+    /// Each nonce is `[0u8; 4] || counter` (big-endian `u64`), taken from an
+    /// `AtomicU64` owned by this vault and shared with its clones, so no two
+    /// encryptions through the vault ever use the same nonce. This removes the
+    /// birthday bound on random 96-bit nonces, which matters when one DEK
+    /// seals many messages, as in [`Vault::encrypt_batch`].
+    ///
+    /// Encryption fails once the counter is exhausted rather than wrapping.
+    #[must_use]
+    pub fn with_counter_nonces(mut self) -> Self {
+        self.nonce_counter = Some(Arc::new(AtomicU64::new(0)));
+        self
+    }
+
+    /// Replaces the RNG used to generate DEKs and random nonces.
+    ///
+    /// Defaults to the operating system RNG. Inject a seeded generator to
+    /// produce reproducible ciphertexts, e.g. wire format test vectors; never
+    /// do so in production, as a predictable RNG makes DEKs guessable. The
+    /// RNG is shared between clones of this vault.
+    #[must_use]
+    pub fn with_rng(mut self, rng: impl CryptoRng + RngCore + Send + 'static) -> Self {
+        self.rng = RngSource::new(rng);
+        self
+    }
+
+    /// Commits each new ciphertext to its DEK.
+    ///
+    /// ChaCha20-Poly1305 and the AES-GCM ciphers are not key-committing: a
+    /// payload can be crafted to authenticate under two different DEKs, so a
+    /// multi-recipient ciphertext could decrypt to different plaintexts for
+    /// different recipients. With this enabled, each header records
+    /// HMAC-SHA256 of its nonce keyed by the DEK, and decryption checks it
+    /// before opening the payload, so any other DEK fails with
+    /// `Error::AuthenticationFailed`.
+    ///
+    /// Committed headers are written as protocol version 4 and are 33 bytes
+    /// longer. Ciphertext is checked whenever it carries a commitment,
+    /// whatever this setting, but uncommitted ciphertext still decrypts, so
+    /// whoever encrypts can simply leave the commitment out; see
+    /// [`Vault::with_required_key_commitment`].
+    #[must_use]
+    pub const fn with_key_commitment(mut self) -> Self {
+        self.key_commitment = true;
+        self
+    }
+
+    /// Commits each new ciphertext to its DEK like
+    /// [`Vault::with_key_commitment`], and refuses to decrypt ciphertext
+    /// without a commitment.
+    ///
+    /// Use this once every stored ciphertext is committed, or when the
+    /// encrypting party isn't trusted: otherwise an uncommitted ciphertext
+    /// crafted to decrypt differently per recipient is still accepted.
+    /// Uncommitted ciphertext fails with `Error::InvalidHeader` before any
+    /// DEK is unwrapped.
+    #[must_use]
+    pub const fn with_required_key_commitment(mut self) -> Self {
+        self.key_commitment = true;
+        self.require_key_commitment = true;
+        self
+    }
+
+    /// Authenticates each new header with a MAC keyed by the DEK.
+    ///
+    /// The header is otherwise covered only indirectly, through the context
+    /// in the AEAD associated data, so a flipped flag bit or a changed field
+    /// surfaces as a misparse or a bare authentication failure. With this
+    /// enabled, each header records HMAC-SHA256 over its fields (see
+    /// [`EncryptionHeader::mac_input`]), and decryption checks it as soon as
+    /// the DEK is unwrapped, before the payload is touched, failing with
+    /// `Error::InvalidHeader`.
+    ///
+    /// Such headers are written as protocol version 4 and are 33 bytes
+    /// longer; releases that only read version 3 can't decrypt them. Headers
+    /// are checked whenever they carry a MAC, whatever this setting.
+    #[must_use]
+    pub const fn with_header_mac(mut self) -> Self {
+        self.header_mac = true;
+        self
     }
 
     /// Encrypts plaintext using envelope encryption.
@@ -85,185 +479,3436 @@ impl<P: KeyProvider> Vault<P> {
     ///
     /// # Returns
     ///
-    /// Ciphertext with embedded header: `[header][encrypted_data]`
+    /// [`Ciphertext`] with embedded header: `[header][encrypted_data]`
     ///
     /// # Errors
     ///
     /// Returns error if:
+    /// - The plaintext exceeds [`MAX_PLAINTEXT_LEN`]
     /// - Key provider operations fail
     /// - Encryption fails
     /// - Header serialization fails
-    pub fn encrypt(&self, plaintext: &[u8], context: &EncryptionContext) -> Result<Vec<u8>, Error> {
-        // Generate a random DEK for this encryption operation
-        let dek = generate_dek();
-
-        // Get the current KEK ID
-        let kek_id = self.provider.current_kek_id()?;
+    pub fn encrypt(
+        &self,
+        plaintext: &[u8],
+        context: &EncryptionContext,
+    ) -> Result<Ciphertext, Error> {
+        self.encrypt_with_aad(plaintext, context, &[])
+    }
 
-        // Wrap the DEK with the KEK
-        let wrapped_dek = self.provider.wrap_dek(&kek_id, dek.expose_secret())?;
+    /// Encrypts plaintext, additionally binding it to caller-supplied associated data.
+    ///
+    /// The AEAD associated data is `context || 0x00 || extra_aad`, so the
+    /// ciphertext only decrypts when the same `extra_aad` is supplied. Use this to
+    /// bind a ciphertext to e.g. a record id or row version so it can't be
+    /// replayed into a different row. The extra AAD is not stored in the header.
+    ///
+    /// An empty `extra_aad` is identical to [`Vault::encrypt`].
+    ///
+    /// # Arguments
+    ///
+    /// * `plaintext` - Data to encrypt
+    /// * `context` - Encryption context for domain separation
+    /// * `extra_aad` - Additional data to authenticate (not encrypted, not stored)
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - The plaintext exceeds [`MAX_PLAINTEXT_LEN`]
+    /// - Key provider operations fail
+    /// - Encryption fails
+    /// - Header serialization fails
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "encrypt",
+            skip_all,
+            fields(
+                kek_id = tracing::field::Empty,
+                cipher_mode = ?self.cipher_mode,
+                payload_size = plaintext.len()
+            )
+        )
+    )]
+    pub fn encrypt_with_aad(
+        &self,
+        plaintext: &[u8],
+        context: &EncryptionContext,
+        extra_aad: &[u8],
+    ) -> Result<Ciphertext, Error> {
+        check_plaintext_len(plaintext.len())?;
+        let envelope = self.new_envelope(context, 1, plaintext.len())?;
 
-        // Generate a random nonce
-        let mut nonce_bytes = [0u8; NONCE_SIZE];
-        OsRng.fill_bytes(&mut nonce_bytes);
+        self.seal(
+            &envelope,
+            &self.next_nonce()?,
+            HeaderFlags::empty(),
+            plaintext,
+            context,
+            extra_aad,
+        )
+    }
 
-        // Encrypt the plaintext with the DEK
-        let ciphertext = match self.cipher_mode {
-            CipherMode::ChaCha20Poly1305 => {
-                let cipher = ChaCha20Poly1305::new_from_slice(dek.expose_secret())
-                    .map_err(|e| Error::EncryptionFailed(format!("Invalid DEK: {e}")))?;
+    /// Encrypts plaintext with its DEK wrapped under `kek_id` rather than the
+    /// current KEK.
+    ///
+    /// For deliberately writing new data under an older KEK, e.g. backfilling
+    /// rows to match a tenant's pinned key. Apart from the KEK the result is
+    /// the same as [`Vault::encrypt`], and it decrypts the same way.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - The plaintext exceeds [`MAX_PLAINTEXT_LEN`]
+    /// - The provider doesn't know `kek_id` (typically
+    ///   [`KeyProviderError::KekNotFound`])
+    /// - Key provider operations fail
+    /// - Encryption fails
+    /// - Header serialization fails
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "encrypt",
+            skip_all,
+            fields(
+                kek_id = tracing::field::Empty,
+                cipher_mode = ?self.cipher_mode,
+                payload_size = plaintext.len()
+            )
+        )
+    )]
+    pub fn encrypt_with_kek(
+        &self,
+        plaintext: &[u8],
+        context: &EncryptionContext,
+        kek_id: &str,
+    ) -> Result<Ciphertext, Error> {
+        check_plaintext_len(plaintext.len())?;
+        let envelope = self.envelope_under(kek_id.to_string(), wrap_aad(context))?;
 
-                let nonce = Nonce::from(nonce_bytes);
+        self.seal(&envelope, &self.next_nonce()?, HeaderFlags::empty(), plaintext, context, &[])
+    }
 
-                // Use context as associated data for additional authentication
-                let aad = context.to_string();
+    /// Encrypts plaintext under a caller-supplied DEK instead of a freshly
+    /// generated one.
+    ///
+    /// For interop with systems that derive DEKs themselves, e.g. one key per
+    /// document from a master secret. The DEK is wrapped under the current
+    /// KEK for `context` like any other, and the result decrypts with
+    /// [`Vault::decrypt`].
+    ///
+    /// # Nonce reuse
+    ///
+    /// Every other method seals each message under a new random DEK, so
+    /// nonces never repeat under one key. Here the caller decides how often a
+    /// DEK is used. With ChaCha20-Poly1305 or AES-128-GCM, two messages under
+    /// the same DEK and nonce reveal the XOR of their plaintexts and let the
+    /// authentication key be recovered, so:
+    /// - Keep the messages per DEK well below 2^32 with random nonces.
+    /// - Don't combine a reused DEK with [`Vault::with_counter_nonces`]: the
+    ///   counter is per vault, so another process or a restart starts again
+    ///   at 0 with the same DEK.
+    /// - Prefer [`CipherMode::Aes256GcmSiv`] when a DEK is reused, which only
+    ///   reveals repeated identical messages on a nonce collision.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - `dek` is not [`CipherMode::key_len`] bytes for the vault's cipher
+    ///   (`Error::InvalidKeyLength`)
+    /// - The plaintext exceeds [`MAX_PLAINTEXT_LEN`]
+    /// - Key provider operations fail
+    /// - Encryption fails
+    /// - Header serialization fails
+    pub fn encrypt_with_dek(
+        &self,
+        plaintext: &[u8],
+        context: &EncryptionContext,
+        dek: &SecretVec<u8>,
+    ) -> Result<Ciphertext, Error> {
+        let expected = self.cipher_mode.key_len();
+        let actual = dek.expose_secret().len();
+        if actual != expected {
+            return Err(Error::InvalidKeyLength { expected, actual });
+        }
+        check_plaintext_len(plaintext.len())?;
 
-                cipher
-                    .encrypt(
-                        &nonce,
-                        chacha20poly1305::aead::Payload { msg: plaintext, aad: aad.as_bytes() },
-                    )
-                    .map_err(|e| {
-                        Error::EncryptionFailed(format!("ChaCha20-Poly1305 encryption failed: {e}"))
-                    })?
-            }
+        let kek_id = self.current_kek_id(context)?;
+        let wrapped_dek = self.wrap_dek(&kek_id, dek, wrap_aad(context))?;
+        let envelope = Envelope {
+            dek: SecretVec::new(dek.expose_secret().clone()),
+            kek_id,
+            wrapped_dek,
+            additional_recipients: Vec::new(),
+            derived: false,
         };
 
-        // Create header
-        let header =
-            EncryptionHeader::new(kek_id, wrapped_dek, HeaderFlags::empty(), nonce_bytes.to_vec());
-
-        // Serialize header
-        let header_bytes = header.to_bytes()?;
-
-        // Combine header and ciphertext
-        let mut result = Vec::with_capacity(header_bytes.len() + ciphertext.len());
-        result.extend_from_slice(&header_bytes);
-        result.extend_from_slice(&ciphertext);
-
-        Ok(result)
+        self.seal(&envelope, &self.next_nonce()?, HeaderFlags::empty(), plaintext, context, &[])
     }
 
-    /// Decrypts ciphertext using envelope encryption.
+    /// Encrypts plaintext, returning the serialized header and the payload separately.
     ///
-    /// # Arguments
-    ///
-    /// * `ciphertext` - Encrypted data with header
-    /// * `context` - Encryption context (must match the one used for encryption)
+    /// For layouts that keep a small metadata column next to a large blob
+    /// column. Concatenating the two halves gives exactly what
+    /// [`Vault::encrypt`] produces. Rewrapping with [`Vault::rewrap_detached`]
+    /// then only rewrites the header, never the payload.
     ///
     /// # Returns
     ///
-    /// The original plaintext.
+    /// A `(header, ciphertext)` pair; decrypt it with [`Vault::decrypt_detached`].
     ///
     /// # Errors
     ///
     /// Returns error if:
-    /// - Header parsing fails
+    /// - The plaintext exceeds [`MAX_PLAINTEXT_LEN`]
     /// - Key provider operations fail
-    /// - Decryption fails
-    /// - Authentication fails
-    pub fn decrypt(
+    /// - Encryption fails
+    /// - Header serialization fails
+    pub fn encrypt_detached(
         &self,
-        ciphertext: &[u8],
+        plaintext: &[u8],
         context: &EncryptionContext,
-    ) -> Result<Vec<u8>, Error> {
-        // Parse header
-        let (header, header_len) = EncryptionHeader::from_bytes(ciphertext)?;
+    ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        Ok(self.encrypt(plaintext, context)?.into_parts())
+    }
 
-        // Extract the encrypted data
-        let encrypted_data = &ciphertext[header_len..];
+    /// Compresses plaintext with DEFLATE, then encrypts it.
+    ///
+    /// Compression always happens before encryption; the header's compressed
+    /// flag tells [`Vault::decrypt`] to inflate the payload after it has been
+    /// authenticated. Worthwhile for large, redundant payloads such as JSON
+    /// documents.
+    ///
+    /// Note that compression leaks information about the plaintext through the
+    /// ciphertext length; avoid it when an attacker can mix chosen input with
+    /// secrets in the same payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Compression fails
+    /// - Key provider operations fail
+    /// - Encryption fails
+    /// - Header serialization fails
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "encrypt",
+            skip_all,
+            fields(
+                kek_id = tracing::field::Empty,
+                cipher_mode = ?self.cipher_mode,
+                payload_size = plaintext.len()
+            )
+        )
+    )]
+    pub fn encrypt_compressed(
+        &self,
+        plaintext: &[u8],
+        context: &EncryptionContext,
+    ) -> Result<Ciphertext, Error> {
+        let compressed = compress(plaintext)?;
+        check_plaintext_len(compressed.len())?;
+        let envelope = self.new_envelope(context, 1, compressed.len())?;
 
-        // Unwrap the DEK
-        let dek = self.provider.unwrap_dek(header.kek_id(), header.wrapped_dek())?;
+        self.seal(
+            &envelope,
+            &self.next_nonce()?,
+            HeaderFlags::empty().with_compressed(),
+            &compressed,
+            context,
+            &[],
+        )
+    }
 
-        // Decrypt the data
-        let plaintext = match self.cipher_mode {
-            CipherMode::ChaCha20Poly1305 => {
-                let cipher = ChaCha20Poly1305::new_from_slice(dek.expose_secret())
-                    .map_err(|e| Error::DecryptionFailed(format!("Invalid DEK: {e}")))?;
-
-                let nonce_bytes: [u8; NONCE_SIZE] = header
-                    .nonce()
-                    .try_into()
-                    .map_err(|_| Error::DecryptionFailed("Invalid nonce size".to_string()))?;
-                let nonce = Nonce::from(nonce_bytes);
-
-                // Use context as associated data for authentication
-                let aad = context.to_string();
-
-                cipher
-                    .decrypt(
-                        &nonce,
-                        chacha20poly1305::aead::Payload {
-                            msg: encrypted_data,
-                            aad: aad.as_bytes(),
-                        },
-                    )
-                    .map_err(|_| Error::AuthenticationFailed)?
-            }
+    /// Encrypts plaintext so that any of several KEKs can decrypt it.
+    ///
+    /// A single DEK is wrapped under every KEK in `kek_ids`. The first becomes
+    /// the header's primary `kek_id`; the rest are stored as additional
+    /// recipients. [`Vault::decrypt`] tries each recipient in order, skipping
+    /// KEKs the provider doesn't know, which lets data stay readable by both
+    /// the old and new key during a migration between KEKs.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - `kek_ids` is empty
+    /// - Key provider operations fail for any KEK
+    /// - Encryption fails
+    /// - Header serialization fails
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "encrypt",
+            skip_all,
+            fields(
+                kek_id = tracing::field::Empty,
+                cipher_mode = ?self.cipher_mode,
+                payload_size = plaintext.len()
+            )
+        )
+    )]
+    pub fn encrypt_multi(
+        &self,
+        plaintext: &[u8],
+        context: &EncryptionContext,
+        kek_ids: &[&str],
+    ) -> Result<Ciphertext, Error> {
+        let Some((primary, others)) = kek_ids.split_first() else {
+            return Err(Error::EncryptionFailed("At least one KEK is required".to_string()));
         };
+        check_plaintext_len(plaintext.len())?;
 
-        Ok(plaintext)
-    }
-}
-
-impl<P: KeyProvider> Clone for Vault<P> {
-    fn clone(&self) -> Self {
-        Self { provider: Arc::clone(&self.provider), cipher_mode: self.cipher_mode }
-    }
-}
+        let aad = wrap_aad(context);
+        let dek = self.rng.generate_dek(self.cipher_mode.key_len());
+        let wrapped_dek = self.wrap_dek(primary, &dek, aad)?;
+        let additional_recipients = others
+            .iter()
+            .map(|kek_id| {
+                let encrypted_dek = self.wrap_dek(kek_id, &dek, aad)?;
+                Ok(WrappedDek { kek_id: (*kek_id).to_string(), encrypted_dek })
+            })
+            .collect::<Result<Vec<_>, KeyProviderError>>()?;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::error::KeyProviderError;
-    use secrecy::SecretVec;
-    use std::collections::HashMap;
-    use std::sync::Mutex;
+        let envelope = Envelope {
+            dek,
+            kek_id: (*primary).to_string(),
+            wrapped_dek,
+            additional_recipients,
+            derived: false,
+        };
 
-    // Mock key provider for testing
-    struct MockKeyProvider {
-        keks: Mutex<HashMap<String, SecretVec<u8>>>,
-        current_kek_id: String,
+        self.seal(&envelope, &self.next_nonce()?, HeaderFlags::empty(), plaintext, context, &[])
     }
 
-    impl MockKeyProvider {
-        fn new() -> Self {
-            let mut keks = HashMap::new();
-            let kek = SecretVec::new(vec![42u8; 32]);
-            keks.insert("test_kek".to_string(), kek);
-
-            Self { keks: Mutex::new(keks), current_kek_id: "test_kek".to_string() }
+    /// Encrypts plaintext under the current KEK with a recovery copy of the
+    /// DEK wrapped under `escrow_kek_id`.
+    ///
+    /// The escrow KEK is stored as an additional recipient (see
+    /// [`Vault::encrypt_multi`]), so the data stays recoverable by the escrow
+    /// key holder after the primary KEK is destroyed, e.g. for a legal hold
+    /// after tenant offboarding. Recovery is plain [`Vault::decrypt`] with a
+    /// provider that knows the escrow KEK; the unknown primary is skipped.
+    /// This also means destroying the primary KEK no longer crypto-shreds
+    /// the data, only destroying the escrow KEK as well does.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::EncryptionFailed` if `escrow_kek_id` is the current
+    /// KEK, or an error if key provider operations or encryption fail.
+    pub fn encrypt_with_escrow(
+        &self,
+        plaintext: &[u8],
+        context: &EncryptionContext,
+        escrow_kek_id: &str,
+    ) -> Result<Ciphertext, Error> {
+        let kek_id = self.current_kek_id(context)?;
+        if kek_id == escrow_kek_id {
+            return Err(Error::EncryptionFailed(
+                "Escrow KEK must differ from the current KEK".to_string(),
+            ));
         }
+
+        self.encrypt_multi(plaintext, context, &[&kek_id, escrow_kek_id])
     }
 
-    // WARNING: This KeyProvider implementation uses simple XOR for DEK wrapping
-    // and is intended ONLY for testing purposes. DO NOT use in production.
-    // In production, use a secure key provider like FileKeyProvider or AWS KMS.
-    impl KeyProvider for MockKeyProvider {
-        fn create_kek(&self) -> Result<String, KeyProviderError> {
-            let kek_id = format!("kek_{}", self.keks.lock().unwrap().len());
-            let kek = SecretVec::new(vec![1u8; 32]);
-            self.keks.lock().unwrap().insert(kek_id.clone(), kek);
-            Ok(kek_id)
+    /// Encrypts many values under a single DEK.
+    ///
+    /// One DEK is generated and wrapped once, then reused for every item with a
+    /// fresh nonce per item (random, or counter-based with
+    /// [`Vault::with_counter_nonces`]). Each output still carries the full header,
+    /// so every blob can be decrypted independently with [`Vault::decrypt`].
+    ///
+    /// Items for different tenants never share a DEK: each tenant in the batch
+    /// gets its own, wrapped under that tenant's KEK (see
+    /// [`KeyProvider::current_kek_id_for_tenant`]).
+    ///
+    /// This amortizes the key provider round-trip, which dominates the cost of
+    /// encrypting many small values when the provider is a remote KMS.
+    ///
+    /// # Arguments
+    ///
+    /// * `items` - Pairs of plaintext and the encryption context to bind it to
+    ///
+    /// # Returns
+    ///
+    /// Ciphertexts in the same order as `items`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Key provider operations fail
+    /// - Encryption fails
+    /// - A nonce would be reused under the shared DEK
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "encrypt_batch",
+            skip_all,
+            fields(
+                kek_id = tracing::field::Empty,
+                cipher_mode = ?self.cipher_mode,
+                items = items.len()
+            )
+        )
+    )]
+    pub fn encrypt_batch(
+        &self,
+        items: &[(&[u8], &EncryptionContext)],
+    ) -> Result<Vec<Ciphertext>, Error> {
+        if items.is_empty() {
+            return Ok(Vec::new());
         }
 
-        fn current_kek_id(&self) -> Result<String, KeyProviderError> {
-            Ok(self.current_kek_id.clone())
+        // Messages and bytes each tenant's DEK will seal
+        let mut usage: HashMap<Option<&str>, (usize, usize)> = HashMap::new();
+        for (plaintext, context) in items {
+            check_plaintext_len(plaintext.len())?;
+            let (messages, bytes) = usage.entry(context.tenant_id()).or_default();
+            *messages += 1;
+            *bytes += plaintext.len();
         }
 
-        fn wrap_dek(&self, kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
-            let keks = self.keks.lock().unwrap();
-            let kek = keks
-                .get(kek_id)
-                .ok_or_else(|| KeyProviderError::KekNotFound(kek_id.to_string()))?;
+        // One DEK and one wrap per tenant in the batch
+        let mut envelopes = HashMap::new();
 
-            // Simple XOR "encryption" for testing
-            let wrapped: Vec<u8> =
-                dek.iter().zip(kek.expose_secret().iter().cycle()).map(|(d, k)| d ^ k).collect();
+        // DEKs are shared, so every nonce in the batch must be distinct
+        let mut seen_nonces = HashSet::with_capacity(items.len());
+        let mut results = Vec::with_capacity(items.len());
 
-            drop(keks);
-            Ok(wrapped)
+        for (plaintext, context) in items {
+            let envelope = match envelopes.entry(context.tenant_id()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let (messages, bytes) = usage[entry.key()];
+                    entry.insert(self.new_envelope(context, messages, bytes)?)
+                }
+            };
+
+            let nonce_bytes = self.next_nonce()?;
+
+            if !seen_nonces.insert(nonce_bytes.clone()) {
+                return Err(Error::EncryptionFailed(
+                    "Nonce collision detected within batch".to_string(),
+                ));
+            }
+
+            results.push(self.seal(
+                envelope,
+                &nonce_bytes,
+                HeaderFlags::empty(),
+                plaintext,
+                context,
+                &[],
+            )?);
+        }
+
+        Ok(results)
+    }
+
+    /// Computes a keyed fingerprint of `plaintext` under `context`, for
+    /// deduplicating stored ciphertext without decrypting it.
+    ///
+    /// Ciphertext is randomized, so two encryptions of one value never match;
+    /// fingerprints do. The fingerprint is HMAC-SHA256 over the context's
+    /// canonical bytes and the plaintext, keyed by a key derived from the
+    /// provider's pepper with [`derive_fingerprint_key`], so it can't be
+    /// correlated with the column's blind indexes. Store it next to the
+    /// ciphertext and deduplicate on it.
+    ///
+    /// Like deterministic encryption, this leaks which rows hold equal values
+    /// in the same context, to anyone who can read the fingerprints. Only
+    /// fingerprint data where that is acceptable. Rotating the pepper changes
+    /// every fingerprint.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the provider has no pepper or key derivation fails.
+    pub fn dedup_fingerprint(
+        &self,
+        plaintext: &[u8],
+        context: &EncryptionContext,
+    ) -> Result<[u8; 32], Error> {
+        let pepper = self.provider.get_pepper()?.ok_or_else(|| {
+            KeyProviderError::PepperUnavailable("Provider has no pepper".to_string())
+        })?;
+        let key = derive_fingerprint_key(&pepper)?;
+
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.expose_secret())
+            .map_err(|_| Error::KeyDerivation)?;
+
+        // Length-prefix the context so it can't run into the plaintext
+        let context_bytes = context.canonical_bytes();
+        mac.update(&(context_bytes.len() as u64).to_be_bytes());
+        mac.update(&context_bytes);
+        mac.update(plaintext);
+
+        Ok(mac.finalize().into_bytes().into())
+    }
+
+    /// Returns the exact length of [`Vault::encrypt`] output for a plaintext of
+    /// `plaintext_len` bytes: header, then plaintext, then the [`TAG_SIZE`] tag.
+    ///
+    /// The header size depends on the KEK id and on how large the provider's
+    /// wrapped DEKs are, so this wraps a throwaway all-zero DEK under the KEK
+    /// `encrypt` would pick for `context` (the tenant's KEK when it has one) to
+    /// measure it. That costs one provider round-trip; cache the result per
+    /// tenant rather than calling this per row.
+    ///
+    /// [`Vault::encrypt_with_aad`] produces the same length. Compressed and
+    /// multi-recipient output is not covered.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::PayloadTooLarge` if `plaintext_len` exceeds
+    /// [`MAX_PLAINTEXT_LEN`], or an error if the key provider has no current
+    /// KEK or wrapping fails.
+    pub fn ciphertext_len(
+        &self,
+        plaintext_len: usize,
+        context: &EncryptionContext,
+    ) -> Result<usize, Error> {
+        check_plaintext_len(plaintext_len)?;
+        let kek_id = self.current_kek_id(context)?;
+        let probe = SecretVec::new(vec![0u8; self.cipher_mode.key_len()]);
+        let wrapped_dek = self.wrap_dek(&kek_id, &probe, wrap_aad(context))?;
+
+        // Field values don't affect the encoded size, only which fields are present
+        let header = EncryptionHeader::new(
+            kek_id,
+            wrapped_dek,
+            HeaderFlags::empty(),
+            vec![0; self.cipher_mode.nonce_len()],
+        )
+        .with_cipher_id(self.cipher_mode.to_id())
+        .with_created_at(0)
+        .with_context_version(0)
+        .with_payload_len(0);
+        let header =
+            if self.key_commitment { header.with_commitment([0; COMMITMENT_SIZE]) } else { header };
+        let header = if self.root_keys.is_some() { header.with_derived_dek() } else { header };
+        let header =
+            if self.header_mac { header.with_header_mac([0; HEADER_MAC_SIZE]) } else { header };
+
+        Ok(header.to_bytes()?.len() + plaintext_len + TAG_SIZE)
+    }
+
+    /// Checks that the key provider is ready, for use in readiness probes.
+    ///
+    /// Delegates to [`KeyProvider::health_check`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the key provider reports it can't serve requests.
+    pub fn health_check(&self) -> Result<(), Error> {
+        Ok(self.provider.health_check()?)
+    }
+
+    /// Returns the key provider, e.g. to generate blind indexes alongside encryption.
+    pub(crate) fn provider(&self) -> &P {
+        &self.provider
+    }
+
+    /// Re-wraps a ciphertext's DEK under a different KEK.
+    ///
+    /// Only the header changes: the DEK is re-wrapped via
+    /// [`KeyProvider::rewrap_dek`] and the header is rebuilt with the new KEK
+    /// identifier and wrapped DEK. The nonce, flags, cipher id, creation
+    /// timestamp, any additional recipients, and encrypted payload are copied
+    /// unchanged, so the data itself is never re-encrypted.
+    ///
+    /// DEKs of tenant contexts may be bound to the tenant; rewrap those with
+    /// [`Vault::rewrap_with_context`].
+    ///
+    /// # Arguments
+    ///
+    /// * `ciphertext` - Encrypted data with header
+    /// * `new_kek_id` - Identifier of the KEK to re-wrap the DEK under
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Key provider operations fail
+    /// - Header serialization fails
+    pub fn rewrap(&self, ciphertext: &Ciphertext, new_kek_id: &str) -> Result<Ciphertext, Error> {
+        let header = ciphertext.header();
+
+        let wrapped_dek =
+            self.provider.rewrap_dek(header.kek_id(), new_kek_id, header.wrapped_dek())?;
+
+        let new_header = header.rewrapped(new_kek_id, wrapped_dek);
+
+        Ciphertext::from_parts(new_header, ciphertext.payload())
+    }
+
+    /// Re-wraps the DEK of a ciphertext encrypted for a tenant.
+    ///
+    /// Like [`Vault::rewrap`], but for providers that bind the tenant to the
+    /// wrapped DEK (see [`KeyProvider::wrap_dek_aad`]): the header doesn't
+    /// record the tenant, so `context` supplies it, and the new wrapped DEK
+    /// stays bound to it. For a context without a tenant this is the same as
+    /// [`Vault::rewrap`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Key provider operations fail, e.g. `context` names another tenant
+    /// - Header serialization fails
+    pub fn rewrap_with_context(
+        &self,
+        ciphertext: &Ciphertext,
+        new_kek_id: &str,
+        context: &EncryptionContext,
+    ) -> Result<Ciphertext, Error> {
+        let header = ciphertext.header();
+
+        let wrapped_dek = self.provider.rewrap_dek_aad(
+            header.kek_id(),
+            new_kek_id,
+            header.wrapped_dek(),
+            wrap_aad(context),
+        )?;
+
+        let new_header = header.rewrapped(new_kek_id, wrapped_dek);
+
+        Ciphertext::from_parts(new_header, ciphertext.payload())
+    }
+
+    /// Wraps a ciphertext's DEK for another KEK, to grant a recipient access
+    /// to this one ciphertext without sharing plaintext or the original KEK.
+    ///
+    /// The DEK is re-wrapped via [`KeyProvider::rewrap_dek`] and returned;
+    /// `ciphertext` itself is not changed. The recipient decrypts by swapping
+    /// the returned [`WrappedDek`] into the header (see
+    /// [`EncryptionHeader::rewrapped`]) and keeping the nonce and payload.
+    /// Unlike [`Vault::rewrap`], the original KEK keeps working.
+    ///
+    /// DEKs of tenant contexts may be bound to the tenant; share those with
+    /// [`Vault::share_with_context`].
+    ///
+    /// # Arguments
+    ///
+    /// * `ciphertext` - Serialized ciphertext (or just its header)
+    /// * `recipient_kek_id` - Identifier of the recipient's KEK, which this
+    ///   vault's provider must be able to wrap under
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Header parsing fails
+    /// - Key provider operations fail
+    pub fn share(&self, ciphertext: &[u8], recipient_kek_id: &str) -> Result<WrappedDek, Error> {
+        let (header, _) = EncryptionHeader::from_bytes(ciphertext)?;
+
+        let encrypted_dek =
+            self.provider.rewrap_dek(header.kek_id(), recipient_kek_id, header.wrapped_dek())?;
+
+        Ok(WrappedDek { kek_id: recipient_kek_id.to_string(), encrypted_dek })
+    }
+
+    /// Wraps the DEK of a ciphertext encrypted for a tenant for another KEK.
+    ///
+    /// Like [`Vault::share`], but `context` supplies the tenant the DEK is
+    /// bound to (see [`Vault::rewrap_with_context`]), and the shared DEK stays
+    /// bound to it, so the recipient decrypts with the same context.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Header parsing fails
+    /// - Key provider operations fail, e.g. `context` names another tenant
+    pub fn share_with_context(
+        &self,
+        ciphertext: &[u8],
+        recipient_kek_id: &str,
+        context: &EncryptionContext,
+    ) -> Result<WrappedDek, Error> {
+        let (header, _) = EncryptionHeader::from_bytes(ciphertext)?;
+
+        let encrypted_dek = self.provider.rewrap_dek_aad(
+            header.kek_id(),
+            recipient_kek_id,
+            header.wrapped_dek(),
+            wrap_aad(context),
+        )?;
+
+        Ok(WrappedDek { kek_id: recipient_kek_id.to_string(), encrypted_dek })
+    }
+
+    /// Re-wraps a detached header under a different KEK.
+    ///
+    /// The detached counterpart of [`Vault::rewrap`]: takes the header half
+    /// of [`Vault::encrypt_detached`] output and returns the rebuilt header.
+    /// The payload is not needed and stays valid as-is, so only the small
+    /// metadata column has to be written back.
+    ///
+    /// DEKs of tenant contexts may be bound to the tenant; rewrap those with
+    /// [`Vault::rewrap_detached_with_context`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Header parsing fails
+    /// - Key provider operations fail
+    /// - Header serialization fails
+    pub fn rewrap_detached(&self, header_bytes: &[u8], new_kek_id: &str) -> Result<Vec<u8>, Error> {
+        let header = parse_detached_header(header_bytes)?;
+
+        let wrapped_dek =
+            self.provider.rewrap_dek(header.kek_id(), new_kek_id, header.wrapped_dek())?;
+
+        header.rewrapped(new_kek_id, wrapped_dek).to_bytes()
+    }
+
+    /// Re-wraps a detached header encrypted for a tenant.
+    ///
+    /// The detached counterpart of [`Vault::rewrap_with_context`]; `context`
+    /// supplies the tenant the DEK is bound to.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Header parsing fails
+    /// - Key provider operations fail, e.g. `context` names another tenant
+    /// - Header serialization fails
+    pub fn rewrap_detached_with_context(
+        &self,
+        header_bytes: &[u8],
+        new_kek_id: &str,
+        context: &EncryptionContext,
+    ) -> Result<Vec<u8>, Error> {
+        let header = parse_detached_header(header_bytes)?;
+
+        let wrapped_dek = self.provider.rewrap_dek_aad(
+            header.kek_id(),
+            new_kek_id,
+            header.wrapped_dek(),
+            wrap_aad(context),
+        )?;
+
+        header.rewrapped(new_kek_id, wrapped_dek).to_bytes()
+    }
+
+    /// Lazily re-wraps a stream of serialized ciphertexts under a different KEK.
+    ///
+    /// Each blob is parsed and passed through [`Vault::rewrap`] only when the
+    /// returned iterator is advanced, so a whole table can be migrated in
+    /// chunks without loading it into memory. A blob that fails (malformed
+    /// header, unknown KEK, ...) yields an `Err` in its position and the
+    /// stream carries on with the next one, so callers can record failures
+    /// and checkpoint progress.
+    ///
+    /// DEKs of tenant contexts may be bound to the tenant; rewrap those with
+    /// [`Vault::rewrap_iter_with_context`].
+    ///
+    /// # Arguments
+    ///
+    /// * `blobs` - Serialized ciphertexts, e.g. rows read from a database
+    /// * `new_kek_id` - Identifier of the KEK to re-wrap the DEKs under
+    pub fn rewrap_iter<'a, I>(
+        &'a self,
+        blobs: I,
+        new_kek_id: &'a str,
+    ) -> impl Iterator<Item = Result<Vec<u8>, Error>> + 'a
+    where
+        I: Iterator<Item = Vec<u8>> + 'a,
+    {
+        blobs.map(move |blob| {
+            let ciphertext = Ciphertext::from_bytes(blob)?;
+            Ok(self.rewrap(&ciphertext, new_kek_id)?.into_bytes())
+        })
+    }
+
+    /// Lazily re-wraps a stream of one tenant's ciphertexts, like
+    /// [`Vault::rewrap_iter`] but through [`Vault::rewrap_with_context`] with
+    /// `context` for every blob.
+    pub fn rewrap_iter_with_context<'a, I>(
+        &'a self,
+        blobs: I,
+        new_kek_id: &'a str,
+        context: &'a EncryptionContext,
+    ) -> impl Iterator<Item = Result<Vec<u8>, Error>> + 'a
+    where
+        I: Iterator<Item = Vec<u8>> + 'a,
+    {
+        blobs.map(move |blob| {
+            let ciphertext = Ciphertext::from_bytes(blob)?;
+            Ok(self.rewrap_with_context(&ciphertext, new_kek_id, context)?.into_bytes())
+        })
+    }
+
+    /// Upgrades a stored ciphertext to the current format and current KEK.
+    ///
+    /// Unlike [`Vault::rewrap`], which only touches the wrapped DEK, this
+    /// decrypts the blob (accepting any supported protocol version) and
+    /// re-encrypts the plaintext under a fresh DEK, the current KEK, and
+    /// [`PROTOCOL_VERSION`], bound to the same `context`. Compressed payloads
+    /// stay compressed.
+    ///
+    /// A blob that is already on the current version and KEK is returned
+    /// unchanged, so migration jobs can safely run over a whole table.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Header parsing fails
+    /// - Key provider operations fail
+    /// - Decryption or authentication fails (e.g. the wrong `context`)
+    /// - Re-encryption fails
+    pub fn migrate(&self, old: &[u8], context: &EncryptionContext) -> Result<Ciphertext, Error> {
+        let ciphertext = Ciphertext::from_bytes(old.to_vec())?;
+
+        if ciphertext.version() >= PROTOCOL_VERSION
+            && ciphertext.kek_id() == self.current_kek_id(context)?
+        {
+            return Ok(ciphertext);
+        }
+
+        let plaintext = Zeroizing::new(self.decrypt(&ciphertext, context)?);
+
+        if ciphertext.header().flags().is_compressed() {
+            self.encrypt_compressed(&plaintext, context)
+        } else {
+            self.encrypt(&plaintext, context)
+        }
+    }
+
+    /// Decrypts ciphertext using envelope encryption.
+    ///
+    /// Every cryptographic failure ends the same way: a DEK the provider
+    /// can't unwrap (e.g. the wrong KEK), a key commitment mismatch, a DEK of
+    /// the wrong size for the cipher and a tampered payload all run the AEAD
+    /// and return `Error::AuthenticationFailed`, so the error can't tell an
+    /// attacker which check failed. Malformed headers, unknown KEK ids and
+    /// unavailable providers are still reported as such.
+    ///
+    /// # Arguments
+    ///
+    /// * `ciphertext` - Encrypted data with header
+    /// * `context` - Encryption context (must match the one used for encryption)
+    ///
+    /// # Returns
+    ///
+    /// The original plaintext.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Key provider operations fail
+    /// - Decryption fails
+    /// - Authentication fails, including a DEK that doesn't unwrap
+    pub fn decrypt(
+        &self,
+        ciphertext: &Ciphertext,
+        context: &EncryptionContext,
+    ) -> Result<Vec<u8>, Error> {
+        self.decrypt_with_aad(ciphertext, context, &[])
+    }
+
+    /// Decrypts raw ciphertext bytes, e.g. a column value read from a database.
+    ///
+    /// Equivalent to [`Ciphertext::from_bytes`] followed by [`Vault::decrypt`],
+    /// except that when the header records a payload length only that many
+    /// bytes are decrypted and anything after them is ignored. Older blobs
+    /// without a recorded length are decrypted to the end of the buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Header parsing fails
+    /// - Key provider operations fail
+    /// - Decryption fails
+    /// - Authentication fails
+    pub fn decrypt_bytes(
+        &self,
+        ciphertext: &[u8],
+        context: &EncryptionContext,
+    ) -> Result<Vec<u8>, Error> {
+        let (header, header_len) = EncryptionHeader::from_bytes(ciphertext)?;
+        let end = payload_end(&header, header_len, ciphertext.len())?;
+        self.open(&header, &ciphertext[header_len..end], context, &[])
+    }
+
+    /// Decrypts output of [`Vault::encrypt_detached`].
+    ///
+    /// # Arguments
+    ///
+    /// * `header_bytes` - The serialized header, exactly as returned
+    /// * `ciphertext` - The encrypted payload
+    /// * `context` - Encryption context (must match the one used for encryption)
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Header parsing fails, including trailing bytes after the header
+    /// - Key provider operations fail
+    /// - Decryption fails
+    /// - Authentication fails (including a header paired with the wrong payload)
+    pub fn decrypt_detached(
+        &self,
+        header_bytes: &[u8],
+        ciphertext: &[u8],
+        context: &EncryptionContext,
+    ) -> Result<Vec<u8>, Error> {
+        let header = parse_detached_header(header_bytes)?;
+        self.open(&header, ciphertext, context, &[])
+    }
+
+    /// Encrypts a large plaintext as independently decryptable chunks.
+    ///
+    /// The plaintext is split into `chunk_size`-byte chunks (the last may be
+    /// shorter), all sealed under one DEK following the STREAM construction:
+    /// chunk `i` uses the header nonce's first 8 bytes followed by `i` as a
+    /// big-endian `u32`, and its associated data binds the context, `i`, and
+    /// whether it is the final chunk. Any one chunk can then be decrypted on
+    /// its own with [`Vault::decrypt_chunk`], while a chunk moved to another
+    /// index fails authentication. Each chunk is [`TAG_SIZE`] bytes longer
+    /// than its plaintext.
+    ///
+    /// An empty plaintext yields a single empty chunk.
+    ///
+    /// # Returns
+    ///
+    /// The serialized header and the encrypted chunks, in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - `chunk_size` is zero or a chunk exceeds [`MAX_PLAINTEXT_LEN`]
+    /// - The plaintext exceeds [`CipherMode::max_message_bytes`]
+    ///   (`Error::PayloadTooLarge`); split it and encrypt each part
+    ///   separately, so each gets a fresh DEK and nonce
+    /// - The plaintext needs more than `u32::MAX` chunks
+    /// - Key provider operations fail
+    /// - Encryption fails
+    pub fn encrypt_chunked(
+        &self,
+        plaintext: &[u8],
+        chunk_size: usize,
+        context: &EncryptionContext,
+    ) -> Result<(Vec<u8>, Vec<Vec<u8>>), Error> {
+        let max_dek_bytes = self.cipher_mode.max_message_bytes();
+        self.seal_chunks(plaintext, chunk_size, context, None, max_dek_bytes)
+    }
+
+    /// Encrypts a large plaintext as chunks like [`Vault::encrypt_chunked`],
+    /// additionally binding `aad` to the first chunk.
+    ///
+    /// Decrypting chunk 0 then requires the same `aad` (see
+    /// [`Vault::decrypt_chunked_with_aad`]), which ties the encrypted data to
+    /// e.g. its metadata record without storing the record in the header.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Vault::encrypt_chunked`].
+    pub fn encrypt_chunked_with_aad(
+        &self,
+        plaintext: &[u8],
+        chunk_size: usize,
+        context: &EncryptionContext,
+        aad: StreamAad<'_>,
+    ) -> Result<(Vec<u8>, Vec<Vec<u8>>), Error> {
+        let max_dek_bytes = self.cipher_mode.max_message_bytes();
+        self.seal_chunks(plaintext, chunk_size, context, Some(aad.digest()), max_dek_bytes)
+    }
+
+    /// Splits and encrypts `plaintext`, binding `aad_digest` to chunk 0.
+    ///
+    /// Every chunk is sealed under the same DEK, so the plaintext as a whole
+    /// must not exceed `max_dek_bytes`, normally the cipher's
+    /// [`CipherMode::max_message_bytes`].
+    fn seal_chunks(
+        &self,
+        plaintext: &[u8],
+        chunk_size: usize,
+        context: &EncryptionContext,
+        aad_digest: Option<[u8; 32]>,
+        max_dek_bytes: u64,
+    ) -> Result<(Vec<u8>, Vec<Vec<u8>>), Error> {
+        if chunk_size == 0 {
+            return Err(Error::EncryptionFailed("Chunk size must be non-zero".to_string()));
+        }
+        check_plaintext_len(chunk_size.min(plaintext.len()))?;
+        if u64::try_from(plaintext.len()).map_or(true, |len| len > max_dek_bytes) {
+            return Err(Error::PayloadTooLarge {
+                len: plaintext.len(),
+                max: usize::try_from(max_dek_bytes).unwrap_or(usize::MAX),
+            });
+        }
+
+        let chunks: Vec<&[u8]> =
+            if plaintext.is_empty() { vec![&[]] } else { plaintext.chunks(chunk_size).collect() };
+        let last_index = u32::try_from(chunks.len() - 1)
+            .map_err(|_| Error::EncryptionFailed("Too many chunks".to_string()))?;
+
+        // Chunk nonces share the header nonce's prefix and end in the chunk
+        // index, so a DEK reused across streams (as a deriving vault would)
+        // repeats them. Each stream gets a fresh DEK instead.
+        let envelope = self.envelope_under(self.current_kek_id(context)?, wrap_aad(context))?;
+        let dek = &envelope.dek;
+        let nonce_bytes = self.next_nonce()?;
+        let header =
+            self.envelope_header(&envelope, dek, HeaderFlags::empty(), &nonce_bytes, context)?;
+        let header = self.authenticate_header(header, dek)?;
+
+        let encrypted = (0..=last_index)
+            .zip(chunks)
+            .map(|(index, chunk)| {
+                self.encrypt_payload(
+                    dek,
+                    &chunk_nonce(&nonce_bytes, index),
+                    chunk,
+                    context,
+                    &chunk_aad(index, index == last_index, aad_digest.as_ref()),
+                )
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok((header.to_bytes()?, encrypted))
+    }
+
+    /// Decrypts one chunk of [`Vault::encrypt_chunked`] output by its index,
+    /// without touching the other chunks.
+    ///
+    /// # Arguments
+    ///
+    /// * `header_bytes` - The serialized header, exactly as returned
+    /// * `chunk_index` - Position of the chunk, starting at 0
+    /// * `chunk_ciphertext` - The encrypted chunk
+    /// * `context` - Encryption context (must match the one used for encryption)
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Header parsing fails, including trailing bytes after the header
+    /// - Key provider operations fail
+    /// - Authentication fails, including for a chunk from another index
+    pub fn decrypt_chunk(
+        &self,
+        header_bytes: &[u8],
+        chunk_index: u32,
+        chunk_ciphertext: &[u8],
+        context: &EncryptionContext,
+    ) -> Result<Vec<u8>, Error> {
+        self.open_one_chunk(header_bytes, chunk_index, chunk_ciphertext, context, None)
+    }
+
+    /// Decrypts one chunk of [`Vault::encrypt_chunked_with_aad`] output by
+    /// its index.
+    ///
+    /// `aad` only matters for chunk 0, where it must match the data bound at
+    /// encryption.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Vault::decrypt_chunk`], including authentication failure of
+    /// chunk 0 under a mismatched `aad`.
+    pub fn decrypt_chunk_with_aad(
+        &self,
+        header_bytes: &[u8],
+        chunk_index: u32,
+        chunk_ciphertext: &[u8],
+        context: &EncryptionContext,
+        aad: StreamAad<'_>,
+    ) -> Result<Vec<u8>, Error> {
+        self.open_one_chunk(
+            header_bytes,
+            chunk_index,
+            chunk_ciphertext,
+            context,
+            Some(aad.digest()),
+        )
+    }
+
+    /// Decrypts the chunk at `index`, whether or not it is the final one.
+    fn open_one_chunk(
+        &self,
+        header_bytes: &[u8],
+        index: u32,
+        chunk_ciphertext: &[u8],
+        context: &EncryptionContext,
+        aad_digest: Option<[u8; 32]>,
+    ) -> Result<Vec<u8>, Error> {
+        let header = parse_detached_header(header_bytes)?;
+        let dek = self.unwrap_chunk_dek(&header, context)?;
+        let aad_digest = aad_digest.as_ref();
+
+        // Random access doesn't know the chunk count, so accept either position
+        self.open_chunk(&header, &dek, index, false, chunk_ciphertext, context, aad_digest).or_else(
+            |_| self.open_chunk(&header, &dek, index, true, chunk_ciphertext, context, aad_digest),
+        )
+    }
+
+    /// Decrypts every chunk of [`Vault::encrypt_chunked`] output and joins
+    /// the plaintext.
+    ///
+    /// Unlike decrypting chunk by chunk, this also detects truncation: only
+    /// the final chunk may carry the final-chunk marker.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Header parsing fails, including trailing bytes after the header
+    /// - Key provider operations fail
+    /// - Any chunk fails authentication, or chunks are missing or reordered
+    pub fn decrypt_chunked(
+        &self,
+        header_bytes: &[u8],
+        chunks: &[Vec<u8>],
+        context: &EncryptionContext,
+    ) -> Result<Vec<u8>, Error> {
+        self.open_chunks(header_bytes, chunks, context, None)
+    }
+
+    /// Decrypts every chunk of [`Vault::encrypt_chunked_with_aad`] output and
+    /// joins the plaintext.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Vault::decrypt_chunked`]; a mismatched `aad` fails
+    /// authentication at the first chunk.
+    pub fn decrypt_chunked_with_aad(
+        &self,
+        header_bytes: &[u8],
+        chunks: &[Vec<u8>],
+        context: &EncryptionContext,
+        aad: StreamAad<'_>,
+    ) -> Result<Vec<u8>, Error> {
+        self.open_chunks(header_bytes, chunks, context, Some(aad.digest()))
+    }
+
+    /// Decrypts chunks of [`Vault::encrypt_chunked`] output as they arrive,
+    /// writing plaintext to `out` only after every chunk has authenticated.
+    ///
+    /// Decrypting chunk by chunk with [`Vault::decrypt_chunk`] hands out each
+    /// chunk's plaintext before later chunks are checked, so a tampered or
+    /// truncated stream is only noticed after some of it was acted on. Here
+    /// the plaintext is held in memory until the final chunk authenticates
+    /// and then written in one go; on any failure nothing reaches `out` and
+    /// the buffer is zeroized.
+    ///
+    /// The whole plaintext is buffered, so memory use grows to the stream's
+    /// plaintext size; `max_buffered` caps it and fails the stream once
+    /// exceeded. For streams too large to hold, authenticate every chunk in
+    /// a first pass with [`Vault::decrypt_chunked`] discarding the output,
+    /// then decrypt them again, which needs the chunks to be read twice.
+    ///
+    /// # Returns
+    ///
+    /// The number of plaintext bytes written to `out`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Header parsing fails, including trailing bytes after the header
+    /// - Key provider operations fail
+    /// - Any chunk fails authentication, or chunks are missing or reordered
+    /// - The plaintext exceeds `max_buffered` bytes
+    /// - Writing to `out` fails
+    pub fn decrypt_stream_verified<W: Write>(
+        &self,
+        header_bytes: &[u8],
+        chunks: impl IntoIterator<Item = Vec<u8>>,
+        context: &EncryptionContext,
+        max_buffered: usize,
+        out: &mut W,
+    ) -> Result<usize, Error> {
+        let header = parse_detached_header(header_bytes)?;
+        let mut chunks = chunks.into_iter().enumerate().peekable();
+        if chunks.peek().is_none() {
+            return Err(Error::DecryptionFailed("No chunks".to_string()));
+        }
+        let dek = self.unwrap_chunk_dek(&header, context)?;
+
+        let mut plaintext = Zeroizing::new(Vec::new());
+        while let Some((index, chunk)) = chunks.next() {
+            let chunk_index = u32::try_from(index)
+                .map_err(|_| Error::DecryptionFailed("Too many chunks".to_string()))?;
+            let is_last = chunks.peek().is_none();
+            let decrypted = Zeroizing::new(self.open_chunk(
+                &header,
+                &dek,
+                chunk_index,
+                is_last,
+                &chunk,
+                context,
+                None,
+            )?);
+
+            if plaintext.len() + decrypted.len() > max_buffered {
+                return Err(Error::DecryptionFailed(format!(
+                    "Stream plaintext exceeds buffer limit of {max_buffered} bytes"
+                )));
+            }
+            // Grow by hand so no unzeroized copy is left behind by a reallocation
+            if plaintext.capacity() < plaintext.len() + decrypted.len() {
+                let mut grown = Zeroizing::new(Vec::with_capacity(
+                    (plaintext.len() + decrypted.len()).max(plaintext.capacity() * 2),
+                ));
+                grown.extend_from_slice(&plaintext);
+                plaintext = grown;
+            }
+            plaintext.extend_from_slice(&decrypted);
+        }
+
+        // Every chunk, including the final marker, has authenticated
+        out.write_all(&plaintext)?;
+        Ok(plaintext.len())
+    }
+
+    /// Decrypts all chunks in order, checking only the last is marked final.
+    fn open_chunks(
+        &self,
+        header_bytes: &[u8],
+        chunks: &[Vec<u8>],
+        context: &EncryptionContext,
+        aad_digest: Option<[u8; 32]>,
+    ) -> Result<Vec<u8>, Error> {
+        let header = parse_detached_header(header_bytes)?;
+        let Some(last_index) = chunks.len().checked_sub(1) else {
+            return Err(Error::DecryptionFailed("No chunks".to_string()));
+        };
+        let dek = self.unwrap_chunk_dek(&header, context)?;
+
+        let mut plaintext = Vec::new();
+        for (index, chunk) in chunks.iter().enumerate() {
+            let chunk_index = u32::try_from(index)
+                .map_err(|_| Error::DecryptionFailed("Too many chunks".to_string()))?;
+            let decrypted = self.open_chunk(
+                &header,
+                &dek,
+                chunk_index,
+                index == last_index,
+                chunk,
+                context,
+                aad_digest.as_ref(),
+            )?;
+            plaintext.extend_from_slice(&decrypted);
+        }
+        Ok(plaintext)
+    }
+
+    /// Checks the context version and unwraps the DEK of a chunked header.
+    fn unwrap_chunk_dek(
+        &self,
+        header: &EncryptionHeader,
+        context: &EncryptionContext,
+    ) -> Result<SecretVec<u8>, Error> {
+        if let Some(expected) = header.context_version() {
+            if expected != context.version() {
+                return Err(Error::ContextVersionMismatch { expected, actual: context.version() });
+            }
+        }
+        self.unwrap_for_open(header, context)
+    }
+
+    /// Authenticates and decrypts one chunk at `index`.
+    #[allow(clippy::too_many_arguments)]
+    fn open_chunk(
+        &self,
+        header: &EncryptionHeader,
+        dek: &SecretVec<u8>,
+        index: u32,
+        is_last: bool,
+        chunk_ciphertext: &[u8],
+        context: &EncryptionContext,
+        aad_digest: Option<&[u8; 32]>,
+    ) -> Result<Vec<u8>, Error> {
+        // A nonce of the wrong size is passed through untouched, for
+        // open_with_dek to reject like any other tampered header
+        let chunk_header = if header.nonce().len() == header_cipher_mode(header)?.nonce_len() {
+            header.clone().with_nonce(chunk_nonce(header.nonce(), index))
+        } else {
+            header.clone()
+        };
+
+        self.open_with_dek(
+            &chunk_header,
+            dek,
+            chunk_ciphertext,
+            context,
+            &chunk_aad(index, is_last, aad_digest),
+        )
+    }
+
+    /// Decrypts ciphertext produced by [`Vault::encrypt_with_aad`].
+    ///
+    /// # Arguments
+    ///
+    /// * `ciphertext` - Encrypted data with header
+    /// * `context` - Encryption context (must match the one used for encryption)
+    /// * `extra_aad` - Additional data (must match the bytes used for encryption)
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - Key provider operations fail
+    /// - Decryption fails
+    /// - Authentication fails (including a mismatched `extra_aad`)
+    pub fn decrypt_with_aad(
+        &self,
+        ciphertext: &Ciphertext,
+        context: &EncryptionContext,
+        extra_aad: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        self.open(ciphertext.header(), ciphertext.payload(), context, extra_aad)
+    }
+
+    /// Decrypts ciphertext whose context version is one of `versions`, returning
+    /// the version that authenticated alongside the plaintext.
+    ///
+    /// Useful while a column is being rotated and holds ciphertext from several
+    /// context versions. `base_context` supplies everything but the version.
+    ///
+    /// When the header records the context version, that version is used
+    /// directly, provided it is one of the candidates. Headers written before
+    /// the version was recorded are tried against each candidate in order
+    /// until one authenticates, with a single DEK unwrap.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::ContextVersionMismatch` if the recorded version is not a
+    /// candidate, `Error::AuthenticationFailed` if no candidate authenticates,
+    /// or an error if key provider operations or decryption fail.
+    pub fn decrypt_any_version(
+        &self,
+        ciphertext: &Ciphertext,
+        base_context: &EncryptionContext,
+        versions: &[u32],
+    ) -> Result<(u32, Vec<u8>), Error> {
+        let header = ciphertext.header();
+
+        if let Some(recorded) = header.context_version() {
+            if !versions.contains(&recorded) {
+                return Err(Error::ContextVersionMismatch {
+                    expected: recorded,
+                    actual: base_context.version(),
+                });
+            }
+
+            let context = base_context.clone().with_version(recorded);
+            let plaintext = self.open(header, ciphertext.payload(), &context, &[])?;
+            return Ok((recorded, plaintext));
+        }
+
+        let dek = self.unwrap_for_open(header, base_context)?;
+        for &version in versions {
+            let context = base_context.clone().with_version(version);
+            match self.open_with_dek(header, &dek, ciphertext.payload(), &context, &[]) {
+                Err(Error::AuthenticationFailed) => {}
+                result => return result.map(|plaintext| (version, plaintext)),
+            }
+        }
+
+        Err(Error::AuthenticationFailed)
+    }
+
+    /// Unwraps the DEK and authenticates and decrypts `encrypted_data`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "decrypt",
+            skip_all,
+            fields(
+                kek_id = header.kek_id(),
+                cipher_mode = tracing::field::Empty,
+                payload_size = encrypted_data.len()
+            )
+        )
+    )]
+    fn open(
+        &self,
+        header: &EncryptionHeader,
+        encrypted_data: &[u8],
+        context: &EncryptionContext,
+        extra_aad: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        // A version mismatch would only surface as an authentication failure
+        if let Some(expected) = header.context_version() {
+            if expected != context.version() {
+                return Err(Error::ContextVersionMismatch { expected, actual: context.version() });
+            }
+        }
+
+        check_payload_len(header, encrypted_data.len())?;
+
+        // Unwrap the DEK
+        let dek = self.unwrap_for_open(header, context)?;
+
+        self.open_with_dek(header, &dek, encrypted_data, context, extra_aad)
+    }
+
+    /// Unwraps the header's DEK for decryption.
+    ///
+    /// A cryptographic failure yields an empty DEK rather than an error, so
+    /// [`Vault::open_with_dek`] still runs the AEAD and fails with
+    /// `Error::AuthenticationFailed`, just as for a tampered payload.
+    fn unwrap_for_open(
+        &self,
+        header: &EncryptionHeader,
+        context: &EncryptionContext,
+    ) -> Result<SecretVec<u8>, Error> {
+        match self.unwrap_dek(header, context) {
+            Err(error) if is_cryptographic_failure(&error) => Ok(SecretVec::new(Vec::new())),
+            result => result,
+        }
+    }
+
+    /// Authenticates and decrypts `encrypted_data` with an already unwrapped DEK.
+    fn open_with_dek(
+        &self,
+        header: &EncryptionHeader,
+        dek: &SecretVec<u8>,
+        encrypted_data: &[u8],
+        context: &EncryptionContext,
+        extra_aad: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let cipher_mode = header_cipher_mode(header)?;
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("cipher_mode", tracing::field::debug(cipher_mode));
+
+        // The cipher must only ever see a key and nonce of its own size. Any
+        // other DEK (a header relabeled with another cipher, or the stand-in
+        // for one that didn't unwrap) or nonce is swapped for a throwaway
+        // one, so the AEAD still runs and the failure looks like any other
+        let dek_fits = dek.expose_secret().len() == cipher_mode.key_len();
+        let decoy = Zeroizing::new(vec![0u8; cipher_mode.key_len()]);
+        let key = if dek_fits { dek.expose_secret().as_slice() } else { decoy.as_slice() };
+        let nonce_fits = header.nonce().len() == cipher_mode.nonce_len();
+        let decoy_nonce = vec![0u8; cipher_mode.nonce_len()];
+        let nonce = if nonce_fits { header.nonce() } else { decoy_nonce.as_slice() };
+
+        // Use context (and any extra AAD) as associated data for authentication
+        let aad = associated_data(context, extra_aad);
+        let payload = chacha20poly1305::aead::Payload { msg: encrypted_data, aad: &aad };
+
+        // Decrypt the data
+        let plaintext = match aead_open(cipher_mode, key, nonce, payload) {
+            Some(plaintext) if dek_fits && nonce_fits => plaintext,
+            _ => return Err(authentication_failed(header)),
+        };
+
+        // Inflate only after the payload has been authenticated
+        if header.flags().is_compressed() {
+            let compressed = Zeroizing::new(plaintext);
+            return decompress(&compressed, self.max_decompressed_size);
+        }
+
+        Ok(plaintext)
+    }
+
+    /// Returns the nonce for the next encryption, sized for the vault's
+    /// cipher.
+    fn next_nonce(&self) -> Result<Vec<u8>, Error> {
+        let mut nonce_bytes = vec![0u8; self.cipher_mode.nonce_len()];
+        let Some(counter) = &self.nonce_counter else {
+            self.rng.fill_bytes(&mut nonce_bytes);
+            return Ok(nonce_bytes);
+        };
+
+        let value = counter
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |value| value.checked_add(1))
+            .map_err(|_| Error::EncryptionFailed("Nonce counter exhausted".to_string()))?;
+
+        let counter_start = nonce_bytes.len() - 8;
+        nonce_bytes[counter_start..].copy_from_slice(&value.to_be_bytes());
+        Ok(nonce_bytes)
+    }
+
+    /// Returns the current KEK for the context's tenant, or the shared
+    /// current KEK when the context has no tenant.
+    fn current_kek_id(&self, context: &EncryptionContext) -> Result<String, KeyProviderError> {
+        context.tenant_id().map_or_else(
+            || self.provider.current_kek_id(),
+            |tenant| self.provider.current_kek_id_for_tenant(tenant),
+        )
+    }
+
+    /// Generates a fresh DEK and wraps it under the current KEK for `context`,
+    /// for sealing `messages` messages totalling `bytes` bytes of plaintext.
+    fn new_envelope(
+        &self,
+        context: &EncryptionContext,
+        messages: usize,
+        bytes: usize,
+    ) -> Result<Envelope, Error> {
+        // Get the current KEK ID, which may be specific to the tenant
+        let kek_id = self.current_kek_id(context)?;
+
+        match &self.root_keys {
+            Some(root_keys) => {
+                self.root_envelope(root_keys, kek_id, wrap_aad(context), messages, bytes)
+            }
+            None => self.envelope_under(kek_id, wrap_aad(context)),
+        }
+    }
+
+    /// Returns the root key envelope for `kek_id` and `aad`, charging it with
+    /// `messages` and `bytes`. Generates and wraps a root key on first use,
+    /// and a new one when the charge would take the current one past its
+    /// budget.
+    fn root_envelope(
+        &self,
+        root_keys: &RootKeys,
+        kek_id: String,
+        aad: &[u8],
+        messages: usize,
+        bytes: usize,
+    ) -> Result<Envelope, Error> {
+        let messages = u64::try_from(messages).unwrap_or(u64::MAX);
+        let bytes = u64::try_from(bytes).unwrap_or(u64::MAX);
+        let mut roots = root_keys.roots.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // Held across the wrap so concurrent first uses agree on one root key
+        let root = match roots.entry((kek_id, aad.to_vec())) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let kek_id = entry.key().0.clone();
+                entry.insert(RootKey::new(self.envelope_under(kek_id, aad)?))
+            }
+        };
+        let spent = root.messages.saturating_add(messages) > root_keys.max_messages
+            || root.bytes.saturating_add(bytes) > root_keys.max_bytes;
+        if spent {
+            *root = RootKey::new(self.envelope_under(root.envelope.kek_id.clone(), aad)?);
+        }
+        root.messages = root.messages.saturating_add(messages);
+        root.bytes = root.bytes.saturating_add(bytes);
+
+        let envelope = Envelope {
+            dek: SecretVec::new(root.envelope.dek.expose_secret().clone()),
+            kek_id: root.envelope.kek_id.clone(),
+            wrapped_dek: root.envelope.wrapped_dek.clone(),
+            additional_recipients: Vec::new(),
+            derived: true,
+        };
+        drop(roots);
+
+        Ok(envelope)
+    }
+
+    /// Returns the DEK derived for `context` when `envelope` holds a root
+    /// key, or `None` when its DEK is used directly.
+    fn derived_dek(
+        &self,
+        envelope: &Envelope,
+        context: &EncryptionContext,
+    ) -> Result<Option<SecretVec<u8>>, Error> {
+        if !envelope.derived {
+            return Ok(None);
+        }
+        self.derive_context_dek(
+            &envelope.kek_id,
+            &envelope.wrapped_dek,
+            &envelope.dek,
+            context,
+            self.cipher_mode.key_len(),
+        )
+        .map(Some)
+    }
+
+    /// Derives the DEK for `context` from an unwrapped root key, consulting
+    /// the DEK cache first when enabled.
+    fn derive_context_dek(
+        &self,
+        kek_id: &str,
+        wrapped_root: &[u8],
+        root: &SecretVec<u8>,
+        context: &EncryptionContext,
+        len: usize,
+    ) -> Result<SecretVec<u8>, Error> {
+        #[cfg(feature = "dek-cache")]
+        if let Some(cache) = &self.dek_cache {
+            let info = context.canonical_bytes();
+            if let Some(dek) = cache.get_derived(kek_id, wrapped_root, &info) {
+                return Ok(dek);
+            }
+
+            let dek = derive_dek_sized(root, context, len)?;
+            cache.insert_derived(kek_id, wrapped_root, &info, &dek);
+            return Ok(dek);
+        }
+
+        #[cfg(not(feature = "dek-cache"))]
+        let _ = (self, kek_id, wrapped_root);
+        derive_dek_sized(root, context, len)
+    }
+
+    /// Generates a fresh DEK and wraps it under `kek_id`, bound to `aad`.
+    fn envelope_under(&self, kek_id: String, aad: &[u8]) -> Result<Envelope, Error> {
+        // Generate a random DEK sized for the cipher
+        let dek = self.rng.generate_dek(self.cipher_mode.key_len());
+
+        // Wrap the DEK with the KEK
+        let wrapped_dek = self.wrap_dek(&kek_id, &dek, aad)?;
+
+        Ok(Envelope { dek, kek_id, wrapped_dek, additional_recipients: Vec::new(), derived: false })
+    }
+
+    /// Wraps `dek` under `kek_id` with the key provider, bound to `aad`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "wrap_dek", skip_all, fields(kek_id = kek_id))
+    )]
+    fn wrap_dek(
+        &self,
+        kek_id: &str,
+        dek: &SecretVec<u8>,
+        aad: &[u8],
+    ) -> Result<Vec<u8>, KeyProviderError> {
+        self.provider.wrap_dek_aad(kek_id, dek.expose_secret(), aad)
+    }
+
+    /// Unwraps the header's DEK, deriving the context's DEK from it when the
+    /// header is marked as derived, and checks it against any key commitment
+    /// and header MAC.
+    fn unwrap_dek(
+        &self,
+        header: &EncryptionHeader,
+        context: &EncryptionContext,
+    ) -> Result<SecretVec<u8>, Error> {
+        if self.require_key_commitment && header.commitment().is_none() {
+            return Err(Error::InvalidHeader("Key commitment required".to_string()));
+        }
+
+        let dek = self.unwrap_cached_dek(header, wrap_aad(context))?;
+        let dek = if header.derives_dek() {
+            let len = header_cipher_mode(header)?.key_len();
+            self.derive_context_dek(header.kek_id(), header.wrapped_dek(), &dek, context, len)?
+        } else {
+            dek
+        };
+
+        // Reported once the AEAD has run; see `Vault::unwrap_for_open`
+        if let Some(commitment) = header.commitment() {
+            commitment_mac(&dek, header.nonce())?
+                .verify_slice(commitment)
+                .map_err(|_| Error::AuthenticationFailed)?;
+        }
+
+        if let Some(expected) = header.header_mac() {
+            header_mac(&dek, header)?
+                .verify_slice(expected)
+                .map_err(|_| Error::InvalidHeader("Header MAC mismatch".to_string()))?;
+        }
+
+        Ok(dek)
+    }
+
+    /// Unwraps the header's DEK, consulting the DEK cache first when enabled.
+    fn unwrap_cached_dek(
+        &self,
+        header: &EncryptionHeader,
+        aad: &[u8],
+    ) -> Result<SecretVec<u8>, Error> {
+        #[cfg(feature = "dek-cache")]
+        if let Some(cache) = &self.dek_cache {
+            if let Some(dek) = cache.get(header.kek_id(), header.wrapped_dek(), aad) {
+                return Ok(dek);
+            }
+
+            let dek = self.unwrap_any_recipient(header, aad)?;
+            cache.insert(header.kek_id(), header.wrapped_dek(), aad, &dek);
+            return Ok(dek);
+        }
+
+        self.unwrap_any_recipient(header, aad)
+    }
+
+    /// Unwraps the DEK with the first recipient KEK the provider knows.
+    fn unwrap_any_recipient(
+        &self,
+        header: &EncryptionHeader,
+        aad: &[u8],
+    ) -> Result<SecretVec<u8>, Error> {
+        let recipients = iter::once((header.kek_id(), header.wrapped_dek())).chain(
+            header
+                .additional_recipients()
+                .iter()
+                .map(|recipient| (recipient.kek_id.as_str(), recipient.encrypted_dek.as_slice())),
+        );
+
+        let mut unknown = Vec::new();
+        for (kek_id, wrapped_dek) in recipients {
+            match self.provider_unwrap_dek(kek_id, wrapped_dek, aad) {
+                Err(KeyProviderError::KekNotFound(_)) => unknown.push(kek_id),
+                result => return Ok(result?),
+            }
+        }
+
+        Err(KeyProviderError::KekNotFound(unknown.join(", ")).into())
+    }
+
+    /// Unwraps a single wrapped DEK with the key provider.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "unwrap_dek", skip_all, fields(kek_id = kek_id))
+    )]
+    fn provider_unwrap_dek(
+        &self,
+        kek_id: &str,
+        wrapped_dek: &[u8],
+        aad: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        self.provider.unwrap_dek_aad(kek_id, wrapped_dek, aad)
+    }
+
+    /// Encrypts `plaintext` under the envelope's DEK and prepends the header.
+    ///
+    /// The header records the context version so a mismatched context can be
+    /// reported as such on decryption.
+    fn seal(
+        &self,
+        envelope: &Envelope,
+        nonce_bytes: &[u8],
+        flags: HeaderFlags,
+        plaintext: &[u8],
+        context: &EncryptionContext,
+        extra_aad: &[u8],
+    ) -> Result<Ciphertext, Error> {
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("kek_id", envelope.kek_id.as_str());
+
+        let derived = self.derived_dek(envelope, context)?;
+        let dek = derived.as_ref().unwrap_or(&envelope.dek);
+        let ciphertext = self.encrypt_payload(dek, nonce_bytes, plaintext, context, extra_aad)?;
+
+        let header = self
+            .envelope_header(envelope, dek, flags, nonce_bytes, context)?
+            .with_payload_len(u32::try_from(ciphertext.len()).map_err(|_| {
+                Error::PayloadTooLarge { len: plaintext.len(), max: MAX_PLAINTEXT_LEN }
+            })?);
+        let header = self.authenticate_header(header, dek)?;
+
+        // Serialize header and ciphertext into a single buffer
+        Ciphertext::from_parts(header, &ciphertext)
+    }
+
+    /// Builds the header for a payload sealed under `envelope`, committing
+    /// it to the payload DEK `dek` when key commitment is enabled.
+    fn envelope_header(
+        &self,
+        envelope: &Envelope,
+        dek: &SecretVec<u8>,
+        flags: HeaderFlags,
+        nonce_bytes: &[u8],
+        context: &EncryptionContext,
+    ) -> Result<EncryptionHeader, Error> {
+        let header = EncryptionHeader::new(
+            envelope.kek_id.as_str(),
+            envelope.wrapped_dek.clone(),
+            flags,
+            nonce_bytes.to_vec(),
+        )
+        .with_cipher_id(self.cipher_mode.to_id())
+        .with_created_at((self.clock)())
+        .with_context_version(context.version())
+        .with_additional_recipients(envelope.additional_recipients.clone());
+        let header = if envelope.derived { header.with_derived_dek() } else { header };
+
+        if !self.key_commitment {
+            return Ok(header);
+        }
+        let commitment = commitment_mac(dek, nonce_bytes)?.finalize().into_bytes();
+        Ok(header.with_commitment(commitment.into()))
+    }
+
+    /// Adds the header MAC when enabled. Must run once every other header
+    /// field is set.
+    fn authenticate_header(
+        &self,
+        header: EncryptionHeader,
+        dek: &SecretVec<u8>,
+    ) -> Result<EncryptionHeader, Error> {
+        if !self.header_mac {
+            return Ok(header);
+        }
+        let mac = header_mac(dek, &header)?.finalize().into_bytes();
+        Ok(header.with_header_mac(mac.into()))
+    }
+
+    /// Encrypts `plaintext` with the DEK under the vault's cipher.
+    fn encrypt_payload(
+        &self,
+        dek: &SecretVec<u8>,
+        nonce_bytes: &[u8],
+        plaintext: &[u8],
+        context: &EncryptionContext,
+        extra_aad: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        // Nonces come from next_nonce, so each is the size its cipher expects
+        let aad = associated_data(context, extra_aad);
+        let payload = chacha20poly1305::aead::Payload { msg: plaintext, aad: &aad };
+
+        match self.cipher_mode {
+            CipherMode::ChaCha20Poly1305 => {
+                let cipher = ChaCha20Poly1305::new_from_slice(dek.expose_secret())
+                    .map_err(|e| Error::EncryptionFailed(format!("Invalid DEK: {e}")))?;
+
+                cipher.encrypt(GenericArray::from_slice(nonce_bytes), payload).map_err(|e| {
+                    Error::EncryptionFailed(format!("ChaCha20-Poly1305 encryption failed: {e}"))
+                })
+            }
+            CipherMode::Aes256Gcm => {
+                let cipher = Aes256Gcm::new_from_slice(dek.expose_secret())
+                    .map_err(|e| Error::EncryptionFailed(format!("Invalid DEK: {e}")))?;
+
+                cipher.encrypt(GenericArray::from_slice(nonce_bytes), payload).map_err(|e| {
+                    Error::EncryptionFailed(format!("AES-256-GCM encryption failed: {e}"))
+                })
+            }
+            CipherMode::XChaCha20Poly1305 => {
+                let cipher = XChaCha20Poly1305::new_from_slice(dek.expose_secret())
+                    .map_err(|e| Error::EncryptionFailed(format!("Invalid DEK: {e}")))?;
+
+                cipher.encrypt(GenericArray::from_slice(nonce_bytes), payload).map_err(|e| {
+                    Error::EncryptionFailed(format!("XChaCha20-Poly1305 encryption failed: {e}"))
+                })
+            }
+            CipherMode::Aes128Gcm => {
+                let cipher = Aes128Gcm::new_from_slice(dek.expose_secret())
+                    .map_err(|e| Error::EncryptionFailed(format!("Invalid DEK: {e}")))?;
+
+                cipher.encrypt(GenericArray::from_slice(nonce_bytes), payload).map_err(|e| {
+                    Error::EncryptionFailed(format!("AES-128-GCM encryption failed: {e}"))
+                })
+            }
+            CipherMode::Aes256GcmSiv => {
+                let cipher = Aes256GcmSiv::new_from_slice(dek.expose_secret())
+                    .map_err(|e| Error::EncryptionFailed(format!("Invalid DEK: {e}")))?;
+
+                cipher.encrypt(GenericArray::from_slice(nonce_bytes), payload).map_err(|e| {
+                    Error::EncryptionFailed(format!("AES-256-GCM-SIV encryption failed: {e}"))
+                })
+            }
+        }
+    }
+}
+
+/// Builder for a [`Vault`], created with [`Vault::builder`].
+#[allow(clippy::struct_excessive_bools)]
+pub struct VaultBuilder<P: KeyProvider> {
+    provider: P,
+    cipher_mode: CipherMode,
+    max_decompressed_size: usize,
+    clock: Arc<dyn Fn() -> u64 + Send + Sync>,
+    counter_nonces: bool,
+    rng: RngSource,
+    key_commitment: bool,
+    require_key_commitment: bool,
+    header_mac: bool,
+    deriving: bool,
+    #[cfg(feature = "dek-cache")]
+    dek_cache_capacity: usize,
+}
+
+impl<P: KeyProvider> VaultBuilder<P> {
+    /// Sets the cipher used for encryption. Defaults to [`CipherMode::default`].
+    #[must_use]
+    pub const fn cipher(mut self, cipher_mode: CipherMode) -> Self {
+        self.cipher_mode = cipher_mode;
+        self
+    }
+
+    /// Enables the DEK cache; see [`Vault::with_dek_cache`].
+    #[cfg(feature = "dek-cache")]
+    #[must_use]
+    pub const fn dek_cache(mut self, capacity: usize) -> Self {
+        self.dek_cache_capacity = capacity;
+        self
+    }
+
+    /// Uses counter-based nonces; see [`Vault::with_counter_nonces`].
+    #[must_use]
+    pub const fn counter_nonces(mut self) -> Self {
+        self.counter_nonces = true;
+        self
+    }
+
+    /// Sets the RNG for DEKs and nonces; see [`Vault::with_rng`].
+    #[must_use]
+    pub fn rng(mut self, rng: impl CryptoRng + RngCore + Send + 'static) -> Self {
+        self.rng = RngSource::new(rng);
+        self
+    }
+
+    /// Sets the header timestamp clock; see [`Vault::with_clock`].
+    #[must_use]
+    pub fn clock(mut self, clock: impl Fn() -> u64 + Send + Sync + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Commits ciphertext to its DEK; see [`Vault::with_key_commitment`].
+    #[must_use]
+    pub const fn key_commitment(mut self) -> Self {
+        self.key_commitment = true;
+        self
+    }
+
+    /// Commits ciphertext to its DEK and refuses uncommitted ciphertext; see
+    /// [`Vault::with_required_key_commitment`].
+    #[must_use]
+    pub const fn require_key_commitment(mut self) -> Self {
+        self.key_commitment = true;
+        self.require_key_commitment = true;
+        self
+    }
+
+    /// Authenticates headers with a MAC; see [`Vault::with_header_mac`].
+    #[must_use]
+    pub const fn header_mac(mut self) -> Self {
+        self.header_mac = true;
+        self
+    }
+
+    /// Derives each context's DEK from a root key; see [`Vault::deriving`].
+    #[must_use]
+    pub const fn deriving(mut self) -> Self {
+        self.deriving = true;
+        self
+    }
+
+    /// Sets the decompression limit; see [`Vault::with_max_decompressed_size`].
+    #[must_use]
+    pub const fn max_decompressed(mut self, max: usize) -> Self {
+        self.max_decompressed_size = max;
+        self
+    }
+
+    /// Builds the configured Vault.
+    pub fn build(self) -> Vault<P> {
+        Vault {
+            provider: Arc::new(self.provider),
+            cipher_mode: self.cipher_mode,
+            max_decompressed_size: self.max_decompressed_size,
+            clock: self.clock,
+            nonce_counter: self.counter_nonces.then(|| Arc::new(AtomicU64::new(0))),
+            rng: self.rng,
+            key_commitment: self.key_commitment,
+            require_key_commitment: self.require_key_commitment,
+            header_mac: self.header_mac,
+            root_keys: self.deriving.then(|| Arc::new(RootKeys::new(self.cipher_mode))),
+            #[cfg(feature = "dek-cache")]
+            dek_cache: NonZeroUsize::new(self.dek_cache_capacity)
+                .map(|cap| Arc::new(DekCache::new(cap))),
+        }
+    }
+}
+
+/// A DEK together with its wrapped form(s) and the KEK(s) that wrapped it.
+struct Envelope {
+    dek: SecretVec<u8>,
+    kek_id: String,
+    wrapped_dek: Vec<u8>,
+    additional_recipients: Vec<WrappedDek>,
+    /// Whether `dek` is a root key the payload DEK is derived from per context
+    derived: bool,
+}
+
+/// Root keys of a deriving vault (see [`Vault::deriving`]), keyed by KEK id
+/// and wrap associated data, so each tenant gets its own.
+struct RootKeys {
+    roots: Mutex<HashMap<(String, Vec<u8>), RootKey>>,
+    /// Messages a root key seals before it is replaced
+    max_messages: u64,
+    /// Plaintext bytes a root key seals before it is replaced
+    max_bytes: u64,
+}
+
+impl RootKeys {
+    /// Random nonces collide with non-negligible probability past 2^32
+    /// messages under one key.
+    const MAX_MESSAGES: u64 = 1 << 32;
+
+    fn new(cipher_mode: CipherMode) -> Self {
+        Self {
+            roots: Mutex::default(),
+            max_messages: Self::MAX_MESSAGES,
+            max_bytes: cipher_mode.max_message_bytes(),
+        }
+    }
+}
+
+/// A root key envelope and what it has sealed so far.
+struct RootKey {
+    envelope: Envelope,
+    messages: u64,
+    bytes: u64,
+}
+
+impl RootKey {
+    const fn new(envelope: Envelope) -> Self {
+        Self { envelope, messages: 0, bytes: 0 }
+    }
+}
+
+/// Returns the cipher a header's payload was sealed with. Headers written
+/// before cipher ids existed are ChaCha20-Poly1305.
+fn header_cipher_mode(header: &EncryptionHeader) -> Result<CipherMode, Error> {
+    header.cipher_id().map_or(Ok(CipherMode::ChaCha20Poly1305), CipherMode::from_id)
+}
+
+/// Returns the associated data a DEK is wrapped with for `context`: its
+/// tenant, or nothing when it has none (see [`KeyProvider::wrap_dek_aad`]).
+fn wrap_aad(context: &EncryptionContext) -> &[u8] {
+    context.tenant_id().map_or(&[], str::as_bytes)
+}
+
+/// Starts the key commitment MAC of a header: HMAC-SHA256 keyed by the DEK
+/// over a label and the header nonce.
+fn commitment_mac(dek: &SecretVec<u8>, nonce: &[u8]) -> Result<Hmac<Sha256>, Error> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(dek.expose_secret())
+        .map_err(|_| Error::KeyDerivation)?;
+    mac.update(b"sifredb-key-commitment");
+    mac.update(nonce);
+    Ok(mac)
+}
+
+/// Starts the header MAC of a header: HMAC-SHA256 keyed by the DEK over a
+/// label and [`EncryptionHeader::mac_input`].
+fn header_mac(dek: &SecretVec<u8>, header: &EncryptionHeader) -> Result<Hmac<Sha256>, Error> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(dek.expose_secret())
+        .map_err(|_| Error::KeyDerivation)?;
+    mac.update(b"sifredb-header-mac");
+    mac.update(&header.mac_input()?);
+    Ok(mac)
+}
+
+/// Derives the nonce of chunk `index` from a chunked header's nonce: its
+/// leading bytes followed by the index.
+fn chunk_nonce(nonce: &[u8], index: u32) -> Vec<u8> {
+    let mut chunk_nonce = nonce.to_vec();
+    chunk_nonce[nonce.len() - CHUNK_INDEX_SIZE..].copy_from_slice(&index.to_be_bytes());
+    chunk_nonce
+}
+
+/// Extra associated data of chunk `index`: `index || is_last`, followed for
+/// chunk 0 by the digest of any [`StreamAad`].
+fn chunk_aad(index: u32, is_last: bool, aad_digest: Option<&[u8; 32]>) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(5 + 32);
+    aad.extend_from_slice(&index.to_be_bytes());
+    aad.push(u8::from(is_last));
+    if let (0, Some(digest)) = (index, aad_digest) {
+        aad.extend_from_slice(digest);
+    }
+    aad
+}
+
+/// Rejects plaintext whose payload length wouldn't fit the header field,
+/// before any key or cipher work is done.
+const fn check_plaintext_len(len: usize) -> Result<(), Error> {
+    if len > MAX_PLAINTEXT_LEN {
+        return Err(Error::PayloadTooLarge { len, max: MAX_PLAINTEXT_LEN });
+    }
+    Ok(())
+}
+
+/// Parses a detached header, which must not be followed by any other bytes.
+fn parse_detached_header(header_bytes: &[u8]) -> Result<EncryptionHeader, Error> {
+    let (header, header_len) = EncryptionHeader::from_bytes(header_bytes)?;
+    if header_len != header_bytes.len() {
+        return Err(Error::InvalidHeader(format!(
+            "Unexpected {} trailing bytes after detached header",
+            header_bytes.len() - header_len
+        )));
+    }
+    Ok(header)
+}
+
+/// Opens `payload` with `cipher_mode` under `key`, or returns `None` if it
+/// doesn't authenticate. `nonce` must already be checked against
+/// [`CipherMode::nonce_len`].
+fn aead_open(
+    cipher_mode: CipherMode,
+    key: &[u8],
+    nonce: &[u8],
+    payload: chacha20poly1305::aead::Payload<'_, '_>,
+) -> Option<Vec<u8>> {
+    match cipher_mode {
+        CipherMode::ChaCha20Poly1305 => ChaCha20Poly1305::new_from_slice(key)
+            .ok()?
+            .decrypt(GenericArray::from_slice(nonce), payload)
+            .ok(),
+        CipherMode::Aes256Gcm => Aes256Gcm::new_from_slice(key)
+            .ok()?
+            .decrypt(GenericArray::from_slice(nonce), payload)
+            .ok(),
+        CipherMode::XChaCha20Poly1305 => XChaCha20Poly1305::new_from_slice(key)
+            .ok()?
+            .decrypt(GenericArray::from_slice(nonce), payload)
+            .ok(),
+        CipherMode::Aes128Gcm => Aes128Gcm::new_from_slice(key)
+            .ok()?
+            .decrypt(GenericArray::from_slice(nonce), payload)
+            .ok(),
+        CipherMode::Aes256GcmSiv => Aes256GcmSiv::new_from_slice(key)
+            .ok()?
+            .decrypt(GenericArray::from_slice(nonce), payload)
+            .ok(),
+    }
+}
+
+/// Whether `error` means the ciphertext doesn't authenticate under the keys
+/// at hand, as opposed to a malformed header or an unknown or unavailable
+/// KEK.
+const fn is_cryptographic_failure(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::AuthenticationFailed
+            | Error::KeyProvider(
+                KeyProviderError::UnwrapFailed(_) | KeyProviderError::UnwrapFailedWithSource { .. }
+            )
+    )
+}
+
+/// Builds the authentication failure error, emitting a countable event when
+/// tracing is enabled.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables, clippy::missing_const_for_fn))]
+fn authentication_failed(header: &EncryptionHeader) -> Error {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        monotonic_counter.authentication_failures = 1_u64,
+        kek_id = header.kek_id(),
+        "authentication failed"
+    );
+
+    Error::AuthenticationFailed
+}
+
+/// Returns the current system time in Unix milliseconds.
+fn system_clock() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX))
+}
+
+/// Source of the randomness behind a vault's DEKs and random nonces.
+///
+/// Defaults to [`OsRng`]; [`Vault::with_rng`] swaps in any cryptographically
+/// secure generator. Clones share the same generator.
+#[derive(Clone, Default)]
+pub struct RngSource {
+    /// `None` means the operating system RNG, which needs no lock
+    rng: Option<Arc<Mutex<dyn RngCore + Send>>>,
+}
+
+impl RngSource {
+    /// Wraps a cryptographically secure RNG.
+    pub fn new(rng: impl CryptoRng + RngCore + Send + 'static) -> Self {
+        Self { rng: Some(Arc::new(Mutex::new(rng))) }
+    }
+
+    /// Fills `dest` with random bytes.
+    pub fn fill_bytes(&self, dest: &mut [u8]) {
+        match &self.rng {
+            // A panic mid-fill leaves the generator usable, so poisoning is ignored
+            Some(rng) => rng.lock().unwrap_or_else(PoisonError::into_inner).fill_bytes(dest),
+            None => OsRng.fill_bytes(dest),
+        }
+    }
+
+    /// Generates a random DEK of `len` bytes.
+    fn generate_dek(&self, len: usize) -> SecretVec<u8> {
+        let mut dek = vec![0u8; len];
+        self.fill_bytes(&mut dek);
+        SecretVec::new(dek)
+    }
+}
+
+impl fmt::Debug for RngSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.rng.is_some() { "RngSource(custom)" } else { "RngSource(OsRng)" })
+    }
+}
+
+/// Compresses a payload with DEFLATE.
+fn compress(data: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(data)
+        .map_err(|e| Error::EncryptionFailed(format!("Compression failed: {e}")))?;
+    encoder
+        .finish()
+        .map(Zeroizing::new)
+        .map_err(|e| Error::EncryptionFailed(format!("Compression failed: {e}")))
+}
+
+/// Inflates a DEFLATE payload, refusing to produce more than `max` bytes.
+fn decompress(data: &[u8], max: usize) -> Result<Vec<u8>, Error> {
+    let limit = u64::try_from(max).unwrap_or(u64::MAX).saturating_add(1);
+    let mut decoder = DeflateDecoder::new(data).take(limit);
+
+    let mut plaintext = Vec::new();
+    decoder
+        .read_to_end(&mut plaintext)
+        .map_err(|e| Error::DecryptionFailed(format!("Decompression failed: {e}")))?;
+
+    if plaintext.len() > max {
+        plaintext.zeroize();
+        return Err(Error::DecryptionFailed(format!(
+            "Decompressed payload exceeds limit of {max} bytes"
+        )));
+    }
+
+    Ok(plaintext)
+}
+
+/// Builds the AEAD associated data: `context || 0x00 || extra_aad`.
+///
+/// With no extra AAD this is just the canonical context bytes, which keeps
+/// ciphertext produced before extra AAD existed decryptable.
+fn associated_data(context: &EncryptionContext, extra_aad: &[u8]) -> Vec<u8> {
+    let mut aad = context.canonical_bytes();
+    if !extra_aad.is_empty() {
+        aad.push(0x00);
+        aad.extend_from_slice(extra_aad);
+    }
+    aad
+}
+
+impl<P: KeyProvider> Clone for Vault<P> {
+    fn clone(&self) -> Self {
+        Self {
+            provider: Arc::clone(&self.provider),
+            cipher_mode: self.cipher_mode,
+            max_decompressed_size: self.max_decompressed_size,
+            clock: Arc::clone(&self.clock),
+            nonce_counter: self.nonce_counter.clone(),
+            rng: self.rng.clone(),
+            key_commitment: self.key_commitment,
+            require_key_commitment: self.require_key_commitment,
+            header_mac: self.header_mac,
+            root_keys: self.root_keys.clone(),
+            #[cfg(feature = "dek-cache")]
+            dek_cache: self.dek_cache.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicUsize;
+
+    // Mock key provider for testing
+    struct MockKeyProvider {
+        keks: Mutex<HashMap<String, SecretVec<u8>>>,
+        current_kek_id: String,
+        wrap_calls: AtomicUsize,
+        unwrap_calls: AtomicUsize,
+    }
+
+    impl MockKeyProvider {
+        fn new() -> Self {
+            let mut keks = HashMap::new();
+            let kek = SecretVec::new(vec![42u8; 32]);
+            keks.insert("test_kek".to_string(), kek);
+
+            Self {
+                keks: Mutex::new(keks),
+                current_kek_id: "test_kek".to_string(),
+                wrap_calls: AtomicUsize::new(0),
+                unwrap_calls: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    // WARNING: This KeyProvider implementation uses simple XOR for DEK wrapping
+    // and is intended ONLY for testing purposes. DO NOT use in production.
+    // In production, use a secure key provider like FileKeyProvider or AWS KMS.
+    impl KeyProvider for MockKeyProvider {
+        fn create_kek(&self) -> Result<String, KeyProviderError> {
+            let kek_id = format!("kek_{}", self.keks.lock().unwrap().len());
+            let kek = SecretVec::new(vec![1u8; 32]);
+            self.keks.lock().unwrap().insert(kek_id.clone(), kek);
+            Ok(kek_id)
+        }
+
+        fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+            Ok(self.current_kek_id.clone())
+        }
+
+        fn wrap_dek(&self, kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+            self.wrap_calls.fetch_add(1, Ordering::SeqCst);
+            let keks = self.keks.lock().unwrap();
+            let kek = keks
+                .get(kek_id)
+                .ok_or_else(|| KeyProviderError::KekNotFound(kek_id.to_string()))?;
+
+            // Simple XOR "encryption" for testing
+            let wrapped: Vec<u8> =
+                dek.iter().zip(kek.expose_secret().iter().cycle()).map(|(d, k)| d ^ k).collect();
+
+            drop(keks);
+            Ok(wrapped)
+        }
+
+        fn unwrap_dek(
+            &self,
+            kek_id: &str,
+            wrapped_dek: &[u8],
+        ) -> Result<SecretVec<u8>, KeyProviderError> {
+            self.unwrap_calls.fetch_add(1, Ordering::SeqCst);
+            let keks = self.keks.lock().unwrap();
+            let kek = keks
+                .get(kek_id)
+                .ok_or_else(|| KeyProviderError::KekNotFound(kek_id.to_string()))?;
+
+            // Simple XOR "decryption" for testing (XOR is symmetric)
+            let dek: Vec<u8> = wrapped_dek
+                .iter()
+                .zip(kek.expose_secret().iter().cycle())
+                .map(|(w, k)| w ^ k)
+                .collect();
+
+            drop(keks);
+            Ok(SecretVec::new(dek))
+        }
+
+        fn get_pepper(&self) -> Result<Option<SecretVec<u8>>, KeyProviderError> {
+            Ok(Some(SecretVec::new(vec![7u8; 32])))
+        }
+    }
+
+    #[test]
+    fn test_dedup_fingerprint_is_deterministic() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("documents", "body");
+
+        let first = vault.encrypt(b"same contents", &context).unwrap();
+        let second = vault.encrypt(b"same contents", &context).unwrap();
+        assert_ne!(first.as_bytes(), second.as_bytes());
+
+        let fingerprint = vault.dedup_fingerprint(b"same contents", &context).unwrap();
+        assert_eq!(fingerprint, vault.dedup_fingerprint(b"same contents", &context).unwrap());
+        assert_ne!(fingerprint, vault.dedup_fingerprint(b"other contents", &context).unwrap());
+        assert_eq!(vault.provider.wrap_calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_dedup_fingerprint_separates_contexts() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("documents", "body");
+
+        let fingerprint = vault.dedup_fingerprint(b"same contents", &context).unwrap();
+        for other in [
+            EncryptionContext::new("documents", "title"),
+            context.clone().with_tenant("tenant_a"),
+            context.with_version(2),
+        ] {
+            assert_ne!(fingerprint, vault.dedup_fingerprint(b"same contents", &other).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_dedup_fingerprint_requires_pepper() {
+        let vault = Vault::new(NoPepperProvider(MockKeyProvider::new()), CipherMode::default());
+        let context = EncryptionContext::new("documents", "body");
+
+        let result = vault.dedup_fingerprint(b"same contents", &context);
+        assert!(matches!(result, Err(Error::KeyProvider(KeyProviderError::PepperUnavailable(_)))));
+    }
+
+    /// Wraps a provider without passing its pepper through.
+    struct NoPepperProvider(MockKeyProvider);
+
+    impl KeyProvider for NoPepperProvider {
+        fn create_kek(&self) -> Result<String, KeyProviderError> {
+            self.0.create_kek()
+        }
+
+        fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+            self.0.current_kek_id()
+        }
+
+        fn wrap_dek(&self, kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+            self.0.wrap_dek(kek_id, dek)
+        }
+
+        fn unwrap_dek(
+            &self,
+            kek_id: &str,
+            wrapped_dek: &[u8],
+        ) -> Result<SecretVec<u8>, KeyProviderError> {
+            self.0.unwrap_dek(kek_id, wrapped_dek)
+        }
+    }
+
+    #[test]
+    fn test_peek_header_reads_kek_id_without_provider() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+
+        let header = crate::header::peek_header(ciphertext.as_bytes()).unwrap();
+
+        assert_eq!(header.kek_id(), "test_kek");
+        assert_eq!(header.version(), crate::header::PROTOCOL_VERSION);
+        assert_eq!(&header, ciphertext.header());
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_vault_ciphertext_len_matches_encrypt() {
+        let context = EncryptionContext::new("users", "email").with_version(3);
+
+        for mode in CipherMode::ALL {
+            let vault = Vault::new(MockKeyProvider::new(), mode);
+
+            for len in [0, 1, 17, 255, 4096] {
+                let ciphertext = vault.encrypt(&vec![b'x'; len], &context).unwrap();
+                assert_eq!(
+                    vault.ciphertext_len(len, &context).unwrap(),
+                    ciphertext.as_bytes().len()
+                );
+            }
+        }
+
+        // A tenant's KEK id can be longer than the shared one
+        let context = context.with_tenant("acme-industries");
+        let vault = Vault::new(TenantKeyProvider::new(&["acme-industries"]), CipherMode::default());
+        for len in [0, 17] {
+            let ciphertext = vault.encrypt(&vec![b'x'; len], &context).unwrap();
+            assert_eq!(ciphertext.kek_id(), "kek_acme-industries");
+            assert_eq!(vault.ciphertext_len(len, &context).unwrap(), ciphertext.as_bytes().len());
+        }
+    }
+
+    #[test]
+    fn test_vault_key_commitment_round_trip() {
+        let context = EncryptionContext::new("users", "email");
+
+        for mode in CipherMode::ALL {
+            let plain = Vault::new(MockKeyProvider::new(), mode);
+            let committed =
+                Vault::builder(MockKeyProvider::new()).cipher(mode).key_commitment().build();
+
+            let uncommitted_ct = plain.encrypt(b"alice@example.com", &context).unwrap();
+            let committed_ct = committed.encrypt(b"alice@example.com", &context).unwrap();
+            assert_eq!(uncommitted_ct.header().commitment(), None);
+            assert_eq!(committed_ct.version(), crate::header::MAX_PROTOCOL_VERSION);
+            assert_eq!(
+                committed.ciphertext_len(17, &context).unwrap(),
+                committed_ct.as_bytes().len()
+            );
+
+            // Either vault reads both formats
+            for vault in [&plain, &committed] {
+                assert_eq!(vault.decrypt(&uncommitted_ct, &context).unwrap(), b"alice@example.com");
+                assert_eq!(vault.decrypt(&committed_ct, &context).unwrap(), b"alice@example.com");
+            }
+        }
+
+        // Chunks derive their nonces from the committed header nonce
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default()).with_key_commitment();
+        let (header, chunks) = vault.encrypt_chunked(&[7u8; 100], 32, &context).unwrap();
+        assert!(crate::header::peek_header(&header).unwrap().commitment().is_some());
+        assert_eq!(vault.decrypt_chunked(&header, &chunks, &context).unwrap(), vec![7u8; 100]);
+    }
+
+    #[test]
+    fn test_vault_key_commitment_mismatch_fails() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default()).with_key_commitment();
+        let context = EncryptionContext::new("users", "email");
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+
+        // A wrong commitment fails even though the payload itself is intact
+        let mut commitment = *ciphertext.header().commitment().unwrap();
+        commitment[0] ^= 0x01;
+        let header = ciphertext.header().clone().with_commitment(commitment);
+        let tampered = Ciphertext::from_parts(header, ciphertext.payload()).unwrap();
+        assert!(matches!(vault.decrypt(&tampered, &context), Err(Error::AuthenticationFailed)));
+
+        // So does a recipient whose wrapped DEK differs from the committed one
+        let other_dek = vault.provider.wrap_dek("test_kek", &[9u8; DEK_SIZE]).unwrap();
+        let header = ciphertext.header().rewrapped("test_kek", other_dek);
+        let swapped = Ciphertext::from_parts(header, ciphertext.payload()).unwrap();
+        let unwraps = vault.provider.unwrap_calls.load(Ordering::SeqCst);
+        assert!(matches!(vault.decrypt(&swapped, &context), Err(Error::AuthenticationFailed)));
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), unwraps + 1);
+
+        assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), b"alice@example.com");
+    }
+
+    #[test]
+    fn test_vault_required_key_commitment_rejects_stripped() {
+        let vault = Vault::builder(MockKeyProvider::new()).require_key_commitment().build();
+        let lenient = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+        assert!(ciphertext.header().commitment().is_some());
+
+        // Stripping the commitment still authenticates, so only the policy catches it
+        let mut stripped = EncryptionHeader::new(
+            ciphertext.kek_id(),
+            ciphertext.header().wrapped_dek().to_vec(),
+            ciphertext.header().flags(),
+            ciphertext.header().nonce().to_vec(),
+        );
+        if let Some(cipher_id) = ciphertext.header().cipher_id() {
+            stripped = stripped.with_cipher_id(cipher_id);
+        }
+        if let Some(created_at) = ciphertext.header().created_at() {
+            stripped = stripped.with_created_at(created_at);
+        }
+        if let Some(context_version) = ciphertext.header().context_version() {
+            stripped = stripped.with_context_version(context_version);
+        }
+        if let Some(payload_len) = ciphertext.header().payload_len() {
+            stripped = stripped.with_payload_len(payload_len);
+        }
+        let stripped = Ciphertext::from_parts(stripped, ciphertext.payload()).unwrap();
+        assert_eq!(lenient.decrypt(&stripped, &context).unwrap(), b"alice@example.com");
+
+        let unwraps = vault.provider.unwrap_calls.load(Ordering::SeqCst);
+        let result = vault.decrypt(&stripped, &context);
+        assert!(
+            matches!(result, Err(Error::InvalidHeader(msg)) if msg == "Key commitment required")
+        );
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), unwraps);
+
+        assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), b"alice@example.com");
+    }
+
+    #[test]
+    fn test_vault_header_mac_round_trip() {
+        let context = EncryptionContext::new("users", "email");
+        let plain = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let ciphertext = plain.encrypt(b"alice@example.com", &context).unwrap();
+        assert_eq!(ciphertext.header().header_mac(), None);
+        assert_eq!(ciphertext.version(), PROTOCOL_VERSION);
+
+        for mode in CipherMode::ALL {
+            let vault = Vault::builder(MockKeyProvider::new()).cipher(mode).header_mac().build();
+            let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+
+            assert!(ciphertext.header().header_mac().is_some());
+            assert_eq!(ciphertext.version(), crate::header::MAX_PROTOCOL_VERSION);
+            assert_eq!(vault.ciphertext_len(17, &context).unwrap(), ciphertext.as_bytes().len());
+            assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), b"alice@example.com");
+
+            // Rewrapping doesn't need the DEK and keeps the MAC valid
+            let rewrapped = vault.rewrap(&ciphertext, "test_kek").unwrap();
+            assert_eq!(rewrapped.header().header_mac(), ciphertext.header().header_mac());
+            assert_eq!(vault.decrypt(&rewrapped, &context).unwrap(), b"alice@example.com");
+        }
+
+        // Chunked headers are authenticated too
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default()).with_header_mac();
+        let (header, chunks) = vault.encrypt_chunked(&[7u8; 100], 32, &context).unwrap();
+        assert!(crate::header::peek_header(&header).unwrap().header_mac().is_some());
+        assert_eq!(vault.decrypt_chunked(&header, &chunks, &context).unwrap(), vec![7u8; 100]);
+    }
+
+    #[test]
+    fn test_vault_header_mac_detects_tampering() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default()).with_header_mac();
+        let context = EncryptionContext::new("users", "email");
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+        let header = ciphertext.header();
+        let flags_pos = 1 + 1 + header.kek_id().len() + 2 + header.wrapped_dek().len();
+        let created_at_pos = flags_pos + 3;
+
+        // The timestamp isn't otherwise authenticated at all
+        let mut retimed = ciphertext.as_bytes().to_vec();
+        retimed[created_at_pos + 7] ^= 0x01;
+        let retimed = Ciphertext::from_bytes(retimed).unwrap();
+        assert!(matches!(
+            vault.decrypt(&retimed, &context),
+            Err(Error::InvalidHeader(msg)) if msg == "Header MAC mismatch"
+        ));
+
+        // A flipped flag bit is caught before the payload is touched
+        let mut reflagged = ciphertext.as_bytes().to_vec();
+        reflagged[flags_pos] ^= 0x02;
+        let reflagged = Ciphertext::from_bytes(reflagged).unwrap();
+        assert!(reflagged.header().flags().is_compressed());
+        assert!(matches!(
+            vault.decrypt(&reflagged, &context),
+            Err(Error::InvalidHeader(msg)) if msg == "Header MAC mismatch"
+        ));
+
+        // Vaults without the option still check headers that carry a MAC
+        let reader = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        assert!(matches!(reader.decrypt(&retimed, &context), Err(Error::InvalidHeader(_))));
+        assert_eq!(reader.decrypt(&ciphertext, &context).unwrap(), b"alice@example.com");
+    }
+
+    #[test]
+    fn test_vault_encrypt_decrypt_round_trip() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let plaintext = b"alice@example.com";
+        let ciphertext = vault.encrypt(plaintext, &context).expect("Encryption failed");
+        let decrypted = vault.decrypt(&ciphertext, &context).expect("Decryption failed");
+
+        assert_eq!(plaintext, &decrypted[..]);
+    }
+
+    #[test]
+    fn test_vault_different_plaintexts() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let plaintext1 = b"alice@example.com";
+        let plaintext2 = b"bob@example.com";
+
+        let ciphertext1 = vault.encrypt(plaintext1, &context).unwrap();
+        let ciphertext2 = vault.encrypt(plaintext2, &context).unwrap();
+
+        // Different plaintexts should produce different ciphertexts
+        assert_ne!(ciphertext1, ciphertext2);
+
+        let decrypted1 = vault.decrypt(&ciphertext1, &context).unwrap();
+        let decrypted2 = vault.decrypt(&ciphertext2, &context).unwrap();
+
+        assert_eq!(plaintext1, &decrypted1[..]);
+        assert_eq!(plaintext2, &decrypted2[..]);
+    }
+
+    #[test]
+    fn test_vault_different_contexts() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+
+        let context1 = EncryptionContext::new("users", "email");
+        let context2 = EncryptionContext::new("users", "name");
+
+        let plaintext = b"alice@example.com";
+
+        let ciphertext1 = vault.encrypt(plaintext, &context1).unwrap();
+        let ciphertext2 = vault.encrypt(plaintext, &context2).unwrap();
+
+        // Same plaintext with different contexts should produce different ciphertexts
+        assert_ne!(ciphertext1, ciphertext2);
+
+        // Decrypt with correct contexts
+        let decrypted1 = vault.decrypt(&ciphertext1, &context1).unwrap();
+        let decrypted2 = vault.decrypt(&ciphertext2, &context2).unwrap();
+
+        assert_eq!(plaintext, &decrypted1[..]);
+        assert_eq!(plaintext, &decrypted2[..]);
+    }
+
+    #[test]
+    fn test_vault_wrong_context_fails() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+
+        let context1 = EncryptionContext::new("users", "email");
+        let context2 = EncryptionContext::new("users", "name");
+
+        let plaintext = b"alice@example.com";
+        let ciphertext = vault.encrypt(plaintext, &context1).unwrap();
+
+        // Decrypt with wrong context should fail authentication
+        let result = vault.decrypt(&ciphertext, &context2);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+    }
+
+    #[test]
+    fn test_vault_context_version_mismatch() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let v1 = EncryptionContext::new("users", "email").with_version(1);
+        let v2 = EncryptionContext::new("users", "email").with_version(2);
+
+        let ciphertext = vault.encrypt(b"alice@example.com", &v1).unwrap();
+        assert_eq!(ciphertext.header().context_version(), Some(1));
+
+        let result = vault.decrypt(&ciphertext, &v2);
+        assert!(matches!(result, Err(Error::ContextVersionMismatch { expected: 1, actual: 2 })));
+
+        // Reported before any key material is touched
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(vault.decrypt(&ciphertext, &v1).unwrap(), b"alice@example.com");
+    }
+
+    #[test]
+    fn test_vault_unrecorded_context_version_fails_authentication() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let v1 = EncryptionContext::new("users", "email").with_version(1);
+        let v2 = EncryptionContext::new("users", "email").with_version(2);
+
+        // Headers written before the version was recorded can only fail authentication
+        let ciphertext = vault.encrypt(b"alice@example.com", &v1).unwrap();
+        let legacy = vault.decrypt_bytes(&downgrade_to_v1(&ciphertext), &v2);
+        assert!(matches!(legacy, Err(Error::AuthenticationFailed)));
+
+        // Same version but another column is still a plain authentication failure
+        let other_column = EncryptionContext::new("users", "phone").with_version(1);
+        let result = vault.decrypt(&ciphertext, &other_column);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+    }
+
+    #[test]
+    fn test_vault_decrypt_any_version() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let base = EncryptionContext::new("users", "email");
+
+        let v1 = vault.encrypt(b"alice@example.com", &base.clone().with_version(1)).unwrap();
+        let v3 = vault.encrypt(b"bob@example.com", &base.clone().with_version(3)).unwrap();
+
+        let (version, plaintext) = vault.decrypt_any_version(&v1, &base, &[1, 2, 3]).unwrap();
+        assert_eq!((version, plaintext.as_slice()), (1, &b"alice@example.com"[..]));
+
+        let (version, plaintext) = vault.decrypt_any_version(&v3, &base, &[1, 2, 3]).unwrap();
+        assert_eq!((version, plaintext.as_slice()), (3, &b"bob@example.com"[..]));
+
+        // The recorded version must be one of the candidates
+        let result = vault.decrypt_any_version(&v3, &base, &[1, 2]);
+        assert!(matches!(result, Err(Error::ContextVersionMismatch { expected: 3, .. })));
+    }
+
+    #[test]
+    fn test_vault_decrypt_any_version_legacy_header() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let base = EncryptionContext::new("users", "email");
+
+        let v3 = vault.encrypt(b"bob@example.com", &base.clone().with_version(3)).unwrap();
+        let legacy = Ciphertext::from_bytes(downgrade_to_v1(&v3)).unwrap();
+        assert_eq!(legacy.header().context_version(), None);
+
+        let unwraps_before = vault.provider.unwrap_calls.load(Ordering::SeqCst);
+        let (version, plaintext) = vault.decrypt_any_version(&legacy, &base, &[1, 2, 3]).unwrap();
+        assert_eq!((version, plaintext.as_slice()), (3, &b"bob@example.com"[..]));
+
+        // Trying several versions unwraps the DEK only once
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), unwraps_before + 1);
+
+        let result = vault.decrypt_any_version(&legacy, &base, &[1, 2]);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+    }
+
+    #[test]
+    fn test_vault_schema_fingerprint_mismatch_fails_authentication() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let before = EncryptionContext::new("users", "email").with_schema_fingerprint([1; 8]);
+        let same = EncryptionContext::new("users", "email").with_schema_fingerprint([1; 8]);
+        let after = EncryptionContext::new("users", "email").with_schema_fingerprint([2; 8]);
+        let unbound = EncryptionContext::new("users", "email");
+
+        let ciphertext = vault.encrypt(b"alice@example.com", &before).unwrap();
+        assert_eq!(vault.decrypt(&ciphertext, &same).unwrap(), b"alice@example.com");
+
+        for context in [&after, &unbound] {
+            let result = vault.decrypt(&ciphertext, context);
+            assert!(matches!(result, Err(Error::AuthenticationFailed)));
+        }
+    }
+
+    #[test]
+    fn test_vault_empty_plaintext() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let plaintext = b"";
+        let ciphertext = vault.encrypt(plaintext, &context).unwrap();
+        let decrypted = vault.decrypt(&ciphertext, &context).unwrap();
+
+        assert_eq!(plaintext, &decrypted[..]);
+    }
+
+    #[test]
+    fn test_vault_large_plaintext() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "data");
+
+        let plaintext = vec![42u8; 10000];
+        let ciphertext = vault.encrypt(&plaintext, &context).unwrap();
+        let decrypted = vault.decrypt(&ciphertext, &context).unwrap();
+
+        assert_eq!(plaintext, decrypted);
+    }
+
+    #[test]
+    fn test_vault_corrupted_ciphertext_fails() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let plaintext = b"alice@example.com";
+        let mut ciphertext = vault.encrypt(plaintext, &context).unwrap().into_bytes();
+
+        // Corrupt the ciphertext
+        let len = ciphertext.len();
+        if len > 10 {
+            ciphertext[len - 1] ^= 0xFF;
+        }
+
+        // Decryption should fail
+        let result = vault.decrypt_bytes(&ciphertext, &context);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_vault_decrypt_bytes_nonce_overruns_data() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        // Header claims a 200-byte nonce but the input ends after 3 bytes
+        let header = EncryptionHeader::new("test_kek", vec![1; 32], HeaderFlags::empty(), vec![]);
+        let mut bytes = header.to_bytes().unwrap();
+        *bytes.last_mut().unwrap() = 200;
+        bytes.extend_from_slice(&[0; 3]);
+
+        let result = vault.decrypt_bytes(&bytes, &context);
+        assert!(matches!(result, Err(Error::InvalidHeader(_))));
+        assert!(matches!(Ciphertext::from_bytes(bytes), Err(Error::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn test_vault_decrypt_bytes_arbitrary_input_never_panics() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+        let valid = vault.encrypt(b"alice@example.com", &context).unwrap().into_bytes();
+
+        // Every truncation and every single-byte corruption must fail cleanly
+        for len in 0..valid.len() {
+            assert!(vault.decrypt_bytes(&valid[..len], &context).is_err());
+        }
+        for i in 0..valid.len() {
+            let mut corrupted = valid.clone();
+            corrupted[i] ^= 0xFF;
+            let _ = vault.decrypt_bytes(&corrupted, &context);
+        }
+    }
+
+    #[test]
+    fn test_vault_decrypt_bytes_round_trip() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+        let stored: Vec<u8> = ciphertext.into();
+
+        let decrypted = vault.decrypt_bytes(&stored, &context).unwrap();
+        assert_eq!(decrypted, b"alice@example.com");
+
+        // Plaintext passed where ciphertext is expected is rejected up front
+        let result = vault.decrypt_bytes(b"alice@example.com", &context);
+        assert!(matches!(result, Err(Error::UnsupportedVersion { .. })));
+    }
+
+    #[test]
+    fn test_vault_packed_ciphertexts_split() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+        let plaintexts: [&[u8]; 3] = [b"alice@example.com", b"", b"carol@example.com"];
+
+        let packed: Vec<u8> = plaintexts
+            .iter()
+            .flat_map(|plaintext| vault.encrypt(plaintext, &context).unwrap().into_bytes())
+            .collect();
+
+        let decrypted: Vec<Vec<u8>> = Ciphertext::parse_all(&packed)
+            .map(|ciphertext| vault.decrypt(&ciphertext.unwrap(), &context).unwrap())
+            .collect();
+        assert_eq!(decrypted, plaintexts);
+
+        // A payload that doesn't match its recorded length is rejected before unwrapping
+        let single = vault.encrypt(b"alice", &context).unwrap().into_bytes();
+        let result = vault.decrypt_bytes(&single[..single.len() - 1], &context);
+        assert!(matches!(result, Err(Error::InvalidHeader(_))));
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_vault_decrypt_bytes_bounded_by_payload_len() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+        assert_eq!(
+            ciphertext.header().payload_len().map(|len| len as usize),
+            Some(ciphertext.payload().len())
+        );
+        assert_eq!(ciphertext.version(), PROTOCOL_VERSION);
+
+        // Framed inside a larger structure, trailing bytes aren't consumed
+        let mut framed = ciphertext.as_bytes().to_vec();
+        framed.extend_from_slice(b"next record");
+        assert_eq!(vault.decrypt_bytes(&framed, &context).unwrap(), b"alice@example.com");
+
+        // Blobs written without a recorded length still decrypt to end of buffer
+        let legacy_header = EncryptionHeader::new(
+            ciphertext.kek_id(),
+            ciphertext.header().wrapped_dek().to_vec(),
+            HeaderFlags::empty(),
+            ciphertext.header().nonce().to_vec(),
+        );
+        let mut legacy = legacy_header.to_bytes().unwrap();
+        assert_eq!(legacy_header.payload_len(), None);
+        legacy.extend_from_slice(ciphertext.payload());
+        assert_eq!(vault.decrypt_bytes(&legacy, &context).unwrap(), b"alice@example.com");
+    }
+
+    #[test]
+    fn test_vault_seeded_rng_is_reproducible() {
+        use rand_chacha::rand_core::SeedableRng;
+        use rand_chacha::ChaCha20Rng;
+
+        let context = EncryptionContext::new("users", "email");
+        let encrypt_seeded = |seed: u64| {
+            Vault::builder(MockKeyProvider::new())
+                .rng(ChaCha20Rng::seed_from_u64(seed))
+                .clock(|| 1_700_000_000_000)
+                .build()
+                .encrypt(b"alice@example.com", &context)
+                .unwrap()
+        };
+
+        let first = encrypt_seeded(7);
+        let second = encrypt_seeded(7);
+        assert_eq!(first.as_bytes(), second.as_bytes());
+        assert_ne!(first.as_bytes(), encrypt_seeded(8).as_bytes());
+
+        // The nonce is drawn after the DEK, both from the seeded stream
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let mut expected = [0u8; DEK_SIZE + NONCE_SIZE];
+        rng.fill_bytes(&mut expected);
+        assert_eq!(first.header().nonce(), &expected[DEK_SIZE..]);
+
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        assert_eq!(vault.decrypt(&first, &context).unwrap(), b"alice@example.com");
+
+        // Clones share the generator, so they never repeat each other's nonces
+        let vault = vault.with_rng(ChaCha20Rng::seed_from_u64(7));
+        let clone = vault.clone();
+        let a = vault.encrypt(b"x", &context).unwrap();
+        let b = clone.encrypt(b"x", &context).unwrap();
+        assert_ne!(a.header().nonce(), b.header().nonce());
+    }
+
+    #[test]
+    fn test_vault_share_with_recipient_kek() {
+        let owner = MockKeyProvider::new();
+        owner.keks.lock().unwrap().insert("partner_kek".to_string(), SecretVec::new(vec![9; 32]));
+        let vault = Vault::new(owner, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        // The partner's provider holds only the partner KEK
+        let partner = MockKeyProvider::new();
+        {
+            let mut keks = partner.keks.lock().unwrap();
+            keks.clear();
+            keks.insert("partner_kek".to_string(), SecretVec::new(vec![9; 32]));
+        }
+        let partner_vault = Vault::new(partner, CipherMode::default());
+
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+        let original = ciphertext.as_bytes().to_vec();
+        let shared = vault.share(ciphertext.as_bytes(), "partner_kek").unwrap();
+
+        assert_eq!(shared.kek_id, "partner_kek");
+        assert_eq!(ciphertext.as_bytes(), original.as_slice());
+        assert!(partner_vault.decrypt(&ciphertext, &context).is_err());
+
+        // Swapping the shared DEK into the header is enough for the partner
+        let header = ciphertext.header().rewrapped(shared.kek_id, shared.encrypted_dek);
+        let for_partner = Ciphertext::from_parts(header, ciphertext.payload()).unwrap();
+        assert_eq!(for_partner.header().nonce(), ciphertext.header().nonce());
+        assert_eq!(partner_vault.decrypt(&for_partner, &context).unwrap(), b"alice@example.com");
+
+        // The owner can still read the original
+        assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), b"alice@example.com");
+
+        assert!(matches!(
+            vault.share(ciphertext.as_bytes(), "unknown_kek"),
+            Err(Error::KeyProvider(KeyProviderError::KekNotFound(_)))
+        ));
+    }
+
+    #[test]
+    fn test_vault_rejects_plaintext_over_framing_limit() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        assert_eq!(MAX_PLAINTEXT_LEN + TAG_SIZE, u32::MAX as usize);
+        assert!(check_plaintext_len(MAX_PLAINTEXT_LEN).is_ok());
+        assert!(matches!(
+            check_plaintext_len(MAX_PLAINTEXT_LEN + 1),
+            Err(Error::PayloadTooLarge { len, max }) if len == MAX_PLAINTEXT_LEN + 1
+                && max == MAX_PLAINTEXT_LEN
+        ));
+
+        // The size estimator applies the same limit without allocating
+        let header_len = vault.ciphertext_len(0, &context).unwrap() - TAG_SIZE;
+        assert_eq!(
+            vault.ciphertext_len(MAX_PLAINTEXT_LEN, &context).unwrap(),
+            header_len + u32::MAX as usize
+        );
+        assert!(matches!(
+            vault.ciphertext_len(MAX_PLAINTEXT_LEN + 1, &context),
+            Err(Error::PayloadTooLarge { .. })
+        ));
+        assert!(matches!(
+            vault.ciphertext_len(usize::MAX, &context),
+            Err(Error::PayloadTooLarge { .. })
+        ));
+
+        // The check runs before the provider is asked for anything
+        let wraps = vault.provider.wrap_calls.load(Ordering::SeqCst);
+        let _ = vault.ciphertext_len(MAX_PLAINTEXT_LEN + 1, &context);
+        assert_eq!(vault.provider.wrap_calls.load(Ordering::SeqCst), wraps);
+    }
+
+    #[test]
+    fn test_vault_decrypt_chunk_random_access() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("documents", "body");
+        let plaintext: Vec<u8> = (0..18u8).collect();
+
+        let (header, chunks) = vault.encrypt_chunked(&plaintext, 4, &context).unwrap();
+        assert_eq!(chunks.len(), 5);
+        assert_eq!(chunks[4].len(), 2 + TAG_SIZE);
+
+        // Chunk 3 alone, without the others
+        let unwraps = vault.provider.unwrap_calls.load(Ordering::SeqCst);
+        assert_eq!(
+            vault.decrypt_chunk(&header, 3, &chunks[3], &context).unwrap(),
+            [12, 13, 14, 15]
+        );
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), unwraps + 1);
+        assert_eq!(vault.decrypt_chunk(&header, 4, &chunks[4], &context).unwrap(), [16, 17]);
+
+        assert_eq!(vault.decrypt_chunked(&header, &chunks, &context).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_vault_decrypt_chunk_rejects_wrong_index() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::Aes128Gcm);
+        let context = EncryptionContext::new("documents", "body");
+        let plaintext = [7u8; 20];
+
+        let (header, chunks) = vault.encrypt_chunked(&plaintext, 4, &context).unwrap();
+
+        // Identical plaintext chunks still only decrypt at their own index
+        for wrong in [0, 2, 4, 5] {
+            let result = vault.decrypt_chunk(&header, wrong, &chunks[3], &context);
+            assert!(matches!(result, Err(Error::AuthenticationFailed)), "index {wrong}");
+        }
+        let other = EncryptionContext::new("documents", "title");
+        assert!(vault.decrypt_chunk(&header, 3, &chunks[3], &other).is_err());
+
+        // Whole-blob decryption catches reordering and truncation
+        let mut swapped = chunks.clone();
+        swapped.swap(1, 2);
+        assert!(vault.decrypt_chunked(&header, &swapped, &context).is_err());
+        assert!(vault.decrypt_chunked(&header, &chunks[..4], &context).is_err());
+        assert!(vault.decrypt_chunked(&header, &[], &context).is_err());
+
+        // Chunks are not single-shot ciphertexts
+        assert!(vault.decrypt_detached(&header, &chunks[0], &context).is_err());
+    }
+
+    #[test]
+    fn test_vault_chunked_aad_binds_first_chunk() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("files", "content");
+        let plaintext: Vec<u8> = (0..18u8).collect();
+        let manifest = b"{\"name\":\"report.pdf\",\"owner\":42}";
+
+        let (header, chunks) = vault
+            .encrypt_chunked_with_aad(&plaintext, 4, &context, StreamAad::Bytes(manifest))
+            .unwrap();
+
+        let aad = StreamAad::Bytes(manifest);
+        assert_eq!(
+            vault.decrypt_chunked_with_aad(&header, &chunks, &context, aad).unwrap(),
+            plaintext
+        );
+
+        // A precomputed digest stands in for the data
+        let digest = StreamAad::Sha256Digest(Sha256::digest(manifest).into());
+        assert_eq!(
+            vault.decrypt_chunked_with_aad(&header, &chunks, &context, digest).unwrap(),
+            plaintext
+        );
+
+        // Only chunk 0 carries the AAD; later chunks still decrypt on their own
+        assert_eq!(
+            vault.decrypt_chunk(&header, 3, &chunks[3], &context).unwrap(),
+            [12, 13, 14, 15]
+        );
+        assert_eq!(
+            vault.decrypt_chunk_with_aad(&header, 0, &chunks[0], &context, aad).unwrap(),
+            [0, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_vault_chunked_aad_mismatch_fails_first_chunk() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("files", "content");
+        let plaintext = [9u8; 10];
+
+        let (header, chunks) = vault
+            .encrypt_chunked_with_aad(&plaintext, 4, &context, StreamAad::Bytes(b"manifest v1"))
+            .unwrap();
+
+        let wrong = StreamAad::Bytes(b"manifest v2");
+        let result = vault.decrypt_chunked_with_aad(&header, &chunks, &context, wrong);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+        let result = vault.decrypt_chunk_with_aad(&header, 0, &chunks[0], &context, wrong);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+
+        // Dropping the AAD fails too, and so does adding one to a plain stream
+        assert!(vault.decrypt_chunked(&header, &chunks, &context).is_err());
+        let (plain_header, plain_chunks) = vault.encrypt_chunked(&plaintext, 4, &context).unwrap();
+        let empty = StreamAad::Bytes(&[]);
+        assert!(vault
+            .decrypt_chunked_with_aad(&plain_header, &plain_chunks, &context, empty)
+            .is_err());
+    }
+
+    #[test]
+    fn test_vault_encrypt_chunked_edge_cases() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("documents", "body");
+
+        let (header, chunks) = vault.encrypt_chunked(b"", 4, &context).unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert!(vault.decrypt_chunked(&header, &chunks, &context).unwrap().is_empty());
+
+        let (header, chunks) = vault.encrypt_chunked(b"abcd", 4, &context).unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(vault.decrypt_chunked(&header, &chunks, &context).unwrap(), b"abcd");
+
+        assert!(matches!(
+            vault.encrypt_chunked(b"abcd", 0, &context),
+            Err(Error::EncryptionFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_cipher_max_message_bytes() {
+        assert_eq!(CipherMode::ChaCha20Poly1305.max_message_bytes(), 274_877_906_880);
+        assert_eq!(CipherMode::XChaCha20Poly1305.max_message_bytes(), 274_877_906_880);
+        assert_eq!(CipherMode::Aes256Gcm.max_message_bytes(), 68_719_476_704);
+        assert_eq!(CipherMode::Aes128Gcm.max_message_bytes(), 68_719_476_704);
+        assert_eq!(CipherMode::Aes256GcmSiv.max_message_bytes(), 68_719_476_736);
+
+        // Single ciphertexts stay well within every cipher's limit
+        for mode in CipherMode::ALL {
+            assert!(MAX_PLAINTEXT_LEN as u64 + (TAG_SIZE as u64) < mode.max_message_bytes());
+        }
+    }
+
+    #[test]
+    fn test_vault_encrypt_chunked_stops_at_dek_limit() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::Aes128Gcm);
+        let context = EncryptionContext::new("documents", "body");
+        let plaintext = [7u8; 101];
+
+        // Pretend the cipher allows only 100 bytes per DEK
+        let (header, chunks) =
+            vault.seal_chunks(&plaintext[..100], 10, &context, None, 100).unwrap();
+        assert_eq!(vault.decrypt_chunked(&header, &chunks, &context).unwrap(), &plaintext[..100]);
+        assert_eq!(vault.provider.wrap_calls.load(Ordering::SeqCst), 1);
+
+        let result = vault.seal_chunks(&plaintext, 10, &context, None, 100);
+        assert!(matches!(result, Err(Error::PayloadTooLarge { len: 101, max: 100 })));
+
+        // Refused before a DEK is generated or wrapped
+        assert_eq!(vault.provider.wrap_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_vault_decrypt_stream_verified_round_trip() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("documents", "body");
+        let plaintext: Vec<u8> = (0..=255).collect();
+
+        let (header, chunks) = vault.encrypt_chunked(&plaintext, 10, &context).unwrap();
+        let mut out = Vec::new();
+        let written =
+            vault.decrypt_stream_verified(&header, chunks, &context, 1024, &mut out).unwrap();
+
+        assert_eq!(written, plaintext.len());
+        assert_eq!(out, plaintext);
+    }
+
+    #[test]
+    fn test_vault_decrypt_stream_verified_releases_nothing_on_failure() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("documents", "body");
+        let plaintext = vec![7u8; 100];
+        let (header, chunks) = vault.encrypt_chunked(&plaintext, 10, &context).unwrap();
+
+        // A corrupted last chunk withholds the nine valid chunks before it
+        let mut corrupted = chunks.clone();
+        let last = corrupted.last_mut().unwrap();
+        last[0] ^= 0x01;
+        let mut out = Vec::new();
+        let result = vault.decrypt_stream_verified(&header, corrupted, &context, 1024, &mut out);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+        assert!(out.is_empty());
+
+        // So does a stream cut short before the final chunk
+        let truncated = chunks[..chunks.len() - 1].to_vec();
+        let result = vault.decrypt_stream_verified(&header, truncated, &context, 1024, &mut out);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+        assert!(out.is_empty());
+
+        // And a stream larger than the buffer
+        let result = vault.decrypt_stream_verified(&header, chunks, &context, 99, &mut out);
+        assert!(matches!(result, Err(Error::DecryptionFailed(_))));
+        assert!(out.is_empty());
+
+        let result = vault.decrypt_stream_verified(&header, Vec::new(), &context, 99, &mut out);
+        assert!(matches!(result, Err(Error::DecryptionFailed(_))));
+    }
+
+    #[test]
+    fn test_vault_encrypt_with_dek_round_trip() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("documents", "body");
+        let dek = SecretVec::new(vec![0x42; DEK_SIZE]);
+
+        let first = vault.encrypt_with_dek(b"document one", &context, &dek).unwrap();
+        let second = vault.encrypt_with_dek(b"document two", &context, &dek).unwrap();
+
+        // Both decrypt normally, with the supplied DEK in the header
+        assert_eq!(vault.decrypt(&first, &context).unwrap(), b"document one");
+        assert_eq!(vault.decrypt(&second, &context).unwrap(), b"document two");
+        assert_eq!(first.header().kek_id(), "test_kek");
+        let unwrapped =
+            vault.provider.unwrap_dek("test_kek", first.header().wrapped_dek()).unwrap();
+        assert_eq!(unwrapped.expose_secret(), dek.expose_secret());
+        assert_ne!(first.header().nonce(), second.header().nonce());
+
+        // The same DEK outside the vault opens the payload
+        let cipher = ChaCha20Poly1305::new_from_slice(dek.expose_secret()).unwrap();
+        let nonce = chacha20poly1305::Nonce::from_slice(first.header().nonce());
+        let aad = context.canonical_bytes();
+        let payload = chacha20poly1305::aead::Payload { msg: first.payload(), aad: &aad };
+        assert_eq!(cipher.decrypt(nonce, payload).unwrap(), b"document one");
+    }
+
+    #[test]
+    fn test_vault_encrypt_with_dek_rejects_wrong_length() {
+        let context = EncryptionContext::new("documents", "body");
+        let aes128 = Vault::new(MockKeyProvider::new(), CipherMode::Aes128Gcm);
+
+        let result = aes128.encrypt_with_dek(b"data", &context, &SecretVec::new(vec![1; 32]));
+        assert!(matches!(result, Err(Error::InvalidKeyLength { expected: 16, actual: 32 })));
+        assert_eq!(aes128.provider.wrap_calls.load(Ordering::SeqCst), 0);
+
+        let ciphertext = aes128.encrypt_with_dek(b"data", &context, &SecretVec::new(vec![1; 16]));
+        assert_eq!(aes128.decrypt(&ciphertext.unwrap(), &context).unwrap(), b"data");
+    }
+
+    #[test]
+    fn test_vault_detached_round_trip() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("users", "avatar");
+
+        let (header, payload) = vault.encrypt_detached(b"large blob", &context).unwrap();
+        assert_eq!(EncryptionHeader::from_bytes(&header).unwrap().1, header.len());
+
+        let decrypted = vault.decrypt_detached(&header, &payload, &context).unwrap();
+        assert_eq!(decrypted, b"large blob");
+
+        // The halves concatenate into a regular ciphertext
+        let joined = [header.as_slice(), payload.as_slice()].concat();
+        assert_eq!(vault.decrypt_bytes(&joined, &context).unwrap(), b"large blob");
+
+        // A header with anything appended is rejected
+        let result = vault.decrypt_detached(&joined, &payload, &context);
+        assert!(matches!(result, Err(Error::InvalidHeader(_))));
+
+        // A header is bound to its own payload
+        let (_, other_payload) = vault.encrypt_detached(b"large blob", &context).unwrap();
+        let result = vault.decrypt_detached(&header, &other_payload, &context);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+    }
+
+    #[test]
+    fn test_vault_rewrap_detached_changes_only_header() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("users", "avatar");
+
+        let (header, payload) = vault.encrypt_detached(b"large blob", &context).unwrap();
+        let payload_before = payload.clone();
+        let new_kek_id = vault.provider.create_kek().unwrap();
+
+        let new_header = vault.rewrap_detached(&header, &new_kek_id).unwrap();
+
+        assert_ne!(new_header, header);
+        let (parsed, _) = EncryptionHeader::from_bytes(&new_header).unwrap();
+        assert_eq!(parsed.kek_id(), new_kek_id);
+        assert_eq!(payload, payload_before);
+
+        let decrypted = vault.decrypt_detached(&new_header, &payload, &context).unwrap();
+        assert_eq!(decrypted, b"large blob");
+    }
+
+    #[test]
+    fn test_vault_health_check_delegates_to_provider() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        assert!(vault.health_check().is_ok());
+    }
+
+    #[test]
+    fn test_vault_builder_round_trip() {
+        let builder = Vault::builder(MockKeyProvider::new())
+            .cipher(CipherMode::Aes128Gcm)
+            .counter_nonces()
+            .clock(|| 1_700_000_000_000)
+            .max_decompressed(1024);
+        #[cfg(feature = "dek-cache")]
+        let builder = builder.dek_cache(8);
+        let vault = builder.build();
+        let context = EncryptionContext::new("users", "email");
+
+        let first = vault.encrypt(b"alice@example.com", &context).unwrap();
+        let second = vault.encrypt_compressed(&[b'a'; 512], &context).unwrap();
+
+        assert_eq!(first.header().cipher_id(), Some(CipherMode::Aes128Gcm.to_id()));
+        assert_eq!(first.header().created_at(), Some(1_700_000_000_000));
+        assert_eq!(first.header().nonce(), &[0; NONCE_SIZE]);
+        assert_eq!(second.header().nonce()[NONCE_SIZE - 1], 1);
+
+        assert_eq!(vault.decrypt(&first, &context).unwrap(), b"alice@example.com");
+        assert_eq!(vault.decrypt(&second, &context).unwrap(), vec![b'a'; 512]);
+
+        // The decompression limit was applied
+        let too_big = vault.encrypt_compressed(&[b'a'; 2048], &context).unwrap();
+        assert!(vault.decrypt(&too_big, &context).is_err());
+
+        #[cfg(feature = "dek-cache")]
+        {
+            vault.decrypt(&first, &context).unwrap();
+            assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), 3);
+        }
+    }
+
+    #[test]
+    fn test_vault_builder_defaults_match_new() {
+        let vault = Vault::builder(MockKeyProvider::new()).build();
+
+        assert_eq!(vault.cipher_mode, CipherMode::default());
+        assert_eq!(vault.max_decompressed_size, DEFAULT_MAX_DECOMPRESSED_SIZE);
+        assert!(vault.nonce_counter.is_none());
+    }
+
+    #[test]
+    fn test_vault_debug_omits_provider() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+
+        let debug = format!("{vault:?}");
+        assert!(debug.contains("ChaCha20Poly1305"));
+        assert!(!debug.contains("42, 42"));
+        assert!(!debug.contains("test_kek"));
+    }
+
+    #[test]
+    fn test_vault_clone() {
+        let provider = MockKeyProvider::new();
+        let vault1 = Vault::new(provider, CipherMode::default());
+        let vault2 = vault1.clone();
+
+        let context = EncryptionContext::new("users", "email");
+        let plaintext = b"test";
+
+        let ciphertext = vault1.encrypt(plaintext, &context).unwrap();
+        let decrypted = vault2.decrypt(&ciphertext, &context).unwrap();
+
+        assert_eq!(plaintext, &decrypted[..]);
+    }
+
+    #[test]
+    fn test_vault_encrypt_batch_wraps_once() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let plaintexts: Vec<Vec<u8>> =
+            (0..1000).map(|i| format!("user{i}@example.com").into_bytes()).collect();
+        let items: Vec<(&[u8], &EncryptionContext)> =
+            plaintexts.iter().map(|p| (p.as_slice(), &context)).collect();
+
+        let ciphertexts = vault.encrypt_batch(&items).expect("Batch encryption failed");
+
+        // A single DEK was wrapped for the whole batch
+        assert_eq!(vault.provider.wrap_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(ciphertexts.len(), plaintexts.len());
+
+        // Every blob carries its own header with a distinct nonce
+        let mut nonces = HashSet::new();
+        for (plaintext, ciphertext) in plaintexts.iter().zip(&ciphertexts) {
+            let header = ciphertext.header();
+            assert!(nonces.insert(header.nonce().to_vec()), "Nonce reused within batch");
+
+            let decrypted = vault.decrypt(ciphertext, &context).unwrap();
+            assert_eq!(plaintext, &decrypted);
+        }
+    }
+
+    #[test]
+    fn test_vault_encrypt_batch_mixed_contexts() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+
+        let context1 = EncryptionContext::new("users", "email");
+        let context2 = EncryptionContext::new("users", "name").with_tenant("tenant_1");
+
+        let items: Vec<(&[u8], &EncryptionContext)> =
+            vec![(b"alice@example.com", &context1), (b"Alice", &context2)];
+        let ciphertexts = vault.encrypt_batch(&items).unwrap();
+
+        assert_eq!(vault.decrypt(&ciphertexts[0], &context1).unwrap(), b"alice@example.com");
+        assert_eq!(vault.decrypt(&ciphertexts[1], &context2).unwrap(), b"Alice");
+
+        // Contexts are still bound per item
+        let result = vault.decrypt(&ciphertexts[0], &context2);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+    }
+
+    /// Keeps a separate KEK per tenant, named `kek_{tenant}`.
+    struct TenantKeyProvider(MockKeyProvider);
+
+    impl TenantKeyProvider {
+        fn new(tenants: &[&str]) -> Self {
+            let inner = MockKeyProvider::new();
+            for (i, tenant) in tenants.iter().enumerate() {
+                let kek = SecretVec::new(vec![u8::try_from(i).unwrap() + 1; 32]);
+                inner.keks.lock().unwrap().insert(format!("kek_{tenant}"), kek);
+            }
+            Self(inner)
+        }
+    }
+
+    impl KeyProvider for TenantKeyProvider {
+        fn create_kek(&self) -> Result<String, KeyProviderError> {
+            self.0.create_kek()
+        }
+
+        fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+            self.0.current_kek_id()
+        }
+
+        fn current_kek_id_for_tenant(&self, tenant: &str) -> Result<String, KeyProviderError> {
+            let kek_id = format!("kek_{tenant}");
+            if self.0.keks.lock().unwrap().contains_key(&kek_id) {
+                Ok(kek_id)
+            } else {
+                Err(KeyProviderError::NoActiveKek)
+            }
+        }
+
+        fn wrap_dek(&self, kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+            self.0.wrap_dek(kek_id, dek)
         }
 
         fn unwrap_dek(
@@ -271,156 +3916,1200 @@ mod tests {
             kek_id: &str,
             wrapped_dek: &[u8],
         ) -> Result<SecretVec<u8>, KeyProviderError> {
-            let keks = self.keks.lock().unwrap();
-            let kek = keks
-                .get(kek_id)
-                .ok_or_else(|| KeyProviderError::KekNotFound(kek_id.to_string()))?;
+            self.0.unwrap_dek(kek_id, wrapped_dek)
+        }
+    }
+
+    #[test]
+    fn test_vault_uses_tenant_kek() {
+        let vault = Vault::new(TenantKeyProvider::new(&["acme", "globex"]), CipherMode::default());
+        let acme = EncryptionContext::new("users", "email").with_tenant("acme");
+        let globex = EncryptionContext::new("users", "email").with_tenant("globex");
+        let shared = EncryptionContext::new("users", "email");
+
+        let acme_ct = vault.encrypt(b"alice@acme.test", &acme).unwrap();
+        let globex_ct = vault.encrypt(b"bob@globex.test", &globex).unwrap();
+        let shared_ct = vault.encrypt(b"carol@example.com", &shared).unwrap();
+
+        assert_eq!(acme_ct.kek_id(), "kek_acme");
+        assert_eq!(globex_ct.kek_id(), "kek_globex");
+        assert_eq!(shared_ct.kek_id(), "test_kek");
+
+        assert_eq!(vault.decrypt(&acme_ct, &acme).unwrap(), b"alice@acme.test");
+        assert_eq!(vault.decrypt(&globex_ct, &globex).unwrap(), b"bob@globex.test");
+
+        // Revoking one tenant's KEK leaves the other tenant readable
+        vault.provider.0.keks.lock().unwrap().remove("kek_acme");
+        assert!(vault.decrypt(&acme_ct, &acme).is_err());
+        assert_eq!(vault.decrypt(&globex_ct, &globex).unwrap(), b"bob@globex.test");
+
+        // A tenant without a KEK can't encrypt at all
+        let initech = EncryptionContext::new("users", "email").with_tenant("initech");
+        assert!(matches!(
+            vault.encrypt(b"x", &initech),
+            Err(Error::KeyProvider(KeyProviderError::NoActiveKek))
+        ));
+    }
+
+    #[test]
+    fn test_vault_encrypt_batch_one_dek_per_tenant() {
+        let vault = Vault::new(TenantKeyProvider::new(&["acme", "globex"]), CipherMode::default());
+        let acme = EncryptionContext::new("users", "email").with_tenant("acme");
+        let globex = EncryptionContext::new("users", "email").with_tenant("globex");
+
+        let items: Vec<(&[u8], &EncryptionContext)> =
+            vec![(b"a1", &acme), (b"g1", &globex), (b"a2", &acme)];
+        let ciphertexts = vault.encrypt_batch(&items).unwrap();
+
+        assert_eq!(vault.provider.0.wrap_calls.load(Ordering::SeqCst), 2);
+        let kek_ids: Vec<&str> = ciphertexts.iter().map(Ciphertext::kek_id).collect();
+        assert_eq!(kek_ids, ["kek_acme", "kek_globex", "kek_acme"]);
+        assert_eq!(ciphertexts[0].header().wrapped_dek(), ciphertexts[2].header().wrapped_dek());
+        assert_eq!(vault.decrypt(&ciphertexts[1], &globex).unwrap(), b"g1");
+    }
+
+    #[test]
+    fn test_vault_encrypt_batch_empty() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+
+        let ciphertexts = vault.encrypt_batch(&[]).unwrap();
+
+        assert!(ciphertexts.is_empty());
+        assert_eq!(vault.provider.wrap_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[cfg(feature = "dek-cache")]
+    #[test]
+    fn test_vault_dek_cache_skips_provider_unwrap() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default()).with_dek_cache(16);
+        let context = EncryptionContext::new("users", "email");
+
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+
+        vault.decrypt(&ciphertext, &context).unwrap();
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), 1);
+
+        // Second decrypt of the same blob is served from the cache
+        let decrypted = vault.decrypt(&ciphertext, &context).unwrap();
+        assert_eq!(decrypted, b"alice@example.com");
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), 1);
+
+        // Clearing the cache forces the provider to be consulted again
+        vault.clear_dek_cache();
+        vault.decrypt(&ciphertext, &context).unwrap();
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[cfg(feature = "dek-cache")]
+    #[test]
+    fn test_vault_dek_cache_keyed_by_tenant() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default()).with_dek_cache(16);
+        let acme = EncryptionContext::new("users", "email").with_tenant("acme");
+        let globex = EncryptionContext::new("users", "email").with_tenant("globex");
+        let acme_ct = vault.encrypt(b"alice@acme.test", &acme).unwrap();
+        let globex_ct = vault.encrypt(b"bob@globex.test", &globex).unwrap();
+
+        vault.decrypt(&acme_ct, &acme).unwrap();
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), 1);
+
+        // acme's wrapped DEK in globex's header misses the cache, so the
+        // provider gets to check the tenant binding
+        let pasted =
+            globex_ct.header().rewrapped("test_kek", acme_ct.header().wrapped_dek().to_vec());
+        let tampered = Ciphertext::from_parts(pasted, acme_ct.payload()).unwrap();
+        let result = vault.decrypt(&tampered, &globex);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[cfg(feature = "dek-cache")]
+    #[test]
+    fn test_vault_dek_cache_shared_across_batch() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default()).with_dek_cache(16);
+        let context = EncryptionContext::new("users", "email");
+
+        let items: Vec<(&[u8], &EncryptionContext)> =
+            vec![(b"a", &context), (b"b", &context), (b"c", &context)];
+        let ciphertexts = vault.encrypt_batch(&items).unwrap();
+
+        for ciphertext in &ciphertexts {
+            vault.decrypt(ciphertext, &context).unwrap();
+        }
+
+        // All blobs share one wrapped DEK, so only the first decrypt unwraps
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(feature = "dek-cache")]
+    #[test]
+    fn test_vault_dek_cache_evicts_least_recently_used() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default()).with_dek_cache(1);
+        let context = EncryptionContext::new("users", "email");
+
+        let ciphertext1 = vault.encrypt(b"first", &context).unwrap();
+        let ciphertext2 = vault.encrypt(b"second", &context).unwrap();
+
+        vault.decrypt(&ciphertext1, &context).unwrap();
+        vault.decrypt(&ciphertext2, &context).unwrap();
+        vault.decrypt(&ciphertext1, &context).unwrap();
+
+        // Capacity of one means the first DEK was evicted by the second
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_vault_deriving_dek_stable_per_context() {
+        let vault = Vault::deriving(MockKeyProvider::new(), CipherMode::default());
+        let email = EncryptionContext::new("users", "email");
+        let phone = EncryptionContext::new("users", "phone");
+
+        let first = vault.encrypt(b"alice@example.com", &email).unwrap();
+        let second = vault.encrypt(b"bob@example.com", &email).unwrap();
+        let third = vault.encrypt(b"555-0100", &phone).unwrap();
+
+        // One root key is wrapped once and shared by every context
+        assert_eq!(vault.provider.wrap_calls.load(Ordering::SeqCst), 1);
+        assert!(first.header().derives_dek());
+        assert_eq!(first.version(), crate::header::MAX_PROTOCOL_VERSION);
+        assert_eq!(first.header().wrapped_dek(), second.header().wrapped_dek());
+        assert_eq!(first.header().wrapped_dek(), third.header().wrapped_dek());
+        assert_eq!(vault.ciphertext_len(17, &email).unwrap(), first.as_bytes().len());
+
+        // The derived DEK is the same for a context and differs across contexts
+        let envelope = vault.new_envelope(&email, 1, 0).unwrap();
+        let email_dek = vault.derived_dek(&envelope, &email).unwrap().unwrap();
+        let again = vault.derived_dek(&envelope, &email).unwrap().unwrap();
+        let phone_dek = vault.derived_dek(&envelope, &phone).unwrap().unwrap();
+        assert_eq!(email_dek.expose_secret(), again.expose_secret());
+        assert_ne!(email_dek.expose_secret(), phone_dek.expose_secret());
+        assert_ne!(email_dek.expose_secret(), envelope.dek.expose_secret());
+
+        // A plain vault over the same keys decrypts derived ciphertexts
+        assert_eq!(vault.decrypt(&first, &email).unwrap(), b"alice@example.com");
+        let plain = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        assert_eq!(plain.decrypt(&second, &email).unwrap(), b"bob@example.com");
+        assert_eq!(plain.decrypt(&third, &phone).unwrap(), b"555-0100");
+        assert!(plain.decrypt(&third, &email).is_err());
+
+        // Chunked streams get a DEK of their own, not a derived one
+        let (header, chunks) = vault.encrypt_chunked(&[7u8; 100], 32, &email).unwrap();
+        assert!(!crate::header::peek_header(&header).unwrap().derives_dek());
+        assert_eq!(plain.decrypt_chunked(&header, &chunks, &email).unwrap(), vec![7u8; 100]);
+    }
+
+    #[test]
+    fn test_vault_deriving_chunked_streams_use_fresh_deks() {
+        let vault = Vault::builder(MockKeyProvider::new()).deriving().counter_nonces().build();
+        let context = EncryptionContext::new("files", "body");
+        let first_plaintext = [0x11u8; 64];
+        let second_plaintext = [0x22u8; 64];
+
+        let single = vault.encrypt(&first_plaintext, &context).unwrap();
+        let (first_header, first) = vault.encrypt_chunked(&first_plaintext, 32, &context).unwrap();
+        let (second_header, second) =
+            vault.encrypt_chunked(&second_plaintext, 32, &context).unwrap();
+
+        // Each stream wraps its own DEK rather than reusing the root key
+        let (first_parsed, _) = EncryptionHeader::from_bytes(&first_header).unwrap();
+        let (second_parsed, _) = EncryptionHeader::from_bytes(&second_header).unwrap();
+        assert!(!first_parsed.derives_dek());
+        assert_ne!(first_parsed.wrapped_dek(), second_parsed.wrapped_dek());
+        assert_ne!(first_parsed.wrapped_dek(), single.header().wrapped_dek());
+
+        // Equal chunk nonces under different keys leak nothing about the plaintexts
+        let xor = |a: &[u8], b: &[u8]| -> Vec<u8> { a.iter().zip(b).map(|(x, y)| x ^ y).collect() };
+        assert_ne!(
+            xor(&first[0][..32], &second[0][..32]),
+            xor(&first_plaintext[..32], &second_plaintext[..32])
+        );
+
+        assert_eq!(
+            vault.decrypt_chunked(&first_header, &first, &context).unwrap(),
+            first_plaintext
+        );
+    }
+
+    #[test]
+    fn test_vault_deriving_root_key_retired_at_budget() {
+        let mut vault = Vault::deriving(MockKeyProvider::new(), CipherMode::default());
+        vault.root_keys =
+            Some(Arc::new(RootKeys { roots: Mutex::default(), max_messages: 2, max_bytes: 10 }));
+        let context = EncryptionContext::new("users", "email");
+
+        let first = vault.encrypt(b"abc", &context).unwrap();
+        let second = vault.encrypt(b"def", &context).unwrap();
+        let third = vault.encrypt(b"ghi", &context).unwrap();
+        assert_eq!(first.header().wrapped_dek(), second.header().wrapped_dek());
+        assert_ne!(second.header().wrapped_dek(), third.header().wrapped_dek());
+
+        // The byte budget retires a root key too
+        let fourth = vault.encrypt(b"0123456789", &context).unwrap();
+        assert_ne!(third.header().wrapped_dek(), fourth.header().wrapped_dek());
+        assert_eq!(vault.provider.wrap_calls.load(Ordering::SeqCst), 3);
+
+        for (ciphertext, plaintext) in
+            [(first, &b"abc"[..]), (third, b"ghi"), (fourth, b"0123456789")]
+        {
+            assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), plaintext);
+        }
+    }
+
+    #[cfg(feature = "dek-cache")]
+    #[test]
+    fn test_vault_deriving_consults_dek_cache() {
+        let vault =
+            Vault::deriving(MockKeyProvider::new(), CipherMode::default()).with_dek_cache(16);
+        let context = EncryptionContext::new("users", "email");
+
+        let first = vault.encrypt(b"alice@example.com", &context).unwrap();
+
+        // Replace the cached derived DEK; the next encryption must pick it up
+        let cache = vault.dek_cache.as_ref().unwrap();
+        let bogus = SecretVec::new(vec![9u8; DEK_SIZE]);
+        let info = context.canonical_bytes();
+        cache.insert_derived("test_kek", first.header().wrapped_dek(), &info, &bogus);
+
+        let second = vault.encrypt(b"bob@example.com", &context).unwrap();
+
+        let uncached = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        assert_eq!(uncached.decrypt(&first, &context).unwrap(), b"alice@example.com");
+        assert!(uncached.decrypt(&second, &context).is_err());
+        assert_eq!(vault.decrypt(&second, &context).unwrap(), b"bob@example.com");
+    }
+
+    #[test]
+    fn test_vault_rewrap_rebuilds_header() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+        let new_kek_id = vault.provider.create_kek().unwrap();
+
+        let rewrapped = vault.rewrap(&ciphertext, &new_kek_id).expect("Rewrap failed");
+
+        let (old_header, old_len) = EncryptionHeader::from_bytes(ciphertext.as_bytes()).unwrap();
+        let (new_header, new_len) = EncryptionHeader::from_bytes(rewrapped.as_bytes()).unwrap();
+
+        // Header now points at the new KEK with a freshly wrapped DEK
+        assert_eq!(old_header.kek_id(), "test_kek");
+        assert_eq!(new_header.kek_id(), new_kek_id);
+        assert_ne!(new_header.wrapped_dek(), old_header.wrapped_dek());
+
+        // Everything else is carried over untouched
+        assert_eq!(new_header.version(), old_header.version());
+        assert_eq!(new_header.flags(), old_header.flags());
+        assert_eq!(new_header.nonce(), old_header.nonce());
+        assert_eq!(&rewrapped.as_bytes()[new_len..], &ciphertext.as_bytes()[old_len..]);
+
+        let decrypted = vault.decrypt(&rewrapped, &context).unwrap();
+        assert_eq!(decrypted, b"alice@example.com");
+    }
+
+    #[test]
+    fn test_vault_encrypt_with_kek() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let pinned_kek_id = vault.provider.create_kek().unwrap();
+        assert_ne!(vault.provider.current_kek_id().unwrap(), pinned_kek_id);
+
+        let ciphertext =
+            vault.encrypt_with_kek(b"alice@example.com", &context, &pinned_kek_id).unwrap();
+        assert_eq!(ciphertext.kek_id(), pinned_kek_id);
+        assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), b"alice@example.com");
+
+        // The current KEK is still used by plain encryption
+        assert_eq!(vault.encrypt(b"bob@example.com", &context).unwrap().kek_id(), "test_kek");
+    }
+
+    #[test]
+    fn test_vault_encrypt_with_unknown_kek() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let result = vault.encrypt_with_kek(b"alice@example.com", &context, "missing_kek");
+        assert!(matches!(result, Err(Error::KeyProvider(KeyProviderError::KekNotFound(_)))));
+    }
+
+    #[test]
+    fn test_vault_rewrap_iter() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+        let plaintexts: [&[u8]; 3] = [b"alice", b"bob", b"carol"];
+
+        let new_kek_id = vault.provider.create_kek().unwrap();
+
+        // A lazy source, as when streaming rows from a database
+        let blobs = plaintexts
+            .iter()
+            .map(|plaintext| vault.encrypt(plaintext, &context).unwrap().into_bytes());
+        let mut rewrapped = vault.rewrap_iter(blobs, &new_kek_id);
+
+        // Nothing is rewrapped until the iterator is advanced
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), 0);
+        let first = rewrapped.next().unwrap().unwrap();
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), 1);
+
+        let rewrapped: Vec<Vec<u8>> =
+            std::iter::once(first).chain(rewrapped.map(Result::unwrap)).collect();
+        assert_eq!(rewrapped.len(), 3);
+
+        vault.provider.keks.lock().unwrap().remove("test_kek");
+        for (blob, plaintext) in rewrapped.iter().zip(plaintexts) {
+            let ciphertext = Ciphertext::from_bytes(blob.clone()).unwrap();
+            assert_eq!(ciphertext.kek_id(), new_kek_id);
+            assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn test_vault_rewrap_iter_yields_errors_per_blob() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+        let new_kek_id = vault.provider.create_kek().unwrap();
+
+        let good = vault.encrypt(b"alice", &context).unwrap().into_bytes();
+        let blobs = vec![good.clone(), vec![0xFF, 0x00], good];
+
+        let results: Vec<_> = vault.rewrap_iter(blobs.into_iter(), &new_kek_id).collect();
+
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+    }
+
+    #[test]
+    fn test_vault_rewrap_unknown_kek_fails() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+
+        let result = vault.rewrap(&ciphertext, "missing_kek");
+        assert!(matches!(result, Err(Error::KeyProvider(KeyProviderError::KekNotFound(_)))));
+    }
+
+    #[test]
+    fn test_vault_extra_aad_round_trip() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let ciphertext = vault.encrypt_with_aad(b"alice@example.com", &context, b"row:42").unwrap();
+        let decrypted = vault.decrypt_with_aad(&ciphertext, &context, b"row:42").unwrap();
+
+        assert_eq!(decrypted, b"alice@example.com");
+    }
+
+    #[test]
+    fn test_vault_extra_aad_mismatch_fails() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let ciphertext = vault.encrypt_with_aad(b"alice@example.com", &context, b"row:42").unwrap();
+
+        // Replaying into a different row fails authentication
+        let result = vault.decrypt_with_aad(&ciphertext, &context, b"row:43");
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+
+        // Omitting the extra AAD fails as well
+        let result = vault.decrypt(&ciphertext, &context);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+    }
+
+    #[test]
+    fn test_vault_empty_extra_aad_matches_plain_encrypt() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+        let decrypted = vault.decrypt_with_aad(&ciphertext, &context, &[]).unwrap();
+        assert_eq!(decrypted, b"alice@example.com");
+
+        let ciphertext = vault.encrypt_with_aad(b"alice@example.com", &context, &[]).unwrap();
+        let decrypted = vault.decrypt(&ciphertext, &context).unwrap();
+        assert_eq!(decrypted, b"alice@example.com");
+    }
+
+    #[test]
+    fn test_vault_compressed_round_trip() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("documents", "body");
+
+        let plaintext = r#"{"name":"alice","email":"alice@example.com"}"#.repeat(200);
+        let ciphertext = vault.encrypt_compressed(plaintext.as_bytes(), &context).unwrap();
+
+        let header = ciphertext.header();
+        assert!(header.flags().is_compressed());
+
+        // Redundant JSON shrinks well below its original size
+        assert!(ciphertext.as_bytes().len() < plaintext.len() / 4);
+
+        let decrypted = vault.decrypt(&ciphertext, &context).unwrap();
+        assert_eq!(decrypted, plaintext.as_bytes());
+    }
+
+    #[test]
+    fn test_vault_compressed_incompressible_round_trip() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("files", "blob");
+
+        let mut plaintext = vec![0u8; 4096];
+        OsRng.fill_bytes(&mut plaintext);
+
+        let ciphertext = vault.encrypt_compressed(&plaintext, &context).unwrap();
+        let decrypted = vault.decrypt(&ciphertext, &context).unwrap();
+
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn test_vault_uncompressed_flag_not_set() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+        let header = ciphertext.header();
+
+        assert!(!header.flags().is_compressed());
+    }
+
+    #[test]
+    fn test_vault_decompression_bomb_rejected() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default()).with_max_decompressed_size(1024);
+        let context = EncryptionContext::new("documents", "body");
+
+        // 1 MiB of zeros compresses to around a kilobyte
+        let plaintext = vec![0u8; 1024 * 1024];
+        let ciphertext = vault.encrypt_compressed(&plaintext, &context).unwrap();
+
+        let result = vault.decrypt(&ciphertext, &context);
+        assert!(
+            matches!(result, Err(Error::DecryptionFailed(msg)) if msg.contains("exceeds limit"))
+        );
+
+        // Exactly at the limit is still accepted
+        let ciphertext = vault.encrypt_compressed(&[0u8; 1024], &context).unwrap();
+        assert_eq!(vault.decrypt(&ciphertext, &context).unwrap().len(), 1024);
+    }
+
+    #[test]
+    fn test_vault_stamps_created_at_from_clock() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default()).with_clock(|| 1_700_000_000_000);
+        let context = EncryptionContext::new("users", "email");
+
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+        let header = ciphertext.header();
 
-            // Simple XOR "decryption" for testing (XOR is symmetric)
-            let dek: Vec<u8> = wrapped_dek
-                .iter()
-                .zip(kek.expose_secret().iter().cycle())
-                .map(|(w, k)| w ^ k)
-                .collect();
+        assert_eq!(header.version(), crate::header::PROTOCOL_VERSION);
+        assert_eq!(header.created_at(), Some(1_700_000_000_000));
+        assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), b"alice@example.com");
 
-            drop(keks);
-            Ok(SecretVec::new(dek))
-        }
+        // Rewrapping keeps the original creation time
+        let rewrapped = vault.rewrap(&ciphertext, "test_kek").unwrap();
+        let header = rewrapped.header();
+        assert_eq!(header.created_at(), Some(1_700_000_000_000));
     }
 
     #[test]
-    fn test_vault_encrypt_decrypt_round_trip() {
+    fn test_vault_default_clock_is_current_time() {
         let provider = MockKeyProvider::new();
         let vault = Vault::new(provider, CipherMode::default());
         let context = EncryptionContext::new("users", "email");
 
-        let plaintext = b"alice@example.com";
-        let ciphertext = vault.encrypt(plaintext, &context).expect("Encryption failed");
-        let decrypted = vault.decrypt(&ciphertext, &context).expect("Decryption failed");
+        let before = system_clock();
+        let ciphertext = vault.encrypt(b"data", &context).unwrap();
+        let after = system_clock();
 
-        assert_eq!(plaintext, &decrypted[..]);
+        let header = ciphertext.header();
+        let created_at = header.created_at().unwrap();
+        assert!((before..=after).contains(&created_at));
     }
 
     #[test]
-    fn test_vault_different_plaintexts() {
+    fn test_vault_counter_nonces_distinct() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default()).with_counter_nonces();
+        let context = EncryptionContext::new("users", "email");
+
+        let mut nonces = HashSet::new();
+        for _ in 0..100_000 {
+            let ciphertext = vault.encrypt(b"x", &context).unwrap();
+            let header = ciphertext.header();
+            nonces.insert(header.nonce().to_vec());
+        }
+
+        assert_eq!(nonces.len(), 100_000);
+    }
+
+    #[test]
+    fn test_vault_counter_nonces_with_batch() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default()).with_counter_nonces();
+        let context = EncryptionContext::new("users", "email");
+
+        let plaintexts: Vec<Vec<u8>> =
+            (0..100).map(|i| format!("user{i}@example.com").into_bytes()).collect();
+        let items: Vec<(&[u8], &EncryptionContext)> =
+            plaintexts.iter().map(|p| (p.as_slice(), &context)).collect();
+
+        let ciphertexts = vault.encrypt_batch(&items).unwrap();
+
+        // Nonces under the shared DEK are consecutive counter values
+        for (i, (plaintext, ciphertext)) in plaintexts.iter().zip(&ciphertexts).enumerate() {
+            let header = ciphertext.header();
+            let mut expected = [0u8; NONCE_SIZE];
+            expected[4..].copy_from_slice(&(i as u64).to_be_bytes());
+            assert_eq!(header.nonce(), expected);
+
+            assert_eq!(&vault.decrypt(ciphertext, &context).unwrap(), plaintext);
+        }
+
+        // A clone keeps counting from the same counter
+        let cloned = vault.clone();
+        drop(vault);
+        let ciphertext = cloned.encrypt(b"next", &context).unwrap();
+        let header = ciphertext.header();
+        assert_eq!(header.nonce()[4..], 100u64.to_be_bytes());
+    }
+
+    #[test]
+    fn test_vault_counter_nonces_exhausted() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default()).with_counter_nonces();
+        let context = EncryptionContext::new("users", "email");
+
+        vault.nonce_counter.as_ref().unwrap().store(u64::MAX, Ordering::SeqCst);
+
+        let result = vault.encrypt(b"data", &context);
+        assert!(matches!(result, Err(Error::EncryptionFailed(_))));
+    }
+
+    #[test]
+    fn test_vault_encrypt_multi_either_kek_decrypts() {
         let provider = MockKeyProvider::new();
+        let second_kek = provider.create_kek().unwrap();
         let vault = Vault::new(provider, CipherMode::default());
         let context = EncryptionContext::new("users", "email");
 
-        let plaintext1 = b"alice@example.com";
-        let plaintext2 = b"bob@example.com";
+        let ciphertext = vault
+            .encrypt_multi(b"alice@example.com", &context, &["test_kek", &second_kek])
+            .unwrap();
 
-        let ciphertext1 = vault.encrypt(plaintext1, &context).unwrap();
-        let ciphertext2 = vault.encrypt(plaintext2, &context).unwrap();
+        let header = ciphertext.header();
+        assert!(header.flags().has_multiple_recipients());
+        assert_eq!(header.kek_id(), "test_kek");
+        assert_eq!(header.additional_recipients().len(), 1);
+        assert_eq!(header.additional_recipients()[0].kek_id, second_kek);
 
-        // Different plaintexts should produce different ciphertexts
-        assert_ne!(ciphertext1, ciphertext2);
+        // Only the primary KEK known
+        let removed = vault.provider.keks.lock().unwrap().remove(&second_kek).unwrap();
+        assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), b"alice@example.com");
 
-        let decrypted1 = vault.decrypt(&ciphertext1, &context).unwrap();
-        let decrypted2 = vault.decrypt(&ciphertext2, &context).unwrap();
+        // Only the additional KEK known: the unknown primary is skipped
+        vault.provider.keks.lock().unwrap().insert(second_kek, removed);
+        vault.provider.keks.lock().unwrap().remove("test_kek");
+        assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), b"alice@example.com");
 
-        assert_eq!(plaintext1, &decrypted1[..]);
-        assert_eq!(plaintext2, &decrypted2[..]);
+        // Neither KEK known
+        vault.provider.keks.lock().unwrap().clear();
+        let result = vault.decrypt(&ciphertext, &context);
+        assert!(matches!(result, Err(Error::KeyProvider(KeyProviderError::KekNotFound(_)))));
     }
 
     #[test]
-    fn test_vault_different_contexts() {
+    fn test_vault_escrow_recovers_after_primary_kek_removed() {
         let provider = MockKeyProvider::new();
+        let escrow_kek = provider.create_kek().unwrap();
         let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email").with_tenant("tenant_a");
 
-        let context1 = EncryptionContext::new("users", "email");
-        let context2 = EncryptionContext::new("users", "name");
+        let ciphertext =
+            vault.encrypt_with_escrow(b"alice@example.com", &context, &escrow_kek).unwrap();
+        let header = ciphertext.header();
+        assert_eq!(header.kek_id(), "test_kek");
+        assert_eq!(header.additional_recipients()[0].kek_id, escrow_kek);
+        assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), b"alice@example.com");
 
-        let plaintext = b"alice@example.com";
+        // The primary KEK is destroyed; the escrow holder can still recover
+        vault.provider.keks.lock().unwrap().remove("test_kek");
+        assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), b"alice@example.com");
 
-        let ciphertext1 = vault.encrypt(plaintext, &context1).unwrap();
-        let ciphertext2 = vault.encrypt(plaintext, &context2).unwrap();
+        // Without the escrow KEK too, the data is gone
+        vault.provider.keks.lock().unwrap().remove(&escrow_kek);
+        assert!(matches!(
+            vault.decrypt(&ciphertext, &context),
+            Err(Error::KeyProvider(KeyProviderError::KekNotFound(_)))
+        ));
+    }
 
-        // Same plaintext with different contexts should produce different ciphertexts
-        assert_ne!(ciphertext1, ciphertext2);
+    #[test]
+    fn test_vault_escrow_rejects_current_kek() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
 
-        // Decrypt with correct contexts
-        let decrypted1 = vault.decrypt(&ciphertext1, &context1).unwrap();
-        let decrypted2 = vault.decrypt(&ciphertext2, &context2).unwrap();
+        let result = vault.encrypt_with_escrow(b"data", &context, "test_kek");
+        assert!(matches!(result, Err(Error::EncryptionFailed(_))));
 
-        assert_eq!(plaintext, &decrypted1[..]);
-        assert_eq!(plaintext, &decrypted2[..]);
+        let result = vault.encrypt_with_escrow(b"data", &context, "missing_kek");
+        assert!(matches!(result, Err(Error::KeyProvider(KeyProviderError::KekNotFound(_)))));
     }
 
     #[test]
-    fn test_vault_wrong_context_fails() {
+    fn test_vault_encrypt_multi_single_kek_is_compact() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default()).with_clock(|| 0);
+        let context = EncryptionContext::new("users", "email");
+
+        let multi = vault.encrypt_multi(b"data", &context, &["test_kek"]).unwrap();
+        let single = vault.encrypt(b"data", &context).unwrap();
+
+        assert!(!multi.header().flags().has_multiple_recipients());
+        assert_eq!(multi.as_bytes().len(), single.as_bytes().len());
+        assert_eq!(vault.decrypt(&multi, &context).unwrap(), b"data");
+    }
+
+    #[test]
+    fn test_vault_encrypt_multi_requires_kek() {
         let provider = MockKeyProvider::new();
         let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
 
-        let context1 = EncryptionContext::new("users", "email");
-        let context2 = EncryptionContext::new("users", "name");
+        let result = vault.encrypt_multi(b"data", &context, &[]);
+        assert!(matches!(result, Err(Error::EncryptionFailed(_))));
 
-        let plaintext = b"alice@example.com";
-        let ciphertext = vault.encrypt(plaintext, &context1).unwrap();
+        let result = vault.encrypt_multi(b"data", &context, &["test_kek", "missing_kek"]);
+        assert!(matches!(result, Err(Error::KeyProvider(KeyProviderError::KekNotFound(_)))));
+    }
 
-        // Decrypt with wrong context should fail authentication
-        let result = vault.decrypt(&ciphertext, &context2);
-        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+    /// Re-serializes a ciphertext with a protocol version 1 header.
+    fn downgrade_to_v1(ciphertext: &Ciphertext) -> Vec<u8> {
+        let header = ciphertext.header();
+        let legacy = EncryptionHeader::new(
+            header.kek_id(),
+            header.wrapped_dek().to_vec(),
+            header.flags(),
+            header.nonce().to_vec(),
+        );
+
+        let mut bytes = legacy.to_bytes().unwrap();
+        bytes[0] = 1;
+        bytes.extend_from_slice(ciphertext.payload());
+        bytes
     }
 
     #[test]
-    fn test_vault_empty_plaintext() {
+    fn test_vault_migrate_v1_blob() {
         let provider = MockKeyProvider::new();
-        let vault = Vault::new(provider, CipherMode::default());
+        let vault = Vault::new(provider, CipherMode::default()).with_clock(|| 1_700_000_000_000);
         let context = EncryptionContext::new("users", "email");
 
-        let plaintext = b"";
-        let ciphertext = vault.encrypt(plaintext, &context).unwrap();
-        let decrypted = vault.decrypt(&ciphertext, &context).unwrap();
+        let legacy = downgrade_to_v1(&vault.encrypt(b"alice@example.com", &context).unwrap());
+        assert_eq!(EncryptionHeader::from_bytes(&legacy).unwrap().0.version(), 1);
 
-        assert_eq!(plaintext, &decrypted[..]);
+        let migrated = vault.migrate(&legacy, &context).unwrap();
+        assert_eq!(migrated.version(), PROTOCOL_VERSION);
+        assert_eq!(migrated.header().created_at(), Some(1_700_000_000_000));
+        assert_eq!(vault.decrypt(&migrated, &context).unwrap(), b"alice@example.com");
+
+        // The wrong context can't migrate the blob
+        let other = EncryptionContext::new("users", "phone");
+        assert!(matches!(vault.migrate(&legacy, &other), Err(Error::AuthenticationFailed)));
     }
 
     #[test]
-    fn test_vault_large_plaintext() {
+    fn test_vault_migrate_keeps_compression() {
         let provider = MockKeyProvider::new();
         let vault = Vault::new(provider, CipherMode::default());
-        let context = EncryptionContext::new("users", "data");
+        let context = EncryptionContext::new("documents", "body");
+        let plaintext = "lorem ipsum ".repeat(100);
 
-        let plaintext = vec![42u8; 10000];
-        let ciphertext = vault.encrypt(&plaintext, &context).unwrap();
-        let decrypted = vault.decrypt(&ciphertext, &context).unwrap();
+        let compressed = vault.encrypt_compressed(plaintext.as_bytes(), &context).unwrap();
+        let migrated = vault.migrate(&downgrade_to_v1(&compressed), &context).unwrap();
 
-        assert_eq!(plaintext, decrypted);
+        assert!(migrated.header().flags().is_compressed());
+        assert_eq!(vault.decrypt(&migrated, &context).unwrap(), plaintext.as_bytes());
     }
 
     #[test]
-    fn test_vault_corrupted_ciphertext_fails() {
+    fn test_vault_migrate_current_blob_is_noop() {
         let provider = MockKeyProvider::new();
         let vault = Vault::new(provider, CipherMode::default());
         let context = EncryptionContext::new("users", "email");
 
-        let plaintext = b"alice@example.com";
-        let mut ciphertext = vault.encrypt(plaintext, &context).unwrap();
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+        let migrated = vault.migrate(ciphertext.as_bytes(), &context).unwrap();
 
-        // Corrupt the ciphertext
-        let len = ciphertext.len();
-        if len > 10 {
-            ciphertext[len - 1] ^= 0xFF;
-        }
+        assert_eq!(migrated, ciphertext);
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), 0);
+    }
 
-        // Decryption should fail
-        let result = vault.decrypt(&ciphertext, &context);
-        assert!(result.is_err());
+    #[test]
+    fn test_vault_aes128_gcm_round_trip() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::Aes128Gcm);
+        let context = EncryptionContext::new("users", "ssn");
+
+        let ciphertext = vault.encrypt(b"123-45-6789", &context).unwrap();
+        let header = ciphertext.header();
+        assert_eq!(header.cipher_id(), Some(CipherMode::Aes128Gcm.to_id()));
+
+        // The DEK is 128 bits
+        let dek = vault.provider.unwrap_dek(header.kek_id(), header.wrapped_dek()).unwrap();
+        assert_eq!(dek.expose_secret().len(), 16);
+
+        assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), b"123-45-6789");
+
+        // The header, not the vault's mode, selects the cipher on decrypt
+        let chacha_vault = Vault {
+            provider: Arc::clone(&vault.provider),
+            ..Vault::new(MockKeyProvider::new(), CipherMode::ChaCha20Poly1305)
+        };
+        assert_eq!(chacha_vault.decrypt(&ciphertext, &context).unwrap(), b"123-45-6789");
     }
 
     #[test]
-    fn test_vault_clone() {
+    fn test_cipher_mode_key_len() {
+        assert_eq!(CipherMode::ChaCha20Poly1305.key_len(), 32);
+        assert_eq!(CipherMode::Aes256Gcm.key_len(), 32);
+        assert_eq!(CipherMode::XChaCha20Poly1305.key_len(), 32);
+        assert_eq!(CipherMode::Aes256GcmSiv.key_len(), 32);
+        assert_eq!(CipherMode::Aes128Gcm.key_len(), 16);
+    }
+
+    #[test]
+    fn test_vault_xchacha20_poly1305_round_trip() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::XChaCha20Poly1305);
+        let context = EncryptionContext::new("users", "ssn");
+
+        let ciphertext = vault.encrypt(b"123-45-6789", &context).unwrap();
+        assert_eq!(ciphertext.header().cipher_id(), Some(3));
+        assert_eq!(ciphertext.header().nonce().len(), XNONCE_SIZE);
+        assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), b"123-45-6789");
+
+        // Chunk nonces keep the wider nonce and end in the chunk index
+        let (header, chunks) = vault.encrypt_chunked(&[b'x'; 100], 30, &context).unwrap();
+        assert_eq!(vault.decrypt_chunked(&header, &chunks, &context).unwrap(), vec![b'x'; 100]);
+
+        let counting = Vault::builder(MockKeyProvider::new())
+            .cipher(CipherMode::XChaCha20Poly1305)
+            .counter_nonces()
+            .build();
+        let first = counting.encrypt(b"alice", &context).unwrap();
+        let second = counting.encrypt(b"bob", &context).unwrap();
+        assert_eq!(first.header().nonce(), &[0; XNONCE_SIZE]);
+        assert_eq!(second.header().nonce()[XNONCE_SIZE - 1], 1);
+        assert_eq!(counting.decrypt(&second, &context).unwrap(), b"bob");
+
+        // A 12-byte nonce under the XChaCha20 cipher id is rejected, not padded
+        let short = EncryptionHeader::new(
+            ciphertext.header().kek_id(),
+            ciphertext.header().wrapped_dek().to_vec(),
+            ciphertext.header().flags(),
+            vec![0; NONCE_SIZE],
+        )
+        .with_cipher_id(CipherMode::XChaCha20Poly1305.to_id());
+        let mut bytes = short.to_bytes().unwrap();
+        bytes.extend_from_slice(ciphertext.payload());
+        assert!(matches!(vault.decrypt_bytes(&bytes, &context), Err(Error::AuthenticationFailed)));
+    }
+
+    #[test]
+    fn test_vault_aes256_gcm_siv_round_trip() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::Aes256GcmSiv);
+        let context = EncryptionContext::new("users", "ssn");
+
+        let ciphertext = vault.encrypt(b"123-45-6789", &context).unwrap();
+        assert_eq!(ciphertext.header().cipher_id(), Some(CipherMode::Aes256GcmSiv.to_id()));
+        assert_eq!(ciphertext.header().nonce().len(), NONCE_SIZE);
+        assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), b"123-45-6789");
+
+        let compressed = vault.encrypt_compressed(&[b'x'; 256], &context).unwrap();
+        assert_eq!(vault.decrypt(&compressed, &context).unwrap(), vec![b'x'; 256]);
+
+        let wrong_context = EncryptionContext::new("users", "email");
+        assert!(matches!(
+            vault.decrypt(&ciphertext, &wrong_context),
+            Err(Error::AuthenticationFailed)
+        ));
+    }
+
+    #[test]
+    fn test_vault_aes256_gcm_siv_nonce_reuse() {
+        // Documents GCM-SIV behavior under a repeated nonce: unrelated messages
+        // still round-trip and produce unrelated ciphertexts; only identical
+        // messages become recognizable as identical.
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::Aes256GcmSiv);
+        let context = EncryptionContext::new("users", "ssn");
+        let envelope = vault.new_envelope(&context, 1, 0).unwrap();
+        let nonce = [7u8; NONCE_SIZE];
+
+        let first =
+            vault.seal(&envelope, &nonce, HeaderFlags::empty(), b"alice", &context, &[]).unwrap();
+        let second =
+            vault.seal(&envelope, &nonce, HeaderFlags::empty(), b"bobby", &context, &[]).unwrap();
+        let repeat =
+            vault.seal(&envelope, &nonce, HeaderFlags::empty(), b"alice", &context, &[]).unwrap();
+
+        assert_eq!(vault.decrypt(&first, &context).unwrap(), b"alice");
+        assert_eq!(vault.decrypt(&second, &context).unwrap(), b"bobby");
+
+        // Equal-length messages don't share a keystream: XOR of ciphertexts
+        // is not the XOR of plaintexts, unlike a stream cipher under reuse
+        let ciphertext_xor: Vec<u8> =
+            first.payload().iter().zip(second.payload()).take(5).map(|(a, b)| a ^ b).collect();
+        let plaintext_xor: Vec<u8> = b"alice".iter().zip(b"bobby").map(|(a, b)| a ^ b).collect();
+        assert_ne!(ciphertext_xor, plaintext_xor);
+
+        assert_eq!(first.payload(), repeat.payload());
+    }
+
+    #[test]
+    fn test_vault_dek_size_matches_cipher_mode() {
+        let context = EncryptionContext::new("users", "ssn");
+
+        for mode in CipherMode::ALL {
+            let vault = Vault::new(MockKeyProvider::new(), mode);
+
+            let ciphertext = vault.encrypt(b"123-45-6789", &context).unwrap();
+            let header = ciphertext.header();
+            let dek = vault.provider.unwrap_dek(header.kek_id(), header.wrapped_dek()).unwrap();
+            assert_eq!(dek.expose_secret().len(), mode.key_len(), "{mode:?}");
+
+            let multi = vault.encrypt_multi(b"123-45-6789", &context, &["test_kek"]).unwrap();
+            let header = multi.header();
+            let dek = vault.provider.unwrap_dek(header.kek_id(), header.wrapped_dek()).unwrap();
+            assert_eq!(dek.expose_secret().len(), mode.key_len(), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn test_vault_chacha_rejects_16_byte_dek() {
         let provider = MockKeyProvider::new();
-        let vault1 = Vault::new(provider, CipherMode::default());
-        let vault2 = vault1.clone();
+        let vault = Vault::new(provider, CipherMode::Aes128Gcm);
+        let context = EncryptionContext::new("users", "ssn");
+
+        // Relabel an AES-128 ciphertext as ChaCha20-Poly1305
+        let ciphertext = vault.encrypt(b"123-45-6789", &context).unwrap();
+        let header = ciphertext.header();
+        let relabeled = EncryptionHeader::new(
+            header.kek_id(),
+            header.wrapped_dek().to_vec(),
+            header.flags(),
+            header.nonce().to_vec(),
+        )
+        .with_cipher_id(CipherMode::ChaCha20Poly1305.to_id());
+        let mut bytes = relabeled.to_bytes().unwrap();
+        bytes.extend_from_slice(ciphertext.payload());
+
+        // The 16-byte DEK never reaches the cipher, and the failure is the
+        // same as for a tampered payload
+        let result = vault.decrypt_bytes(&bytes, &context);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+    }
 
+    #[test]
+    fn test_vault_wrong_nonce_size_fails_authentication() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
         let context = EncryptionContext::new("users", "email");
-        let plaintext = b"test";
 
-        let ciphertext = vault1.encrypt(plaintext, &context).unwrap();
-        let decrypted = vault2.decrypt(&ciphertext, &context).unwrap();
+        // A header whose nonce doesn't fit its cipher fails like a tampered
+        // payload, after the DEK unwrap and AEAD have run
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+        let header = ciphertext.header().clone().with_nonce(vec![0; XNONCE_SIZE]);
+        let mut bytes = header.to_bytes().unwrap();
+        bytes.extend_from_slice(ciphertext.payload());
+        let unwraps = vault.provider.unwrap_calls.load(Ordering::SeqCst);
+        assert!(matches!(vault.decrypt_bytes(&bytes, &context), Err(Error::AuthenticationFailed)));
+        assert_eq!(vault.provider.unwrap_calls.load(Ordering::SeqCst), unwraps + 1);
 
-        assert_eq!(plaintext, &decrypted[..]);
+        // Chunked headers too, including nonces too short to carry a chunk index
+        let (header_bytes, chunks) = vault.encrypt_chunked(&[7u8; 100], 32, &context).unwrap();
+        let (header, _) = EncryptionHeader::from_bytes(&header_bytes).unwrap();
+        for nonce_len in [0, 3, XNONCE_SIZE] {
+            let mangled = header.clone().with_nonce(vec![0; nonce_len]).to_bytes().unwrap();
+            assert!(matches!(
+                vault.decrypt_chunked(&mangled, &chunks, &context),
+                Err(Error::AuthenticationFailed)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_vault_cryptographic_failures_are_uniform() {
+        let vault = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+        let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+
+        // Same KEK id, different key material: the DEK unwraps to garbage
+        let wrong_kek = Vault::new(MockKeyProvider::new(), CipherMode::default());
+        wrong_kek
+            .provider
+            .keks
+            .lock()
+            .unwrap()
+            .insert("test_kek".to_string(), SecretVec::new(vec![43u8; 32]));
+        let result = wrong_kek.decrypt(&ciphertext, &context);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+
+        // A provider that detects the wrong KEK fails the same way
+        let rejecting =
+            Vault::new(RejectingKeyProvider(MockKeyProvider::new()), CipherMode::default());
+        let result = rejecting.decrypt(&ciphertext, &context);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+        let (header, chunks) = vault.encrypt_chunked(&[7u8; 100], 32, &context).unwrap();
+        let result = rejecting.decrypt_chunked(&header, &chunks, &context);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+
+        let mut tampered = ciphertext.as_bytes().to_vec();
+        *tampered.last_mut().unwrap() ^= 0x01;
+        let result = vault.decrypt_bytes(&tampered, &context);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+
+        // An unknown KEK is a lookup failure, not a cryptographic one
+        let relabeled = ciphertext
+            .header()
+            .rewrapped("missing_kek", ciphertext.header().wrapped_dek().to_vec());
+        let mut bytes = relabeled.to_bytes().unwrap();
+        bytes.extend_from_slice(ciphertext.payload());
+        let result = vault.decrypt_bytes(&bytes, &context);
+        assert!(matches!(result, Err(Error::KeyProvider(KeyProviderError::KekNotFound(_)))));
+    }
+
+    /// Fails every unwrap as a provider with authenticated wrapping does
+    /// under the wrong KEK.
+    struct RejectingKeyProvider(MockKeyProvider);
+
+    impl KeyProvider for RejectingKeyProvider {
+        fn create_kek(&self) -> Result<String, KeyProviderError> {
+            self.0.create_kek()
+        }
+
+        fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+            self.0.current_kek_id()
+        }
+
+        fn wrap_dek(&self, kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+            self.0.wrap_dek(kek_id, dek)
+        }
+
+        fn unwrap_dek(
+            &self,
+            _kek_id: &str,
+            _wrapped_dek: &[u8],
+        ) -> Result<SecretVec<u8>, KeyProviderError> {
+            Err(KeyProviderError::UnwrapFailed("Authentication tag mismatch".to_string()))
+        }
+    }
+
+    #[test]
+    fn test_cipher_state_zeroizes_on_drop() {
+        use zeroize::ZeroizeOnDrop;
+
+        // Fails to compile if the ciphers' zeroize features get dropped
+        fn assert_zeroize_on_drop<T: ZeroizeOnDrop>() {}
+        assert_zeroize_on_drop::<ChaCha20Poly1305>();
+        assert_zeroize_on_drop::<chacha20::ChaCha20>();
+        assert_zeroize_on_drop::<aes::Aes128>();
+        assert_zeroize_on_drop::<aes::Aes256>();
+
+        let context = EncryptionContext::new("users", "email");
+        for mode in CipherMode::ALL {
+            let vault = Vault::new(MockKeyProvider::new(), mode);
+            let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+            assert_eq!(vault.decrypt(&ciphertext, &context).unwrap(), b"alice@example.com");
+        }
+    }
+
+    #[test]
+    fn test_vault_unknown_cipher_id() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "email");
+
+        let ciphertext = vault.encrypt(b"data", &context).unwrap();
+        let header = ciphertext.header();
+        let relabeled = EncryptionHeader::new(
+            header.kek_id(),
+            header.wrapped_dek().to_vec(),
+            header.flags(),
+            header.nonce().to_vec(),
+        )
+        .with_cipher_id(0xFF);
+        let mut bytes = relabeled.to_bytes().unwrap();
+        bytes.extend_from_slice(ciphertext.payload());
+
+        let result = vault.decrypt_bytes(&bytes, &context);
+        assert!(matches!(result, Err(Error::UnsupportedCipher(0xFF))));
+    }
+
+    #[test]
+    fn test_cipher_mode_id_round_trip() {
+        for mode in CipherMode::ALL {
+            assert_eq!(CipherMode::from_id(mode.to_id()).unwrap(), mode);
+        }
+
+        // The assignment is fixed by the wire format
+        assert_eq!(CipherMode::ALL.map(CipherMode::to_id), [1, 2, 3, 4, 5]);
+        assert_eq!(CipherMode::from_id(2).unwrap(), CipherMode::Aes256Gcm);
+        assert_eq!(CipherMode::from_id(3).unwrap(), CipherMode::XChaCha20Poly1305);
+        assert_eq!(CipherMode::from_id(4).unwrap(), CipherMode::Aes256GcmSiv);
+        assert_eq!(CipherMode::from_id(5).unwrap(), CipherMode::Aes128Gcm);
+    }
+
+    #[test]
+    fn test_cipher_mode_unknown_id_rejected() {
+        for id in [0, 6, 7, 0xFF] {
+            assert!(
+                matches!(CipherMode::from_id(id), Err(Error::UnsupportedCipher(got)) if got == id)
+            );
+        }
+    }
+
+    /// Subscriber that records span and event fields as formatted strings.
+    #[cfg(feature = "tracing")]
+    #[derive(Default)]
+    struct CapturingSubscriber {
+        spans: Mutex<Vec<(&'static tracing::Metadata<'static>, String)>>,
+        entered: Mutex<Vec<tracing::span::Id>>,
+        events: Mutex<Vec<String>>,
+    }
+
+    #[cfg(feature = "tracing")]
+    impl CapturingSubscriber {
+        /// Returns the recorded fields of every span with the given name.
+        fn spans_named(&self, name: &str) -> Vec<String> {
+            self.spans
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(metadata, _)| metadata.name() == name)
+                .map(|(_, fields)| fields.clone())
+                .collect()
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    struct FieldRecorder<'a>(&'a mut String);
+
+    #[cfg(feature = "tracing")]
+    impl tracing::field::Visit for FieldRecorder<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            use std::fmt::Write as _;
+            write!(self.0, "{}={value:?} ", field.name()).unwrap();
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for CapturingSubscriber {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut fields = String::new();
+            attrs.record(&mut FieldRecorder(&mut fields));
+            let mut spans = self.spans.lock().unwrap();
+            spans.push((attrs.metadata(), fields));
+            tracing::span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &tracing::span::Id, values: &tracing::span::Record<'_>) {
+            let index = usize::try_from(span.into_u64()).unwrap() - 1;
+            values.record(&mut FieldRecorder(&mut self.spans.lock().unwrap()[index].1));
+        }
+
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            let mut fields = String::new();
+            event.record(&mut FieldRecorder(&mut fields));
+            self.events.lock().unwrap().push(fields);
+        }
+
+        fn enter(&self, span: &tracing::span::Id) {
+            self.entered.lock().unwrap().push(span.clone());
+        }
+
+        fn exit(&self, _span: &tracing::span::Id) {
+            self.entered.lock().unwrap().pop();
+        }
+
+        fn current_span(&self) -> tracing_core::span::Current {
+            let entered = self.entered.lock().unwrap();
+            entered.last().map_or_else(tracing_core::span::Current::none, |id| {
+                let index = usize::try_from(id.into_u64()).unwrap() - 1;
+                tracing_core::span::Current::new(id.clone(), self.spans.lock().unwrap()[index].0)
+            })
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_vault_tracing_encrypt_span_fields() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let context = EncryptionContext::new("users", "ssn");
+        let plaintext = b"123-45-6789";
+
+        let subscriber = Arc::new(CapturingSubscriber::default());
+        tracing::subscriber::with_default(Arc::clone(&subscriber), || {
+            vault.encrypt(plaintext, &context).unwrap();
+        });
+
+        let encrypt_spans = subscriber.spans_named("encrypt");
+        assert_eq!(encrypt_spans.len(), 1);
+        assert!(encrypt_spans[0].contains("kek_id=\"test_kek\""));
+        assert!(encrypt_spans[0].contains("cipher_mode=ChaCha20Poly1305"));
+        assert!(encrypt_spans[0].contains(&format!("payload_size={}", plaintext.len())));
+
+        let wrap_spans = subscriber.spans_named("wrap_dek");
+        assert_eq!(wrap_spans.len(), 1);
+        assert!(wrap_spans[0].contains("kek_id=\"test_kek\""));
+
+        // Neither plaintext nor key material is recorded
+        for (_, fields) in subscriber.spans.lock().unwrap().iter() {
+            assert!(!fields.contains("123-45-6789"));
+            assert!(!fields.contains(&format!("{:?}", plaintext.as_slice())));
+            assert!(!fields.contains(&format!("{:?}", [42u8; 32])));
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_vault_tracing_authentication_failure_event() {
+        let provider = MockKeyProvider::new();
+        let vault = Vault::new(provider, CipherMode::default());
+        let ciphertext = vault.encrypt(b"data", &EncryptionContext::new("users", "ssn")).unwrap();
+
+        let subscriber = Arc::new(CapturingSubscriber::default());
+        tracing::subscriber::with_default(Arc::clone(&subscriber), || {
+            let result = vault.decrypt(&ciphertext, &EncryptionContext::new("users", "email"));
+            assert!(matches!(result, Err(Error::AuthenticationFailed)));
+        });
+
+        let decrypt_spans = subscriber.spans_named("decrypt");
+        assert_eq!(decrypt_spans.len(), 1);
+        assert!(decrypt_spans[0].contains("cipher_mode=ChaCha20Poly1305"));
+        assert_eq!(subscriber.spans_named("unwrap_dek").len(), 1);
+
+        let events = subscriber.events.lock().unwrap().clone();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].contains("monotonic_counter.authentication_failures=1"));
+        assert!(events[0].contains("kek_id=\"test_kek\""));
     }
 }
@@ -0,0 +1,180 @@
+//! Wire format test vectors.
+//!
+//! Each vector is the exact output of an encryption with a seeded RNG, a
+//! fixed clock and fixed keys. A change to any of these bytes means stored
+//! data written by an earlier release may no longer decrypt, so a failure
+//! here must be treated as a format break, not fixed by updating the hex.
+//!
+//! The vault vectors are protocol version 3 headers with the cipher id,
+//! creation time, context version and payload length fields set. After a
+//! protocol version bump, keep these bytes as decrypt-only vectors and add
+//! new ones for the new version.
+
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use secrecy::{ExposeSecret, SecretVec};
+use sifredb::ciphertext::Ciphertext;
+use sifredb::context::EncryptionContext;
+use sifredb::deterministic::DeterministicVault;
+use sifredb::error::KeyProviderError;
+use sifredb::header::{EncryptionHeader, HeaderFlags};
+use sifredb::key_provider::KeyProvider;
+use sifredb::vault::{CipherMode, Vault};
+
+/// Creation time recorded in every vector's header.
+const CREATED_AT: u64 = 1_700_000_000;
+
+/// Provider with one fixed KEK that wraps by XOR.
+///
+/// Only suitable for pinning the byte layout; it offers no protection.
+struct FixedKeyProvider;
+
+impl FixedKeyProvider {
+    const KEK_ID: &'static str = "kek_v1";
+    const KEK: [u8; 32] = [0xA5; 32];
+
+    fn xor(kek_id: &str, data: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+        if kek_id != Self::KEK_ID {
+            return Err(KeyProviderError::KekNotFound(kek_id.to_string()));
+        }
+        Ok(data.iter().zip(Self::KEK.iter().cycle()).map(|(d, k)| d ^ k).collect())
+    }
+}
+
+impl KeyProvider for FixedKeyProvider {
+    fn create_kek(&self) -> Result<String, KeyProviderError> {
+        Ok(Self::KEK_ID.to_string())
+    }
+
+    fn current_kek_id(&self) -> Result<String, KeyProviderError> {
+        Ok(Self::KEK_ID.to_string())
+    }
+
+    fn wrap_dek(&self, kek_id: &str, dek: &[u8]) -> Result<Vec<u8>, KeyProviderError> {
+        Self::xor(kek_id, dek)
+    }
+
+    fn unwrap_dek(
+        &self,
+        kek_id: &str,
+        wrapped_dek: &[u8],
+    ) -> Result<SecretVec<u8>, KeyProviderError> {
+        Self::xor(kek_id, wrapped_dek).map(SecretVec::new)
+    }
+}
+
+/// A vault whose DEKs and nonces come from `seed` and whose clock is fixed.
+fn seeded_vault(cipher_mode: CipherMode, seed: u64) -> Vault<FixedKeyProvider> {
+    Vault::new(FixedKeyProvider, cipher_mode)
+        .with_rng(ChaCha20Rng::seed_from_u64(seed))
+        .with_clock(|| CREATED_AT)
+}
+
+fn deterministic_vault() -> DeterministicVault {
+    DeterministicVault::new(SecretVec::new(vec![0x42; 64])).unwrap()
+}
+
+#[test]
+fn vector_chacha20_poly1305() {
+    const EXPECTED: &str = "03066b656b5f763100203f92e1f5e0c5c63b63d51204d8ec8e829ba2deaf331b502e02d3a2dc40e1f1cb7401000000006553f10000000001000000210c000efec87c5749ec1157912e0fd99cdb3f313f5d91e75a0f200da5bf0a2ffca0126ac0b9dbbb8a81c60aea2e8f";
+    let vault = seeded_vault(CipherMode::ChaCha20Poly1305, 1);
+    let context = EncryptionContext::new("users", "email");
+
+    let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+    assert_eq!(hex::encode(ciphertext.as_bytes()), EXPECTED);
+
+    let stored = Ciphertext::from_bytes(hex::decode(EXPECTED).unwrap()).unwrap();
+    assert_eq!(vault.decrypt(&stored, &context).unwrap(), b"alice@example.com");
+}
+
+#[test]
+fn vector_chacha20_poly1305_with_aad() {
+    const EXPECTED: &str = "03066b656b5f763100202bae12f6ea6aeab709d1fd5e25375bf73940cb051ecd48139efac96eb32f08367401000000006553f10000000001000000170c2cf8ccb495646e17340d11bb7b607aa1cad952dd0935512d86fe7b92807d2fecdf46a6";
+    let vault = seeded_vault(CipherMode::ChaCha20Poly1305, 2);
+    let context = EncryptionContext::new("orders", "total");
+
+    let ciphertext = vault.encrypt_with_aad(b"1234.50", &context, b"order:42").unwrap();
+    assert_eq!(hex::encode(ciphertext.as_bytes()), EXPECTED);
+
+    let stored = Ciphertext::from_bytes(hex::decode(EXPECTED).unwrap()).unwrap();
+    assert_eq!(vault.decrypt_with_aad(&stored, &context, b"order:42").unwrap(), b"1234.50");
+}
+
+#[test]
+fn vector_aes_256_gcm_siv_tenant_versioned() {
+    const EXPECTED: &str = "03066b656b5f763100205a969a3a7f347539037e927f851212a222f8e9f807fc91a8256b1501182be30a7404000000006553f100000000020000001b0cdc45a2165ed7eaf73d2956d2c491b59c0efdf90730be1ec086d67caba247c920acd9c9f1587afc";
+    let vault = seeded_vault(CipherMode::Aes256GcmSiv, 3);
+    let context = EncryptionContext::new("users", "ssn").with_tenant("tenant_a").with_version(2);
+
+    let ciphertext = vault.encrypt(b"123-45-6789", &context).unwrap();
+    assert_eq!(hex::encode(ciphertext.as_bytes()), EXPECTED);
+
+    let stored = Ciphertext::from_bytes(hex::decode(EXPECTED).unwrap()).unwrap();
+    assert_eq!(vault.decrypt(&stored, &context).unwrap(), b"123-45-6789");
+}
+
+#[test]
+fn vector_aes_256_gcm() {
+    const EXPECTED: &str = "03066b656b5f76310020a2e38a28f279632b50f4d20f4936c03621deea2bbd4773c711a22c0755746a237402000000006553f10000000001000000210c98e60b0f3d4fdfbc29577f23b2df76421feb432a5c3ca9366b7f77a73b54e7a9083bab4e0db7c9199cef1d0af1";
+    let vault = seeded_vault(CipherMode::Aes256Gcm, 4);
+    let context = EncryptionContext::new("users", "email");
+
+    let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+    assert_eq!(hex::encode(ciphertext.as_bytes()), EXPECTED);
+
+    let stored = Ciphertext::from_bytes(hex::decode(EXPECTED).unwrap()).unwrap();
+    assert_eq!(vault.decrypt(&stored, &context).unwrap(), b"alice@example.com");
+}
+
+#[test]
+fn vector_xchacha20_poly1305() {
+    const EXPECTED: &str = "03066b656b5f763100207545da05e669c539f3dc9bdbebbf3f5a5c3cfded93eebf75f598a346157f8b707403000000006553f1000000000100000021186a9fa8817078c1bcf963829a2fa80cee9dce73ad916412300329d057ea02c760e7a1ab75598c0cca1da7e65040a1879636d51b3432fdb8496e";
+    let vault = seeded_vault(CipherMode::XChaCha20Poly1305, 5);
+    let context = EncryptionContext::new("users", "email");
+
+    let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+    assert_eq!(hex::encode(ciphertext.as_bytes()), EXPECTED);
+
+    let stored = Ciphertext::from_bytes(hex::decode(EXPECTED).unwrap()).unwrap();
+    assert_eq!(vault.decrypt(&stored, &context).unwrap(), b"alice@example.com");
+}
+
+#[test]
+fn vector_deterministic() {
+    const EXPECTED: &str = "8e5fe0f10cabc337f93c26eec029fcf1bc47327c426d9379ff57b08a2344e4e213";
+    let vault = deterministic_vault();
+    let context = EncryptionContext::new("users", "email");
+
+    let ciphertext = vault.encrypt(b"alice@example.com", &context).unwrap();
+    assert_eq!(hex::encode(&ciphertext), EXPECTED);
+
+    let stored = hex::decode(EXPECTED).unwrap();
+    assert_eq!(vault.decrypt(&stored, &context).unwrap(), b"alice@example.com");
+}
+
+#[test]
+fn vector_header_with_deterministic_flag() {
+    const EXPECTED: &str = "03066b656b5f76310020b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4010c222222222222222222222222";
+    let wrapped_dek = FixedKeyProvider::xor(FixedKeyProvider::KEK_ID, &[0x11; 32]).unwrap();
+    let header = EncryptionHeader::new(
+        FixedKeyProvider::KEK_ID,
+        wrapped_dek.clone(),
+        HeaderFlags::empty().with_deterministic(),
+        vec![0x22; 12],
+    );
+
+    assert_eq!(hex::encode(header.to_bytes().unwrap()), EXPECTED);
+
+    let bytes = hex::decode(EXPECTED).unwrap();
+    let (parsed, len) = EncryptionHeader::from_bytes(&bytes).unwrap();
+    assert_eq!(len, bytes.len());
+    assert_eq!(parsed.version(), 3);
+    assert!(parsed.flags().is_deterministic());
+    assert_eq!(parsed.kek_id(), FixedKeyProvider::KEK_ID);
+    assert_eq!(parsed.wrapped_dek(), wrapped_dek);
+    assert_eq!(parsed.nonce(), [0x22; 12]);
+    assert_eq!(
+        FixedKeyProvider.unwrap_dek(parsed.kek_id(), parsed.wrapped_dek()).unwrap().expose_secret(),
+        &[0x11; 32]
+    );
+}
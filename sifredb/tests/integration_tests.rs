@@ -1,10 +1,17 @@
 //! Integration tests for sifredb with FileKeyProvider.
 
-use sifredb::blind_index::generate_blind_index;
+use secrecy::ExposeSecret;
+use sifredb::blind_index::{
+    generate_blind_index, generate_blind_index_versioned, verify_blind_index_versioned,
+};
+use sifredb::ciphertext::Ciphertext;
 use sifredb::context::{EncryptionContext, IndexContext};
+use sifredb::error::{Error, KeyProviderError};
+use sifredb::header::EncryptionHeader;
 use sifredb::key_provider::KeyProvider;
 use sifredb::vault::{CipherMode, Vault};
 use sifredb_key_file::FileKeyProvider;
+use std::time::{Duration, SystemTime};
 use tempfile::TempDir;
 
 #[test]
@@ -185,3 +192,783 @@ fn test_context_as_aad() {
     let decrypted = vault.decrypt(&ciphertext, &context1).expect("Decryption failed");
     assert_eq!(plaintext, &decrypted[..]);
 }
+
+#[test]
+fn test_rewrap_after_key_rotation() {
+    // Create a temporary directory for keys
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+
+    // Initialize the key directory
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+
+    // Encrypt with KEK v1
+    let provider = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    let vault = Vault::new(provider, CipherMode::default());
+    let context = EncryptionContext::new("users", "email");
+    let plaintext = b"alice@example.com";
+    let ciphertext = vault.encrypt(plaintext, &context).expect("Encryption failed");
+
+    // Rotate to KEK v2
+    let provider2 = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    let new_kek_id = provider2.create_kek().expect("Failed to create new KEK");
+    let vault2 = Vault::new(provider2, CipherMode::default());
+
+    // Rewrap uses the provider's default unwrap-then-wrap path
+    let rewrapped = vault2.rewrap(&ciphertext, &new_kek_id).expect("Rewrap failed");
+    assert_eq!(rewrapped.kek_id(), "kek_v2");
+
+    let decrypted = vault2.decrypt(&rewrapped, &context).expect("Decryption failed");
+    assert_eq!(plaintext, &decrypted[..]);
+}
+
+#[test]
+fn test_kek_metadata_for_fresh_kek() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+
+    let provider = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    let new_kek_id = provider.create_kek().expect("Failed to create new KEK");
+
+    let metadata = provider.kek_metadata(&new_kek_id).expect("Failed to read metadata");
+    assert_eq!(metadata.id, "kek_v2");
+    assert!(metadata.is_current);
+
+    // A just-written key file is at most a few seconds old
+    let created_at = metadata.created_at.expect("Missing creation time");
+    let age = SystemTime::now().duration_since(created_at).unwrap_or(Duration::ZERO);
+    assert!(age < Duration::from_secs(60));
+
+    // The rotated-out KEK is no longer current
+    let old = provider.kek_metadata("kek_v1").expect("Failed to read metadata");
+    assert!(!old.is_current);
+    assert!(old.created_at.is_some());
+}
+
+#[test]
+fn test_kek_metadata_unknown_kek() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+
+    let provider = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    let result = provider.kek_metadata("kek_v99");
+    assert!(matches!(result, Err(KeyProviderError::KekNotFound(_))));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_create_kek_recovers_from_interrupted_swap() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+
+    // Simulate a crash after the temp link was created but before the rename
+    let temp_link = key_dir.join("current.tmp");
+    std::os::unix::fs::symlink("kek_v2.key", &temp_link).expect("Failed to create temp link");
+
+    // The real pointer is untouched, so the directory is still usable
+    let provider = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    assert_eq!(provider.current_kek_id().unwrap(), "kek_v1");
+
+    let new_kek_id = provider.create_kek().expect("Failed to create new KEK");
+    assert_eq!(new_kek_id, "kek_v2");
+
+    // The stale temp link was consumed and current resolves to the new KEK
+    assert!(std::fs::symlink_metadata(&temp_link).is_err());
+    assert_eq!(provider.current_kek_id().unwrap(), "kek_v2");
+    assert!(FileKeyProvider::new(key_dir).is_ok());
+
+    // Repeated rotation never leaves the temp link behind
+    provider.create_kek().expect("Failed to create new KEK");
+    assert!(std::fs::symlink_metadata(&temp_link).is_err());
+    assert_eq!(provider.current_kek_id().unwrap(), "kek_v3");
+}
+
+#[test]
+fn test_staged_keks_activate_on_demand() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    FileKeyProvider::init(temp_dir.path()).expect("Failed to initialize keys");
+    let provider = FileKeyProvider::new(temp_dir.path()).expect("Failed to create provider");
+
+    let staged1 = provider.create_kek_inactive().expect("Failed to stage KEK");
+    let staged2 = provider.create_kek_inactive().expect("Failed to stage KEK");
+    assert_eq!((staged1.as_str(), staged2.as_str()), ("kek_v2", "kek_v3"));
+
+    // Staging leaves the current KEK alone
+    assert_eq!(provider.current_kek_id().unwrap(), "kek_v1");
+    assert_eq!(
+        std::fs::read_link(temp_dir.path().join("current")).unwrap(),
+        std::path::Path::new("kek_v1.key")
+    );
+
+    let vault = Vault::new(provider, CipherMode::default());
+    let context = EncryptionContext::new("users", "email");
+    let before = vault.encrypt(b"alice@example.com", &context).expect("Encryption failed");
+    assert_eq!(before.kek_id(), "kek_v1");
+
+    // Activating the second staged KEK skips the first
+    let admin = FileKeyProvider::new(temp_dir.path()).expect("Failed to create provider");
+    admin.activate_kek(&staged2).expect("Failed to activate KEK");
+    assert_eq!(admin.current_kek_id().unwrap(), staged2);
+
+    let after = vault.encrypt(b"bob@example.com", &context).expect("Encryption failed");
+    assert_eq!(after.kek_id(), staged2);
+    assert_eq!(vault.decrypt(&before, &context).unwrap(), b"alice@example.com");
+    assert_eq!(vault.decrypt(&after, &context).unwrap(), b"bob@example.com");
+
+    // Later KEKs never reuse a staged version
+    assert_eq!(admin.create_kek().expect("Failed to create new KEK"), "kek_v4");
+
+    for missing in ["kek_v9", "pepper", "../kek_v1", "tenants/acme/kek_v1"] {
+        let result = admin.activate_kek(missing);
+        assert!(matches!(result, Err(KeyProviderError::KekNotFound(_))), "{missing}");
+    }
+    assert_eq!(admin.current_kek_id().unwrap(), "kek_v4");
+}
+
+#[test]
+fn test_pepper_rotation_keeps_old_indexes_verifiable() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+
+    let provider = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    let context = IndexContext::new("users", "email");
+    let email = b"alice@example.com";
+
+    let old_index =
+        generate_blind_index_versioned(&provider, email, &context).expect("Index failed");
+    assert_eq!(old_index.pepper_version, 1);
+
+    provider.rotate_pepper().expect("Pepper rotation failed");
+    assert_eq!(provider.current_pepper_version().unwrap(), 2);
+    assert!(key_dir.join("pepper.key").exists());
+    assert!(key_dir.join("pepper_v2.key").exists());
+
+    // New indexes use the new pepper
+    let new_index =
+        generate_blind_index_versioned(&provider, email, &context).expect("Index failed");
+    assert_eq!(new_index.pepper_version, 2);
+    assert_ne!(new_index.index, old_index.index);
+
+    // Old indexes still verify against their own pepper version
+    assert!(verify_blind_index_versioned(&provider, email, &context, &old_index).unwrap());
+    assert!(verify_blind_index_versioned(&provider, email, &context, &new_index).unwrap());
+
+    // Reopening the directory keeps the rotated state
+    let reopened = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    assert_eq!(reopened.current_pepper_version().unwrap(), 2);
+}
+
+#[test]
+fn test_file_provider_health_check_passes() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+
+    let provider = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    provider.health_check().expect("Healthy key directory failed health check");
+
+    // Still healthy after a rotation moves the symlink
+    provider.create_kek().expect("Failed to create new KEK");
+    provider.health_check().expect("Rotated key directory failed health check");
+}
+
+#[test]
+fn test_file_provider_health_check_missing_symlink() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+
+    let provider = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    std::fs::remove_file(key_dir.join("current")).expect("Failed to remove symlink");
+
+    assert!(matches!(provider.health_check(), Err(KeyProviderError::NoActiveKek)));
+}
+
+#[test]
+fn test_file_provider_new_on_uninitialized_directory() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+
+    let result = FileKeyProvider::new(key_dir);
+    assert!(matches!(result, Err(KeyProviderError::NotInitialized(path)) if path == key_dir));
+
+    // Once initialized the same directory loads
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+    FileKeyProvider::new(key_dir).expect("Failed to create provider");
+}
+
+#[test]
+fn test_file_provider_new_with_missing_pointer() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+    std::fs::remove_file(key_dir.join("current")).expect("Failed to remove symlink");
+
+    // Keys are still there, so only the pointer needs repairing
+    let result = FileKeyProvider::new(key_dir);
+    assert!(matches!(result, Err(KeyProviderError::NoActiveKek)));
+}
+
+#[test]
+fn test_repair_restores_current_pointer() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+    let provider = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    provider.create_kek().expect("Failed to create new KEK");
+    let latest = provider.create_kek().expect("Failed to create new KEK");
+    assert_eq!(latest, "kek_v3");
+
+    std::fs::remove_file(key_dir.join("current")).expect("Failed to remove symlink");
+    assert!(matches!(FileKeyProvider::new(key_dir), Err(KeyProviderError::NoActiveKek)));
+
+    assert_eq!(FileKeyProvider::repair(key_dir).expect("Repair failed"), latest);
+    let provider = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    assert_eq!(provider.current_kek_id().unwrap(), latest);
+    provider.health_check().expect("Repaired key directory failed health check");
+}
+
+#[test]
+fn test_repair_skips_staged_keks() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+    let provider = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    let staged = provider.create_kek_inactive().expect("Failed to stage KEK");
+    let activated = provider.create_kek_inactive().expect("Failed to stage KEK");
+    provider.activate_kek(&activated).expect("Failed to activate KEK");
+    provider.create_kek_inactive().expect("Failed to stage KEK");
+    assert_eq!((staged.as_str(), activated.as_str()), ("kek_v2", "kek_v3"));
+
+    // kek_v4 is staged and kek_v3 was activated, so kek_v3 was current
+    std::fs::remove_file(key_dir.join("current")).expect("Failed to remove symlink");
+    assert_eq!(FileKeyProvider::repair(key_dir).expect("Repair failed"), activated);
+
+    // A directory holding only staged KEKs has nothing to repair to
+    let staged_dir = TempDir::new().expect("Failed to create temp dir");
+    FileKeyProvider::init(staged_dir.path()).expect("Failed to initialize keys");
+    std::fs::write(staged_dir.path().join("kek_v1.staged"), b"").expect("Failed to mark KEK");
+    std::fs::remove_file(staged_dir.path().join("current")).expect("Failed to remove symlink");
+    let result = FileKeyProvider::repair(staged_dir.path());
+    assert!(matches!(result, Err(KeyProviderError::NoActiveKek)));
+}
+
+#[test]
+fn test_repair_refuses_uninitialized_directory() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+    let result = FileKeyProvider::repair(temp_dir.path());
+    assert!(matches!(result, Err(KeyProviderError::NotInitialized(_))));
+    assert!(!temp_dir.path().join("current").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_file_provider_health_check_insecure_kek() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+
+    let provider = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    let kek_path = key_dir.join("kek_v1.key");
+    std::fs::set_permissions(&kek_path, std::fs::Permissions::from_mode(0o644))
+        .expect("Failed to change permissions");
+
+    assert!(provider.health_check().is_err());
+}
+
+#[test]
+fn test_wrapped_dek_bound_to_kek_id() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+
+    let provider = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    provider.create_kek().expect("Failed to create new KEK");
+
+    // Give kek_v2 the same key bytes as kek_v1, so only the KEK ID differs
+    std::fs::copy(key_dir.join("kek_v1.key"), key_dir.join("kek_v2.key"))
+        .expect("Failed to copy KEK");
+
+    let dek = [7u8; 32];
+    let wrapped = provider.wrap_dek("kek_v1", &dek).expect("Failed to wrap DEK");
+    let unwrapped = provider.unwrap_dek("kek_v1", &wrapped).expect("Failed to unwrap DEK");
+    assert_eq!(unwrapped.expose_secret(), &dek);
+
+    // Relabeling the wrapped DEK with another KEK ID fails authentication
+    let result = provider.unwrap_dek("kek_v2", &wrapped);
+    assert!(matches!(result, Err(KeyProviderError::UnwrapFailed(_))));
+
+    // So does a tampered kek_id in a ciphertext header
+    let vault = Vault::new(provider, CipherMode::default());
+    let context = EncryptionContext::new("users", "email");
+    let ciphertext = vault.encrypt(b"alice@example.com", &context).expect("Encryption failed");
+    let header = ciphertext.header();
+    assert_eq!(header.kek_id(), "kek_v2");
+
+    let relabeled = EncryptionHeader::new(
+        "kek_v1",
+        header.wrapped_dek().to_vec(),
+        header.flags(),
+        header.nonce().to_vec(),
+    );
+    let mut tampered = relabeled.to_bytes().expect("Failed to serialize header");
+    tampered.extend_from_slice(&ciphertext.as_bytes()[header.to_bytes().unwrap().len()..]);
+    assert!(vault.decrypt_bytes(&tampered, &context).is_err());
+}
+
+#[test]
+fn test_legacy_wrapped_dek_still_unwraps() {
+    use chacha20poly1305::aead::{Aead, KeyInit};
+    use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+    let provider = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+
+    // Legacy layout: nonce || ciphertext, with no format byte and no AAD.
+    // A nonce starting with the format byte exercises the fallback path.
+    let kek = std::fs::read(key_dir.join("kek_v1.key")).expect("Failed to read KEK");
+    let cipher = ChaCha20Poly1305::new_from_slice(&kek).expect("Invalid KEK");
+    let dek = [9u8; 32];
+
+    for first_byte in [0x00, 0x02] {
+        let mut nonce = [3u8; 12];
+        nonce[0] = first_byte;
+        let mut legacy = nonce.to_vec();
+        legacy.extend(cipher.encrypt(&Nonce::from(nonce), dek.as_slice()).expect("Encrypt failed"));
+
+        let unwrapped = provider.unwrap_dek("kek_v1", &legacy).expect("Legacy unwrap failed");
+        assert_eq!(unwrapped.expose_secret(), &dek);
+    }
+}
+
+#[test]
+fn test_kek_cache_avoids_rereading_key_file() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+
+    let provider = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    let kek_id = provider.current_kek_id().expect("No active KEK");
+    let dek = [7u8; 32];
+    let wrapped = provider.wrap_dek(&kek_id, &dek).expect("First wrap failed");
+
+    // Once cached, the KEK file is no longer needed
+    std::fs::remove_file(key_dir.join(format!("{kek_id}.key"))).expect("Failed to remove KEK");
+    provider.wrap_dek(&kek_id, &dek).expect("Second wrap re-read the KEK file");
+    let unwrapped = provider.unwrap_dek(&kek_id, &wrapped).expect("Unwrap failed");
+    assert_eq!(unwrapped.expose_secret(), &dek);
+
+    // After eviction the provider goes back to disk
+    provider.clear_key_cache();
+    assert!(matches!(provider.wrap_dek(&kek_id, &dek), Err(KeyProviderError::KekNotFound(_))));
+}
+
+#[test]
+fn test_ciphertext_len_with_file_provider() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+
+    let provider = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    let vault = Vault::new(provider, CipherMode::default());
+    let context = EncryptionContext::new("users", "email");
+
+    for len in [0, 32, 1024] {
+        let ciphertext = vault.encrypt(&vec![0u8; len], &context).expect("Encryption failed");
+        let estimate = vault.ciphertext_len(len, &context).expect("Estimate failed");
+        assert_eq!(estimate, ciphertext.as_bytes().len());
+    }
+}
+
+#[test]
+fn test_key_bundle_round_trip() {
+    let source_dir = TempDir::new().expect("Failed to create temp dir");
+    FileKeyProvider::init(source_dir.path()).expect("Failed to initialize keys");
+    let admin = FileKeyProvider::new(source_dir.path()).expect("Failed to create provider");
+    admin.rotate_pepper().expect("Failed to rotate pepper");
+    let provider = FileKeyProvider::new(source_dir.path()).expect("Failed to create provider");
+    let vault = Vault::new(provider, CipherMode::default());
+    let context = EncryptionContext::new("users", "email");
+
+    // Ciphertext under both an old and the current KEK
+    let old = vault.encrypt(b"alice@example.com", &context).expect("Encryption failed");
+    let new_kek_id = admin.create_kek().expect("Failed to create KEK");
+    let new = vault.encrypt(b"bob@example.com", &context).expect("Encryption failed");
+    let bundle = admin.export_bundle(b"correct horse").expect("Failed to export bundle");
+
+    let target_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = target_dir.path().join("keys");
+    FileKeyProvider::import_bundle(&key_dir, &bundle, b"correct horse")
+        .expect("Failed to import bundle");
+
+    let imported = FileKeyProvider::new(&key_dir).expect("Failed to load imported keys");
+    assert_eq!(imported.current_kek_id().unwrap(), new_kek_id);
+    assert_eq!(imported.current_pepper_version().unwrap(), 2);
+    for version in 1..=2 {
+        assert_eq!(
+            imported.get_pepper_version(version).unwrap().unwrap().expose_secret(),
+            admin.get_pepper_version(version).unwrap().unwrap().expose_secret()
+        );
+    }
+
+    let restored = Vault::new(imported, CipherMode::default());
+    assert_eq!(restored.decrypt(&old, &context).unwrap(), b"alice@example.com");
+    assert_eq!(restored.decrypt(&new, &context).unwrap(), b"bob@example.com");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(key_dir.join("kek_v1.key")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    // Importing over existing keys is refused
+    let result = FileKeyProvider::import_bundle(&key_dir, &bundle, b"correct horse");
+    assert!(matches!(result, Err(KeyProviderError::CreationFailed(_))));
+}
+
+#[test]
+fn test_key_bundle_includes_tenant_keks() {
+    let source_dir = TempDir::new().expect("Failed to create temp dir");
+    FileKeyProvider::init(source_dir.path()).expect("Failed to initialize keys");
+    let admin = FileKeyProvider::new(source_dir.path()).expect("Failed to create provider");
+    admin.create_tenant_kek("acme").expect("Failed to create tenant KEK");
+    let acme_kek = admin.create_tenant_kek("acme").expect("Failed to rotate tenant KEK");
+    let provider = FileKeyProvider::new(source_dir.path()).expect("Failed to create provider");
+    let vault = Vault::new(provider, CipherMode::default());
+    let acme = EncryptionContext::new("users", "email").with_tenant("acme");
+    let shared = EncryptionContext::new("users", "email");
+
+    let acme_ct = vault.encrypt(b"alice@acme.test", &acme).expect("Encryption failed");
+    let shared_ct = vault.encrypt(b"bob@example.com", &shared).expect("Encryption failed");
+    assert_eq!(acme_ct.kek_id(), acme_kek);
+    let bundle = admin.export_bundle(b"correct horse").expect("Failed to export bundle");
+
+    let target_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = target_dir.path().join("keys");
+    FileKeyProvider::import_bundle(&key_dir, &bundle, b"correct horse")
+        .expect("Failed to import bundle");
+
+    let imported = FileKeyProvider::new(&key_dir).expect("Failed to load imported keys");
+    assert_eq!(imported.current_kek_id_for_tenant("acme").unwrap(), acme_kek);
+    assert!(key_dir.join("tenants/acme/kek_v1.key").is_file());
+
+    let restored = Vault::new(imported, CipherMode::default());
+    assert_eq!(restored.decrypt(&acme_ct, &acme).unwrap(), b"alice@acme.test");
+    assert_eq!(restored.decrypt(&shared_ct, &shared).unwrap(), b"bob@example.com");
+}
+
+#[test]
+fn test_key_bundle_rejects_wrong_passphrase_and_tampering() {
+    let source_dir = TempDir::new().expect("Failed to create temp dir");
+    FileKeyProvider::init(source_dir.path()).expect("Failed to initialize keys");
+    let provider = FileKeyProvider::new(source_dir.path()).expect("Failed to create provider");
+    let bundle = provider.export_bundle(b"correct horse").expect("Failed to export bundle");
+
+    let target_dir = TempDir::new().expect("Failed to create temp dir");
+    let result = FileKeyProvider::import_bundle(target_dir.path(), &bundle, b"wrong horse");
+    assert!(matches!(result, Err(KeyProviderError::UnwrapFailed(_))));
+
+    let mut tampered = bundle.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    let result = FileKeyProvider::import_bundle(target_dir.path(), &tampered, b"correct horse");
+    assert!(matches!(result, Err(KeyProviderError::UnwrapFailed(_))));
+
+    let result = FileKeyProvider::import_bundle(target_dir.path(), &bundle[..10], b"correct horse");
+    assert!(matches!(result, Err(KeyProviderError::UnwrapFailed(_))));
+
+    // Nothing was written by the failed imports
+    assert_eq!(std::fs::read_dir(target_dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn test_tenant_keks_are_isolated() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    FileKeyProvider::init(temp_dir.path()).expect("Failed to initialize keys");
+    let provider = FileKeyProvider::new(temp_dir.path()).expect("Failed to create provider");
+
+    let acme_kek = provider.create_tenant_kek("acme").expect("Failed to create tenant KEK");
+    let globex_kek = provider.create_tenant_kek("globex").expect("Failed to create tenant KEK");
+    assert_eq!(acme_kek, "tenants/acme/kek_v1");
+    assert_ne!(acme_kek, globex_kek);
+    assert!(temp_dir.path().join("tenants/acme/current").exists());
+
+    assert_eq!(provider.current_kek_id_for_tenant("acme").unwrap(), acme_kek);
+    assert_eq!(provider.current_kek_id_for_tenant("globex").unwrap(), globex_kek);
+    // Tenants without a directory share the current KEK
+    assert_eq!(provider.current_kek_id_for_tenant("initech").unwrap(), "kek_v1");
+    assert!(provider.kek_metadata(&acme_kek).unwrap().is_current);
+    assert!(provider.kek_metadata("kek_v1").unwrap().is_current);
+
+    assert!(matches!(
+        provider.create_tenant_kek("../escape"),
+        Err(KeyProviderError::CreationFailed(_))
+    ));
+
+    let vault = Vault::new(provider, CipherMode::default());
+    let acme = EncryptionContext::new("users", "email").with_tenant("acme");
+    let globex = EncryptionContext::new("users", "email").with_tenant("globex");
+
+    let acme_ct = vault.encrypt(b"alice@acme.test", &acme).expect("Encryption failed");
+    let globex_ct = vault.encrypt(b"bob@globex.test", &globex).expect("Encryption failed");
+    assert_eq!(acme_ct.kek_id(), acme_kek);
+    assert_eq!(globex_ct.kek_id(), globex_kek);
+
+    // Destroying one tenant's keys leaves the other tenant readable
+    std::fs::remove_dir_all(temp_dir.path().join("tenants/acme")).unwrap();
+    let provider = FileKeyProvider::new(temp_dir.path()).expect("Failed to create provider");
+    let vault = Vault::new(provider, CipherMode::default());
+    assert!(vault.decrypt(&acme_ct, &acme).is_err());
+    assert_eq!(vault.decrypt(&globex_ct, &globex).unwrap(), b"bob@globex.test");
+}
+
+#[test]
+fn test_revoked_tenant_kek_does_not_fall_back_to_shared() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    FileKeyProvider::init(temp_dir.path()).expect("Failed to initialize keys");
+    let provider = FileKeyProvider::new(temp_dir.path()).expect("Failed to create provider");
+    provider.create_tenant_kek("acme").expect("Failed to create tenant KEK");
+
+    // Revoke the tenant by deleting its KEK, leaving `current` dangling
+    std::fs::remove_file(temp_dir.path().join("tenants/acme/kek_v1.key")).unwrap();
+    let provider = FileKeyProvider::new(temp_dir.path()).expect("Failed to create provider");
+    assert!(matches!(
+        provider.current_kek_id_for_tenant("acme"),
+        Err(KeyProviderError::KekNotFound(_))
+    ));
+    assert!(matches!(
+        provider.current_kek_id_for_tenant("../escape"),
+        Err(KeyProviderError::KekNotFound(_))
+    ));
+
+    let vault = Vault::new(provider, CipherMode::default());
+    let acme = EncryptionContext::new("users", "email").with_tenant("acme");
+    assert!(vault.encrypt(b"alice@acme.test", &acme).is_err());
+
+    let shared = EncryptionContext::new("users", "email");
+    assert_eq!(vault.encrypt(b"bob@example.com", &shared).unwrap().kek_id(), "kek_v1");
+}
+
+#[test]
+fn test_file_provider_rejects_kek_ids_outside_key_dir() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path().join("keys");
+    FileKeyProvider::init(&key_dir).expect("Failed to initialize keys");
+    let provider = FileKeyProvider::new(&key_dir).expect("Failed to create provider");
+    let wrapped = provider.wrap_dek("kek_v1", &[7; 32]).expect("Failed to wrap DEK");
+
+    // A valid KEK file outside the key directory, reachable by path
+    std::fs::copy(key_dir.join("kek_v1.key"), temp_dir.path().join("outside.key")).unwrap();
+    let outside = temp_dir.path().join("outside");
+    let outside = outside.to_str().unwrap();
+
+    for kek_id in
+        ["../outside", outside, "tenants/../kek_v1", "tenants/acme/../../kek_v1", "pepper"]
+    {
+        let result = provider.unwrap_dek(kek_id, &wrapped);
+        assert!(matches!(result, Err(KeyProviderError::KekNotFound(_))), "{kek_id}");
+        assert!(matches!(provider.kek_metadata(kek_id), Err(KeyProviderError::KekNotFound(_))));
+    }
+}
+
+#[test]
+fn test_tenant_kek_rotation() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    FileKeyProvider::init(temp_dir.path()).expect("Failed to initialize keys");
+    let provider = FileKeyProvider::new(temp_dir.path()).expect("Failed to create provider");
+    let admin = FileKeyProvider::new(temp_dir.path()).expect("Failed to create provider");
+    let vault = Vault::new(provider, CipherMode::default());
+    let context = EncryptionContext::new("users", "email").with_tenant("acme");
+
+    // Written under the shared KEK before the tenant was isolated
+    let shared = vault.encrypt(b"alice@acme.test", &context).expect("Encryption failed");
+    assert_eq!(shared.kek_id(), "kek_v1");
+
+    admin.create_tenant_kek("acme").expect("Failed to create tenant KEK");
+    let v2 = admin.create_tenant_kek("acme").expect("Failed to rotate tenant KEK");
+    assert_eq!(v2, "tenants/acme/kek_v2");
+    assert!(!admin.kek_metadata("tenants/acme/kek_v1").unwrap().is_current);
+
+    let rotated = vault.encrypt(b"alice@acme.test", &context).expect("Encryption failed");
+    assert_eq!(rotated.kek_id(), v2);
+    assert_eq!(vault.decrypt(&shared, &context).unwrap(), b"alice@acme.test");
+    assert_eq!(vault.decrypt(&rotated, &context).unwrap(), b"alice@acme.test");
+}
+
+#[test]
+fn test_wrapped_dek_bound_to_aad() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    FileKeyProvider::init(temp_dir.path()).expect("Failed to initialize keys");
+    let provider = FileKeyProvider::new(temp_dir.path()).expect("Failed to create provider");
+
+    let dek = [7u8; 32];
+    let wrapped = provider.wrap_dek_aad("kek_v1", &dek, b"acme").expect("Failed to wrap DEK");
+    let unwrapped =
+        provider.unwrap_dek_aad("kek_v1", &wrapped, b"acme").expect("Failed to unwrap DEK");
+    assert_eq!(unwrapped.expose_secret(), &dek);
+
+    // Another tenant's AAD, or none at all, fails authentication
+    let result = provider.unwrap_dek_aad("kek_v1", &wrapped, b"globex");
+    assert!(matches!(result, Err(KeyProviderError::UnwrapFailed(_))));
+    assert!(provider.unwrap_dek("kek_v1", &wrapped).is_err());
+
+    // DEKs wrapped without AAD still unwrap when AAD is supplied
+    let unbound = provider.wrap_dek("kek_v1", &dek).expect("Failed to wrap DEK");
+    let unwrapped =
+        provider.unwrap_dek_aad("kek_v1", &unbound, b"acme").expect("Failed to unwrap DEK");
+    assert_eq!(unwrapped.expose_secret(), &dek);
+
+    // Rewrapping keeps the binding
+    let v2 = provider.create_kek().expect("Failed to create new KEK");
+    let rewrapped =
+        provider.rewrap_dek_aad("kek_v1", &v2, &wrapped, b"acme").expect("Failed to rewrap");
+    assert!(provider.unwrap_dek_aad(&v2, &rewrapped, b"globex").is_err());
+    let unwrapped = provider.unwrap_dek_aad(&v2, &rewrapped, b"acme").expect("Unwrap failed");
+    assert_eq!(unwrapped.expose_secret(), &dek);
+}
+
+#[test]
+fn test_wrapped_dek_not_portable_across_tenants() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    FileKeyProvider::init(temp_dir.path()).expect("Failed to initialize keys");
+    let provider = FileKeyProvider::new(temp_dir.path()).expect("Failed to create provider");
+    let admin = FileKeyProvider::new(temp_dir.path()).expect("Failed to create provider");
+    let vault = Vault::new(provider, CipherMode::default());
+    assert_wrapped_dek_not_portable(&vault, &admin);
+}
+
+#[cfg(feature = "dek-cache")]
+#[test]
+fn test_wrapped_dek_not_portable_across_tenants_with_dek_cache() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    FileKeyProvider::init(temp_dir.path()).expect("Failed to initialize keys");
+    let provider = FileKeyProvider::new(temp_dir.path()).expect("Failed to create provider");
+    let admin = FileKeyProvider::new(temp_dir.path()).expect("Failed to create provider");
+    let vault = Vault::new(provider, CipherMode::default()).with_dek_cache(16);
+    assert_wrapped_dek_not_portable(&vault, &admin);
+}
+
+/// Checks that a DEK wrapped for one tenant doesn't unwrap for another.
+fn assert_wrapped_dek_not_portable(vault: &Vault<FileKeyProvider>, admin: &FileKeyProvider) {
+    // Both tenants share kek_v1, so only the wrap AAD tells them apart
+    let acme = EncryptionContext::new("users", "email").with_tenant("acme");
+    let globex = EncryptionContext::new("users", "email").with_tenant("globex");
+    let acme_ct = vault.encrypt(b"alice@acme.test", &acme).expect("Encryption failed");
+    let globex_ct = vault.encrypt(b"bob@globex.test", &globex).expect("Encryption failed");
+    assert_eq!(acme_ct.kek_id(), globex_ct.kek_id());
+
+    // Reading acme's row first puts its DEK in the cache, if there is one
+    assert_eq!(vault.decrypt(&acme_ct, &acme).unwrap(), b"alice@acme.test");
+
+    // Pasting acme's wrapped DEK into globex's header fails at the unwrap,
+    // reported like any other authentication failure
+    let pasted = globex_ct.header().rewrapped("kek_v1", acme_ct.header().wrapped_dek().to_vec());
+    let mut tampered = pasted.to_bytes().expect("Failed to serialize header");
+    let header_len = globex_ct.header().encoded_len();
+    tampered.extend_from_slice(&globex_ct.as_bytes()[header_len..]);
+    let result = vault.decrypt_bytes(&tampered, &globex);
+    assert!(matches!(result, Err(Error::AuthenticationFailed)));
+
+    // Nor does the unwrap succeed when asked for directly with globex's AAD
+    let result = admin.unwrap_dek_aad("kek_v1", acme_ct.header().wrapped_dek(), b"globex");
+    assert!(result.is_err());
+
+    // A tenant's ciphertext is rewrapped with its own context only
+    let v2 = admin.create_kek().expect("Failed to create new KEK");
+    assert!(vault.rewrap_with_context(&acme_ct, &v2, &globex).is_err());
+    let rewrapped = vault.rewrap_with_context(&acme_ct, &v2, &acme).expect("Rewrap failed");
+    assert_eq!(rewrapped.kek_id(), v2);
+    assert_eq!(vault.decrypt(&rewrapped, &acme).unwrap(), b"alice@acme.test");
+}
+
+#[test]
+fn test_tenant_rewrap_paths_keep_binding() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    FileKeyProvider::init(temp_dir.path()).expect("Failed to initialize keys");
+    let provider = FileKeyProvider::new(temp_dir.path()).expect("Failed to create provider");
+    let admin = FileKeyProvider::new(temp_dir.path()).expect("Failed to create provider");
+    let vault = Vault::new(provider, CipherMode::default());
+    let acme = EncryptionContext::new("users", "email").with_tenant("acme");
+    let globex = EncryptionContext::new("users", "email").with_tenant("globex");
+    let ciphertext = vault.encrypt(b"alice@acme.test", &acme).expect("Encryption failed");
+    let v2 = admin.create_kek().expect("Failed to create new KEK");
+
+    // Sharing keeps the DEK bound to the tenant
+    assert!(vault.share_with_context(ciphertext.as_bytes(), &v2, &globex).is_err());
+    let shared = vault.share_with_context(ciphertext.as_bytes(), &v2, &acme).expect("Share failed");
+    let header = ciphertext.header().rewrapped(&shared.kek_id, shared.encrypted_dek);
+    let mut shared = header.to_bytes().expect("Failed to serialize header");
+    shared.extend_from_slice(&ciphertext.as_bytes()[ciphertext.header().encoded_len()..]);
+    assert_eq!(vault.decrypt_bytes(&shared, &acme).unwrap(), b"alice@acme.test");
+
+    // Detached headers rewrap with the tenant too
+    let (header, payload) = vault.encrypt_detached(b"bob@acme.test", &acme).expect("Failed");
+    assert!(vault.rewrap_detached_with_context(&header, &v2, &globex).is_err());
+    let header = vault.rewrap_detached_with_context(&header, &v2, &acme).expect("Rewrap failed");
+    assert_eq!(vault.decrypt_detached(&header, &payload, &acme).unwrap(), b"bob@acme.test");
+
+    // As does a stream of the tenant's blobs
+    let blobs = vec![ciphertext.as_bytes().to_vec(), ciphertext.as_bytes().to_vec()];
+    let rewrapped: Vec<_> = vault.rewrap_iter_with_context(blobs.into_iter(), &v2, &acme).collect();
+    for blob in rewrapped {
+        let blob = Ciphertext::from_bytes(blob.expect("Rewrap failed")).unwrap();
+        assert_eq!(blob.kek_id(), v2);
+        assert_eq!(vault.decrypt(&blob, &acme).unwrap(), b"alice@acme.test");
+    }
+}
+
+#[test]
+fn test_concurrent_create_kek_assigns_distinct_versions() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+
+    // Separate providers, as separate processes sharing the directory would be
+    let mut created: Vec<String> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                scope.spawn(|| {
+                    let provider =
+                        FileKeyProvider::new(key_dir).expect("Failed to create provider");
+                    provider.create_kek().expect("Failed to create KEK")
+                })
+            })
+            .collect();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    });
+
+    created.sort_by_key(|kek_id| kek_id["kek_v".len()..].parse::<u32>().unwrap());
+    let expected: Vec<String> = (2..=9).map(|version| format!("kek_v{version}")).collect();
+    assert_eq!(created, expected);
+
+    let provider = FileKeyProvider::new(key_dir).expect("Failed to create provider");
+    assert_eq!(provider.current_kek_id().unwrap(), "kek_v9");
+    assert!(!key_dir.join(".lock").exists());
+}
+
+#[test]
+fn test_create_kek_times_out_on_held_lock() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let key_dir = temp_dir.path();
+    FileKeyProvider::init(key_dir).expect("Failed to initialize keys");
+    let provider = FileKeyProvider::new(key_dir)
+        .expect("Failed to create provider")
+        .with_lock_timeout(Duration::from_millis(50));
+
+    // As left behind by a process that crashed mid-rotation
+    std::fs::write(key_dir.join(".lock"), b"").expect("Failed to create lock file");
+    assert!(matches!(provider.create_kek(), Err(KeyProviderError::Locked(_))));
+    assert_eq!(provider.current_kek_id().unwrap(), "kek_v1");
+
+    std::fs::remove_file(key_dir.join(".lock")).expect("Failed to remove lock file");
+    assert_eq!(provider.create_kek().unwrap(), "kek_v2");
+}